@@ -5,6 +5,6 @@ extern crate rcc;
 
 fuzz_target!(|data: &[u8]| {
     if let Ok(s) = std::str::from_utf8(data) {
-        rcc::compile(s.into(), "<fuzz test>".into(), false, false, false);
+        rcc::compile(s.into(), "<fuzz test>".into(), false, false, false, false, false);
     }
 });
@@ -14,6 +14,7 @@ use target_lexicon::Triple;
 use crate::data::{
     prelude::*,
     types::{ArrayType, FunctionType},
+    LengthError,
 };
 use Type::*;
 
@@ -32,34 +33,134 @@ mod x64;
 pub use x64::*;
 
 pub fn union_size(symbols: &[Symbol]) -> Result<SIZE_T, &'static str> {
-    symbols
+    let size = symbols
         .iter()
         .map(|symbol| symbol.ctype.sizeof())
         // max of member sizes
-        .try_fold(1, |n, size| Ok(max(n, size?)))
+        .try_fold(1, |n, size| Ok::<_, &str>(max(n, size?)))?;
+    Ok(align_up(size, struct_align(symbols)?))
 }
 
 pub fn struct_size(symbols: &[Symbol]) -> Result<SIZE_T, &'static str> {
-    symbols.iter().try_fold(0, |offset, symbol| {
-        Ok(Type::next_offset(offset, &symbol.ctype)?)
-    })
+    let (_, bits) = struct_layout(symbols)?;
+    let bytes = (bits + SIZE_T::from(CHAR_BIT) - 1) / SIZE_T::from(CHAR_BIT);
+    // pad the end of the struct so that arrays of structs are aligned
+    Ok(align_up(bytes, struct_align(symbols)?))
 }
 
 pub fn struct_align(members: &[Symbol]) -> Result<SIZE_T, &'static str> {
-    members.iter().try_fold(0, |max, member| {
-        Ok(std::cmp::max(member.ctype.alignof()?, max))
-    })
+    members
+        .iter()
+        // unnamed bitfields do not affect the alignment of a struct (System V ABI 3.1.2)
+        .filter(|member| !member.is_padding())
+        .try_fold(0, |max, member| Ok(std::cmp::max(member.alignof()?, max)))
+}
+
+/// Compute the offset in _bits_ of each member of a struct,
+/// along with the total number of bits used.
+///
+/// This follows the System V ABI: bitfields are packed together
+/// unless they would cross the boundary of a storage unit of their declared type,
+/// and bitfields with a width of 0 start a new storage unit.
+/// A flexible array member is placed at the end of the struct without adding to its size.
+fn struct_layout(members: &[Symbol]) -> Result<(Vec<u64>, u64), &'static str> {
+    let char_bit = u64::from(CHAR_BIT);
+    let mut offsets = Vec::with_capacity(members.len());
+    let mut current = 0;
+    for member in members {
+        match &member.ctype {
+            Bitfield(bitfield) => {
+                let unit = bitfield.ctype.sizeof()? * char_bit;
+                let width = u64::from(bitfield.width);
+                if width == 0 || current / unit != (current + width - 1) / unit {
+                    current = align_up(current, unit);
+                }
+                offsets.push(current);
+                current += width;
+            }
+            // flexible array members take up no space, but still affect alignment (C11 6.7.2.1p18)
+            Array(_, ArrayType::Unbounded) => {
+                current = align_up(current, member.alignof()? * char_bit);
+                offsets.push(current);
+            }
+            ctype => {
+                current = align_up(current, member.alignof()? * char_bit);
+                offsets.push(current);
+                current += ctype.sizeof()? * char_bit;
+            }
+        }
+    }
+    Ok((offsets, current))
+}
+
+/// Round `offset` up to the nearest multiple of `align`
+fn align_up(offset: u64, align: u64) -> u64 {
+    // `align` is 0 for structs that were declared but never defined
+    if align == 0 {
+        return offset;
+    }
+    let rem = offset % align;
+    if rem != 0 {
+        // for example: 7%4 == 3; 7 + ((4 - 3) = 1) == 8; 8 % 4 == 0
+        offset + align - rem
+    } else {
+        offset
+    }
+}
+
+/// The location of a bitfield within a struct or union
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitfieldLayout {
+    /// The offset in bytes of the storage unit containing the bitfield
+    pub offset: u64,
+    /// The offset in bits of the bitfield within its storage unit
+    pub shift: u8,
+    pub width: u8,
+    /// The declared type of the bitfield, which is also the type of its storage unit
+    pub ctype: Type,
+}
+
+impl Symbol {
+    /// The alignment of an object or struct member.
+    ///
+    /// This is the alignment of its type unless it was changed with
+    /// `__attribute__((aligned))` or `__attribute__((packed))`.
+    pub fn alignof(&self) -> Result<SIZE_T, &'static str> {
+        match self.qualifiers.align {
+            Some(align) => Ok(align),
+            None => self.ctype.alignof(),
+        }
+    }
 }
 
 impl Type {
     pub fn can_represent(&self, other: &Type) -> bool {
         self == other
             || *self == Type::Double && *other == Type::Float
+            || *self == Type::LongDouble && other.is_floating()
             || (self.is_integral() && other.is_integral())
                 && (self.sizeof() > other.sizeof()
                     || self.sizeof() == other.sizeof() && self.is_signed() == other.is_signed())
     }
 
+    /// The smallest and largest values of an integral type.
+    ///
+    /// Calling this on a type that isn't integral will panic.
+    pub fn integer_range(&self) -> (i128, i128) {
+        if *self == Bool {
+            return (0, 1);
+        }
+        let bits = self
+            .sizeof()
+            .expect("integer_range should only be called on integral types")
+            * u64::from(CHAR_BIT);
+        if self.is_signed() {
+            (-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+        } else {
+            (0, (1 << bits) - 1)
+        }
+    }
+
     pub fn sizeof(&self) -> Result<SIZE_T, &'static str> {
         match self {
             Bool => Ok(BOOL_SIZE.into()),
@@ -67,27 +168,23 @@ impl Type {
             Short(_) => Ok(SHORT_SIZE.into()),
             Int(_) => Ok(INT_SIZE.into()),
             Long(_) => Ok(LONG_SIZE.into()),
+            LongLong(_) => Ok(LONG_LONG_SIZE.into()),
             Float => Ok(FLOAT_SIZE.into()),
             Double => Ok(DOUBLE_SIZE.into()),
-            Pointer(_) => Ok(PTR_SIZE.into()),
+            LongDouble => Ok(LONG_DOUBLE_SIZE.into()),
+            Pointer(..) => Ok(PTR_SIZE.into()),
             // now for the hard ones
             Array(t, ArrayType::Fixed(l)) => t.sizeof().and_then(|n| Ok(n * l)),
-            Array(_, ArrayType::Unbounded) => Err("cannot take sizeof variable length array"),
-            Enum(_, symbols) => {
-                let uchar = CHAR_BIT as usize;
-                // integer division, but taking the ceiling instead of the floor
-                // https://stackoverflow.com/a/17974/7669110
-                Ok(match (symbols.len() + uchar - 1) / uchar {
-                    0..=8 => 8,
-                    9..=16 => 16,
-                    17..=32 => 32,
-                    33..=64 => 64,
-                    _ => return Err("enum cannot be represented in SIZE_T bits"),
-                })
-            }
+            Array(_, ArrayType::Unbounded) => Err("cannot take sizeof array of unknown size"),
+            Array(_, ArrayType::Variable(_)) => Err(LengthError::Dynamic.into()),
+            Enum(_, members) => Type::enum_type(members).sizeof(),
             Union(struct_type) => union_size(&struct_type.members()),
             Struct(struct_type) => struct_size(&struct_type.members()),
-            Bitfield(_) => unimplemented!("sizeof(bitfield)"),
+            // the size of the storage unit; sizeof cannot be applied to bitfields themselves
+            Bitfield(bitfield) => bitfield.ctype.sizeof(),
+            // the real part followed by the imaginary part (C11 6.2.5p13)
+            Complex(inner) => Ok(inner.sizeof()? * 2),
+            Atomic(inner) => inner.sizeof(),
             // illegal operations
             Function(_) => Err("cannot take `sizeof` a function"),
             Void => Err("cannot take `sizeof` void"),
@@ -102,16 +199,28 @@ impl Type {
             | Short(_)
             | Int(_)
             | Long(_)
+            | LongLong(_)
             | Float
             | Double
-            | Pointer(_)
+            | LongDouble
+            | Pointer(..)
             | Enum(_, _) => self.sizeof(),
-            Array(t, _) => t.alignof(),
+            Array(t, _) | Complex(t) => t.alignof(),
+            // like GCC, atomic objects are aligned to their size when possible,
+            // so that libatomic can use lock-free instructions for them
+            Atomic(t) => {
+                let size = t.sizeof()?;
+                if size.is_power_of_two() && size <= 16 {
+                    Ok(std::cmp::max(size, t.alignof()?))
+                } else {
+                    t.alignof()
+                }
+            }
             // Clang uses the largest alignment of any element as the alignment of the whole
             // Not sure why, but who am I to argue
             // Anyway, Faerie panics if the alignment isn't a power of two so it's probably for the best
             Union(struct_type) | Struct(struct_type) => struct_align(&struct_type.members()),
-            Bitfield(_) => unimplemented!("alignof bitfield"),
+            Bitfield(bitfield) => bitfield.ctype.alignof(),
             Function(_) => Err("cannot take `alignof` function"),
             Void => Err("cannot take `alignof` void"),
             VaList => Err("cannot take `alignof` va_list"),
@@ -122,31 +231,56 @@ impl Type {
         IrType::int(CHAR_BIT * PTR_SIZE).expect("pointer size should be valid")
     }
     pub fn struct_offset(&self, members: &[Symbol], member: InternedStr) -> u64 {
-        let mut current_offset = 0;
-        for formal in members {
+        let (offsets, _) =
+            struct_layout(members).expect("structs should have valid size and alignment");
+        let char_bit = u64::from(CHAR_BIT);
+        for (formal, offset) in members.iter().zip(offsets) {
             if formal.id == member {
-                return current_offset;
+                return match &formal.ctype {
+                    // the offset of the storage unit, not the bitfield itself
+                    Bitfield(bitfield) => {
+                        let unit = bitfield
+                            .ctype
+                            .sizeof()
+                            .expect("bitfields should have integer type")
+                            * char_bit;
+                        offset / unit * unit / char_bit
+                    }
+                    _ => offset / char_bit,
+                };
             }
-            current_offset = Self::next_offset(current_offset, &formal.ctype)
-                .expect("structs should have valid size and alignment");
         }
         unreachable!("cannot call struct_offset for member not in struct");
     }
-    fn next_offset(mut current_offset: u64, ctype: &Type) -> Result<u64, &'static str> {
-        let align = ctype.alignof()?;
-        // round up to the nearest multiple of align
-        let rem = current_offset % align;
-        if rem != 0 {
-            // for example: 7%4 == 3; 7 + ((4 - 3) = 1) == 8; 8 % 4 == 0
-            current_offset += align - rem;
-        }
-        Ok(current_offset + ctype.sizeof()?)
+    /// If `member` is a bitfield of this struct or union, return where it is stored.
+    pub fn bitfield(&self, member: InternedStr) -> Option<BitfieldLayout> {
+        let (members, offset) = match self {
+            Struct(struct_type) => {
+                let members = struct_type.members();
+                let (offsets, _) = struct_layout(&members).ok()?;
+                let index = members.iter().position(|formal| formal.id == member)?;
+                (members, offsets[index])
+            }
+            Union(struct_type) => (struct_type.members(), 0),
+            _ => return None,
+        };
+        let bitfield = match &members.iter().find(|formal| formal.id == member)?.ctype {
+            Bitfield(bitfield) => bitfield.clone(),
+            _ => return None,
+        };
+        let unit = bitfield.ctype.sizeof().ok()? * u64::from(CHAR_BIT);
+        Some(BitfieldLayout {
+            offset: offset / unit * unit / u64::from(CHAR_BIT),
+            shift: (offset % unit) as u8,
+            width: bitfield.width,
+            ctype: *bitfield.ctype,
+        })
     }
     pub fn as_ir_type(&self) -> IrType {
         match self {
             // Integers
             Bool => types::B1,
-            Char(_) | Short(_) | Int(_) | Long(_) | Pointer(_) | Enum(_, _) => {
+            Char(_) | Short(_) | Int(_) | Long(_) | LongLong(_) | Pointer(..) | Enum(_, _) => {
                 let int_size = SIZE_T::from(CHAR_BIT)
                     * self
                         .sizeof()
@@ -165,14 +299,31 @@ impl Type {
             Float => types::F32,
             Double => types::F64,
 
+            // atomics have the same representation as their non-atomic version
+            Atomic(inner) => inner.as_ir_type(),
+
             // Aggregates
             // arrays and functions decay to pointers
-            Function(_) | Array(_, _) => IrType::int(PTR_SIZE * CHAR_BIT)
+            // complex numbers are kept in memory and passed around by address
+            Function(_) | Array(_, _) | Complex(_) => IrType::int(PTR_SIZE * CHAR_BIT)
                 .unwrap_or_else(|| panic!("unsupported size of IR: {}", PTR_SIZE)),
-            // void cannot be loaded or stored
+            // void cannot be loaded or stored,
+            // and Cranelift has no type for x87 `long double`
             _ => types::INVALID,
         }
     }
+    /// The IR types used to pass `self` to or return it from a function.
+    ///
+    /// The System V ABI classifies complex numbers as SSE:
+    /// `double _Complex` is passed as two doubles,
+    /// and `float _Complex` is packed into a single register.
+    pub fn abi_types(&self) -> Vec<IrType> {
+        match self {
+            Complex(inner) if **inner == Float => vec![types::F64],
+            Complex(inner) => vec![inner.as_ir_type(); 2],
+            _ => vec![self.as_ir_type()],
+        }
+    }
 }
 
 impl FunctionType {
@@ -181,9 +332,9 @@ impl FunctionType {
             // no arguments
             Vec::new()
         } else {
-            self.params
-                .iter()
-                .map(|param| AbiParam::new(param.ctype.as_ir_type()))
+            self.passed_types()
+                .flat_map(|ctype| ctype.abi_types())
+                .map(AbiParam::new)
                 .collect()
         };
         if self.varargs {
@@ -200,7 +351,11 @@ impl FunctionType {
         let return_type = if !self.should_return() {
             vec![]
         } else {
-            vec![AbiParam::new(self.return_type.as_ir_type())]
+            self.return_type
+                .abi_types()
+                .into_iter()
+                .map(AbiParam::new)
+                .collect()
         };
         Signature {
             call_conv: *CALLING_CONVENTION,
@@ -293,8 +448,11 @@ mod tests {
     #[test]
     fn second_member() {
         for size in 1..128 {
-            let types = vec![type_for_size(size), Type::Bool];
-            assert_offset(types, 1, size.into());
+            let first = type_for_size(size);
+            // structs are padded up to their alignment, so this isn't always `size`
+            let offset = first.sizeof().unwrap();
+            assert_eq!(offset % first.alignof().unwrap(), 0);
+            assert_offset(vec![first, Type::Bool], 1, offset);
         }
     }
     #[test]
@@ -321,6 +479,19 @@ mod tests {
         let char_struct = type_for_size(5);
         assert_eq!(char_struct.alignof().unwrap(), 4);
         assert_offset(vec![Type::Int(true), Type::Char(true)], 1, 4);
-        assert_eq!(char_struct.sizeof().unwrap(), 5);
+        assert_eq!(char_struct.sizeof().unwrap(), 8);
+    }
+    #[test]
+    fn tail_padding() {
+        // struct { long l; char c; }
+        let long_char = struct_for_types(vec![Type::Long(true), Type::Char(true)]);
+        assert_eq!(long_char.sizeof().unwrap(), 16);
+        // the next member starts after the padding, not directly after `c`
+        assert_offset(vec![long_char.clone(), Type::Char(true)], 1, 16);
+        let array = Type::Array(Box::new(long_char), ArrayType::Fixed(3));
+        assert_eq!(array.sizeof().unwrap(), 48);
+        // struct { char c[3]; } has no padding
+        let chars = Type::Array(Box::new(Type::Char(true)), ArrayType::Fixed(3));
+        assert_eq!(struct_for_types(vec![chars]).sizeof().unwrap(), 3);
     }
 }
@@ -6,7 +6,10 @@ pub const SIZE_MAX: SIZE_T = SIZE_T::max_value();
 
 pub const FLOAT_SIZE: u16 = 4;
 pub const DOUBLE_SIZE: u16 = 8;
+/// x87 extended precision takes 10 bytes, but is padded to 16
+pub const LONG_DOUBLE_SIZE: u16 = 16;
 
+pub const LONG_LONG_SIZE: u16 = 8;
 pub const LONG_SIZE: u16 = 8;
 pub const INT_SIZE: u16 = 4;
 pub const SHORT_SIZE: u16 = 2;
@@ -1,7 +1,8 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use thiserror::Error;
 
-use super::{Expr, Locatable, Location};
+use super::{types::Type, Expr, Locatable, Location};
+use crate::intern::InternedStr;
 
 /// RecoverableResult is a type that represents a Result that can be recovered from.
 ///
@@ -10,8 +11,28 @@ use super::{Expr, Locatable, Location};
 /// [`Recover`]: trait.Recover.html
 pub type RecoverableResult<T, E = CompileError> = Result<T, (E, T)>;
 pub type CompileResult<T> = Result<T, CompileError>;
-pub type CompileError = Locatable<Error>;
-pub type CompileWarning = Locatable<Warning>;
+pub type CompileError = Diagnostic<Error>;
+pub type CompileWarning = Diagnostic<Warning>;
+
+/// An error or warning, where it happened, and anything that helps explain or fix it.
+#[derive(Clone, Debug)]
+pub struct Diagnostic<T> {
+    pub data: T,
+    pub location: Location,
+    /// Secondary messages with their own location, e.g. "previous definition is here"
+    pub notes: Vec<Locatable<String>>,
+    /// Suggested edits to the source that would fix the problem
+    pub fixes: Vec<Fix>,
+}
+
+/// A suggested edit: replace the source at `location` with `replacement`.
+///
+/// If `location` is empty, this inserts `replacement` without removing anything.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fix {
+    pub location: Location,
+    pub replacement: String,
+}
 
 /// ErrorHandler is a struct that hold errors generated by the compiler
 ///
@@ -40,11 +61,13 @@ impl ErrorHandler {
         self.errors.pop_front()
     }
 
-    /// Stopgap to make it easier to transition to lazy warnings.
-    ///
-    /// TODO: Remove this method
-    pub(crate) fn warn<W: Into<Warning>>(&mut self, warning: W, location: Location) {
-        self.warnings.push_back(location.with(warning.into()));
+    /// Add a warning to the error handler.
+    pub(crate) fn warn(&mut self, warning: Warning, location: Location) {
+        self.push_warning(Diagnostic::new(warning, location));
+    }
+    /// Add a warning that already has notes or fixes.
+    pub(crate) fn push_warning(&mut self, warning: CompileWarning) {
+        self.warnings.push_back(warning);
     }
     /// Add an iterator of errors to the error queue
     pub(crate) fn extend<E: Into<CompileError>>(&mut self, iter: impl Iterator<Item = E>) {
@@ -128,30 +151,279 @@ pub enum LexError {
 }
 
 #[derive(Debug, Error, PartialEq, Eq)]
-/// errors are non-exhaustive and may have new variants added at any time
+/// Each kind of warning has its own variant, so it can be controlled with `-W` flags.
+///
+/// Warnings are non-exhaustive and may have new variants added at any time
 pub enum Warning {
-    // for compatibility
     #[error("{0}")]
-    Generic(String),
+    Extension(&'static str),
+
+    #[error("unknown character escape '\\{0}'")]
+    UnknownEscape(char),
+
+    #[error("extraneous semicolon {0}")]
+    ExtraSemicolon(&'static str),
+
+    #[error("declaration does not declare anything")]
+    EmptyDeclaration,
+
+    #[error("type specifier missing, defaults to int")]
+    ImplicitInt,
+
+    #[error("type of parameter '{0}' defaults to 'int'")]
+    ImplicitIntParameter(InternedStr),
+
+    // the qualifiers are formatted, e.g. "const volatile"
+    #[error("{0} has no effect on function return type")]
+    IgnoredQualifiers(String),
+
+    #[error("duplicate declaration specifier '{0}'")]
+    DuplicateSpecifier(String),
+
+    #[error("this is a definition, not a declaration, the 'extern' keyword has no effect")]
+    ExternInitializer,
+
+    // the function and the reason the prototype doesn't match
+    #[error("prototype for '{0}' does not match its declaration without a prototype: {1}")]
+    IncompatiblePrototype(InternedStr, String),
+
+    // the function and what it does wrong: "should not return" or "can return"
+    #[error("function '{0}' declared '_Noreturn' {1}")]
+    InvalidNoreturn(InternedStr, &'static str),
+
+    // the original type of the pointer and the type it was converted to,
+    // along with the qualifiers that were discarded
+    #[error("conversion from '{0}' to '{1}' discards '{2}' qualifier from pointer target type")]
+    DiscardedQualifiers(Type, Type, String),
+
+    // the message is the same as the error for calls to functions with a prototype
+    #[error("{0}")]
+    OldStyleCall(String),
+
+    #[error("{0} will be rewritten internally. help: to silence this warning, rewrite it yourself: `{1}` => `{2}`")]
+    EmptyBody(&'static str, &'static str, &'static str),
+
+    #[error("case value {0} is out of range for type '{1}'")]
+    CaseOutOfRange(i128, Type),
+
+    #[error("enumeration value '{0}' not handled in switch")]
+    UnhandledEnumerator(InternedStr),
+
+    #[error("using the result of an assignment as a condition without parentheses")]
+    AssignmentInCondition,
+
+    #[error("{0}")]
+    IgnoredAttribute(String),
+
+    // `constructor` or `destructor`
+    #[error("{0} priorities from 0 to 100 are reserved for the implementation")]
+    ReservedPriority(String),
+
+    #[error("{0}")]
+    Deprecated(String),
+
+    #[error("ignoring return value of '{0}', declared with attribute 'warn_unused_result'")]
+    UnusedResult(InternedStr),
+
+    #[error("unused function '{0}'")]
+    UnusedFunction(InternedStr),
+
+    #[error("array '{0}' assumed to have one element")]
+    TentativeArray(InternedStr),
+
+    #[error("{0}")]
+    LossyConversion(String),
 
     #[doc(hidden)]
     #[error("internal error: do not construct nonexhaustive variants")]
     __Nonexhaustive,
 }
 
-impl<T: Into<String>> From<T> for Warning {
-    fn from(msg: T) -> Warning {
-        Warning::Generic(msg.into())
+impl Warning {
+    /// The name of every warning flag, as in `-Wname` and `-Wno-name`.
+    pub const FLAGS: &'static [&'static str] = &[
+        "attributes",
+        "constant-conversion",
+        "deprecated-declarations",
+        "discarded-qualifiers",
+        "duplicate-decl-specifier",
+        "empty-body",
+        "empty-declaration",
+        "extern-initializer",
+        "extra-semi",
+        "ignored-qualifiers",
+        "implicit-int",
+        "incompatible-prototype",
+        "invalid-noreturn",
+        "old-style-call",
+        "parentheses",
+        "pedantic",
+        "prio-ctor-dtor",
+        "switch",
+        "switch-outside-range",
+        "tentative-array",
+        "unknown-escape-sequence",
+        "unused-function",
+        "unused-result",
+    ];
+    /// The warnings enabled by `-Wall`, which are off by default like in GCC.
+    pub const ALL: &'static [&'static str] = &["parentheses", "switch", "unused-function"];
+    /// The warnings enabled by `-Wextra`, which are off by default like in GCC.
+    pub const EXTRA: &'static [&'static str] = &["empty-body", "ignored-qualifiers"];
+
+    /// The flag that controls this warning.
+    ///
+    /// These are the same as GCC or Clang where they have an equivalent,
+    /// and won't change between releases.
+    pub fn flag(&self) -> &'static str {
+        use Warning::*;
+        match self {
+            Extension(_) => "pedantic",
+            UnknownEscape(_) => "unknown-escape-sequence",
+            ExtraSemicolon(_) => "extra-semi",
+            // not GCC's `missing-declarations`, which is about functions without a prototype
+            EmptyDeclaration => "empty-declaration",
+            ImplicitInt | ImplicitIntParameter(_) => "implicit-int",
+            IgnoredQualifiers(_) => "ignored-qualifiers",
+            DuplicateSpecifier(_) => "duplicate-decl-specifier",
+            ExternInitializer => "extern-initializer",
+            IncompatiblePrototype(_, _) => "incompatible-prototype",
+            InvalidNoreturn(_, _) => "invalid-noreturn",
+            DiscardedQualifiers(_, _, _) => "discarded-qualifiers",
+            OldStyleCall(_) => "old-style-call",
+            EmptyBody(_, _, _) => "empty-body",
+            CaseOutOfRange(_, _) => "switch-outside-range",
+            UnhandledEnumerator(_) => "switch",
+            AssignmentInCondition => "parentheses",
+            IgnoredAttribute(_) => "attributes",
+            ReservedPriority(_) => "prio-ctor-dtor",
+            Deprecated(_) => "deprecated-declarations",
+            UnusedResult(_) => "unused-result",
+            UnusedFunction(_) => "unused-function",
+            TentativeArray(_) => "tentative-array",
+            LossyConversion(_) => "constant-conversion",
+            __Nonexhaustive => unreachable!("do not construct nonexhaustive variants"),
+        }
     }
 }
 
-impl CompileError {
-    pub(crate) fn semantic(err: Locatable<String>) -> Self {
-        Self::from(err)
+/// What to do with a warning, according to the `-W` flags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WarningLevel {
+    Ignore,
+    Warn,
+    Error,
+}
+
+/// The warnings that were turned on or off, or made into errors, with `-W` flags.
+///
+/// The warnings in `Warning::ALL` and `Warning::EXTRA` are only enabled by `-Wall` and `-Wextra`,
+/// and every other warning is enabled by default.
+/// Flags for specific warnings take precedence over groups and `-Werror`, regardless of order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WarningFlags {
+    // `-Wname` (true) or `-Wno-name` (false), the last one wins
+    enabled: HashMap<&'static str, bool>,
+    // `-Wall` and `-Wextra`
+    all: bool,
+    extra: bool,
+    // `-Werror=name` (true) or `-Wno-error=name` (false), the last one wins
+    errors: HashMap<&'static str, bool>,
+    // `-Werror`
+    all_errors: bool,
+}
+
+impl WarningFlags {
+    /// Apply a flag, without the leading `-W`.
+    ///
+    /// Examples: `all`, `error`, `switch`, `no-switch`, `error=switch`, `no-error=switch`
+    pub fn add(&mut self, flag: &str) -> Result<(), String> {
+        let lookup = |name: &str| {
+            Warning::FLAGS
+                .iter()
+                .find(|&&known| known == name)
+                .copied()
+                .ok_or_else(|| format!("unknown warning option '-W{}'", flag))
+        };
+        match flag {
+            "all" => self.all = true,
+            "no-all" => self.all = false,
+            "extra" => self.extra = true,
+            "no-extra" => self.extra = false,
+            "error" => self.all_errors = true,
+            "no-error" => self.all_errors = false,
+            _ => {
+                let name = flag.trim_start_matches("no-");
+                let enable = name.len() == flag.len();
+                let mut parts = name.splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some("error"), Some(name)) => {
+                        let name = lookup(name)?;
+                        self.errors.insert(name, enable);
+                        // like GCC, `-Werror=name` also enables the warning
+                        if enable {
+                            self.enabled.insert(name, true);
+                        }
+                    }
+                    _ => {
+                        self.enabled.insert(lookup(name)?, enable);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Whether `warning` should be ignored, shown as a warning, or treated as an error.
+    pub fn level(&self, warning: &Warning) -> WarningLevel {
+        let flag = warning.flag();
+        let default = if Warning::ALL.contains(&flag) {
+            self.all
+        } else if Warning::EXTRA.contains(&flag) {
+            self.extra
+        } else {
+            true
+        };
+        if !self.enabled.get(flag).copied().unwrap_or(default) {
+            WarningLevel::Ignore
+        } else if self.errors.get(flag).copied().unwrap_or(self.all_errors) {
+            WarningLevel::Error
+        } else {
+            WarningLevel::Warn
+        }
+    }
+}
+
+impl<T> Diagnostic<T> {
+    pub fn new(data: T, location: Location) -> Self {
+        Diagnostic {
+            data,
+            location,
+            notes: Vec::new(),
+            fixes: Vec::new(),
+        }
+    }
+    /// Add a note pointing somewhere else in the program, usually to an earlier declaration.
+    pub(crate) fn note<S: Into<String>>(mut self, message: S, location: Location) -> Self {
+        self.notes.push(location.with(message.into()));
+        self
+    }
+    /// Suggest replacing the source at `location` with `replacement`.
+    pub(crate) fn fix<S: Into<String>>(mut self, location: Location, replacement: S) -> Self {
+        self.fixes.push(Fix {
+            location,
+            replacement: replacement.into(),
+        });
+        self
     }
     pub fn location(&self) -> Location {
         self.location
     }
+}
+
+impl CompileError {
+    pub(crate) fn semantic(err: Locatable<String>) -> Self {
+        Self::from(err)
+    }
     pub fn is_lex_err(&self) -> bool {
         self.data.is_lex_err()
     }
@@ -187,21 +459,34 @@ impl Error {
     }
 }
 
+// like `Locatable`, only the error or warning itself is compared
+impl<T: PartialEq> PartialEq for Diagnostic<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl From<Locatable<Error>> for CompileError {
+    fn from(err: Locatable<Error>) -> Self {
+        CompileError::new(err.data, err.location)
+    }
+}
+
 impl From<Locatable<String>> for CompileError {
     fn from(err: Locatable<String>) -> Self {
-        err.map(|s| SemanticError::Generic(s).into())
+        CompileError::new(SemanticError::Generic(err.data).into(), err.location)
     }
 }
 
 impl From<Locatable<SemanticError>> for CompileError {
     fn from(err: Locatable<SemanticError>) -> Self {
-        err.map(Error::Semantic)
+        CompileError::new(Error::Semantic(err.data), err.location)
     }
 }
 
 impl From<Locatable<SyntaxError>> for CompileError {
     fn from(err: Locatable<SyntaxError>) -> Self {
-        err.map(Error::Syntax)
+        CompileError::new(Error::Syntax(err.data), err.location)
     }
 }
 
@@ -253,11 +538,11 @@ mod tests {
     use super::*;
 
     fn dummy_error() -> CompileError {
-        Location::default().with(Error::Lex(LexError::UnterminatedComment))
+        Location::default().error(LexError::UnterminatedComment)
     }
 
     fn new_error(error: Error) -> CompileError {
-        Location::default().with(error)
+        Location::default().error(error)
     }
 
     #[test]
@@ -370,4 +655,96 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_warning_flags() {
+        let warning = Warning::EmptyDeclaration;
+        let level = |flags: &[&str]| {
+            let mut warnings = WarningFlags::default();
+            for flag in flags {
+                warnings.add(flag).unwrap();
+            }
+            warnings.level(&warning)
+        };
+        assert_eq!(level(&[]), WarningLevel::Warn);
+        assert_eq!(level(&["all", "extra"]), WarningLevel::Warn);
+        assert_eq!(level(&["no-empty-declaration"]), WarningLevel::Ignore);
+        assert_eq!(
+            level(&["no-empty-declaration", "empty-declaration"]),
+            WarningLevel::Warn
+        );
+        assert_eq!(level(&["error"]), WarningLevel::Error);
+        assert_eq!(level(&["error", "no-error"]), WarningLevel::Warn);
+        assert_eq!(level(&["error=switch"]), WarningLevel::Warn);
+        assert_eq!(
+            level(&["no-empty-declaration", "error=empty-declaration"]),
+            WarningLevel::Error
+        );
+        assert_eq!(
+            level(&["no-error=empty-declaration", "error"]),
+            WarningLevel::Warn
+        );
+        assert_eq!(
+            level(&["error", "no-empty-declaration"]),
+            WarningLevel::Ignore
+        );
+
+        let mut warnings = WarningFlags::default();
+        assert!(warnings.add("not-a-warning").is_err());
+        assert!(warnings.add("error=not-a-warning").is_err());
+        assert!(warnings.add("no-error=").is_err());
+    }
+
+    #[test]
+    fn test_warning_groups() {
+        let warning = Warning::AssignmentInCondition;
+        let level = |flags: &[&str]| {
+            let mut warnings = WarningFlags::default();
+            for flag in flags {
+                warnings.add(flag).unwrap();
+            }
+            warnings.level(&warning)
+        };
+        assert_eq!(level(&[]), WarningLevel::Ignore);
+        assert_eq!(level(&["extra"]), WarningLevel::Ignore);
+        assert_eq!(level(&["all"]), WarningLevel::Warn);
+        assert_eq!(level(&["all", "no-all"]), WarningLevel::Ignore);
+        assert_eq!(level(&["parentheses"]), WarningLevel::Warn);
+        assert_eq!(level(&["no-parentheses", "all"]), WarningLevel::Ignore);
+        assert_eq!(level(&["all", "error"]), WarningLevel::Error);
+        assert_eq!(level(&["error=parentheses"]), WarningLevel::Error);
+
+        let mut warnings = WarningFlags::default();
+        warnings.add("extra").unwrap();
+        assert_eq!(
+            warnings.level(&Warning::IgnoredQualifiers(String::new())),
+            WarningLevel::Warn
+        );
+        for group in &[Warning::ALL, Warning::EXTRA] {
+            for flag in group.iter() {
+                assert!(Warning::FLAGS.contains(flag));
+            }
+        }
+    }
+
+    #[test]
+    fn test_warning_flag_names() {
+        let id = InternedStr::get_or_intern("f");
+        let warnings = vec![
+            Warning::Extension("binary literals are an extension"),
+            Warning::UnknownEscape('q'),
+            Warning::ImplicitIntParameter(id),
+            Warning::InvalidNoreturn(id, "can return"),
+            Warning::CaseOutOfRange(256, Type::Char(true)),
+            Warning::UnusedFunction(id),
+            Warning::LossyConversion(String::new()),
+        ];
+        for warning in warnings {
+            assert!(Warning::FLAGS.contains(&warning.flag()));
+        }
+        assert_eq!(
+            Warning::InvalidNoreturn(id, "can return").to_string(),
+            "function 'f' declared '_Noreturn' can return"
+        );
+    }
 }
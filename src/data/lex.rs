@@ -82,10 +82,19 @@ pub enum Keyword {
 
     // intrinsics
     Sizeof,
+    // GNU extensions for complex numbers: `__real__` and `__imag__`
+    Real,
+    Imag,
     Generic,
     StaticAssert,
     Alignas,
     Alignof,
+
+    // GNU extensions, only recognized in GNU mode
+    Attribute,
+    Extension,
+    Asm,
+    Typeof,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -177,7 +186,23 @@ impl Location {
     }
 
     pub fn error<E: Into<super::error::Error>>(self, error: E) -> super::CompileError {
-        self.with(error.into())
+        super::CompileError::new(error.into(), self)
+    }
+
+    /// An empty location at the start of this one, for inserting text before it.
+    pub fn before(self) -> Location {
+        Location {
+            span: Span::new(self.span.start(), self.span.start()),
+            filename: self.filename,
+        }
+    }
+
+    /// An empty location at the end of this one, for inserting text after it.
+    pub fn after(self) -> Location {
+        Location {
+            span: Span::new(self.span.end(), self.span.end()),
+            filename: self.filename,
+        }
     }
 }
 
@@ -293,6 +318,12 @@ impl std::fmt::Display for Keyword {
             Keyword::ThreadLocal => write!(f, "_Thread_local"),
             Keyword::StaticAssert => write!(f, "_Static_assert"),
             Keyword::VaList => write!(f, "va_list"),
+            Keyword::Real => write!(f, "__real__"),
+            Keyword::Imag => write!(f, "__imag__"),
+            Keyword::Attribute => write!(f, "__attribute__"),
+            Keyword::Extension => write!(f, "__extension__"),
+            Keyword::Asm => write!(f, "__asm__"),
+            Keyword::Typeof => write!(f, "__typeof__"),
             _ => write!(f, "{}", &format!("{:?}", self).to_lowercase()),
         }
     }
@@ -402,7 +433,7 @@ impl From<ComparisonToken> for Token {
 mod test {
     use crate::*;
     fn lexer(s: &str) -> Lexer {
-        Lexer::new("<integration-test>", s.chars(), false)
+        Lexer::new("<integration-test>", s.chars(), false, false)
     }
     #[test]
     fn assignment_display() {
@@ -11,10 +11,12 @@ pub mod types;
 pub mod prelude {
     pub(crate) use super::error::{ErrorHandler, Recover, RecoverableResult};
     pub use super::{
-        error::{CompileError, CompileResult, CompileWarning, Error, SemanticError, SyntaxError},
+        error::{
+            CompileError, CompileResult, CompileWarning, Error, SemanticError, SyntaxError, Warning,
+        },
         lex::{Literal, Locatable, Location, Token},
         types::{StructRef, StructType, Type},
-        Declaration, Expr, ExprType, Stmt, StmtType, Symbol,
+        AtomicOp, Attribute, AttributeArg, Declaration, Expr, ExprType, Stmt, StmtType, Symbol,
     };
     pub use crate::intern::InternedStr;
 }
@@ -43,10 +45,16 @@ pub enum StmtType {
     ),
     Switch(Expr, Box<Stmt>),
     Label(InternedStr),
+    // the value is converted to the promoted type of the controlling expression,
+    // then sign-extended (for signed types) or zero-extended (for unsigned types) to 64 bits
     Case(u64, Option<Box<Stmt>>),
+    // GNU case range: `case low ... high:`, stored the same way as `Case`
+    CaseRange(u64, u64, Option<Box<Stmt>>),
     Default(Option<Box<Stmt>>),
     Expr(Expr),
     Goto(InternedStr),
+    // GNU computed goto: `goto *ptr;`, where `ptr` is the address of a label
+    IndirectGoto(Expr),
     Continue,
     Break,
     Return(Option<Expr>),
@@ -57,6 +65,25 @@ pub enum StmtType {
 pub struct Declaration {
     pub symbol: Symbol,
     pub init: Option<Initializer>,
+    /// GNU attributes from both the declaration specifiers and the declarator
+    pub attributes: Vec<Locatable<Attribute>>,
+    /// the symbol name given with `__asm__("name")`, if different from the identifier
+    pub asm_label: Option<InternedStr>,
+}
+
+/// A GNU attribute, e.g. `aligned(16)` in `int x __attribute__((aligned(16)));`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attribute {
+    /// the name of the attribute, without the optional leading and trailing `__`
+    pub name: InternedStr,
+    pub args: Vec<AttributeArg>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttributeArg {
+    // e.g. `printf` in `format(printf, 1, 2)`
+    Id(InternedStr),
+    Expr(Expr),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -104,6 +131,9 @@ pub enum ExprType {
     Literal(Literal),
     FuncCall(Box<Expr>, Vec<Expr>),
     Member(Box<Expr>, InternedStr),
+    // `__real__` or `__imag__` of a complex number
+    // bool: imaginary (true) or real (false)
+    ComplexPart(Box<Expr>, bool),
     // post increment/decrement
     PostIncrement(Box<Expr>, bool),
     Cast(Box<Expr>),
@@ -137,6 +167,43 @@ pub enum ExprType {
     StaticRef(Box<Expr>),
     // used to work around various bugs, see places this is constructed for details
     Noop(Box<Expr>),
+    // one of the `__atomic_*` or `__c11_atomic_*` builtins
+    // the first argument is always a pointer to the object being operated on,
+    // see `AtomicOp` for the rest
+    Atomic(AtomicOp, Vec<Expr>),
+    // GNU statement expression: `({ int x = f(); x * 2; })`
+    // the value is the last expression statement, which is stored separately
+    StmtExpr(Vec<Stmt>, Option<Box<Expr>>),
+    // GNU label address: `&&label`, which can only be used with `goto *`
+    LabelAddress(InternedStr),
+}
+
+/// An atomic operation, after the parser has normalized the builtin that requested it.
+///
+/// Memory orders are passed as `int` arguments with the same values as `__ATOMIC_*`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AtomicOp {
+    /// ptr, order
+    Load,
+    /// ptr, value, order
+    Store,
+    /// ptr, value, order
+    Exchange,
+    /// ptr, pointer to expected value, desired value, success order, failure order
+    CompareExchange,
+    /// ptr, value, order
+    ///
+    /// `op` is the name used by libatomic (`add`, `sub`, `and`, `or`, `xor`, or `nand`).
+    /// If `fetch_first` is true, this returns the old value, otherwise the new value.
+    ReadModifyWrite { op: &'static str, fetch_first: bool },
+    /// ptr, order
+    TestAndSet,
+    /// ptr, order
+    Clear,
+    /// order
+    ThreadFence,
+    /// order
+    SignalFence,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -162,7 +229,17 @@ pub struct Symbol {
 pub struct Qualifiers {
     pub volatile: bool,
     pub c_const: bool,
+    /// Only allowed for pointers to objects
+    pub restrict: bool,
     pub inline: bool,
+    pub thread_local: bool,
+    /// Only used for pointer declarators; `_Atomic T` is represented as `Type::Atomic`
+    pub atomic: bool,
+    /// Only used for declaration specifiers; `_Noreturn` functions are marked in `FunctionType`
+    pub noreturn: bool,
+    /// The alignment of an object or struct member, if it was changed with
+    /// `__attribute__((aligned))` or `__attribute__((packed))`
+    pub align: Option<SIZE_T>,
 }
 
 #[derive(Debug)]
@@ -173,23 +250,56 @@ impl Qualifiers {
     pub const NONE: Qualifiers = Qualifiers {
         c_const: false,
         volatile: false,
+        restrict: false,
         inline: false,
+        thread_local: false,
+        atomic: false,
+        noreturn: false,
+        align: None,
     };
     pub const VOLATILE: Qualifiers = Qualifiers {
         c_const: false,
         volatile: true,
+        restrict: false,
         inline: false,
+        thread_local: false,
+        atomic: false,
+        noreturn: false,
+        align: None,
     };
     pub const CONST: Qualifiers = Qualifiers {
         c_const: true,
         volatile: false,
+        restrict: false,
         inline: false,
+        thread_local: false,
+        atomic: false,
+        noreturn: false,
+        align: None,
     };
     pub const CONST_VOLATILE: Qualifiers = Qualifiers {
         c_const: true,
         volatile: true,
+        restrict: false,
         inline: false,
+        thread_local: false,
+        atomic: false,
+        noreturn: false,
+        align: None,
     };
+
+    /// Only the type qualifiers, without function specifiers or storage information.
+    ///
+    /// These are the qualifiers that can be part of a pointer type,
+    /// e.g. `const` and `volatile` in `const volatile char *`.
+    pub fn type_qualifiers(self) -> Qualifiers {
+        Qualifiers {
+            c_const: self.c_const,
+            volatile: self.volatile,
+            restrict: self.restrict,
+            ..Qualifiers::NONE
+        }
+    }
 }
 
 pub enum LengthError {
@@ -199,6 +309,30 @@ pub enum LengthError {
     Negative,
 }
 
+impl Attribute {
+    /// The argument of an attribute like `section(".text")`, without its null terminator.
+    ///
+    /// Returns `None` unless there is exactly one argument and it is a string literal.
+    pub fn string_argument(&self) -> Option<String> {
+        match self.args.as_slice() {
+            [AttributeArg::Expr(Expr {
+                expr: ExprType::Literal(Literal::Str(s)),
+                ..
+            })] => Some(s.resolve_and_clone().trim_end_matches('\0').to_string()),
+            _ => None,
+        }
+    }
+    /// The argument of an attribute like `aligned(16)`.
+    ///
+    /// Returns `None` unless there is exactly one argument and it is a non-negative integer constant.
+    pub fn integer_argument(&self) -> Option<SIZE_T> {
+        match self.args.as_slice() {
+            [AttributeArg::Expr(expr)] => expr.clone().const_int().ok(),
+            _ => None,
+        }
+    }
+}
+
 impl Expr {
     pub fn const_int(self) -> error::CompileResult<SIZE_T> {
         use std::convert::TryInto;
@@ -227,6 +361,30 @@ impl Expr {
             location,
         }
     }
+    /// Whether this expression refers to a bitfield member of a struct or union.
+    pub fn is_bitfield(&self) -> bool {
+        match &self.expr {
+            ExprType::Member(cstruct, id) => cstruct.ctype.bitfield(*id).is_some(),
+            _ => false,
+        }
+    }
+}
+
+impl Symbol {
+    /// Whether this is an unnamed bitfield, which only affects the layout of a struct.
+    pub fn is_padding(&self) -> bool {
+        match self.ctype {
+            Type::Bitfield(_) => self.id.is_empty(),
+            _ => false,
+        }
+    }
+    /// Whether this is an anonymous struct or union member (C11 6.7.2.1p13),
+    /// e.g. the union in `struct { int kind; union { int i; float f; }; }`.
+    ///
+    /// The parser gives these members a name which is not a valid identifier.
+    pub fn is_anonymous_member(&self) -> bool {
+        self.ctype.is_struct() && self.id.resolve_and_clone().starts_with('.')
+    }
 }
 
 impl From<LengthError> for String {
@@ -266,6 +424,12 @@ impl<K: Hash + Eq, V> Scope<K, V> {
             .filter_map(|(key, value)| if key == name { Some(value) } else { None })
             .next()
     }
+    pub fn get_mut(&mut self, name: &K) -> Option<&mut V> {
+        self.0
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
     // returns whether the _immediate_ scope contains `name`
     #[inline]
     pub fn insert(&mut self, key: K, value: V) -> Option<V> {
@@ -275,6 +439,11 @@ impl<K: Hash + Eq, V> Scope<K, V> {
     pub fn get_immediate(&self, name: &K) -> Option<&V> {
         self.0.last().unwrap().get(name)
     }
+    /// Look up `name` at file scope, even if it is shadowed by a local declaration.
+    #[inline]
+    pub fn get_global(&self, name: &K) -> Option<&V> {
+        self.0.first().unwrap().get(name)
+    }
     #[inline]
     pub fn get_all_immediate(&mut self) -> &mut HashMap<K, V> {
         self.0.last_mut().unwrap()
@@ -326,6 +495,9 @@ impl Display for StorageClass {
 
 impl Display for Qualifiers {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.thread_local {
+            write!(f, "_Thread_local ")?;
+        }
         write!(
             f,
             "{}",
@@ -335,7 +507,11 @@ impl Display for Qualifiers {
                 (false, true) => "volatile ",
                 (false, false) => "",
             }
-        )
+        )?;
+        if self.restrict {
+            write!(f, "restrict ")?;
+        }
+        Ok(())
     }
 }
 
@@ -371,11 +547,73 @@ impl Display for Expr {
             ExprType::Cast(expr) => write!(f, "({})({})", self.ctype, expr),
             ExprType::Sizeof(ty) => write!(f, "sizeof({})", ty),
             ExprType::Member(compound, id) => write!(f, "({}).{}", compound, id),
+            ExprType::ComplexPart(expr, imag) => {
+                write!(
+                    f,
+                    "{}({})",
+                    if *imag { "__imag__" } else { "__real__" },
+                    expr
+                )
+            }
             ExprType::PostIncrement(expr, inc) => {
                 write!(f, "({}){}", expr, if *inc { "++" } else { "--" })
             }
             ExprType::StaticRef(expr) => write!(f, "&{}", expr),
             ExprType::Noop(expr) => write!(f, "{}", expr),
+            ExprType::Atomic(op, args) => write!(f, "__atomic_{}({})", op, join(args)),
+            ExprType::StmtExpr(stmts, result) => {
+                write!(f, "({{ ")?;
+                for stmt in stmts {
+                    write!(f, "{}", stmt.data)?;
+                }
+                if let Some(result) = result {
+                    write!(f, "{}; ", result)?;
+                }
+                write!(f, "}})")
+            }
+            ExprType::LabelAddress(label) => write!(f, "&&{}", label),
+        }
+    }
+}
+
+impl Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.args.is_empty() {
+            write!(f, "({})", join(&self.args))?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for AttributeArg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AttributeArg::Id(id) => write!(f, "{}", id),
+            AttributeArg::Expr(expr) => write!(f, "{}", expr),
+        }
+    }
+}
+
+impl Display for AtomicOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AtomicOp::Load => write!(f, "load"),
+            AtomicOp::Store => write!(f, "store"),
+            AtomicOp::Exchange => write!(f, "exchange"),
+            AtomicOp::CompareExchange => write!(f, "compare_exchange"),
+            AtomicOp::ReadModifyWrite {
+                op,
+                fetch_first: true,
+            } => write!(f, "fetch_{}", op),
+            AtomicOp::ReadModifyWrite {
+                op,
+                fetch_first: false,
+            } => write!(f, "{}_fetch", op),
+            AtomicOp::TestAndSet => write!(f, "test_and_set"),
+            AtomicOp::Clear => write!(f, "clear"),
+            AtomicOp::ThreadFence => write!(f, "thread_fence"),
+            AtomicOp::SignalFence => write!(f, "signal_fence"),
         }
     }
 }
@@ -442,7 +680,19 @@ impl StmtType {
                     " ;".into()
                 }
             ),
+            StmtType::CaseRange(low, high, stmt) => write!(
+                f,
+                "case {} ... {}:{}",
+                low,
+                high,
+                if let Some(stmt) = stmt {
+                    format!("\n{}", stmt.data)
+                } else {
+                    " ;".into()
+                }
+            ),
             StmtType::Goto(id) => write!(f, "goto {};", id),
+            StmtType::IndirectGoto(target) => write!(f, "goto *{};", target),
             StmtType::Label(id) => write!(f, "{}: ", id),
             StmtType::While(condition, None) => write!(f, "while ({}) {{}}", condition),
             StmtType::While(condition, Some(body)) => {
@@ -521,6 +771,13 @@ impl Display for Symbol {
 impl Display for Declaration {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.symbol)?;
+        if let Some(label) = self.asm_label {
+            write!(f, " __asm__(\"{}\")", label)?;
+        }
+        if !self.attributes.is_empty() {
+            let attributes: Vec<_> = self.attributes.iter().map(|attr| &attr.data).collect();
+            write!(f, " __attribute__(({}))", join(&attributes))?;
+        }
         match &self.init {
             Some(Initializer::FunctionBody(body)) => {
                 writeln!(f, " {{")?;
@@ -547,7 +804,10 @@ impl PartialEq for Symbol {
     fn eq(&self, other: &Self) -> bool {
         self.ctype == other.ctype
             && self.id == other.id
-            && self.qualifiers == other.qualifiers
+            // `inline` only changes which declaration provides the definition (C11 6.7.4p7),
+            // and the alignment can be increased by a later declaration
+            && Qualifiers { inline: false, align: None, ..self.qualifiers }
+                == Qualifiers { inline: false, align: None, ..other.qualifiers }
             && (self.storage_class == other.storage_class
                 || !self.ctype.is_function()
                     && (self.storage_class == StorageClass::Auto
@@ -573,11 +833,14 @@ mod tests {
             "short *(*)[1][2][3]",
             "_Bool",
             "struct s",
+            "const char *",
+            "char *const *",
+            "const volatile int *restrict *",
         ];
         for ty in types.iter() {
-            let mut lexer = Lexer::new("<integration-test>", ty.chars(), false);
+            let mut lexer = Lexer::new("<integration-test>", ty.chars(), false, false);
             let first = lexer.next().unwrap().unwrap();
-            let mut parser = Parser::new(first, &mut lexer, false);
+            let mut parser = Parser::new(first, &mut lexer, false, false);
 
             let parsed_ty = parser.type_name().unwrap().data.0;
             assert_eq!(&parsed_ty.to_string(), *ty);
@@ -1,4 +1,4 @@
-use super::Symbol;
+use super::{Expr, Qualifiers, Symbol};
 use crate::arch::SIZE_T;
 use crate::intern::InternedStr;
 pub use struct_ref::{StructRef, StructType};
@@ -115,6 +115,56 @@ mod struct_ref {
                 StructType::Named(_, struct_ref) => struct_ref.get(),
             }
         }
+        /// Return the members which take part in initialization.
+        ///
+        /// This is every member except unnamed bitfields (C11 6.7.9p9)
+        /// and flexible array members, which cannot be initialized.
+        pub fn initialized_members(&self) -> Vec<Symbol> {
+            self.members()
+                .iter()
+                .filter(|member| !member.is_padding() && !member.ctype.is_flexible_array())
+                .cloned()
+                .collect()
+        }
+        /// Find the member named `id`, looking inside anonymous struct and union members.
+        ///
+        /// Returns the members that have to be accessed in order to reach `id`,
+        /// ending with `id` itself.
+        pub fn member_path(&self, id: super::InternedStr) -> Option<Vec<Symbol>> {
+            let members = self.members();
+            if let Some(member) = members.iter().find(|member| member.id == id) {
+                return Some(vec![member.clone()]);
+            }
+            members
+                .iter()
+                .filter(|member| member.is_anonymous_member())
+                .find_map(|member| {
+                    let inner = match &member.ctype {
+                        super::Type::Struct(inner) | super::Type::Union(inner) => inner,
+                        _ => unreachable!("anonymous members should be structs or unions"),
+                    };
+                    let mut path = inner.member_path(id)?;
+                    path.insert(0, member.clone());
+                    Some(path)
+                })
+        }
+        /// The names of all members that can be accessed with `.`,
+        /// including the members of anonymous structs and unions.
+        pub fn member_names(&self) -> Vec<super::InternedStr> {
+            let mut names = Vec::new();
+            for member in self.members().iter() {
+                match &member.ctype {
+                    super::Type::Struct(inner) | super::Type::Union(inner)
+                        if member.is_anonymous_member() =>
+                    {
+                        names.extend(inner.member_names())
+                    }
+                    _ if member.is_padding() => {}
+                    _ => names.push(member.id),
+                }
+            }
+            names
+        }
         /// Return whether the struct has no members.
         ///
         /// For `Named` structs, this occurs whenever we have seen
@@ -139,17 +189,32 @@ pub enum Type {
     Short(bool),
     Int(bool),
     Long(bool),
+    /// `long long`, which is a different type from `long` even when they have the same size
+    LongLong(bool),
     Float,
     Double,
-    Pointer(Box<Type>),
+    /// `long double`, which uses the x87 80-bit extended precision format
+    LongDouble,
+    /// A complex floating type, e.g. `double _Complex`.
+    /// The inner type is the type of the real and imaginary parts.
+    Complex(Box<Type>),
+    /// An atomic type, e.g. `_Atomic int` or `_Atomic(int)`.
+    /// Reading or writing an lvalue of this type is sequentially consistent.
+    Atomic(Box<Type>),
+    /// A pointer to an object with the given qualifiers,
+    /// e.g. `const char *` is `Pointer(Char, CONST)` and `char *const *` is
+    /// `Pointer(Pointer(Char, NONE), CONST)`. Qualifiers of the pointer itself belong to
+    /// whatever holds it: a `Symbol` or the pointer one level up.
+    Pointer(Box<Type>, Qualifiers),
     Array(Box<Type>, ArrayType),
     Function(FunctionType),
     Union(StructType),
     Struct(StructType),
-    /// Enums should always have members, since tentative definitions are not allowed
+    /// Enums should always have members, since tentative definitions are not allowed.
+    /// Enums have the same representation as `Type::enum_type`.
     Enum(Option<InternedStr>, Vec<(InternedStr, i64)>),
-    /// This should probably be merged into Structs at some point
-    Bitfield(Vec<BitfieldType>),
+    /// The type of a struct or union member declared with a width, e.g. `int i: 5;`
+    Bitfield(BitfieldType),
     /// This is the type used for variadic arguments.
     VaList,
     /// A semantic error occured while parsing this type.
@@ -160,10 +225,12 @@ pub enum Type {
 pub enum ArrayType {
     Fixed(SIZE_T),
     Unbounded,
+    /// A variable length array, e.g. `int a[n]`.
+    /// The expression is the number of elements, converted to `unsigned long`.
+    Variable(Box<Expr>),
 }
 
 #[derive(Clone, Debug, Eq)]
-// note: old-style declarations are not supported at this time
 pub struct FunctionType {
     // why Symbol instead of Type?
     // 1. we need to know qualifiers for the params. if we made that part of Type,
@@ -175,14 +242,19 @@ pub struct FunctionType {
     pub return_type: Box<Type>,
     pub params: Vec<Symbol>,
     pub varargs: bool,
+    /// An old-style definition, e.g. `int f(a, b) int a; char *b; { ... }`.
+    /// The parameters are known, but like `int f()` it doesn't provide a prototype.
+    pub old_style: bool,
+    /// Declared `_Noreturn`. This doesn't affect whether two function types are compatible.
+    pub noreturn: bool,
 }
 
-#[allow(dead_code)]
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct BitfieldType {
-    pub offset: i32,
-    pub name: Option<InternedStr>,
-    pub ctype: Type,
+    /// The declared type of the bitfield. This decides the size of its storage unit.
+    pub ctype: Box<Type>,
+    /// The number of bits in the bitfield
+    pub width: u8,
 }
 
 impl Type {
@@ -215,58 +287,127 @@ impl Type {
     pub fn is_signed(&self) -> bool {
         use Type::*;
         match self {
-            Bool | Char(true) | Short(true) | Int(true) | Long(true) | Enum(_, _) => true,
+            Bool | Char(true) | Short(true) | Int(true) | Long(true) | LongLong(true) => true,
+            Enum(_, members) => Type::enum_type(members).is_signed(),
             _ => false,
         }
     }
+    /// The integer type compatible with an enum (C11 6.7.2.2p4).
+    ///
+    /// This is `int`, unless an enumerator is too large for `int`,
+    /// in which case it is `unsigned int`, the same as GCC.
+    pub fn enum_type(members: &[(InternedStr, i64)]) -> Type {
+        let fits_int = members
+            .iter()
+            .all(|&(_, value)| value <= i64::from(std::i32::MAX));
+        Type::Int(fits_int)
+    }
     #[inline]
     pub fn is_integral(&self) -> bool {
         use Type::*;
         match self {
-            Bool | Char(_) | Short(_) | Int(_) | Long(_) | Enum(_, _) => true,
+            Bool | Char(_) | Short(_) | Int(_) | Long(_) | LongLong(_) | Enum(_, _) => true,
             _ => false,
         }
     }
     #[inline]
     pub fn is_floating(&self) -> bool {
         match self {
-            Type::Float | Type::Double => true,
+            Type::Float | Type::Double | Type::LongDouble => true,
+            _ => false,
+        }
+    }
+    #[inline]
+    pub fn is_complex(&self) -> bool {
+        match self {
+            Type::Complex(_) => true,
             _ => false,
         }
     }
     #[inline]
+    pub fn is_atomic(&self) -> bool {
+        match self {
+            Type::Atomic(_) => true,
+            _ => false,
+        }
+    }
+    /// The non-atomic version of this type.
+    ///
+    /// This is the type of the value of an atomic lvalue (C11 6.3.2.1p2).
+    #[inline]
+    pub fn strip_atomic(&self) -> &Type {
+        match self {
+            Type::Atomic(inner) => inner,
+            _ => self,
+        }
+    }
+    #[inline]
     pub fn is_arithmetic(&self) -> bool {
-        self.is_integral() || self.is_floating()
+        self.is_integral() || self.is_floating() || self.is_complex()
     }
     #[inline]
     pub fn is_pointer(&self) -> bool {
         match self {
-            Type::Pointer(_) => true,
+            Type::Pointer(..) => true,
             _ => false,
         }
     }
     #[inline]
     pub fn is_void_pointer(&self) -> bool {
         match self {
-            Type::Pointer(t) => **t == Type::Void,
+            Type::Pointer(t, _) => **t == Type::Void,
             _ => false,
         }
     }
     #[inline]
     pub fn is_char_pointer(&self) -> bool {
         match self {
-            Type::Pointer(t) => match **t {
+            Type::Pointer(t, _) => match **t {
                 Type::Char(_) => true,
                 _ => false,
             },
             _ => false,
         }
     }
+    /// Whether `restrict` can be applied to this type: a pointer to an object,
+    /// or an array of them (C11 6.7.3p2).
+    pub fn is_object_pointer(&self) -> bool {
+        match self {
+            Type::Pointer(inner, _) => !inner.is_function(),
+            Type::Array(inner, _) => inner.is_object_pointer(),
+            _ => false,
+        }
+    }
+    /// Whether `self` and `other` are pointers to qualified or unqualified versions of
+    /// compatible types, e.g. `const char *` and `char *`.
+    #[inline]
+    pub fn pointee_eq(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Pointer(left, _), Type::Pointer(right, _)) => left.is_compatible(right),
+            _ => false,
+        }
+    }
+    /// Whether two types are compatible (C11 6.2.7).
+    ///
+    /// This is the same as `==`, except that an enum is also compatible
+    /// with its underlying integer type (C11 6.7.2.2p4), e.g. `enum e *` can be converted to `int *`.
+    pub fn is_compatible(&self, other: &Type) -> bool {
+        match (self, other) {
+            _ if self == other => true,
+            (Type::Pointer(left, left_quals), Type::Pointer(right, right_quals)) => {
+                left_quals == right_quals && left.is_compatible(right)
+            }
+            (Type::Enum(_, members), other) | (other, Type::Enum(_, members)) => {
+                Type::enum_type(members) == *other
+            }
+            _ => false,
+        }
+    }
     #[inline]
     /// used for pointer addition and subtraction, see section 6.5.6 of the C11 standard
     pub fn is_pointer_to_complete_object(&self) -> bool {
         match self {
-            Type::Pointer(ctype) => ctype.is_complete() && !ctype.is_function(),
+            Type::Pointer(ctype, _) => ctype.is_complete() && !ctype.is_function(),
             Type::Array(_, _) => true,
             _ => false,
         }
@@ -282,10 +423,53 @@ impl Type {
     pub fn is_complete(&self) -> bool {
         match self {
             Type::Void | Type::Function(_) | Type::Array(_, ArrayType::Unbounded) => false,
-            // TODO: update when we allow incomplete struct and union types (e.g. `struct s;`)
+            // declared but not yet defined, e.g. `struct s;`
+            Type::Struct(struct_type) | Type::Union(struct_type) => !struct_type.is_empty(),
+            Type::Atomic(inner) => inner.is_complete(),
             _ => true,
         }
     }
+    /// Whether this is the type of a flexible array member, e.g. `char data[]`.
+    ///
+    /// The parser only allows these as the last member of a struct.
+    pub fn is_flexible_array(&self) -> bool {
+        match self {
+            Type::Array(_, ArrayType::Unbounded) => true,
+            _ => false,
+        }
+    }
+    /// Whether this is a struct ending in a flexible array member,
+    /// or a union containing such a struct (C11 6.7.2.1p3).
+    pub fn has_flexible_array(&self) -> bool {
+        match self {
+            Type::Struct(struct_type) => struct_type
+                .members()
+                .last()
+                .map_or(false, |member| member.ctype.is_flexible_array()),
+            Type::Union(struct_type) => struct_type
+                .members()
+                .iter()
+                .any(|member| member.ctype.has_flexible_array()),
+            _ => false,
+        }
+    }
+    /// Whether this is a variable length array, or an array of them.
+    pub fn is_vla(&self) -> bool {
+        match self {
+            Type::Array(_, ArrayType::Variable(_)) => true,
+            Type::Array(inner, _) => inner.is_vla(),
+            _ => false,
+        }
+    }
+    /// Whether the size of this type or a type it points to is only known at runtime,
+    /// e.g. `int (*)[n]` (C11 6.7.6p3).
+    pub fn is_variably_modified(&self) -> bool {
+        match self {
+            Type::Array(_, ArrayType::Variable(_)) => true,
+            Type::Array(inner, _) | Type::Pointer(inner, _) => inner.is_variably_modified(),
+            _ => false,
+        }
+    }
     #[inline]
     pub fn is_function(&self) -> bool {
         match self {
@@ -293,6 +477,15 @@ impl Type {
             _ => false,
         }
     }
+    /// The type of an expression which refers to a struct member of type `self`.
+    ///
+    /// This is the same as `self`, except that bitfields have their declared type.
+    pub fn member_type(self) -> Type {
+        match self {
+            Type::Bitfield(bitfield) => *bitfield.ctype,
+            other => other,
+        }
+    }
     pub fn member_offset(&self, member: InternedStr) -> Result<u64, ()> {
         match self {
             Type::Struct(stype) => Ok(self.struct_offset(&stype.members(), member)),
@@ -312,20 +505,16 @@ impl Eq for ArrayType {}
 impl PartialEq for FunctionType {
     fn eq(&self, other: &Self) -> bool {
         // no prototype: any parameters are allowed
-        // TODO: issue a warning if a function has empty parameters, it's a holdover
-        // from C89
-        self.params.is_empty()
-            || other.params.is_empty()
+        // the parser warns if a prototype doesn't match the promoted parameters
+        !self.has_prototype()
+            || !other.has_prototype()
             || self.varargs == other.varargs
             && self.return_type == other.return_type
-            // don't require parameter names and storage_class to match
+            // don't require parameter names, storage_class, or qualifiers to match (C11 6.7.6.3p15)
             && self.params
                 .iter()
                 .zip(other.params.iter())
-                .all(|(this_param, other_param)| {
-                    this_param.ctype == other_param.ctype
-                        && this_param.qualifiers == other_param.qualifiers
-                })
+                .all(|(this_param, other_param)| this_param.ctype == other_param.ctype)
     }
 }
 
@@ -339,7 +528,7 @@ use std::fmt::{self, Formatter};
 
 pub fn print_type(ctype: &Type, name: Option<InternedStr>, f: &mut Formatter) -> fmt::Result {
     print_pre(ctype, f)?;
-    print_mid(ctype, name, f)?;
+    print_mid(ctype, name, Qualifiers::NONE, f)?;
     print_post(ctype, f)
 }
 
@@ -354,9 +543,25 @@ fn print_pre(ctype: &Type, f: &mut Formatter) -> fmt::Result {
             };
             write!(f, "{}{}", if *signed { "" } else { "unsigned " }, substr)
         }
+        LongLong(signed) => write!(f, "{}long long", if *signed { "" } else { "unsigned " }),
+        LongDouble => write!(f, "long double"),
         Bool => write!(f, "_Bool"),
         Float | Double | Void => write!(f, "{}", format!("{:?}", ctype).to_lowercase()),
-        Pointer(inner) | Array(inner, _) => print_pre(inner, f),
+        Complex(inner) => write!(f, "{} _Complex", inner),
+        Atomic(inner) => write!(f, "_Atomic({})", inner),
+        Pointer(inner, qualifiers) => {
+            // qualifiers of a pointer go after the `*`, see `print_mid`
+            let mut base = &**inner;
+            while let Array(element, _) = base {
+                base = element;
+            }
+            match base {
+                Pointer(..) | Function(_) => {}
+                _ => write!(f, "{}", qualifiers)?,
+            }
+            print_pre(inner, f)
+        }
+        Array(inner, _) => print_pre(inner, f),
         Function(ftype) => write!(f, "{}", ftype.return_type),
         Enum(Some(ident), _) => write!(f, "enum {}", ident),
         Enum(None, _) => write!(f, "<anonymous enum>"),
@@ -364,24 +569,34 @@ fn print_pre(ctype: &Type, f: &mut Formatter) -> fmt::Result {
         Union(_) => write!(f, "<anonymous union>"),
         Struct(StructType::Named(ident, _)) => write!(f, "struct {}", ident),
         Struct(_) => write!(f, "<anonymous struct>"),
-        Bitfield(_) => unimplemented!("printing bitfield type"),
+        Bitfield(bitfield) => print_pre(&bitfield.ctype, f),
         VaList => write!(f, "va_list"),
         Error => write!(f, "<type error>"),
     }
 }
 
-fn print_mid(ctype: &Type, name: Option<InternedStr>, f: &mut Formatter) -> fmt::Result {
+/// `qualifiers` are the qualifiers of `ctype` itself, which only need to be printed for pointers.
+fn print_mid(
+    ctype: &Type,
+    name: Option<InternedStr>,
+    qualifiers: Qualifiers,
+    f: &mut Formatter,
+) -> fmt::Result {
     match ctype {
-        Type::Pointer(to) => {
-            print_mid(to, None, f)?;
+        Type::Pointer(to, pointee_qualifiers) => {
+            print_mid(to, None, *pointee_qualifiers, f)?;
+            let qualifiers = qualifiers.to_string();
+            let qualifiers = qualifiers.trim_end();
+            let name = match name {
+                Some(name) if !qualifiers.is_empty() => format!(" {}", name),
+                name => name.unwrap_or_default().to_string(),
+            };
             match &**to {
-                Type::Array(_, _) | Type::Function(_) => {
-                    write!(f, "(*{})", name.unwrap_or_default())?
-                }
-                _ => write!(f, " *{}", name.unwrap_or_default())?,
+                Type::Array(_, _) | Type::Function(_) => write!(f, "(*{}{})", qualifiers, name)?,
+                _ => write!(f, " *{}{}", qualifiers, name)?,
             }
         }
-        Type::Array(to, _) => print_mid(to, name, f)?,
+        Type::Array(to, _) => print_mid(to, name, qualifiers, f)?,
         _ => {
             if let Some(name) = name {
                 write!(f, " {}", name)?;
@@ -392,13 +607,13 @@ fn print_mid(ctype: &Type, name: Option<InternedStr>, f: &mut Formatter) -> fmt:
 }
 fn print_post(ctype: &Type, f: &mut Formatter) -> fmt::Result {
     match ctype {
-        Type::Pointer(to) => print_post(to, f),
+        Type::Pointer(to, _) => print_post(to, f),
         Type::Array(to, size) => {
-            write!(f, "[")?;
-            if let ArrayType::Fixed(size) = size {
-                write!(f, "{}", size)?;
+            match size {
+                ArrayType::Fixed(size) => write!(f, "[{}]", size)?,
+                ArrayType::Unbounded => write!(f, "[]")?,
+                ArrayType::Variable(_) => write!(f, "[*]")?,
             }
-            write!(f, "]")?;
             print_post(to, f)
         }
         Type::Function(func_type) => {
@@ -421,6 +636,7 @@ fn print_post(ctype: &Type, f: &mut Formatter) -> fmt::Result {
             comma_seperated.push(')');
             write!(f, "{}", comma_seperated)
         }
+        Type::Bitfield(bitfield) => write!(f, ": {}", bitfield.width),
         _ => Ok(()),
     }
 }
@@ -432,4 +648,25 @@ impl FunctionType {
     pub fn has_params(&self) -> bool {
         !(self.params.len() == 1 && self.params[0].ctype == Type::Void)
     }
+    /// Whether the parameter types are checked when the function is called.
+    ///
+    /// `int f()` and old-style definitions don't have a prototype,
+    /// so their arguments have the default argument promotions applied instead.
+    pub fn has_prototype(&self) -> bool {
+        !self.old_style && !self.params.is_empty()
+    }
+    /// The types of the parameters as they are passed to the function.
+    ///
+    /// Callers of an old-style definition have promoted the arguments,
+    /// so the definition has to convert them back to the declared types.
+    pub fn passed_types(&self) -> impl Iterator<Item = Type> + '_ {
+        let old_style = self.old_style;
+        self.params.iter().map(move |param| {
+            if old_style {
+                param.ctype.clone().default_promote()
+            } else {
+                param.ctype.clone()
+            }
+        })
+    }
 }
@@ -107,6 +107,8 @@ impl Expr {
                 // TODO: if a variable were const, could we const fold Ids?
                 _ => self.expr,
             },
+            // computed at runtime
+            ExprType::Sizeof(ref ctype) if ctype.is_vla() => self.expr,
             ExprType::Sizeof(ctype) => {
                 let sizeof = ctype.sizeof().map_err(|data| Locatable {
                     data: data.to_string(),
@@ -249,6 +251,10 @@ impl Expr {
                 let expr = expr.const_fold()?;
                 ExprType::Member(Box::new(expr), member)
             }
+            ExprType::ComplexPart(expr, imag) => {
+                let expr = expr.const_fold()?;
+                ExprType::ComplexPart(Box::new(expr), imag)
+            }
             ExprType::Assign(target, value, token) => {
                 let (target, value) = (target.const_fold()?, value.const_fold()?);
                 // TODO: could we propagate this information somehow?
@@ -282,6 +288,18 @@ impl Expr {
                 ExprType::LogicalOr,
             )?,
             ExprType::StaticRef(inner) => ExprType::StaticRef(Box::new(inner.const_fold()?)),
+            ExprType::Atomic(op, args) => {
+                #[rustfmt::skip]
+                let args: Vec<Expr> = args
+                    .into_iter()
+                    .map(Self::const_fold)
+                    .collect::<CompileResult<_>>()?;
+                // atomic operations are never constant
+                ExprType::Atomic(op, args)
+            }
+            // the statements are folded when they are compiled
+            ExprType::StmtExpr(stmts, result) => ExprType::StmtExpr(stmts, result),
+            ExprType::LabelAddress(label) => ExprType::LabelAddress(label),
         };
         let is_constexpr = match folded {
             ExprType::Literal(_) => true,
@@ -361,17 +379,21 @@ fn cast(expr: Expr, ctype: &Type) -> CompileResult<ExprType> {
 fn const_cast(token: &Literal, ctype: &Type) -> Option<Literal> {
     let token = match (token, ctype) {
         (Int(i), Type::Bool) => Int((*i != 0) as i64),
-        (Int(i), Type::Double) | (Int(i), Type::Float) => Float(*i as f64),
+        (Int(i), ty) if ty.is_floating() => Float(*i as f64),
         (Int(i), ty) if ty.is_integral() && ty.is_signed() => Int(*i),
         (Int(i), ty) if ty.is_integral() => UnsignedInt(*i as u64),
         (UnsignedInt(u), Type::Bool) => Int((*u != 0) as i64),
-        (UnsignedInt(u), Type::Double) | (UnsignedInt(u), Type::Float) => Float(*u as f64),
+        (UnsignedInt(u), ty) if ty.is_floating() => Float(*u as f64),
         (UnsignedInt(u), ty) if ty.is_integral() && ty.is_signed() => Int(*u as i64),
         (UnsignedInt(u), ty) if ty.is_integral() => UnsignedInt(*u),
         (Float(f), Type::Bool) => Int((*f != 0.0) as i64),
-        (Float(f), Type::Double) | (Float(f), Type::Float) => Float(*f),
+        (Float(f), ty) if ty.is_floating() => Float(*f),
         (Float(f), ty) if ty.is_integral() && ty.is_signed() => Int(*f as i64),
         (Float(f), ty) if ty.is_integral() => UnsignedInt(*f as u64),
+        (Char(c), Type::Bool) => Int((*c != 0) as i64),
+        (Char(c), ty) if ty.is_floating() => Float(f64::from(*c)),
+        (Char(c), ty) if ty.is_integral() && ty.is_signed() => Int(i64::from(*c)),
+        (Char(c), ty) if ty.is_integral() => UnsignedInt(u64::from(*c)),
         (Int(i), _) if ctype.is_pointer() && *i >= 0 => UnsignedInt(*i as u64),
         (UnsignedInt(u), _) if ctype.is_pointer() => UnsignedInt(*u),
         (Char(c), _) if ctype.is_pointer() => UnsignedInt(u64::from(*c)),
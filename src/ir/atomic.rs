@@ -0,0 +1,235 @@
+//! Cranelift 0.56 has no atomic instructions, so atomic operations are calls to libatomic.
+//!
+//! libatomic's `__atomic_*_N` functions operate on N-byte integers and take the same
+//! memory orders as the GCC builtins. Other scalar types are converted to and from
+//! integers of the same size.
+
+use cranelift::codegen::ir::{
+    condcodes::IntCC,
+    stackslot::{StackSlotData, StackSlotKind},
+    types, InstBuilder, MemFlags,
+};
+use cranelift::prelude::{FunctionBuilder, Type as IrType, Value as IrValue};
+
+use super::{expr::Value, Compiler};
+use crate::arch::CHAR_BIT;
+use crate::data::{prelude::*, AtomicOp};
+
+/// `__ATOMIC_SEQ_CST`, used for operations on `_Atomic` objects that aren't builtins
+const SEQ_CST: i64 = 5;
+
+impl Compiler {
+    /// Compile one of the atomic builtins. See `AtomicOp` for the arguments.
+    pub(super) fn atomic_op(
+        &mut self,
+        op: AtomicOp,
+        args: Vec<Expr>,
+        ctype: Type,
+        builder: &mut FunctionBuilder,
+    ) -> CompileResult<Value> {
+        // the type of the object being operated on
+        let object = match op {
+            AtomicOp::ThreadFence | AtomicOp::SignalFence => Type::Void,
+            // these always operate on a single byte
+            AtomicOp::TestAndSet | AtomicOp::Clear => Type::Char(false),
+            _ => match &args[0].ctype {
+                Type::Pointer(pointee, _) => pointee.strip_atomic().clone(),
+                _ => unreachable!("parser should only allow pointers for atomic operations"),
+            },
+        };
+        let mut vals = Vec::with_capacity(args.len());
+        for arg in args {
+            vals.push(self.compile_expr(arg, builder)?.ir_val);
+        }
+        let int_type = Self::atomic_int_type(&object);
+        let ir_val = match op {
+            AtomicOp::Load => {
+                let loaded = self.atomic_call("load", &object, &vals, Some(int_type), builder);
+                Self::from_atomic_int(loaded.unwrap(), &object, builder)
+            }
+            AtomicOp::Store => {
+                vals[1] = Self::to_atomic_int(vals[1], &object, builder);
+                self.atomic_call("store", &object, &vals, None, builder);
+                builder.ins().iconst(types::I32, 0)
+            }
+            AtomicOp::Exchange => {
+                vals[1] = Self::to_atomic_int(vals[1], &object, builder);
+                let old = self.atomic_call("exchange", &object, &vals, Some(int_type), builder);
+                Self::from_atomic_int(old.unwrap(), &object, builder)
+            }
+            AtomicOp::CompareExchange => {
+                vals[2] = Self::to_atomic_int(vals[2], &object, builder);
+                let name = "compare_exchange";
+                let success = self.atomic_call(name, &object, &vals, Some(types::I8), builder);
+                builder.ins().icmp_imm(IntCC::NotEqual, success.unwrap(), 0)
+            }
+            AtomicOp::ReadModifyWrite { op, fetch_first } => {
+                vals[1] = Self::to_atomic_int(vals[1], &object, builder);
+                let name = if fetch_first {
+                    format!("fetch_{}", op)
+                } else {
+                    format!("{}_fetch", op)
+                };
+                let result = self.atomic_call(&name, &object, &vals, Some(int_type), builder);
+                Self::from_atomic_int(result.unwrap(), &object, builder)
+            }
+            AtomicOp::TestAndSet => {
+                let name = "test_and_set";
+                let was_set = self.atomic_call(name, &object, &vals, Some(types::I8), builder);
+                builder.ins().icmp_imm(IntCC::NotEqual, was_set.unwrap(), 0)
+            }
+            // libatomic doesn't have a sized version of `__atomic_clear`
+            AtomicOp::Clear => {
+                let zero = builder.ins().iconst(types::I8, 0);
+                let args = [vals[0], zero, vals[1]];
+                self.atomic_call("store", &object, &args, None, builder);
+                builder.ins().iconst(types::I32, 0)
+            }
+            AtomicOp::ThreadFence | AtomicOp::SignalFence => {
+                let name = if op == AtomicOp::ThreadFence {
+                    "atomic_thread_fence"
+                } else {
+                    "atomic_signal_fence"
+                };
+                let func = self.declare_libc(name, &[types::I32], &[]);
+                let func = self.module.declare_func_in_func(func, builder.func);
+                builder.ins().call(func, &vals);
+                builder.ins().iconst(types::I32, 0)
+            }
+        };
+        let ir_type = if ctype == Type::Void {
+            types::I32
+        } else {
+            ctype.as_ir_type()
+        };
+        Ok(Value {
+            ir_val,
+            ir_type,
+            ctype,
+        })
+    }
+    /// Atomically load the `_Atomic` object of type `ctype` stored at `addr`.
+    pub(super) fn atomic_load(
+        &mut self,
+        addr: IrValue,
+        ctype: &Type,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let order = builder.ins().iconst(types::I32, SEQ_CST);
+        let int_type = Self::atomic_int_type(ctype);
+        let loaded = self.atomic_call("load", ctype, &[addr, order], Some(int_type), builder);
+        Self::from_atomic_int(loaded.unwrap(), ctype, builder)
+    }
+    /// Atomically store `val` in the `_Atomic` object of type `ctype` stored at `addr`.
+    pub(super) fn atomic_store(
+        &mut self,
+        addr: IrValue,
+        val: IrValue,
+        ctype: &Type,
+        builder: &mut FunctionBuilder,
+    ) {
+        let order = builder.ins().iconst(types::I32, SEQ_CST);
+        let val = Self::to_atomic_int(val, ctype, builder);
+        self.atomic_call("store", ctype, &[addr, val, order], None, builder);
+    }
+    /// Atomically replace the `_Atomic` object at `addr` with `update(old_value)`.
+    ///
+    /// This is used for compound assignment and increments, which are read-modify-write
+    /// operations on atomic objects (C11 6.5.16.2p3).
+    /// Since `update` can do arbitrary arithmetic, this uses a compare-exchange loop
+    /// instead of `__atomic_fetch_*`.
+    ///
+    /// Returns the old value and the new value.
+    pub(super) fn atomic_update<F>(
+        &mut self,
+        addr: IrValue,
+        ctype: &Type,
+        builder: &mut FunctionBuilder,
+        update: F,
+    ) -> CompileResult<(Value, Value)>
+    where
+        F: FnOnce(Value, &mut FunctionBuilder) -> CompileResult<Value>,
+    {
+        let int_type = Self::atomic_int_type(ctype);
+        // libatomic writes the current value here if the exchange fails
+        let slot = builder.create_stack_slot(StackSlotData {
+            kind: StackSlotKind::ExplicitSlot,
+            size: int_type.bytes(),
+            offset: None,
+        });
+        let expected = builder.ins().stack_addr(Type::ptr_type(), slot, 0);
+        let order = builder.ins().iconst(types::I32, SEQ_CST);
+        let initial = self.atomic_call("load", ctype, &[addr, order], Some(int_type), builder);
+        builder
+            .ins()
+            .store(MemFlags::new(), initial.unwrap(), expected, 0);
+
+        let retry = builder.create_ebb();
+        builder.ins().jump(retry, &[]);
+        builder.switch_to_block(retry);
+        let old = builder.ins().load(int_type, MemFlags::new(), expected, 0);
+        let old = Value {
+            ir_val: Self::from_atomic_int(old, ctype, builder),
+            ir_type: ctype.as_ir_type(),
+            ctype: ctype.clone(),
+        };
+        let new = update(old.clone(), builder)?;
+        let desired = Self::to_atomic_int(new.ir_val, ctype, builder);
+        let args = [addr, expected, desired, order, order];
+        let success = self.atomic_call("compare_exchange", ctype, &args, Some(types::I8), builder);
+        builder.ins().brz(success.unwrap(), retry, &[]);
+        Ok((old, new))
+    }
+    /// Call `__atomic_{name}_{sizeof(ctype)}` from libatomic.
+    ///
+    /// All arguments except the memory orders have the type of the object
+    /// (or are pointers), so the signature can be derived from `args`.
+    fn atomic_call(
+        &mut self,
+        name: &str,
+        ctype: &Type,
+        args: &[IrValue],
+        ret: Option<IrType>,
+        builder: &mut FunctionBuilder,
+    ) -> Option<IrValue> {
+        let size = ctype
+            .sizeof()
+            .expect("parser should only allow atomic objects with a valid size");
+        let name = format!("__atomic_{}_{}", name, size);
+        let params: Vec<_> = args
+            .iter()
+            .map(|&arg| builder.func.dfg.value_type(arg))
+            .collect();
+        let ret: &[IrType] = match &ret {
+            Some(ret) => std::slice::from_ref(ret),
+            None => &[],
+        };
+        let func = self.declare_libc(&name, &params, ret);
+        let func = self.module.declare_func_in_func(func, builder.func);
+        let call = builder.ins().call(func, args);
+        builder.inst_results(call).first().copied()
+    }
+    /// The integer type libatomic uses for objects of type `ctype`.
+    fn atomic_int_type(ctype: &Type) -> IrType {
+        match ctype.sizeof() {
+            Ok(size) => IrType::int(size as u16 * u16::from(CHAR_BIT)).unwrap_or(types::INVALID),
+            Err(_) => types::INVALID,
+        }
+    }
+    fn to_atomic_int(val: IrValue, ctype: &Type, builder: &mut FunctionBuilder) -> IrValue {
+        match ctype {
+            Type::Bool => builder.ins().bint(types::I8, val),
+            Type::Float => builder.ins().bitcast(types::I32, val),
+            Type::Double => builder.ins().bitcast(types::I64, val),
+            _ => val,
+        }
+    }
+    fn from_atomic_int(val: IrValue, ctype: &Type, builder: &mut FunctionBuilder) -> IrValue {
+        match ctype {
+            Type::Bool => builder.ins().icmp_imm(IntCC::NotEqual, val, 0),
+            Type::Float => builder.ins().bitcast(types::F32, val),
+            Type::Double => builder.ins().bitcast(types::F64, val),
+            _ => val,
+        }
+    }
+}
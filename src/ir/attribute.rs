@@ -0,0 +1,295 @@
+//! GNU attributes that change how a declaration is emitted.
+//!
+//! Cranelift 0.56 has no notion of weak references, symbol visibility, aliases, constructors
+//! or sections, so those are applied to the object file after the module is finished.
+//! Constructors and destructors are pointers to the function in `.init_array` and `.fini_array`,
+//! which the C runtime calls before and after `main`.
+//! Objects with a `section` attribute are copied into that section, along with their relocations,
+//! and their symbol is moved to the copy. The unused original is left behind in `.data`.
+
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+use cranelift::codegen::ir::ExternalName;
+use cranelift_module::{DataContext, DataId, FuncId, Init};
+use cranelift_object::ObjectProduct;
+use object::write::{Relocation, SymbolId, SymbolSection};
+use object::{RelocationEncoding, RelocationKind, SectionKind, SymbolFlags, SymbolScope};
+
+use super::{Compiler, Id};
+use crate::arch::{CHAR_BIT, PTR_SIZE};
+use crate::data::{prelude::*, Scope, StorageClass};
+
+// object 0.17 always makes undefined symbols global, so weak ones need their ELF flags set by hand
+const STB_WEAK: u8 = 2;
+const STT_NOTYPE: u8 = 0;
+const STV_DEFAULT: u8 = 0;
+const STV_HIDDEN: u8 = 2;
+
+/// The attributes of an identifier at file scope, combined from all its declarations.
+#[derive(Debug, Default)]
+pub(super) struct ObjectAttributes {
+    weak: bool,
+    hidden: bool,
+    used: bool,
+    /// whether the symbol has internal linkage, for aliases
+    local: bool,
+    alias: Option<Locatable<InternedStr>>,
+    /// the priority of a constructor, `Some(None)` if it doesn't have one
+    constructor: Option<Option<u16>>,
+    destructor: Option<Option<u16>>,
+    section: Option<String>,
+}
+
+/// The contents of an object with a `section` attribute, as given to Cranelift.
+#[derive(Debug)]
+pub(super) struct SectionData {
+    contents: Vec<u8>,
+    align: u64,
+    /// the offset of each address in `contents`, the symbol it points to, and the addend
+    relocations: Vec<(u32, ExternalName, i64)>,
+}
+
+impl ObjectAttributes {
+    fn add(&mut self, decl: &Locatable<Declaration>) {
+        let priority = |attribute: &Locatable<Attribute>| {
+            attribute
+                .data
+                .integer_argument()
+                .and_then(|priority| u16::try_from(priority).ok())
+        };
+        self.local |= decl.data.symbol.storage_class == StorageClass::Static;
+        for attribute in &decl.data.attributes {
+            match attribute.data.name.resolve_and_clone().as_str() {
+                "weak" => self.weak = true,
+                "visibility" => match attribute.data.string_argument().as_deref() {
+                    Some("hidden") | Some("internal") => self.hidden = true,
+                    _ => self.hidden = false,
+                },
+                "used" => self.used = true,
+                "alias" => {
+                    self.alias = attribute
+                        .data
+                        .string_argument()
+                        .map(|target| attribute.location.with(InternedStr::get_or_intern(target)))
+                }
+                "constructor" => self.constructor = Some(priority(attribute)),
+                "destructor" => self.destructor = Some(priority(attribute)),
+                "section" => self.section = attribute.data.string_argument(),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Compiler {
+    /// Remember the attributes of a declaration at file scope.
+    pub(super) fn add_attributes(&mut self, decl: &Locatable<Declaration>) {
+        if decl.data.attributes.is_empty() {
+            return;
+        }
+        let id = decl.data.symbol.id;
+        let attributes = self.object_attributes.entry(id).or_default();
+        attributes.add(decl);
+        if attributes.used {
+            // compile `static inline` functions even if they're never called
+            self.used.insert(id);
+        }
+    }
+    /// Whether `id` has a `section` attribute.
+    pub(super) fn has_section(&self, id: InternedStr) -> bool {
+        self.object_attributes
+            .get(&id)
+            .map_or(false, |attributes| attributes.section.is_some())
+    }
+    /// Remember the contents of `data_id` if `id` has a `section` attribute.
+    ///
+    /// Cranelift always puts data in `.data` or `.rodata`,
+    /// so `apply_attributes` has to copy it to the right section once the module is finished.
+    pub(super) fn record_section_data(
+        &mut self,
+        id: InternedStr,
+        data_id: DataId,
+        ctx: &DataContext,
+        align: u64,
+    ) {
+        if !self.has_section(id) {
+            return;
+        }
+        let description = ctx.description();
+        let contents = match &description.init {
+            Init::Bytes { contents } => contents.to_vec(),
+            init => vec![0; init.size()],
+        };
+        let functions = description
+            .function_relocs
+            .iter()
+            .map(|&(offset, func)| (offset, description.function_decls[func].clone(), 0));
+        let data = description
+            .data_relocs
+            .iter()
+            .map(|&(offset, data, addend)| (offset, description.data_decls[data].clone(), addend));
+        let relocations = functions.chain(data).collect();
+        let data = SectionData {
+            contents,
+            align,
+            relocations,
+        };
+        self.section_data.insert(data_id, data);
+    }
+}
+
+/// Apply `weak`, `visibility`, `alias`, `constructor`, `destructor` and `section`
+/// to the symbols in the finished object file.
+pub(super) fn apply_attributes(
+    product: &mut ObjectProduct,
+    attributes: &HashMap<InternedStr, ObjectAttributes>,
+    section_data: &HashMap<DataId, SectionData>,
+    scope: &Scope<InternedStr, Id>,
+    defined: &HashSet<InternedStr>,
+) -> CompileResult<()> {
+    let lookup = |product: &ObjectProduct, id| match scope.get_global(id) {
+        Some(Id::Function(func_id)) => Some(product.function_symbol(*func_id)),
+        Some(Id::Global(data_id)) => Some(product.data_symbol(*data_id)),
+        _ => None,
+    };
+    let mut array_sections = HashMap::new();
+    let mut ctors = Vec::new();
+    let mut sections = Vec::new();
+    for (id, attributes) in attributes {
+        let symbol_id = match lookup(product, id) {
+            Some(symbol_id) => symbol_id,
+            None => continue,
+        };
+        if let (Some(section), Some(Id::Global(data_id))) =
+            (&attributes.section, scope.get_global(id))
+        {
+            if let Some(data) = section_data.get(data_id) {
+                sections.push((section.as_str(), *data_id, symbol_id, data));
+                // it was declared preemptible so code would refer to it by name,
+                // give it the linkage it really has
+                let symbol = product.object.symbol_mut(symbol_id);
+                symbol.weak = false;
+                symbol.scope = if attributes.local {
+                    SymbolScope::Compilation
+                } else {
+                    SymbolScope::Dynamic
+                };
+            }
+        }
+        if let Some(target) = &attributes.alias {
+            let target_id = match lookup(product, &target.data) {
+                Some(target_id) if defined.contains(&target.data) => target_id,
+                _ => semantic_err!(
+                    format!(
+                        "alias '{}' must refer to a definition in the same file",
+                        target.data
+                    ),
+                    target.location,
+                ),
+            };
+            let (value, size, kind, section) = {
+                let target = product.object.symbol(target_id);
+                (target.value, target.size, target.kind, target.section)
+            };
+            let symbol = product.object.symbol_mut(symbol_id);
+            symbol.value = value;
+            symbol.size = size;
+            symbol.kind = kind;
+            symbol.section = section;
+            symbol.scope = if attributes.local {
+                SymbolScope::Compilation
+            } else {
+                SymbolScope::Dynamic
+            };
+        }
+        let symbol = product.object.symbol_mut(symbol_id);
+        if attributes.weak {
+            symbol.weak = true;
+        }
+        if attributes.hidden && symbol.scope != SymbolScope::Compilation {
+            symbol.scope = SymbolScope::Linkage;
+        }
+        if attributes.weak && symbol.section == SymbolSection::Undefined {
+            let visibility = if attributes.hidden {
+                STV_HIDDEN
+            } else {
+                STV_DEFAULT
+            };
+            symbol.flags = SymbolFlags::Elf {
+                st_info: (STB_WEAK << 4) | STT_NOTYPE,
+                st_other: visibility,
+            };
+        }
+        if let Some(priority) = attributes.constructor {
+            ctors.push((".init_array", priority, *id, symbol_id));
+        }
+        if let Some(priority) = attributes.destructor {
+            ctors.push((".fini_array", priority, *id, symbol_id));
+        }
+    }
+    // `attributes` is a HashMap, sort so objects are in the order they were declared
+    sections.sort_by_key(|&(section, data_id, ..)| (section, data_id.as_u32()));
+    let mut custom_sections = HashMap::new();
+    for (name, _, symbol, data) in sections {
+        let object = &mut product.object;
+        let section = *custom_sections.entry(name).or_insert_with_key(|name| {
+            object.add_section(Vec::new(), name.as_bytes().to_vec(), SectionKind::Data)
+        });
+        let size = data.contents.len() as u64;
+        let offset = object.append_section_data(section, &data.contents, data.align);
+        object.set_symbol_data(symbol, section, offset, size);
+        for (reloc_offset, name, addend) in &data.relocations {
+            let relocation = Relocation {
+                offset: offset + u64::from(*reloc_offset),
+                size: (PTR_SIZE * CHAR_BIT) as u8,
+                kind: RelocationKind::Absolute,
+                encoding: RelocationEncoding::Generic,
+                symbol: external_symbol(product, name),
+                addend: *addend,
+            };
+            product
+                .object
+                .add_relocation(section, relocation)
+                .expect("absolute relocations should be supported for every target");
+        }
+    }
+    // `attributes` is a HashMap, sort so the object file is the same every time
+    ctors.sort_by_key(|&(array, priority, id, _)| (array, priority, id.resolve_and_clone()));
+    for (array, priority, _, symbol) in ctors {
+        let name = match priority {
+            Some(priority) => format!("{}.{:05}", array, priority),
+            None => array.to_string(),
+        };
+        let object = &mut product.object;
+        let section = *array_sections.entry(name).or_insert_with_key(|name| {
+            object.add_section(Vec::new(), name.as_bytes().to_vec(), SectionKind::Data)
+        });
+        let offset = object.append_section_data(section, &[0; PTR_SIZE as usize], PTR_SIZE.into());
+        let relocation = Relocation {
+            offset,
+            size: (PTR_SIZE * CHAR_BIT) as u8,
+            kind: RelocationKind::Absolute,
+            encoding: RelocationEncoding::Generic,
+            symbol,
+            addend: 0,
+        };
+        object
+            .add_relocation(section, relocation)
+            .expect("absolute relocations should be supported for every target");
+    }
+    Ok(())
+}
+
+/// The symbol for a function or data object referenced by a `DataContext`.
+fn external_symbol(product: &ObjectProduct, name: &ExternalName) -> SymbolId {
+    match *name {
+        // cranelift-module puts functions in namespace 0 and data objects in namespace 1
+        ExternalName::User {
+            namespace: 0,
+            index,
+        } => product.function_symbol(FuncId::from_u32(index)),
+        ExternalName::User { index, .. } => product.data_symbol(DataId::from_u32(index)),
+        _ => unreachable!("static initializers only refer to functions and data objects"),
+    }
+}
@@ -0,0 +1,215 @@
+//! Complex numbers are kept in memory, so a complex value in the IR
+//! is always the address of its real part. The imaginary part follows immediately after.
+
+use cranelift::codegen::ir::{
+    condcodes::FloatCC,
+    stackslot::{StackSlotData, StackSlotKind},
+    types, InstBuilder, MemFlags,
+};
+use cranelift::prelude::{FunctionBuilder, Value as IrValue};
+
+use super::Compiler;
+use crate::data::{
+    lex::{ComparisonToken, Token},
+    types::Type,
+};
+
+impl Compiler {
+    /// Load the real and imaginary parts of the complex number stored at `addr`.
+    pub(super) fn complex_parts(
+        addr: IrValue,
+        inner: &Type,
+        builder: &mut FunctionBuilder,
+    ) -> (IrValue, IrValue) {
+        let ir_type = inner.as_ir_type();
+        let real = builder.ins().load(ir_type, MemFlags::new(), addr, 0);
+        let imag = builder
+            .ins()
+            .load(ir_type, MemFlags::new(), addr, ir_type.bytes() as i32);
+        (real, imag)
+    }
+    /// Allocate a temporary on the stack large enough to hold a complex number.
+    fn complex_slot(inner: &Type, builder: &mut FunctionBuilder) -> IrValue {
+        let slot = builder.create_stack_slot(StackSlotData {
+            kind: StackSlotKind::ExplicitSlot,
+            size: inner.as_ir_type().bytes() * 2,
+            offset: None,
+        });
+        builder.ins().stack_addr(Type::ptr_type(), slot, 0)
+    }
+    /// Store `real` and `imag` in a new temporary and return its address.
+    pub(super) fn complex_temp(
+        real: IrValue,
+        imag: IrValue,
+        inner: &Type,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let addr = Self::complex_slot(inner, builder);
+        let offset = inner.as_ir_type().bytes() as i32;
+        builder.ins().store(MemFlags::new(), real, addr, 0);
+        builder.ins().store(MemFlags::new(), imag, addr, offset);
+        addr
+    }
+    /// Split the complex number at `addr` into the values used to pass it to a function.
+    ///
+    /// See `Type::abi_types` for the calling convention.
+    pub(super) fn complex_to_abi(
+        addr: IrValue,
+        inner: &Type,
+        builder: &mut FunctionBuilder,
+    ) -> Vec<IrValue> {
+        if *inner == Type::Float {
+            // both parts are packed into the low 64 bits of a single register
+            let packed = builder.ins().load(types::I64, MemFlags::new(), addr, 0);
+            vec![builder.ins().bitcast(types::F64, packed)]
+        } else {
+            let (real, imag) = Self::complex_parts(addr, inner, builder);
+            vec![real, imag]
+        }
+    }
+    /// The inverse of `complex_to_abi`: store `values` in a new temporary and return its address.
+    pub(super) fn complex_from_abi(
+        values: &[IrValue],
+        inner: &Type,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let addr = Self::complex_slot(inner, builder);
+        Self::store_complex_abi(values, inner, addr, builder);
+        addr
+    }
+    /// Store the values returned by `complex_to_abi` at `addr`.
+    pub(super) fn store_complex_abi(
+        values: &[IrValue],
+        inner: &Type,
+        addr: IrValue,
+        builder: &mut FunctionBuilder,
+    ) {
+        if *inner == Type::Float {
+            let packed = builder.ins().bitcast(types::I64, values[0]);
+            builder.ins().store(MemFlags::new(), packed, addr, 0);
+        } else {
+            let offset = inner.as_ir_type().bytes() as i32;
+            builder.ins().store(MemFlags::new(), values[0], addr, 0);
+            builder
+                .ins()
+                .store(MemFlags::new(), values[1], addr, offset);
+        }
+    }
+    /// Compute `left <token> right` where both operands have type `_Complex inner`.
+    pub(super) fn complex_binary_op(
+        &mut self,
+        left: IrValue,
+        right: IrValue,
+        inner: &Type,
+        token: Token,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let (a, b) = Self::complex_parts(left, inner, builder);
+        let (c, d) = Self::complex_parts(right, inner, builder);
+        let (real, imag) = match token {
+            Token::Plus => (builder.ins().fadd(a, c), builder.ins().fadd(b, d)),
+            Token::Minus => (builder.ins().fsub(a, c), builder.ins().fsub(b, d)),
+            Token::Star | Token::Divide => {
+                // the naive formulas give the wrong answer for infinities and NaNs (C11 G.5.1),
+                // so do the same thing as GCC and Clang and let the runtime library handle it
+                let op = if token == Token::Star { "mul" } else { "div" };
+                let mode = if *inner == Type::Float { 's' } else { 'd' };
+                let name = format!("__{}{}c3", op, mode);
+                let ir_type = inner.as_ir_type();
+                let ret = Type::Complex(Box::new(inner.clone())).abi_types();
+                let func = self.declare_libc(&name, &[ir_type; 4], &ret);
+                let func = self.module.declare_func_in_func(func, builder.func);
+                let call = builder.ins().call(func, &[a, b, c, d]);
+                let results = builder.inst_results(call).to_vec();
+                return Self::complex_from_abi(&results, inner, builder);
+            }
+            _ => unreachable!("parser should only allow arithmetic operators for complex numbers"),
+        };
+        Self::complex_temp(real, imag, inner, builder)
+    }
+    /// Compare two complex numbers for equality. Complex numbers are not ordered.
+    pub(super) fn complex_compare(
+        left: IrValue,
+        right: IrValue,
+        inner: &Type,
+        token: ComparisonToken,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let (a, b) = Self::complex_parts(left, inner, builder);
+        let (c, d) = Self::complex_parts(right, inner, builder);
+        match token {
+            ComparisonToken::EqualEqual => {
+                let real = builder.ins().fcmp(FloatCC::Equal, a, c);
+                let imag = builder.ins().fcmp(FloatCC::Equal, b, d);
+                builder.ins().band(real, imag)
+            }
+            ComparisonToken::NotEqual => {
+                let real = builder.ins().fcmp(FloatCC::NotEqual, a, c);
+                let imag = builder.ins().fcmp(FloatCC::NotEqual, b, d);
+                builder.ins().bor(real, imag)
+            }
+            _ => unreachable!("parser should only allow == and != for complex numbers"),
+        }
+    }
+    /// Whether either part of the complex number at `addr` is nonzero.
+    pub(super) fn complex_is_nonzero(
+        addr: IrValue,
+        inner: &Type,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let (real, imag) = Self::complex_parts(addr, inner, builder);
+        let zero = Self::float_zero(inner, builder);
+        let real = builder.ins().fcmp(FloatCC::NotEqual, real, zero);
+        let imag = builder.ins().fcmp(FloatCC::NotEqual, imag, zero);
+        builder.ins().bor(real, imag)
+    }
+    pub(super) fn complex_negate(
+        addr: IrValue,
+        inner: &Type,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let (real, imag) = Self::complex_parts(addr, inner, builder);
+        let (real, imag) = (builder.ins().fneg(real), builder.ins().fneg(imag));
+        Self::complex_temp(real, imag, inner, builder)
+    }
+    /// Convert `val` from `from` to `to`, where at least one of the types is complex.
+    ///
+    /// Converting a real number to a complex type gives an imaginary part of zero;
+    /// converting a complex number to a real type discards the imaginary part (C11 6.3.1.7).
+    pub(super) fn complex_cast(
+        val: IrValue,
+        from: &Type,
+        to: &Type,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        match (from, to) {
+            (Type::Complex(from), Type::Complex(to)) => {
+                let (from_ir, to_ir) = (from.as_ir_type(), to.as_ir_type());
+                let (real, imag) = Self::complex_parts(val, from, builder);
+                let real = Self::cast_ir(from_ir, to_ir, real, true, true, builder);
+                let imag = Self::cast_ir(from_ir, to_ir, imag, true, true, builder);
+                Self::complex_temp(real, imag, to, builder)
+            }
+            (Type::Complex(inner), Type::Bool) => Self::complex_is_nonzero(val, inner, builder),
+            (Type::Complex(inner), real_type) => {
+                let (real, _) = Self::complex_parts(val, inner, builder);
+                let (from_ir, to_ir) = (inner.as_ir_type(), real_type.as_ir_type());
+                Self::cast_ir(from_ir, to_ir, real, true, real_type.is_signed(), builder)
+            }
+            (real_type, Type::Complex(inner)) => {
+                let (from_ir, to_ir) = (real_type.as_ir_type(), inner.as_ir_type());
+                let real = Self::cast_ir(from_ir, to_ir, val, real_type.is_signed(), true, builder);
+                let imag = Self::float_zero(inner, builder);
+                Self::complex_temp(real, imag, inner, builder)
+            }
+            _ => unreachable!("complex_cast called without a complex type"),
+        }
+    }
+    fn float_zero(ctype: &Type, builder: &mut FunctionBuilder) -> IrValue {
+        if *ctype == Type::Float {
+            builder.ins().f32const(0.0)
+        } else {
+            builder.ins().f64const(0.0)
+        }
+    }
+}
@@ -1,11 +1,13 @@
-use cranelift::codegen::ir::{condcodes, types, MemFlags};
+use cranelift::codegen::ir::{condcodes, types, MemFlags, TrapCode};
 use cranelift::prelude::{FunctionBuilder, InstBuilder, Type as IrType, Value as IrValue};
 use log::debug;
 
-use super::{Compiler, Id};
+use super::{thread_local_unsupported, Compiler, Id};
+use crate::arch::BitfieldLayout;
 use crate::data::prelude::*;
 use crate::data::{
     lex::{AssignmentToken, ComparisonToken, Literal, Token},
+    types::ArrayType,
     Expr, ExprType,
 };
 
@@ -14,8 +16,8 @@ type IrResult = CompileResult<Value>;
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) struct Value {
     pub(crate) ir_val: IrValue,
-    ir_type: IrType,
-    ctype: Type,
+    pub(super) ir_type: IrType,
+    pub(super) ctype: Type,
 }
 
 enum FuncCall {
@@ -30,6 +32,9 @@ impl Compiler {
     pub(crate) fn compile_expr(&mut self, expr: Expr, builder: &mut FunctionBuilder) -> IrResult {
         let expr = expr.const_fold()?;
         let location = expr.location;
+        if !expr.lval && super::is_long_double(&expr.ctype) {
+            semantic_err!(super::long_double_unsupported(), location);
+        }
         let ir_type = if expr.lval {
             Type::ptr_type()
         } else {
@@ -39,10 +44,33 @@ impl Compiler {
             ExprType::Literal(token) => {
                 self.compile_literal(ir_type, expr.ctype, token, location, builder)
             }
-            ExprType::Id(var) => self.load_addr(var, builder),
+            ExprType::Id(var) => {
+                if var.qualifiers.thread_local {
+                    semantic_err!(thread_local_unsupported(&var), location);
+                }
+                self.load_addr(var, builder)
+            }
 
             // unary operators
             ExprType::Deref(pointer) => {
+                if pointer.is_bitfield() {
+                    let (addr, layout) = self.bitfield_addr(*pointer, builder)?;
+                    let unit = Self::load_bitfield_unit(addr, &layout, builder);
+                    return Ok(Value {
+                        ir_val: Self::extract_bitfield(unit, &layout, builder),
+                        ir_type,
+                        ctype: expr.ctype,
+                    });
+                }
+                if pointer.ctype.is_atomic() {
+                    Self::check_atomic_scalar(&expr.ctype, location)?;
+                    let addr = self.compile_expr(*pointer, builder)?;
+                    return Ok(Value {
+                        ir_val: self.atomic_load(addr.ir_val, &expr.ctype, builder),
+                        ir_type,
+                        ctype: expr.ctype,
+                    });
+                }
                 let val = self.compile_expr(*pointer, builder)?;
                 let flags = MemFlags::new();
                 Ok(Value {
@@ -127,10 +155,46 @@ impl Compiler {
                     ctype,
                 })
             }
+            ExprType::PostIncrement(lval, increase) if lval.is_bitfield() => {
+                let (addr, layout) = self.bitfield_addr(*lval, builder)?;
+                let unit = Self::load_bitfield_unit(addr, &layout, builder);
+                let previous = Self::extract_bitfield(unit, &layout, builder);
+                let new_value = if layout.ctype == Type::Bool {
+                    // incrementing a boolean always makes it true, decrementing toggles it
+                    if increase {
+                        builder.ins().bconst(types::B1, true)
+                    } else {
+                        let previous = builder.ins().bint(types::I8, previous);
+                        builder.ins().icmp_imm(condcodes::IntCC::Equal, previous, 0)
+                    }
+                } else {
+                    builder
+                        .ins()
+                        .iadd_imm(previous, if increase { 1 } else { -1 })
+                };
+                Self::store_bitfield(unit, new_value, addr, &layout, builder);
+                Ok(Value {
+                    ir_val: previous,
+                    ir_type,
+                    ctype: expr.ctype,
+                })
+            }
+            ExprType::PostIncrement(lval, increase) if lval.ctype.is_atomic() => {
+                let ctype = lval.ctype.strip_atomic().clone();
+                let addr = self.compile_expr(*lval, builder)?;
+                let (previous_value, _) =
+                    self.atomic_update(addr.ir_val, &ctype, builder, |old, builder| {
+                        Ok(Value {
+                            ir_val: Self::increment_ir(&old, increase, builder),
+                            ..old
+                        })
+                    })?;
+                Ok(previous_value)
+            }
             ExprType::PostIncrement(lval, increase) => {
                 let lval = self.compile_expr(*lval, builder)?;
                 let loaded_ctype = match lval.ctype {
-                    Type::Pointer(t) => *t,
+                    Type::Pointer(t, _) => *t,
                     _ => lval.ctype,
                 };
                 let ir_type = loaded_ctype.as_ir_type();
@@ -139,17 +203,7 @@ impl Compiler {
                     ir_type,
                     ctype: loaded_ctype,
                 };
-
-                let addend = if increase { 1 } else { -1 };
-                let (addend_ir, add_func): (_, fn(_, _, _) -> _) = match previous_value.ctype {
-                    Type::Double => (builder.ins().f64const(addend as f64), InstBuilder::fadd),
-                    Type::Float => (builder.ins().f32const(addend as f32), InstBuilder::fadd),
-                    _ => (
-                        builder.ins().iconst(previous_value.ir_type, addend),
-                        InstBuilder::iadd,
-                    ),
-                };
-                let new_value = add_func(builder.ins(), previous_value.ir_val, addend_ir);
+                let new_value = Self::increment_ir(&previous_value, increase, builder);
                 builder
                     .ins()
                     .store(MemFlags::new(), new_value, lval.ir_val, 0);
@@ -165,10 +219,108 @@ impl Compiler {
             ExprType::Ternary(condition, left, right) => {
                 self.ternary(*condition, *left, *right, builder)
             }
-            ExprType::Sizeof(_) => unimplemented!("sizeof variable length arrays"),
+            ExprType::Sizeof(ctype) => {
+                let size = self.vla_sizeof(&ctype, location, builder)?;
+                let ir_val = if ir_type == Type::ptr_type() {
+                    size
+                } else {
+                    builder.ins().ireduce(ir_type, size)
+                };
+                Ok(Value {
+                    ir_val,
+                    ir_type,
+                    ctype: expr.ctype,
+                })
+            }
+            ExprType::ComplexPart(complex, imag) => {
+                let inner = match &complex.ctype {
+                    Type::Complex(inner) => inner.as_ir_type(),
+                    _ => unreachable!(
+                        "parser should only allow __real__ and __imag__ of complex numbers"
+                    ),
+                };
+                let complex = self.compile_expr(*complex, builder)?;
+                let offset = if imag { inner.bytes() } else { 0 };
+                let addr = builder.ins().iadd_imm(complex.ir_val, i64::from(offset));
+                let ir_val = if expr.lval {
+                    addr
+                } else {
+                    builder.ins().load(ir_type, MemFlags::new(), addr, 0)
+                };
+                Ok(Value {
+                    ir_val,
+                    ir_type,
+                    ctype: expr.ctype,
+                })
+            }
             ExprType::StaticRef(_) => {
                 unreachable!("static refs can only appear in top level declarations")
             }
+            ExprType::Atomic(op, args) => self.atomic_op(op, args, expr.ctype, builder),
+            ExprType::StmtExpr(stmts, result) => {
+                self.scope.enter_scope();
+                let value = self.statement_expr(stmts, result, ir_type, expr.ctype, builder);
+                self.scope.leave_scope();
+                value
+            }
+            ExprType::LabelAddress(label) => {
+                let address = self.label_address(label);
+                Ok(Value {
+                    ir_val: builder.ins().iconst(ir_type, address as i64),
+                    ir_type,
+                    ctype: expr.ctype,
+                })
+            }
+        }
+    }
+    /// `({ stmts; result; })`. The caller is responsible for the scope of the block.
+    fn statement_expr(
+        &mut self,
+        stmts: Vec<Stmt>,
+        result: Option<Box<Expr>>,
+        ir_type: IrType,
+        ctype: Type,
+        builder: &mut FunctionBuilder,
+    ) -> IrResult {
+        self.compile_all(stmts, builder)?;
+        if builder.is_filled() {
+            // e.g. `({ return 1; 2; })`: the value still has to be compiled
+            let unreachable = builder.create_ebb();
+            builder.switch_to_block(unreachable);
+        }
+        match result {
+            Some(result) => self.compile_expr(*result, builder),
+            None => Ok(Value {
+                // Just a placeholder.
+                ir_val: builder.ins().iconst(types::I32, 0),
+                ir_type,
+                ctype,
+            }),
+        }
+    }
+    /// The size of `ctype` in bytes, computed at runtime for variable length arrays.
+    pub(super) fn vla_sizeof(
+        &mut self,
+        ctype: &Type,
+        location: Location,
+        builder: &mut FunctionBuilder,
+    ) -> CompileResult<IrValue> {
+        match ctype {
+            Type::Array(inner, ArrayType::Variable(length)) => {
+                let length = self.compile_expr((**length).clone(), builder)?;
+                let inner = self.vla_sizeof(inner, location, builder)?;
+                Ok(builder.ins().imul(length.ir_val, inner))
+            }
+            Type::Array(inner, ArrayType::Fixed(length)) if inner.is_vla() => {
+                let inner = self.vla_sizeof(inner, location, builder)?;
+                Ok(builder.ins().imul_imm(inner, *length as i64))
+            }
+            _ => {
+                let size = ctype
+                    .sizeof()
+                    .map_err(|err| location.with(err.to_string()))?;
+                Ok(builder.ins().iconst(Type::ptr_type(), size as i64))
+            }
         }
     }
     fn ternary(
@@ -288,6 +440,14 @@ impl Compiler {
             self.compile_expr(left, builder)?,
             self.compile_expr(right, builder)?,
         );
+        if let Type::Complex(inner) = &ctype {
+            let ir_val = self.complex_binary_op(left.ir_val, right.ir_val, inner, token, builder);
+            return Ok(Value {
+                ir_val,
+                ir_type: left.ir_type,
+                ctype,
+            });
+        }
         Self::binary_assign_ir(left, right, ctype, token, builder)
     }
     fn binary_assign_ir(
@@ -336,12 +496,21 @@ impl Compiler {
     fn cast(&mut self, expr: Expr, ctype: Type, builder: &mut FunctionBuilder) -> IrResult {
         // calculate this here before it's moved to `compile_expr`
         let orig_signed = expr.ctype.is_signed();
+        let orig_ctype = expr.ctype.clone();
         let original = self.compile_expr(expr, builder)?;
         if ctype == Type::Void {
             // this cast is a no-op, it's just here for the frontend
             return Ok(original);
         }
         let cast_type = ctype.as_ir_type();
+        if orig_ctype.is_complex() || ctype.is_complex() {
+            let ir_val = Self::complex_cast(original.ir_val, &orig_ctype, &ctype, builder);
+            return Ok(Value {
+                ir_val,
+                ir_type: cast_type,
+                ctype,
+            });
+        }
         let cast = Self::cast_ir(
             original.ir_type,
             cast_type,
@@ -356,7 +525,7 @@ impl Compiler {
             ctype,
         })
     }
-    fn cast_ir(
+    pub(super) fn cast_ir(
         from: IrType,
         to: IrType,
         val: IrValue,
@@ -427,9 +596,15 @@ impl Compiler {
         }
     }
     fn logical_not(&mut self, expr: Expr, builder: &mut FunctionBuilder) -> IrResult {
+        let ctype = expr.ctype.clone();
         let Value {
             ir_type, ir_val, ..
         } = self.compile_expr(expr, builder)?;
+        // `!z` is the same as `!(_Bool)z`
+        let (ir_type, ir_val) = match &ctype {
+            Type::Complex(inner) => (types::B1, Self::complex_is_nonzero(ir_val, inner, builder)),
+            _ => (ir_type, ir_val),
+        };
         let ir_bool = match ir_type {
             types::F32 => {
                 let zero = builder.ins().f32const(0.0);
@@ -455,13 +630,19 @@ impl Compiler {
         })
     }
     fn negate(&mut self, expr: Expr, builder: &mut FunctionBuilder) -> IrResult {
-        self.unary_op(expr, builder, |ir_val, ir_type, _, builder| match ir_type {
-            i if i.is_int() => builder.ins().irsub_imm(ir_val, 0),
-            f if f.is_float() => builder.ins().fneg(ir_val),
-            _ => unreachable!("parser should catch illegal types"),
-        })
+        self.unary_op(
+            expr,
+            builder,
+            |ir_val, ir_type, ctype, builder| match ctype {
+                Type::Complex(inner) => Self::complex_negate(ir_val, inner, builder),
+                _ if ir_type.is_int() => builder.ins().irsub_imm(ir_val, 0),
+                _ if ir_type.is_float() => builder.ins().fneg(ir_val),
+                _ => unreachable!("parser should catch illegal types"),
+            },
+        )
     }
-    fn load_addr(&self, var: Symbol, builder: &mut FunctionBuilder) -> IrResult {
+    fn load_addr(&mut self, var: Symbol, builder: &mut FunctionBuilder) -> IrResult {
+        self.use_function(var.id)?;
         let ptr_type = Type::ptr_type();
         let ir_val = match self.scope.get(&var.id).unwrap() {
             Id::Function(func_id) => {
@@ -473,8 +654,9 @@ impl Compiler {
                 builder.ins().global_value(ptr_type, global)
             }
             Id::Local(stack_slot) => builder.ins().stack_addr(ptr_type, *stack_slot, 0),
+            Id::Vla(stack_slot) => builder.ins().stack_load(ptr_type, *stack_slot, 0),
         };
-        let ctype = Type::Pointer(Box::new(var.ctype));
+        let ctype = Type::Pointer(Box::new(var.ctype), var.qualifiers.type_qualifiers());
         Ok(Value {
             ir_type: ptr_type,
             ir_val,
@@ -488,13 +670,19 @@ impl Compiler {
         token: ComparisonToken,
         builder: &mut FunctionBuilder,
     ) -> IrResult {
+        let complex = match &left.ctype {
+            Type::Complex(inner) => Some((**inner).clone()),
+            _ => None,
+        };
         let (left, right) = (
             self.compile_expr(left, builder)?,
             self.compile_expr(right, builder)?,
         );
         assert_eq!(left.ir_type, right.ir_type);
 
-        let ir_val = if left.ir_type.is_int() {
+        let ir_val = if let Some(inner) = &complex {
+            Self::complex_compare(left.ir_val, right.ir_val, inner, token, builder)
+        } else if left.ir_type.is_int() {
             let code = token.to_int_compare(left.ctype.is_signed());
             builder.ins().icmp(code, left.ir_val, right.ir_val)
         } else {
@@ -515,14 +703,29 @@ impl Compiler {
         token: AssignmentToken,
         builder: &mut FunctionBuilder,
     ) -> IrResult {
+        if lval.is_bitfield() {
+            return self.bitfield_assignment(lval, rval, token, builder);
+        }
+        if lval.ctype.is_atomic() {
+            return self.atomic_assignment(lval, rval, token, builder);
+        }
         let ctype = lval.ctype.clone();
         let location = lval.location;
         let (target, value) = (
             self.compile_expr(lval, builder)?,
             self.compile_expr(rval, builder)?,
         );
-        if let Type::Union(_) | Type::Struct(_) = ctype {
-            if token != AssignmentToken::Equal {
+        let value = match &ctype {
+            Type::Complex(inner) if token != AssignmentToken::Equal => {
+                let token = token.without_assignment();
+                let ir_val =
+                    self.complex_binary_op(target.ir_val, value.ir_val, inner, token, builder);
+                Value { ir_val, ..value }
+            }
+            _ => value,
+        };
+        if let Type::Union(_) | Type::Struct(_) | Type::Complex(_) = ctype {
+            if token != AssignmentToken::Equal && !ctype.is_complex() {
                 unreachable!("struct should not have a valid complex assignment");
             }
             use std::convert::TryInto;
@@ -532,7 +735,10 @@ impl Compiler {
                 .expect("if sizeof() succeeds so should alignof()")
                 .try_into()
                 .expect("align should never be more than 255 bytes");
-            builder.emit_small_memmove(
+            // the source and destination can only overlap exactly (C11 6.5.16.1p3).
+            // `emit_small_memmove` always loads pointer-sized values,
+            // so it copies the wrong number of bytes for e.g. 4 or 16 byte structs.
+            builder.emit_small_memcpy(
                 self.module.target_config(),
                 target.ir_val,
                 value.ir_val,
@@ -570,6 +776,203 @@ impl Compiler {
             .store(MemFlags::new(), value.ir_val, target_val, 0);
         Ok(value)
     }
+    /// Assignment to an `_Atomic` object.
+    ///
+    /// Compound assignment is a single read-modify-write operation (C11 6.5.16.2p3).
+    fn atomic_assignment(
+        &mut self,
+        lval: Expr,
+        rval: Expr,
+        token: AssignmentToken,
+        builder: &mut FunctionBuilder,
+    ) -> IrResult {
+        let ctype = lval.ctype.strip_atomic().clone();
+        Self::check_atomic_scalar(&ctype, lval.location)?;
+        let (target, value) = (
+            self.compile_expr(lval, builder)?,
+            self.compile_expr(rval, builder)?,
+        );
+        if token == AssignmentToken::Equal {
+            self.atomic_store(target.ir_val, value.ir_val, &ctype, builder);
+            return Ok(value);
+        }
+        let token = token.without_assignment();
+        let (_, new_value) =
+            self.atomic_update(target.ir_val, &ctype, builder, |old, builder| {
+                Self::binary_assign_ir(old, value, ctype.clone(), token, builder)
+            })?;
+        Ok(new_value)
+    }
+    /// libatomic can only operate on objects the size of an integer.
+    fn check_atomic_scalar(ctype: &Type, location: Location) -> CompileResult<()> {
+        if !ctype.is_scalar() || ctype.is_complex() {
+            semantic_err!(
+                format!("atomic objects of type '{}' are not supported", ctype),
+                location,
+            );
+        }
+        Ok(())
+    }
+    /// `value + 1` or `value - 1`, for increments and decrements
+    fn increment_ir(value: &Value, increase: bool, builder: &mut FunctionBuilder) -> IrValue {
+        let addend = if increase { 1 } else { -1 };
+        let (addend_ir, add_func): (_, fn(_, _, _) -> _) = match value.ctype {
+            Type::Double => (builder.ins().f64const(addend as f64), InstBuilder::fadd),
+            Type::Float => (builder.ins().f32const(addend as f32), InstBuilder::fadd),
+            _ => (
+                builder.ins().iconst(value.ir_type, addend),
+                InstBuilder::iadd,
+            ),
+        };
+        add_func(builder.ins(), value.ir_val, addend_ir)
+    }
+    fn bitfield_assignment(
+        &mut self,
+        lval: Expr,
+        rval: Expr,
+        token: AssignmentToken,
+        builder: &mut FunctionBuilder,
+    ) -> IrResult {
+        let ctype = lval.ctype.clone();
+        let (addr, layout) = self.bitfield_addr(lval, builder)?;
+        let mut value = self.compile_expr(rval, builder)?;
+        // load after evaluating the right hand side in case it modifies the same storage unit
+        let unit = Self::load_bitfield_unit(addr, &layout, builder);
+        if token != AssignmentToken::Equal {
+            let target = Value {
+                ir_val: Self::extract_bitfield(unit, &layout, builder),
+                ir_type: ctype.as_ir_type(),
+                ctype: ctype.clone(),
+            };
+            value = Self::binary_assign_ir(
+                target,
+                value,
+                ctype.clone(),
+                token.without_assignment(),
+                builder,
+            )?;
+        }
+        Ok(Value {
+            ir_val: Self::store_bitfield(unit, value.ir_val, addr, &layout, builder),
+            ir_type: value.ir_type,
+            ctype,
+        })
+    }
+    /// Return the address of the storage unit for a bitfield member
+    pub(super) fn bitfield_addr(
+        &mut self,
+        member: Expr,
+        builder: &mut FunctionBuilder,
+    ) -> CompileResult<(IrValue, BitfieldLayout)> {
+        let (cstruct, id) = match member.expr {
+            ExprType::Member(cstruct, id) => (cstruct, id),
+            _ => unreachable!("only struct members can be bitfields"),
+        };
+        let layout = cstruct
+            .ctype
+            .bitfield(id)
+            .expect("should only be called for bitfields");
+        let pointer = self.compile_expr(*cstruct, builder)?;
+        let addr = builder.ins().iadd_imm(pointer.ir_val, layout.offset as i64);
+        Ok((addr, layout))
+    }
+    // bitfields are manipulated as at least an i32,
+    // since Cranelift has poor support for arithmetic on smaller integers
+    fn bitfield_work_type(layout: &BitfieldLayout) -> IrType {
+        match Self::bitfield_unit_type(layout).bits() {
+            0..=32 => types::I32,
+            _ => types::I64,
+        }
+    }
+    fn bitfield_unit_type(layout: &BitfieldLayout) -> IrType {
+        // booleans are B1 in the IR, which can't be stored in memory
+        if layout.ctype == Type::Bool {
+            types::I8
+        } else {
+            layout.ctype.as_ir_type()
+        }
+    }
+    /// Load the whole storage unit containing a bitfield
+    pub(super) fn load_bitfield_unit(
+        addr: IrValue,
+        layout: &BitfieldLayout,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let work_type = Self::bitfield_work_type(layout);
+        let flags = MemFlags::new();
+        match Self::bitfield_unit_type(layout) {
+            types::I8 => builder.ins().uload8(work_type, flags, addr, 0),
+            types::I16 => builder.ins().uload16(work_type, flags, addr, 0),
+            ty => builder.ins().load(ty, flags, addr, 0),
+        }
+    }
+    /// Get the value of the bitfield from its storage unit,
+    /// sign extending if the bitfield is signed.
+    fn extract_bitfield(
+        unit: IrValue,
+        layout: &BitfieldLayout,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let work_type = Self::bitfield_work_type(layout);
+        let bits = i64::from(work_type.bits());
+        let (shift, width) = (i64::from(layout.shift), i64::from(layout.width));
+        let value = if layout.ctype.is_signed() && layout.ctype != Type::Bool {
+            // move the bitfield to the top of the register, then shift it back down
+            let value = builder.ins().ishl_imm(unit, bits - shift - width);
+            builder.ins().sshr_imm(value, bits - width)
+        } else {
+            let value = builder.ins().ushr_imm(unit, shift);
+            if width < bits {
+                builder.ins().band_imm(value, (1 << width) - 1)
+            } else {
+                value
+            }
+        };
+        let ir_type = layout.ctype.as_ir_type();
+        if layout.ctype == Type::Bool {
+            builder.ins().icmp_imm(condcodes::IntCC::NotEqual, value, 0)
+        } else if ir_type.bits() < work_type.bits() {
+            builder.ins().ireduce(ir_type, value)
+        } else {
+            value
+        }
+    }
+    /// Store `value` in a bitfield, given the previous contents of its storage unit.
+    ///
+    /// Returns the value of the bitfield after the store,
+    /// which may differ from `value` if `value` does not fit in the bitfield.
+    pub(super) fn store_bitfield(
+        unit: IrValue,
+        value: IrValue,
+        addr: IrValue,
+        layout: &BitfieldLayout,
+        builder: &mut FunctionBuilder,
+    ) -> IrValue {
+        let work_type = Self::bitfield_work_type(layout);
+        let value = if layout.ctype == Type::Bool {
+            builder.ins().bint(work_type, value)
+        } else if layout.ctype.as_ir_type().bits() < work_type.bits() {
+            builder.ins().uextend(work_type, value)
+        } else {
+            value
+        };
+        let mask = if u16::from(layout.width) == work_type.bits() {
+            !0
+        } else {
+            (1_u64 << layout.width) - 1
+        } << layout.shift;
+        let value = builder.ins().ishl_imm(value, i64::from(layout.shift));
+        let value = builder.ins().band_imm(value, mask as i64);
+        let cleared = builder.ins().band_imm(unit, !mask as i64);
+        let new_unit = builder.ins().bor(cleared, value);
+        let flags = MemFlags::new();
+        match Self::bitfield_unit_type(layout) {
+            types::I8 => builder.ins().istore8(flags, new_unit, addr, 0),
+            types::I16 => builder.ins().istore16(flags, new_unit, addr, 0),
+            _ => builder.ins().store(flags, new_unit, addr, 0),
+        };
+        Self::extract_bitfield(new_unit, layout, builder)
+    }
     fn call(
         &mut self,
         func: FuncCall,
@@ -578,12 +981,26 @@ impl Compiler {
         builder: &mut FunctionBuilder,
     ) -> IrResult {
         use crate::data::{Qualifiers, StorageClass};
-        use cranelift::codegen::ir::{AbiParam, ArgumentPurpose};
 
         let mut ftype = match ctype {
             Type::Function(ftype) => ftype,
             _ => unreachable!("parser should only allow calling functions"),
         };
+        if !ftype.has_prototype() {
+            // the parser has already applied the default argument promotions,
+            // so the arguments are passed exactly as they are
+            ftype.old_style = false;
+            ftype.params = args
+                .iter()
+                .map(|arg| Symbol {
+                    ctype: arg.ctype.clone(),
+                    id: Default::default(),
+                    init: true,
+                    qualifiers: Qualifiers::NONE,
+                    storage_class: StorageClass::Auto,
+                })
+                .collect();
+        }
         let mut float_variadic = 0;
         if ftype.varargs {
             // needs to be done before we move the args by compiling them
@@ -595,6 +1012,8 @@ impl Compiler {
             for arg in &args[ftype.params.len()..] {
                 if arg.ctype.is_floating() {
                     float_variadic += 1;
+                } else if arg.ctype.is_complex() {
+                    float_variadic += arg.ctype.abi_types().len() as i64;
                 }
                 debug!("adding variadic arg with type {}", arg.ctype);
                 ftype.params.push(Symbol {
@@ -606,10 +1025,17 @@ impl Compiler {
                 });
             }
         }
-        let mut compiled_args: Vec<IrValue> = args
-            .into_iter()
-            .map(|arg| self.compile_expr(arg, builder).map(|val| val.ir_val))
-            .collect::<CompileResult<_>>()?;
+        let mut compiled_args: Vec<IrValue> = Vec::with_capacity(args.len());
+        for arg in args {
+            let ctype = arg.ctype.clone();
+            let val = self.compile_expr(arg, builder)?;
+            match &ctype {
+                Type::Complex(inner) => {
+                    compiled_args.extend(Self::complex_to_abi(val.ir_val, inner, builder))
+                }
+                _ => compiled_args.push(val.ir_val),
+            }
+        }
         if ftype.varargs {
             debug!("adding number of float args");
             let float_ir = builder.ins().iconst(types::I8, float_variadic);
@@ -617,6 +1043,7 @@ impl Compiler {
         }
         let call = match func {
             FuncCall::Named(func_name) => {
+                self.use_function(func_name)?;
                 let func_id = match self.scope.get(&func_name) {
                     Some(Id::Function(func_id)) => *func_id,
                     _ => panic!("parser should catch illegal function calls"),
@@ -624,24 +1051,10 @@ impl Compiler {
                 let func_ref = self.module.declare_func_in_func(func_id, builder.func);
                 let call = builder.ins().call(func_ref, compiled_args.as_slice());
                 // stolen from https://github.com/bjorn3/rustc_codegen_cranelift/blob/82fde5b62281fa51a/src/abi/mod.rs#L535
-                if ftype.varargs {
-                    let call_sig = builder.func.dfg.call_signature(call).unwrap();
-                    let al = self
-                        .module
-                        .isa()
-                        .register_info()
-                        .parse_regunit("rax")
-                        .expect("x86 should have an rax register");
-                    let float_arg = AbiParam::special_reg(types::I8, ArgumentPurpose::Normal, al);
-                    // NOTE: this is added both here and in signature() because we overwrite the previous params
-                    let abi_params = ftype
-                        .params
-                        .into_iter()
-                        .map(|param| AbiParam::new(param.ctype.as_ir_type()))
-                        .chain(std::iter::once(float_arg))
-                        .collect();
-                    builder.func.dfg.signatures[call_sig].params = abi_params;
-                }
+                // the declaration doesn't always match the call: variadic and unprototyped calls
+                // pass extra arguments, and a prototype can disagree with an old-style definition
+                let call_sig = builder.func.dfg.call_signature(call).unwrap();
+                builder.func.dfg.signatures[call_sig] = ftype.signature(self.module.isa());
                 call
             }
             FuncCall::Indirect(callee) => {
@@ -652,10 +1065,21 @@ impl Compiler {
                     .call_indirect(sigref, callee.ir_val, compiled_args.as_slice())
             }
         };
-        let ir_val = match builder.inst_results(call).first() {
+        if ftype.noreturn {
+            // anything after the call is unreachable,
+            // but it still has to be compiled, e.g. `die("oops"); return 0;`
+            builder.ins().trap(TrapCode::UnreachableCodeReached);
+            let unreachable = builder.create_ebb();
+            builder.switch_to_block(unreachable);
+        }
+        let ir_val = match (builder.inst_results(call).first(), &*ftype.return_type) {
             // Just a placeholder.
-            None => builder.ins().iconst(types::I32, 0),
-            Some(ret) => *ret,
+            (None, _) => builder.ins().iconst(types::I32, 0),
+            (Some(_), Type::Complex(inner)) => {
+                let results = builder.inst_results(call).to_vec();
+                Self::complex_from_abi(&results, inner, builder)
+            }
+            (Some(ret), _) => *ret,
         };
         Ok(Value {
             ir_val,
@@ -1,28 +1,38 @@
+mod atomic;
+mod attribute;
+mod complex;
 mod expr;
 mod static_init;
 mod stmt;
 
-use std::collections::{HashMap, VecDeque};
-use std::convert::TryFrom;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::{TryFrom, TryInto};
 
 use cranelift::codegen::{
     self,
+    cursor::{Cursor, FuncCursor},
     ir::{
         entities::StackSlot,
         function::Function,
         stackslot::{StackSlotData, StackSlotKind},
-        ExternalName, InstBuilder, MemFlags,
+        AbiParam, ExternalName, InstBuilder, MemFlags, TrapCode,
     },
     isa,
     settings::{self, Configurable},
 };
 use cranelift::frontend::Switch;
-use cranelift::prelude::{Ebb, FunctionBuilder, FunctionBuilderContext, Signature};
+use cranelift::prelude::{
+    Ebb, FunctionBuilder, FunctionBuilderContext, Signature, Type as IrType, Value as IrValue,
+};
 use cranelift_module::{self, DataId, FuncId, Linkage, Module as CraneliftModule};
 use cranelift_object::{ObjectBackend, ObjectBuilder, ObjectProduct, ObjectTrapCollection};
 
-use crate::arch::TARGET;
-use crate::data::{prelude::*, types::FunctionType, Initializer, Scope, StorageClass};
+use crate::arch::{CALLING_CONVENTION, PTR_SIZE, SIZE_T, TARGET};
+use crate::data::{
+    prelude::*,
+    types::{ArrayType, FunctionType},
+    Initializer, Scope, StorageClass,
+};
 use crate::utils;
 
 type Module = CraneliftModule<ObjectBackend>;
@@ -31,6 +41,8 @@ enum Id {
     Function(FuncId),
     Global(DataId),
     Local(StackSlot),
+    /// A variable length array. The stack slot holds a pointer to the array.
+    Vla(StackSlot),
 }
 
 struct Compiler {
@@ -41,18 +53,56 @@ struct Compiler {
     last_saw_loop: bool,
     strings: HashMap<InternedStr, DataId>,
     loops: Vec<(Ebb, Ebb)>,
-    // switch, default, end
+    // switch, GNU case ranges, default, end
     // if default is empty once we get to the end of a switch body,
     // we didn't see a default case
-    switches: Vec<(Switch, Option<Ebb>, Ebb)>,
+    switches: Vec<(Switch, Vec<(u64, u64, Ebb)>, Option<Ebb>, Ebb)>,
+    // the blocks for the labels in the current function
     labels: HashMap<InternedStr, Ebb>,
+    // the labels whose address was taken with `&&label`, in order.
+    // the address of a label is its index in this list plus one, so it's never null.
+    label_addresses: Vec<InternedStr>,
+    // the block that dispatches `goto *ptr` to the label with address `ptr`
+    indirect_goto: Option<Ebb>,
+    // the number of read-only initializers we've emitted for automatic variables
+    templates: usize,
+    // the variable length arrays in the current function
+    vlas: Vec<StackSlot>,
+    // the signatures of the functions defined in this file
+    definitions: HashMap<InternedStr, Signature>,
+    // inline definitions and `static inline` functions, which are only compiled if they're used
+    inline_functions: Vec<(InternedStr, FunctionType, Vec<Stmt>, Location)>,
+    // every identifier that might refer to a function in `inline_functions`
+    used: HashSet<InternedStr>,
+    // tentative definitions that haven't yet been followed by a definition with an initializer
+    tentative: Vec<(DataId, Symbol, Location)>,
+    // data objects that have been defined with an initializer
+    initialized: HashSet<DataId>,
+    // whether tentative definitions should be emitted as common symbols
+    common: bool,
+    // the tentative definitions to emit as common symbols, with their size and alignment
+    common_symbols: Vec<(DataId, SIZE_T, SIZE_T)>,
+    // symbols renamed with `__asm__("name")`
+    asm_labels: HashMap<InternedStr, InternedStr>,
+    // the GNU attributes of identifiers at file scope, like `section` and `weak`
+    object_attributes: HashMap<InternedStr, attribute::ObjectAttributes>,
+    // the contents of objects with a `section` attribute, to copy into that section
+    section_data: HashMap<DataId, attribute::SectionData>,
     error_handler: ErrorHandler,
 }
 
+// aggregates larger than this with constant initializers
+// are copied from static memory instead of stored one member at a time
+const MAX_INLINE_INIT: SIZE_T = 32;
+
+// the largest alignment Cranelift gives a stack slot
+const MAX_STACK_ALIGN: SIZE_T = 16;
+
 /// Compile a program from a high level IR to a Cranelift Module
 pub(crate) fn compile(
     program: Vec<Locatable<Declaration>>,
     debug: bool,
+    common: bool,
 ) -> (
     Result<ObjectProduct, CompileError>,
     VecDeque<CompileWarning>,
@@ -63,7 +113,39 @@ pub(crate) fn compile(
     );
     // really we'd like to have all errors but that requires a refactor
     let mut err = None;
-    let mut compiler = Compiler::new(name, debug);
+    let mut compiler = Compiler::new(name, debug, common);
+    // Cranelift requires every declaration of a function to have the same signature,
+    // but C allows `int f();` to be followed by `int f(int a) { ... }`
+    for decl in &program {
+        if let Some(label) = decl.data.asm_label {
+            compiler.asm_labels.insert(decl.data.symbol.id, label);
+        }
+        compiler.add_attributes(decl);
+        if let (Type::Function(ftype), Some(Initializer::FunctionBody(_))) =
+            (&decl.data.symbol.ctype, &decl.data.init)
+        {
+            let signature = ftype.signature(compiler.module.isa());
+            compiler.definitions.insert(decl.data.symbol.id, signature);
+        }
+    }
+    // if every declaration of a function is `inline` (without `extern`),
+    // its definition is an inline definition and doesn't provide an external definition
+    // (C11 6.7.4p7). These are compiled as local functions, and only if they're used.
+    let not_inline: HashSet<_> = program
+        .iter()
+        .filter(|decl| decl.data.symbol.ctype.is_function() && !decl.data.symbol.qualifiers.inline)
+        .map(|decl| decl.data.symbol.id)
+        .collect();
+    // the targets of `alias("name")` must be defined in this file
+    let defined: HashSet<_> = program
+        .iter()
+        .filter(|decl| {
+            let symbol = &decl.data.symbol;
+            decl.data.init.is_some()
+                || !symbol.ctype.is_function() && symbol.storage_class != StorageClass::Extern
+        })
+        .map(|decl| decl.data.symbol.id)
+        .collect();
     for decl in program {
         let current = match (decl.data.symbol.ctype.clone(), decl.data.init) {
             (Type::Function(func_type), None) => compiler
@@ -75,6 +157,15 @@ pub(crate) fn compile(
                 )
                 .map(|_| ()),
             (Type::Void, _) => unreachable!("parser let an incomplete type through"),
+            (Type::Function(func_type), Some(Initializer::FunctionBody(stmts)))
+                if !not_inline.contains(&decl.data.symbol.id) =>
+            {
+                let id = decl.data.symbol.id;
+                compiler
+                    .inline_functions
+                    .push((id, func_type, stmts, decl.location));
+                Ok(())
+            }
             (Type::Function(func_type), Some(Initializer::FunctionBody(stmts))) => compiler
                 .compile_func(
                     decl.data.symbol.id,
@@ -93,16 +184,32 @@ pub(crate) fn compile(
             break;
         }
     }
+    if err.is_none() {
+        err = compiler
+            .compile_inline_functions()
+            .and_then(|()| compiler.define_tentative())
+            .err();
+    }
     let warns = compiler.error_handler.warnings;
     if let Some(err) = err {
         (Err(err), warns)
     } else {
-        (Ok(compiler.module.finish()), warns)
+        let mut product = compiler.module.finish();
+        static_init::define_common(&mut product, &compiler.common_symbols);
+        let result = attribute::apply_attributes(
+            &mut product,
+            &compiler.object_attributes,
+            &compiler.section_data,
+            &compiler.scope,
+            &defined,
+        )
+        .map(|()| product);
+        (result, warns)
     }
 }
 
 impl Compiler {
-    fn new(name: String, debug: bool) -> Compiler {
+    fn new(name: String, debug: bool, common: bool) -> Compiler {
         let mut flags_builder = settings::builder();
         // allow creating shared libraries
         flags_builder
@@ -139,11 +246,25 @@ impl Compiler {
             loops: Vec::new(),
             switches: Vec::new(),
             labels: HashMap::new(),
+            label_addresses: Vec::new(),
+            indirect_goto: None,
+            templates: 0,
+            vlas: Vec::new(),
+            definitions: HashMap::new(),
+            inline_functions: Vec::new(),
+            used: HashSet::new(),
+            tentative: Vec::new(),
+            initialized: HashSet::new(),
+            common_symbols: Vec::new(),
             // the initial value doesn't really matter
             last_saw_loop: true,
             strings: Default::default(),
+            asm_labels: HashMap::new(),
+            object_attributes: HashMap::new(),
+            section_data: HashMap::new(),
             error_handler: Default::default(),
             debug,
+            common,
         }
     }
     // we have to consider the following cases:
@@ -175,13 +296,49 @@ impl Compiler {
             StorageClass::Static => Linkage::Local,
             StorageClass::Register | StorageClass::Typedef => unreachable!(),
         };
+        let signature = match self.definitions.get(&id) {
+            Some(definition) => definition,
+            None => signature,
+        };
+        let name = self.linkage_name(id);
         let func_id = self
             .module
-            .declare_function(get_str!(id), linkage, &signature)
+            .declare_function(get_str!(name), linkage, signature)
             .unwrap_or_else(|err| utils::fatal(err, 6));
         self.scope.insert(id, Id::Function(func_id));
         Ok(func_id)
     }
+    /// The name of `id` in the object file, which can be changed with `__asm__("name")`.
+    fn linkage_name(&self, id: InternedStr) -> InternedStr {
+        *self.asm_labels.get(&id).unwrap_or(&id)
+    }
+    /// Note that `id` was referenced, in case it's an inline function.
+    ///
+    /// Inline functions aren't declared until they're used,
+    /// so this also makes sure `id` is in scope.
+    fn use_function(&mut self, id: InternedStr) -> CompileResult<()> {
+        self.used.insert(id);
+        if self.scope.get(&id).is_none() {
+            if let Some((_, ftype, ..)) = self.inline_functions.iter().find(|f| f.0 == id) {
+                let signature = ftype.signature(self.module.isa());
+                self.declare_func(id, &signature, StorageClass::Static, true)?;
+            }
+        }
+        Ok(())
+    }
+    /// Compile the inline functions that were used.
+    /// Compiling one can use another, so keep going until there are no more.
+    fn compile_inline_functions(&mut self) -> CompileResult<()> {
+        while let Some(index) = self
+            .inline_functions
+            .iter()
+            .position(|(id, ..)| self.used.contains(id))
+        {
+            let (id, ftype, stmts, location) = self.inline_functions.remove(index);
+            self.compile_func(id, ftype, StorageClass::Static, stmts, location)?;
+        }
+        Ok(())
+    }
     /// declare an object on the stack
     fn declare_stack(
         &mut self,
@@ -189,6 +346,9 @@ impl Compiler {
         location: Location,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
+        if let Some(label) = decl.asm_label {
+            self.asm_labels.insert(decl.symbol.id, label);
+        }
         if let Type::Function(ftype) = decl.symbol.ctype {
             self.declare_func(
                 decl.symbol.id,
@@ -198,6 +358,16 @@ impl Compiler {
             )?;
             return Ok(());
         }
+        if decl.symbol.storage_class == StorageClass::Extern {
+            // refers to the same object as the declaration at file scope, which may be in another file
+            return self.store_static(decl.symbol, None, location);
+        }
+        if decl.symbol.qualifiers.thread_local {
+            semantic_err!(thread_local_unsupported(&decl.symbol), location);
+        }
+        if decl.symbol.ctype.is_vla() {
+            return self.declare_vla(decl.symbol, location, builder);
+        }
         let u64_size = match decl.symbol.ctype.sizeof() {
             Ok(size) => size,
             Err(err) => {
@@ -207,6 +377,19 @@ impl Compiler {
                 }))
             }
         };
+        // Cranelift aligns a stack slot to the largest power of two (up to 16) that divides its size,
+        // so a slot that needs more alignment than its type gets padded
+        let u64_size = match decl.symbol.qualifiers.align {
+            Some(align) if align > MAX_STACK_ALIGN => semantic_err!(
+                format!(
+                    "cannot align local variable '{}' to more than {} bytes",
+                    decl.symbol.id, MAX_STACK_ALIGN
+                ),
+                location,
+            ),
+            Some(align) => (u64_size + align - 1) / align * align,
+            None => u64_size,
+        };
         let kind = StackSlotKind::ExplicitSlot;
         let size = match u32::try_from(u64_size) {
             Ok(size) => size,
@@ -223,26 +406,259 @@ impl Compiler {
         let stack_slot = builder.create_stack_slot(data);
         self.scope.insert(decl.symbol.id, Id::Local(stack_slot));
         if let Some(init) = decl.init {
-            self.store_stack(init, stack_slot, builder)?;
+            self.store_stack(init, &decl.symbol.ctype, stack_slot, location, builder)?;
         }
         Ok(())
     }
+    /// Cranelift cannot adjust the stack at runtime, so variable length arrays live on the heap.
+    ///
+    /// The allocation is reused each time the declaration is reached
+    /// and freed when the function returns.
+    fn declare_vla(
+        &mut self,
+        symbol: Symbol,
+        location: Location,
+        builder: &mut FunctionBuilder,
+    ) -> CompileResult<()> {
+        let ptr_type = Type::ptr_type();
+        let slot = builder.create_stack_slot(StackSlotData {
+            kind: StackSlotKind::ExplicitSlot,
+            size: PTR_SIZE.into(),
+            offset: None,
+        });
+        // the slot has to be null the first time `realloc` sees it,
+        // even if the declaration is jumped over
+        let entry = builder
+            .func
+            .layout
+            .entry_block()
+            .expect("should be compiling a function");
+        let mut cursor = FuncCursor::new(builder.func).at_first_insertion_point(entry);
+        let null = cursor.ins().iconst(ptr_type, 0);
+        cursor.ins().stack_store(null, slot, 0);
+
+        let size = self.vla_sizeof(&symbol.ctype, location, builder)?;
+        let previous = builder.ins().stack_load(ptr_type, slot, 0);
+        let realloc = self.declare_libc("realloc", &[ptr_type, ptr_type], &[ptr_type]);
+        let realloc = self.module.declare_func_in_func(realloc, builder.func);
+        let call = builder.ins().call(realloc, &[previous, size]);
+        let array = builder.inst_results(call)[0];
+        // running out of memory for a VLA is the same as overflowing the stack
+        builder.ins().trapz(array, TrapCode::StackOverflow);
+        builder.ins().stack_store(array, slot, 0);
+        self.vlas.push(slot);
+        self.scope.insert(symbol.id, Id::Vla(slot));
+        Ok(())
+    }
+    /// Free every variable length array in `func` before each return.
+    fn free_vlas(&mut self, func: &mut Function) {
+        if self.vlas.is_empty() {
+            return;
+        }
+        let ptr_type = Type::ptr_type();
+        let free = self.declare_libc("free", &[ptr_type], &[]);
+        let free = self.module.declare_func_in_func(free, func);
+        let mut cursor = FuncCursor::new(func);
+        while cursor.next_ebb().is_some() {
+            while let Some(inst) = cursor.next_inst() {
+                if cursor.func.dfg[inst].opcode().is_return() {
+                    // inserted before the return
+                    for &slot in &self.vlas {
+                        let array = cursor.ins().stack_load(ptr_type, slot, 0);
+                        cursor.ins().call(free, &[array]);
+                    }
+                }
+            }
+        }
+        self.vlas.clear();
+    }
+    /// Declare a function from the C standard library that the program may not have declared.
+    fn declare_libc(&mut self, name: &str, params: &[IrType], ret: &[IrType]) -> FuncId {
+        let mut signature = Signature::new(*CALLING_CONVENTION);
+        signature.params = params.iter().map(|&param| AbiParam::new(param)).collect();
+        signature.returns = ret.iter().map(|&ret| AbiParam::new(ret)).collect();
+        self.module
+            .declare_function(name, Linkage::Import, &signature)
+            .unwrap_or_else(|err| utils::fatal(err, 6))
+    }
     fn store_stack(
         &mut self,
         init: Initializer,
+        ctype: &Type,
         stack_slot: StackSlot,
+        location: Location,
+        builder: &mut FunctionBuilder,
+    ) -> CompileResult<()> {
+        // aggregates are initialized one member at a time relative to this address,
+        // so `stack_store` can't be used even for scalars
+        let addr = builder.ins().stack_addr(Type::ptr_type(), stack_slot, 0);
+        if let Initializer::InitializerList(_) = init {
+            let size = ctype
+                .sizeof()
+                .map_err(|err| location.with(err.to_string()))?;
+            let align = ctype
+                .alignof()
+                .expect("if sizeof() succeeds so should alignof()")
+                .try_into()
+                .expect("align should never be more than 255 bytes");
+            if size > MAX_INLINE_INIT && is_constant_init(&init, ctype) {
+                let template = self.static_template(init, ctype, location)?;
+                let global = self.module.declare_data_in_func(template, builder.func);
+                let template_addr = builder.ins().global_value(Type::ptr_type(), global);
+                builder.emit_small_memcpy(
+                    self.module.target_config(),
+                    addr,
+                    template_addr,
+                    size,
+                    align,
+                    align,
+                );
+                return Ok(());
+            }
+            // members without an initializer are initialized the same as static objects
+            // (C11 6.7.9p21)
+            builder.emit_small_memset(self.module.target_config(), addr, 0, size, align);
+        }
+        self.store_stack_at(init, ctype, addr, 0, &location, builder)
+    }
+    /// Store `init` at `base + offset`, assuming the memory has already been zeroed.
+    fn store_stack_at(
+        &mut self,
+        init: Initializer,
+        ctype: &Type,
+        base: IrValue,
+        offset: i32,
+        location: &Location,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
-        match init {
-            Initializer::Scalar(expr) => {
-                let val = self.compile_expr(*expr, builder)?;
-                // TODO: replace with `builder.ins().stack_store(val.ir_val, stack_slot, 0);`
-                // when Cranelift implements stack_store for i8 and i16
-                let addr = builder.ins().stack_addr(Type::ptr_type(), stack_slot, 0);
-                builder.ins().store(MemFlags::new(), val.ir_val, addr, 0);
+        // atomic objects are initialized the same way as non-atomic ones
+        let ctype = ctype.strip_atomic();
+        let expr = match init {
+            Initializer::Scalar(expr) => *expr,
+            Initializer::InitializerList(mut list) => {
+                match ctype {
+                    Type::Array(inner, ArrayType::Fixed(len)) => {
+                        if list.len() as u64 > *len {
+                            semantic_err!(
+                                format!(
+                                    "too many elements for array (expected {}, got {})",
+                                    len,
+                                    list.len()
+                                ),
+                                *location
+                            );
+                        }
+                        let inner_size = inner
+                            .sizeof()
+                            .map_err(|err| location.with(err.to_string()))?;
+                        for (i, init) in list.into_iter().enumerate() {
+                            let element_offset = offset + (i as u64 * inner_size) as i32;
+                            self.store_stack_at(
+                                init,
+                                inner,
+                                base,
+                                element_offset,
+                                location,
+                                builder,
+                            )?;
+                        }
+                    }
+                    Type::Struct(struct_type) | Type::Union(struct_type) => {
+                        // for unions, the parser only allows initializing the first member
+                        for (member, init) in
+                            struct_type.initialized_members().into_iter().zip(list)
+                        {
+                            let member_offset = ctype
+                                .member_offset(member.id)
+                                .expect("only structs and unions have members")
+                                as i32;
+                            if let Some(layout) = ctype.bitfield(member.id) {
+                                let mut init = init;
+                                // the initializer for a scalar may be enclosed in braces
+                                while let Initializer::InitializerList(mut list) = init {
+                                    init = list.remove(0);
+                                }
+                                let expr = match init {
+                                    Initializer::Scalar(expr) => *expr,
+                                    _ => unreachable!("bitfields should have scalar initializers"),
+                                };
+                                let value = self.compile_expr(expr, builder)?;
+                                let addr = builder
+                                    .ins()
+                                    .iadd_imm(base, i64::from(offset + member_offset));
+                                let unit = Self::load_bitfield_unit(addr, &layout, builder);
+                                Self::store_bitfield(unit, value.ir_val, addr, &layout, builder);
+                                continue;
+                            }
+                            self.store_stack_at(
+                                init,
+                                &member.ctype,
+                                base,
+                                offset + member_offset,
+                                location,
+                                builder,
+                            )?;
+                        }
+                    }
+                    ty if ty.is_scalar() => {
+                        assert_eq!(list.len(), 1);
+                        self.store_stack_at(
+                            list.remove(0),
+                            ctype,
+                            base,
+                            offset,
+                            location,
+                            builder,
+                        )?;
+                    }
+                    Type::Array(_, ArrayType::Unbounded) => semantic_err!(
+                        "nested array must declare the size of each inner array".into(),
+                        *location
+                    ),
+                    _ => unreachable!("parser should only allow initializer lists for objects"),
+                }
+                return Ok(());
             }
-            Initializer::InitializerList(_) => unimplemented!("aggregate dynamic initialization"),
             Initializer::FunctionBody(_) => unreachable!("functions can't be stored on the stack"),
+        };
+        match ctype {
+            Type::Array(_, _) | Type::Struct(_) | Type::Union(_) | Type::Complex(_) => {
+                // a string literal initializing a character array,
+                // or a struct or complex number initialized by another of the same type
+                let align = ctype
+                    .alignof()
+                    .expect("if sizeof() succeeds so should alignof()")
+                    .try_into()
+                    .expect("align should never be more than 255 bytes");
+                let (size, src_align) = match (&expr.expr, ctype) {
+                    (ExprType::Literal(Literal::Str(string)), Type::Array(_, _)) => {
+                        let len = string.len() as u64;
+                        (std::cmp::min(len, ctype.sizeof().unwrap_or(len)), 1)
+                    }
+                    _ => (
+                        ctype
+                            .sizeof()
+                            .map_err(|err| location.with(err.to_string()))?,
+                        align,
+                    ),
+                };
+                let src = self.compile_expr(expr, builder)?;
+                let dst = builder.ins().iadd_imm(base, i64::from(offset));
+                builder.emit_small_memcpy(
+                    self.module.target_config(),
+                    dst,
+                    src.ir_val,
+                    size,
+                    align,
+                    src_align,
+                );
+            }
+            _ => {
+                let val = self.compile_expr(expr, builder)?;
+                builder
+                    .ins()
+                    .store(MemFlags::new(), val.ir_val, base, offset);
+            }
         }
         Ok(())
     }
@@ -250,20 +666,25 @@ impl Compiler {
     // there's an easier way to make parameters modifiable.
     fn store_stack_params(
         &mut self,
-        params: Vec<Symbol>,
+        func_type: &FunctionType,
         func_start: Ebb,
         location: &Location,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
+        let passed: Vec<_> = func_type.passed_types().collect();
         // Cranelift requires that all EBB params are declared up front
-        let ir_vals: Vec<_> = params
+        let ir_vals: Vec<Vec<_>> = passed
             .iter()
-            .map(|param| {
-                let ir_type = param.ctype.as_ir_type();
-                Ok(builder.append_ebb_param(func_start, ir_type))
+            .map(|ctype| {
+                ctype
+                    .abi_types()
+                    .into_iter()
+                    .map(|ir_type| builder.append_ebb_param(func_start, ir_type))
+                    .collect()
             })
-            .collect::<CompileResult<_>>()?;
-        for (param, ir_val) in params.into_iter().zip(ir_vals) {
+            .collect();
+        let params = func_type.params.iter().zip(passed);
+        for ((param, passed), ir_vals) in params.zip(ir_vals) {
             let u64_size = match param.ctype.sizeof() {
                 Err(data) => semantic_err!(data.into(), *location),
                 Ok(size) => size,
@@ -289,11 +710,63 @@ impl Compiler {
             // then this can be replaced with `builder.ins().stack_store(ir_val, slot, 0);`
             // See https://github.com/CraneStation/cranelift/issues/433
             let addr = builder.ins().stack_addr(Type::ptr_type(), slot, 0);
-            builder.ins().store(MemFlags::new(), ir_val, addr, 0);
+            if let Type::Complex(inner) = &param.ctype {
+                // the parts arrive in registers and have to be put back in memory
+                Self::store_complex_abi(&ir_vals, inner, addr, builder);
+            } else {
+                // parameters of old-style definitions arrive promoted, e.g. `char` as `int`
+                let val = Self::cast_ir(
+                    passed.as_ir_type(),
+                    param.ctype.as_ir_type(),
+                    ir_vals[0],
+                    passed.is_signed(),
+                    param.ctype.is_signed(),
+                    builder,
+                );
+                builder.ins().store(MemFlags::new(), val, addr, 0);
+            }
             self.scope.insert(param.id, Id::Local(slot));
         }
         Ok(())
     }
+    /// Whether the current block can't be reached from the start of the function,
+    /// e.g. after a call to a `_Noreturn` function or an infinite loop.
+    ///
+    /// Blocks are only sealed at the end of the function, so `FunctionBuilder::is_unreachable`
+    /// can't tell yet. Instead, this follows the branches from the entry block
+    /// and from any label whose address was taken, since it can be reached with a computed goto.
+    /// It should only be called if an instruction is going to be added to the block.
+    fn is_unreachable(&self, builder: &mut FunctionBuilder) -> bool {
+        use cranelift::codegen::ir::instructions::BranchInfo;
+        let current = match builder.cursor().current_ebb() {
+            Some(ebb) => ebb,
+            None => return false,
+        };
+        let func = &builder.func;
+        let mut pending: Vec<_> = func.layout.entry_block().into_iter().collect();
+        pending.extend(
+            self.label_addresses
+                .iter()
+                .filter_map(|label| self.labels.get(label).copied()),
+        );
+        let mut reachable = HashSet::new();
+        while let Some(ebb) = pending.pop() {
+            if !reachable.insert(ebb) {
+                continue;
+            }
+            for inst in func.layout.ebb_insts(ebb) {
+                match func.dfg[inst].analyze_branch(&func.dfg.value_lists) {
+                    BranchInfo::NotABranch => {}
+                    BranchInfo::SingleDest(dest, _) => pending.push(dest),
+                    BranchInfo::Table(table, default) => {
+                        pending.extend(default);
+                        pending.extend(func.jump_tables[table].iter().copied());
+                    }
+                }
+            }
+        }
+        !reachable.contains(&current)
+    }
     fn compile_func(
         &mut self,
         id: InternedStr,
@@ -302,6 +775,14 @@ impl Compiler {
         stmts: Vec<Stmt>,
         location: Location,
     ) -> CompileResult<()> {
+        if is_long_double(&func_type.return_type)
+            || func_type
+                .params
+                .iter()
+                .any(|param| is_long_double(&param.ctype))
+        {
+            semantic_err!(long_double_unsupported(), location);
+        }
         let signature = func_type.signature(self.module.isa());
         let func_id = self.declare_func(id.clone(), &signature, sc, true)?;
         // external name is meant to be a lookup in a symbol table,
@@ -316,12 +797,26 @@ impl Compiler {
         builder.switch_to_block(func_start);
 
         let should_ret = func_type.should_return();
+        // labels have function scope
+        self.labels.clear();
+        self.label_addresses.clear();
+        // parameters are in the same scope as the outermost block of the function
+        self.scope.enter_scope();
         if func_type.has_params() {
-            self.store_stack_params(func_type.params, func_start, &location, &mut builder)?;
+            self.store_stack_params(&func_type, func_start, &location, &mut builder)?;
         }
         self.compile_all(stmts, &mut builder)?;
+        self.scope.leave_scope();
+        let unreachable = !builder.is_filled() && self.is_unreachable(&mut builder);
+        if func_type.noreturn && !builder.is_filled() && !unreachable {
+            self.error_handler
+                .warn(Warning::InvalidNoreturn(id, "can return"), location);
+        }
         if !builder.is_filled() {
-            if id == InternedStr::get_or_intern("main") {
+            if unreachable {
+                // e.g. the function ends with a call to a `_Noreturn` function
+                builder.ins().trap(TrapCode::UnreachableCodeReached);
+            } else if id == InternedStr::get_or_intern("main") {
                 let ir_int = func_type.return_type.as_ir_type();
                 let zero = [builder.ins().iconst(ir_int, 0)];
                 builder.ins().return_(&zero);
@@ -338,7 +833,11 @@ impl Compiler {
                 builder.ins().return_(&[]);
             }
         }
+        if let Some(dispatch) = self.indirect_goto.take() {
+            self.indirect_goto_dispatch(dispatch, &mut builder);
+        }
         builder.seal_all_blocks();
+        self.free_vlas(builder.func);
         builder.finalize();
 
         let flags = settings::Flags::new(settings::builder());
@@ -361,3 +860,61 @@ impl Compiler {
         Ok(())
     }
 }
+
+/// Cranelift 0.56 can neither put data in a TLS section nor emit the TLS access sequence,
+/// so thread-local variables are rejected instead of silently becoming normal globals.
+fn thread_local_unsupported(symbol: &Symbol) -> String {
+    format!(
+        "cannot compile thread-local variable '{}': \
+         the Cranelift backend does not support thread-local storage",
+        symbol.id
+    )
+}
+
+/// Cranelift 0.56 has no 80-bit floating point type and no way to pass one in memory,
+/// so `long double` can be declared and stored but not computed with.
+///
+/// Lowering it to `double` would silently change the results and break the ABI
+/// with libc functions like `sqrtl`, and unlike `__float128` there are no
+/// software routines in libgcc to call instead.
+/// See IMPLEMENTATION_DEFINED.md for what is supported.
+fn long_double_unsupported() -> String {
+    "cannot compile 'long double' values: \
+     the Cranelift backend does not support x87 extended precision \
+     ('long double' objects can only be declared, statically initialized and have their address taken)"
+        .to_string()
+}
+
+/// Whether `ctype` is `long double` or `long double _Complex`, which can't be computed with.
+fn is_long_double(ctype: &Type) -> bool {
+    match ctype {
+        Type::LongDouble => true,
+        Type::Complex(inner) => **inner == Type::LongDouble,
+        _ => false,
+    }
+}
+
+/// Whether `init` can be computed entirely at compile time,
+/// so that it can be stored in static memory.
+fn is_constant_init(init: &Initializer, ctype: &Type) -> bool {
+    if let Type::Bitfield(bitfield) = ctype {
+        return is_constant_init(init, &bitfield.ctype);
+    }
+    match init {
+        Initializer::Scalar(expr) => match (&expr.expr, ctype) {
+            (ExprType::Literal(Literal::Str(_)), Type::Array(_, _)) => true,
+            // pointers need relocations, which are more trouble than they're worth
+            _ => expr.constexpr && ctype.is_arithmetic(),
+        },
+        Initializer::InitializerList(list) => match ctype {
+            Type::Array(inner, _) => list.iter().all(|init| is_constant_init(init, inner)),
+            Type::Struct(struct_type) | Type::Union(struct_type) => list
+                .iter()
+                .zip(struct_type.initialized_members().iter())
+                .all(|(init, member)| is_constant_init(init, &member.ctype)),
+            ty if ty.is_scalar() => list.len() == 1 && is_constant_init(&list[0], ty),
+            _ => false,
+        },
+        Initializer::FunctionBody(_) => false,
+    }
+}
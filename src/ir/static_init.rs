@@ -5,9 +5,11 @@ use std::convert::{TryFrom, TryInto};
 
 use cranelift::codegen::ir::types;
 use cranelift_module::{DataContext, DataId, Linkage};
+use cranelift_object::ObjectProduct;
+use object::write::SymbolSection;
 
 use super::{Compiler, Id};
-use crate::arch::{PTR_SIZE, TARGET};
+use crate::arch::{BitfieldLayout, PTR_SIZE, SIZE_T, TARGET};
 use crate::data::prelude::*;
 use crate::data::{lex::Literal, types::ArrayType, Initializer, StorageClass};
 
@@ -17,7 +19,7 @@ const ZERO_PTR: [u8; PTR_SIZE as usize] = [0; PTR_SIZE as usize];
 impl Compiler {
     pub(crate) fn store_static(
         &mut self,
-        mut symbol: Symbol,
+        symbol: Symbol,
         init: Option<Initializer>,
         location: Location,
     ) -> CompileResult<()> {
@@ -27,8 +29,22 @@ impl Compiler {
             location,
         };
         let linkage = symbol.storage_class.try_into().map_err(err_closure)?;
+        if symbol.qualifiers.thread_local {
+            // declaring a thread-local variable defined elsewhere is fine, e.g. in a header,
+            // only defining or using one is an error
+            if linkage == Linkage::Import && init.is_none() {
+                return Ok(());
+            }
+            semantic_err!(super::thread_local_unsupported(&symbol), location);
+        }
+        // functions refer to other data in this file by its offset in `.data`,
+        // so objects that will be moved to another section have to be referred to by name
+        let linkage = if linkage != Linkage::Import && self.has_section(symbol.id) {
+            Linkage::Preemptible
+        } else {
+            linkage
+        };
         let align = symbol
-            .ctype
             .alignof()
             .map_err(|err| err.to_string())
             .and_then(|size| {
@@ -36,18 +52,13 @@ impl Compiler {
                     .map_err(|_| format!("align of {} is greater than 256 bytes", symbol.id))
             })
             .map_err(err_closure)?;
-        if align == 0 {
-            // struct that was declared but never used
-            return Ok(());
-        }
+        // `extern struct s my_s;` where `struct s` is never completed:
+        // the alignment is unknown, but the address can still be taken
+        let align = if align == 0 { None } else { Some(align) };
+        let name = self.linkage_name(symbol.id);
         let id = self
             .module
-            .declare_data(
-                get_str!(symbol.id),
-                linkage,
-                !symbol.qualifiers.c_const,
-                Some(align),
-            )
+            .declare_data(get_str!(name), linkage, !symbol.qualifiers.c_const, align)
             .map_err(|err| Locatable {
                 data: format!("error storing static value: {}", err),
                 location,
@@ -60,46 +71,128 @@ impl Compiler {
             return Ok(());
         }
 
-        let mut ctx = DataContext::new();
-        if let Some(init) = init {
-            if let Type::Array(_, size @ ArrayType::Unbounded) = &mut symbol.ctype {
-                if let Some(len) = match &init {
-                    Initializer::InitializerList(list) => Some(list.len()),
-                    Initializer::Scalar(expr) => match &expr.expr {
-                        ExprType::Literal(Literal::Str(s)) => Some(s.len()),
-                        _ => None,
-                    },
-                    _ => None,
-                } {
-                    *size = ArrayType::Fixed(len.try_into().unwrap());
-                };
+        let init = match init {
+            Some(init) => init,
+            // a tentative definition (C11 6.9.2):
+            // there may still be a definition with an initializer later in the file.
+            // keep the latest declaration, since it may have completed the type.
+            None if self.initialized.contains(&id) => return Ok(()),
+            None => {
+                match self.tentative.iter_mut().find(|(old, ..)| *old == id) {
+                    Some(existing) => *existing = (id, symbol, location),
+                    None => self.tentative.push((id, symbol, location)),
+                }
+                return Ok(());
             }
-            let size_t = symbol.ctype.sizeof().map_err(|err| Locatable {
+        };
+        self.tentative.retain(|(old, ..)| *old != id);
+        self.initialized.insert(id);
+
+        let mut ctx = DataContext::new();
+        let size_t = symbol.ctype.sizeof().map_err(|err| Locatable {
+            data: err.to_string(),
+            location,
+        })?;
+        let size = size_t
+            .try_into()
+            .expect("initializer is larger than SIZE_T on host platform");
+        let mut buf = vec![0; size];
+        let offset = 0;
+        self.init_symbol(&mut ctx, &mut buf, offset, init, &symbol.ctype, &location)?;
+        ctx.define(buf.into_boxed_slice());
+        self.define_data(id, &ctx, location)?;
+        self.record_section_data(symbol.id, id, &ctx, u64::from(align.unwrap_or(1)));
+        Ok(())
+    }
+    /// Zero-initialize the tentative definitions that were never given an initializer.
+    ///
+    /// This should be called once, at the end of the translation unit (C11 6.9.2p2).
+    pub(crate) fn define_tentative(&mut self) -> CompileResult<()> {
+        for (id, symbol, location) in std::mem::take(&mut self.tentative) {
+            let align = symbol.alignof();
+            let ctype = match symbol.ctype {
+                // `int a[];` with no later declaration giving the size
+                Type::Array(inner, ArrayType::Unbounded) => {
+                    self.error_handler
+                        .warn(Warning::TentativeArray(symbol.id), location);
+                    *inner
+                }
+                ctype => ctype,
+            };
+            let size = ctype.sizeof().map_err(|err| Locatable {
                 data: err.to_string(),
                 location,
             })?;
-            let size = size_t
-                .try_into()
-                .expect("initializer is larger than SIZE_T on host platform");
-            let mut buf = vec![0; size];
-            let offset = 0;
-            self.init_symbol(&mut ctx, &mut buf, offset, init, &symbol.ctype, &location)?;
-            ctx.define(buf.into_boxed_slice());
-        } else {
-            ctx.define_zeroinit(
-                symbol
-                    .ctype
-                    .sizeof()
-                    .map_err(|err| err_closure(err.to_string()))? as usize,
-            );
-        };
-        self.module.define_data(id, &ctx).map_err(|err| {
+            // under `-fcommon`, the linker merges tentative definitions from different files.
+            // Cranelift can't emit COMMON symbols, so leave it undefined and fix it up later.
+            if self.common
+                && symbol.storage_class != StorageClass::Static
+                && !self.has_section(symbol.id)
+            {
+                let align = align.map_err(|err| Locatable {
+                    data: err.to_string(),
+                    location,
+                })?;
+                self.common_symbols.push((id, size, align));
+                continue;
+            }
+            let mut ctx = DataContext::new();
+            ctx.define_zeroinit(size as usize);
+            self.define_data(id, &ctx, location)?;
+            self.record_section_data(symbol.id, id, &ctx, align.unwrap_or(1));
+        }
+        Ok(())
+    }
+    fn define_data(
+        &mut self,
+        id: DataId,
+        ctx: &DataContext,
+        location: Location,
+    ) -> CompileResult<()> {
+        self.module.define_data(id, ctx).map_err(|err| {
             CompileError::semantic(Locatable {
                 data: format!("error defining static variable: {}", err),
                 location,
             })
         })
     }
+    /// Store a constant initializer for an automatic variable in read-only memory,
+    /// so that it can be copied onto the stack all at once.
+    pub(crate) fn static_template(
+        &mut self,
+        init: Initializer,
+        ctype: &Type,
+        location: Location,
+    ) -> CompileResult<DataId> {
+        let name = format!("init.{}", self.templates);
+        self.templates += 1;
+        let align = ctype
+            .alignof()
+            .map_err(|err| location.with(err.to_string()))?
+            .try_into()
+            .expect("align should never be more than 255 bytes");
+        let id = match self
+            .module
+            .declare_data(&name, Linkage::Local, false, Some(align))
+        {
+            Ok(id) => id,
+            Err(err) => semantic_err!(format!("error declaring initializer: {}", err), location),
+        };
+        let size = ctype
+            .sizeof()
+            .map_err(|err| location.with(err.to_string()))?
+            .try_into()
+            .expect("initializer is larger than SIZE_T on host platform");
+        let mut ctx = DataContext::new();
+        let mut buf = vec![0; size];
+        self.init_symbol(&mut ctx, &mut buf, 0, init, ctype, &location)?;
+        ctx.define(buf.into_boxed_slice());
+        self.module.define_data(id, &ctx).map_err(|err| Locatable {
+            data: format!("error defining initializer: {}", err),
+            location,
+        })?;
+        Ok(id)
+    }
     pub(crate) fn compile_string(
         &mut self,
         string: InternedStr,
@@ -132,8 +225,8 @@ impl Compiler {
         let expr = expr.const_fold()?;
         // static address-of
         match expr.expr {
-            ExprType::StaticRef(inner) => match inner.expr {
-                ExprType::Id(symbol) => self.static_ref(symbol, 0, offset, ctx),
+            ExprType::StaticRef(inner) => match strip_pointer_casts(*inner).expr {
+                ExprType::Id(symbol) => self.static_ref(symbol, 0, offset, expr.location, ctx)?,
                 ExprType::Literal(Literal::Str(str_ref)) => {
                     let str_id = self.compile_string(str_ref, expr.location)?;
                     let str_addr = self.module.declare_data_in_data(str_id, ctx);
@@ -141,18 +234,29 @@ impl Compiler {
                 }
                 ExprType::Literal(ref token) if token.is_zero() => buf.copy_from_slice(&ZERO_PTR),
                 ExprType::Cast(ref inner) if inner.is_zero() => buf.copy_from_slice(&ZERO_PTR),
-                ExprType::Member(struct_expr, member) => {
-                    let member_offset = struct_expr
-                        .ctype
-                        .member_offset(member)
-                        .expect("parser shouldn't allow Member for non-struct types");
-                    if let ExprType::Id(symbol) = struct_expr.expr {
-                        self.static_ref(symbol, member_offset.try_into().unwrap(), offset, ctx);
-                    } else {
-                        semantic_err!(
-                            "expression is not a compile time constant".into(),
-                            struct_expr.location
-                        );
+                ExprType::Member(mut struct_expr, mut member) => {
+                    let mut member_offset = 0;
+                    // nested members, e.g. `&s.inner.x`, or a member of an anonymous union
+                    loop {
+                        member_offset += struct_expr
+                            .ctype
+                            .member_offset(member)
+                            .expect("parser shouldn't allow Member for non-struct types");
+                        match struct_expr.expr {
+                            ExprType::Member(inner, inner_member) => {
+                                struct_expr = inner;
+                                member = inner_member;
+                            }
+                            ExprType::Id(symbol) => {
+                                let member_offset = member_offset.try_into().unwrap();
+                                self.static_ref(symbol, member_offset, offset, expr.location, ctx)?;
+                                break;
+                            }
+                            _ => semantic_err!(
+                                "expression is not a compile time constant".into(),
+                                struct_expr.location
+                            ),
+                        }
                     }
                 }
                 _ => semantic_err!("cannot take the address of an rvalue".into(), expr.location),
@@ -160,7 +264,24 @@ impl Compiler {
             ExprType::Literal(token) => {
                 let bytes =
                     token.into_bytes(&expr.ctype, &expr.location, &mut self.error_handler)?;
-                buf.copy_from_slice(&bytes);
+                // a string literal may be shorter than the array it initializes,
+                // the rest of the array is left zeroed
+                let len = std::cmp::min(buf.len(), bytes.len());
+                buf[..len].copy_from_slice(&bytes[..len]);
+            }
+            // a real constant converted to a complex type, the imaginary part is left zeroed
+            ExprType::Cast(inner) if expr.ctype.is_complex() && !inner.ctype.is_complex() => {
+                let real_type = match expr.ctype {
+                    Type::Complex(real_type) => *real_type,
+                    _ => unreachable!(),
+                };
+                let half = buf.len() / 2;
+                let real = Expr {
+                    expr: ExprType::Cast(inner),
+                    ctype: real_type,
+                    ..expr
+                };
+                self.init_expr(ctx, &mut buf[..half], offset, real)?;
             }
             _ => semantic_err!(
                 "expression is not a compile time constant".into(),
@@ -169,7 +290,18 @@ impl Compiler {
         }
         Ok(())
     }
-    fn static_ref(&self, symbol: Symbol, member_offset: i64, offset: u32, ctx: &mut DataContext) {
+    fn static_ref(
+        &mut self,
+        symbol: Symbol,
+        member_offset: i64,
+        offset: u32,
+        location: Location,
+        ctx: &mut DataContext,
+    ) -> CompileResult<()> {
+        if symbol.qualifiers.thread_local {
+            semantic_err!(super::thread_local_unsupported(&symbol), location);
+        }
+        self.use_function(symbol.id)?;
         match self.scope.get(&symbol.id) {
             Some(Id::Function(func_id)) => {
                 let func_ref = self.module.declare_func_in_data(*func_id, ctx);
@@ -180,19 +312,24 @@ impl Compiler {
                 let global_val = self.module.declare_data_in_data(*data_id, ctx);
                 ctx.write_data_addr(offset, global_val, member_offset);
             }
-            Some(Id::Local(_)) => unreachable!("cannot have local variable at global scope"),
+            Some(Id::Local(_)) | Some(Id::Vla(_)) => {
+                unreachable!("cannot have local variable at global scope")
+            }
             None => unreachable!("parser should catch undeclared variables"),
         }
+        Ok(())
     }
     fn init_symbol(
         &mut self,
         ctx: &mut DataContext,
         buf: &mut [u8],
-        mut offset: u32,
+        offset: u32,
         initializer: Initializer,
         ctype: &Type,
         location: &Location,
     ) -> CompileResult<()> {
+        // atomic objects are initialized the same way as non-atomic ones
+        let ctype = ctype.strip_atomic();
         match initializer {
             Initializer::InitializerList(mut initializers) => match ctype {
                 Type::Array(ty, ArrayType::Unbounded) => {
@@ -217,36 +354,36 @@ impl Compiler {
                     assert_eq!(initializers.len(), 1);
                     self.init_symbol(ctx, buf, offset, initializers.remove(0), ctype, location)
                 }
-                Type::Union(struct_type) => self.init_symbol(
-                    ctx,
-                    buf,
-                    offset,
-                    initializers.remove(0),
-                    &struct_type.members().first().unwrap().ctype,
-                    location,
-                ),
-                Type::Struct(struct_ref) => {
-                    let mut current_offset = 0;
-                    for (member, init) in struct_ref.members().iter().zip(initializers.into_iter())
+                Type::Struct(struct_type) | Type::Union(struct_type) => {
+                    // for unions, the parser only allows initializing the first member
+                    for (member, init) in struct_type
+                        .initialized_members()
+                        .into_iter()
+                        .zip(initializers.into_iter())
                     {
+                        let member_offset: usize = ctype
+                            .member_offset(member.id)
+                            .expect("only structs and unions have members")
+                            .try_into()
+                            .expect("cannot initialize struct larger than host address space");
                         let size_host: usize = member
                             .ctype
                             .sizeof()
                             .map_err(|err| CompileError::semantic(location.with(err.to_string())))?
                             .try_into()
-                            .expect("cannot initialize struct larger than u32");
-                        let size: u32 = size_host
-                            .try_into()
-                            .expect("cannot intialize struct larger than host address space");
-                        let buf_slice = &mut buf[current_offset..current_offset + size_host];
+                            .expect("cannot initialize struct larger than host address space");
+                        let buf_slice = &mut buf[member_offset..member_offset + size_host];
+                        if let Some(layout) = ctype.bitfield(member.id) {
+                            init_bitfield(buf_slice, &layout, init)?;
+                            continue;
+                        }
+                        let offset = offset
+                            + u32::try_from(member_offset)
+                                .expect("cannot initialize struct larger than u32");
                         self.init_symbol(ctx, buf_slice, offset, init, &member.ctype, location)?;
-                        offset += size;
-                        current_offset += size_host;
                     }
                     Ok(())
                 }
-                Type::Bitfield(_) => unimplemented!("bitfield initalizers"),
-
                 Type::Function(_) => unreachable!("function initializers"),
                 Type::Void => unreachable!("initializer for void type"),
                 _ => unreachable!("scalar types should have been handled"),
@@ -305,10 +442,10 @@ macro_rules! cast {
         let cast = $i as $to;
         if cast as $from != $i {
             $handler.warn(
-                &format!(
+                Warning::LossyConversion(format!(
                     "conversion to {} loses precision ({} != {})",
                     $ctype, cast as $from, $i
-                ),
+                )),
                 $location,
             )
         }
@@ -327,6 +464,79 @@ macro_rules! bytes {
     }};
 }
 
+/// Turn the tentative definitions left undefined by `define_tentative` into COMMON symbols.
+pub(super) fn define_common(product: &mut ObjectProduct, symbols: &[(DataId, SIZE_T, SIZE_T)]) {
+    for &(id, size, align) in symbols {
+        let symbol = product.data_symbol(id);
+        let symbol = product.object.symbol_mut(symbol);
+        symbol.section = SymbolSection::Common;
+        symbol.size = size;
+        // for ELF, the value of a COMMON symbol is its alignment
+        symbol.value = align;
+    }
+}
+
+/// Remove casts between pointer types, which don't change the address.
+///
+/// `const char *s = "abc";` converts the string to `const char *` before taking its address.
+fn strip_pointer_casts(mut expr: Expr) -> Expr {
+    loop {
+        match expr.expr {
+            ExprType::Cast(inner) if expr.ctype.is_pointer() && is_address(&inner.ctype) => {
+                expr = *inner;
+            }
+            other => {
+                expr.expr = other;
+                return expr;
+            }
+        }
+    }
+}
+
+fn is_address(ctype: &Type) -> bool {
+    match ctype {
+        Type::Pointer(..) | Type::Array(..) => true,
+        _ => false,
+    }
+}
+
+/// Store the initializer for a bitfield in `buf`, the storage unit for the bitfield.
+fn init_bitfield(
+    buf: &mut [u8],
+    layout: &BitfieldLayout,
+    mut init: Initializer,
+) -> CompileResult<()> {
+    // the initializer for a scalar may be enclosed in braces
+    while let Initializer::InitializerList(mut list) = init {
+        init = list.remove(0);
+    }
+    let expr = match init {
+        Initializer::Scalar(expr) => expr.const_fold()?,
+        _ => unreachable!("bitfields should have scalar initializers"),
+    };
+    let value = match expr.expr {
+        ExprType::Literal(Literal::Int(i)) => i as u64,
+        ExprType::Literal(Literal::UnsignedInt(u)) => u,
+        ExprType::Literal(Literal::Char(c)) => u64::from(c),
+        _ => semantic_err!(
+            "expression is not a compile time constant".into(),
+            expr.location
+        ),
+    };
+    let mask = if layout.width == 64 {
+        !0
+    } else {
+        (1 << layout.width) - 1
+    };
+    // NOTE: this assumes bits are numbered from the least significant bit,
+    // which is true for every little-endian target
+    let mut unit = [0; 8];
+    unit[..buf.len()].copy_from_slice(buf);
+    let unit = u64::from_le_bytes(unit) | (value & mask) << layout.shift;
+    buf.copy_from_slice(&unit.to_le_bytes()[..buf.len()]);
+    Ok(())
+}
+
 impl Literal {
     fn into_bytes(
         self,
@@ -334,6 +544,9 @@ impl Literal {
         location: &Location,
         error_handler: &mut ErrorHandler,
     ) -> CompileResult<Box<[u8]>> {
+        if let (Literal::Float(f), Type::LongDouble) = (&self, ctype) {
+            return Ok(Box::new(x87_extended(*f)));
+        }
         let ir_type = ctype.as_ir_type();
         let big_endian = TARGET
             .endianness()
@@ -387,7 +600,7 @@ impl Literal {
                             "conversion from double to float loses precision ({} is different from {} by more than DBL_EPSILON ({}))",
                             f, std::f64::EPSILON, f64::from(cast)
                         );
-                        error_handler.warn(&warning, *location);
+                        error_handler.warn(Warning::LossyConversion(warning), *location);
                     }
                     let float_as_int = cast.to_bits();
                     bytes!(float_as_int, big_endian)
@@ -404,6 +617,31 @@ impl Literal {
     }
 }
 
+/// Convert `f` to the x87 80-bit extended precision format used for `long double`,
+/// padded to 16 bytes. Every `f64` can be represented exactly.
+fn x87_extended(f: f64) -> [u8; 16] {
+    let bits = f.to_bits();
+    let sign = (bits >> 63) as u16;
+    let exponent = ((bits >> 52) & 0x7ff) as u16;
+    let fraction = bits & ((1 << 52) - 1);
+    // unlike `f64`, the integer part of the significand is stored explicitly
+    let (exponent, significand) = match exponent {
+        0 if fraction == 0 => (0, 0),
+        // subnormal doubles are normal in extended precision
+        0 => {
+            let shift = fraction.leading_zeros();
+            (15372 - shift as u16, fraction << shift)
+        }
+        // infinity and NaN
+        0x7ff => (0x7fff, 1 << 63 | fraction << 11),
+        _ => (exponent + 16383 - 1023, 1 << 63 | fraction << 11),
+    };
+    let mut buf = [0; 16];
+    buf[..8].copy_from_slice(&significand.to_le_bytes());
+    buf[8..10].copy_from_slice(&(sign << 15 | exponent).to_le_bytes());
+    buf
+}
+
 impl TryFrom<StorageClass> for Linkage {
     type Error = String;
     // INVARIANT: this should be the linkage for an object, not for a function
@@ -1,6 +1,7 @@
 use cranelift::codegen::cursor::Cursor;
+use cranelift::codegen::ir::TrapCode;
 use cranelift::frontend::Switch;
-use cranelift::prelude::{Ebb, FunctionBuilder, InstBuilder};
+use cranelift::prelude::{Ebb, FunctionBuilder, InstBuilder, IntCC};
 
 use super::Compiler;
 use crate::data::prelude::*;
@@ -28,7 +29,12 @@ impl Compiler {
             }));
         }
         match stmt.data {
-            StmtType::Compound(stmts) => self.compile_all(stmts, builder),
+            StmtType::Compound(stmts) => {
+                self.scope.enter_scope();
+                let result = self.compile_all(stmts, builder);
+                self.scope.leave_scope();
+                result
+            }
             // INVARIANT: symbol has not yet been declared in this scope
             StmtType::Decl(decls) => {
                 for decl in decls {
@@ -43,8 +49,14 @@ impl Compiler {
             StmtType::Return(expr) => {
                 let mut ret = vec![];
                 if let Some(e) = expr {
+                    let ctype = e.ctype.clone();
                     let val = self.compile_expr(e, builder)?;
-                    ret.push(val.ir_val);
+                    match &ctype {
+                        Type::Complex(inner) => {
+                            ret = Self::complex_to_abi(val.ir_val, inner, builder)
+                        }
+                        _ => ret.push(val.ir_val),
+                    }
                 }
                 builder.ins().return_(&ret);
                 Ok(())
@@ -69,29 +81,35 @@ impl Compiler {
             StmtType::Do(body, condition) => self.do_loop(*body, condition, builder),
             StmtType::Switch(condition, body) => self.switch(condition, *body, builder),
             StmtType::Label(name) => {
-                let new_block = builder.create_ebb();
+                // the parser has already checked that every label is declared once
+                let new_block = self.label_block(name, builder);
                 Self::jump_to_block(new_block, builder);
                 builder.switch_to_block(new_block);
-                if let Some(previous) = self.labels.insert(name, new_block) {
-                    Err(CompileError::semantic(Locatable {
-                        data: format!("redeclaration of label {}", previous),
-                        location: stmt.location,
-                    }))
-                } else {
-                    Ok(())
-                }
+                Ok(())
+            }
+            StmtType::Goto(name) => {
+                let ebb = self.label_block(name, builder);
+                Self::jump_to_block(ebb, builder);
+                Ok(())
+            }
+            StmtType::IndirectGoto(target) => {
+                let target = self.compile_expr(target, builder)?;
+                let dispatch = match self.indirect_goto {
+                    Some(ebb) => ebb,
+                    None => {
+                        let ebb = builder.create_ebb();
+                        builder.append_ebb_param(ebb, target.ir_type);
+                        self.indirect_goto = Some(ebb);
+                        ebb
+                    }
+                };
+                builder.ins().jump(dispatch, &[target.ir_val]);
+                Ok(())
             }
-            StmtType::Goto(name) => match self.labels.get(&name) {
-                Some(ebb) => {
-                    Self::jump_to_block(*ebb, builder);
-                    Ok(())
-                }
-                None => Err(CompileError::semantic(Locatable {
-                    data: format!("use of undeclared label {}", name),
-                    location: stmt.location,
-                })),
-            },
             StmtType::Case(constexpr, inner) => self.case(constexpr, inner, stmt.location, builder),
+            StmtType::CaseRange(low, high, inner) => {
+                self.case_range(low, high, inner, stmt.location, builder)
+            }
             StmtType::Default(inner) => self.default(inner, stmt.location, builder),
         }
     }
@@ -172,8 +190,16 @@ impl Compiler {
 
         // for loops can loop forever: `for (;;) {}`
         if let Some(condition) = maybe_condition {
-            let condition = self.compile_expr(condition, builder)?;
-            builder.ins().brz(condition.ir_val, end_body, &[]);
+            let condition = condition.const_fold()?;
+            // so can `while (1) {}`
+            let forever = match condition.expr {
+                ExprType::Literal(_) => !condition.is_zero(),
+                _ => false,
+            };
+            if !forever {
+                let condition = self.compile_expr(condition, builder)?;
+                builder.ins().brz(condition.ir_val, end_body, &[]);
+            }
         }
 
         if let Some(body) = maybe_body {
@@ -255,12 +281,25 @@ impl Compiler {
         self.last_saw_loop = false;
 
         self.switches
-            .push((Switch::new(), None, builder.create_ebb()));
+            .push((Switch::new(), Vec::new(), None, builder.create_ebb()));
         self.compile_stmt(body, builder)?;
-        let (switch, default, end) = self.switches.pop().unwrap();
+        let (switch, ranges, default, end) = self.switches.pop().unwrap();
 
         Self::jump_to_block(end, builder);
         builder.switch_to_block(dummy_block);
+        // `Switch` has one entry per value, so check ranges separately.
+        // `low <= x && x <= high` is the same as the unsigned comparison `x - low <= high - low`
+        for (low, high, ebb) in ranges {
+            let offset = builder
+                .ins()
+                .iadd_imm(cond_val.ir_val, (low as i64).wrapping_neg());
+            let in_range = builder.ins().icmp_imm(
+                IntCC::UnsignedLessThanOrEqual,
+                offset,
+                high.wrapping_sub(low) as i64,
+            );
+            builder.ins().brnz(in_range, ebb, &[]);
+        }
         switch.emit(
             builder,
             cond_val.ir_val,
@@ -280,7 +319,7 @@ impl Compiler {
         location: Location,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
-        let (switch, _, _) = match self.switches.last_mut() {
+        let (switch, _, _, _) = match self.switches.last_mut() {
             Some(x) => x,
             None => {
                 return Err(CompileError::semantic(Locatable {
@@ -289,28 +328,51 @@ impl Compiler {
                 }))
             }
         };
-        if builder.is_pristine() {
-            let current = builder.cursor().current_ebb().unwrap();
-            switch.set_entry(constexpr, current);
+        let ebb = Self::case_block(builder);
+        switch.set_entry(constexpr, ebb);
+        if let Some(stmt) = stmt {
+            self.compile_stmt(*stmt, builder)
         } else {
-            let new = builder.create_ebb();
-            switch.set_entry(constexpr, new);
-            Self::jump_to_block(new, builder);
-            builder.switch_to_block(new);
+            Ok(())
+        }
+    }
+    fn case_range(
+        &mut self,
+        low: u64,
+        high: u64,
+        stmt: Option<Box<Stmt>>,
+        location: Location,
+        builder: &mut FunctionBuilder,
+    ) -> CompileResult<()> {
+        let (_, ranges, _, _) = match self.switches.last_mut() {
+            Some(x) => x,
+            None => semantic_err!("case outside of switch statement".into(), location),
         };
+        ranges.push((low, high, Self::case_block(builder)));
         if let Some(stmt) = stmt {
             self.compile_stmt(*stmt, builder)
         } else {
             Ok(())
         }
     }
+    /// Start a new block for a `case` label, falling through from the previous statement.
+    fn case_block(builder: &mut FunctionBuilder) -> Ebb {
+        if builder.is_pristine() {
+            builder.cursor().current_ebb().unwrap()
+        } else {
+            let new = builder.create_ebb();
+            Self::jump_to_block(new, builder);
+            builder.switch_to_block(new);
+            new
+        }
+    }
     fn default(
         &mut self,
         inner: Option<Box<Stmt>>,
         location: Location,
         builder: &mut FunctionBuilder,
     ) -> CompileResult<()> {
-        let (_, default, _) = match self.switches.last_mut() {
+        let (_, _, default, _) = match self.switches.last_mut() {
             Some(x) => x,
             None => {
                 return Err(CompileError::semantic(Locatable {
@@ -325,15 +387,7 @@ impl Compiler {
                 location,
             }))
         } else {
-            let default_ebb = if builder.is_pristine() {
-                builder.cursor().current_ebb().unwrap()
-            } else {
-                let new = builder.create_ebb();
-                Self::jump_to_block(new, builder);
-                builder.switch_to_block(new);
-                new
-            };
-            *default = Some(default_ebb);
+            *default = Some(Self::case_block(builder));
             if let Some(stmt) = inner {
                 self.compile_stmt(*stmt, builder)
             } else {
@@ -369,7 +423,7 @@ impl Compiler {
             semantic_err!("'continue' not in loop".into(), location);
         } else {
             // break from switch
-            let (_, _, end_block) = self
+            let (_, _, _, end_block) = self
                 .switches
                 .last()
                 .expect("should be in a switch if last_saw_loop is false");
@@ -377,6 +431,43 @@ impl Compiler {
             Ok(())
         }
     }
+    /// The block for the label `name`, which may not have been reached yet.
+    fn label_block(&mut self, name: InternedStr, builder: &mut FunctionBuilder) -> Ebb {
+        *self
+            .labels
+            .entry(name)
+            .or_insert_with(|| builder.create_ebb())
+    }
+    /// The address of a label, for `&&label`.
+    pub(crate) fn label_address(&mut self, name: InternedStr) -> u64 {
+        let index = match self.label_addresses.iter().position(|&label| label == name) {
+            Some(index) => index,
+            None => {
+                self.label_addresses.push(name);
+                self.label_addresses.len() - 1
+            }
+        };
+        index as u64 + 1
+    }
+    /// Jump from the dispatch block of `goto *ptr` to the label whose address is `ptr`.
+    ///
+    /// Cranelift has no indirect branches, so this is a `Switch` on label addresses.
+    /// It should be emitted after the rest of the function, once every label address is known.
+    pub(crate) fn indirect_goto_dispatch(&mut self, dispatch: Ebb, builder: &mut FunctionBuilder) {
+        builder.switch_to_block(dispatch);
+        let target = builder.ebb_params(dispatch)[0];
+        let mut switch = Switch::new();
+        let labels = std::mem::take(&mut self.label_addresses);
+        for (index, label) in labels.into_iter().enumerate() {
+            let ebb = self.label_block(label, builder);
+            switch.set_entry(index as u64 + 1, ebb);
+        }
+        // jumping anywhere else is undefined behavior
+        let invalid = builder.create_ebb();
+        switch.emit(builder, target, invalid);
+        builder.switch_to_block(invalid);
+        builder.ins().trap(TrapCode::UnreachableCodeReached);
+    }
     #[inline]
     fn jump_to_block(ebb: Ebb, builder: &mut FunctionBuilder) {
         if !builder.is_filled() {
@@ -388,7 +479,10 @@ impl Compiler {
 impl StmtType {
     fn is_jump_target(&self) -> bool {
         match self {
-            StmtType::Case(_, _) | StmtType::Default(_) | StmtType::Label(_) => true,
+            StmtType::Case(_, _)
+            | StmtType::CaseRange(_, _, _)
+            | StmtType::Default(_)
+            | StmtType::Label(_) => true,
             _ => false,
         }
     }
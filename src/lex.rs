@@ -24,6 +24,7 @@ use super::intern::InternedStr;
 ///
 /// let lexer = Lexer::new("<stdin>".to_string(),
 ///                        "int main(void) { char *hello = \"hi\"; }".chars(),
+///                         false,
 ///                         false);
 /// for token in lexer {
 ///     assert!(token.is_ok());
@@ -39,6 +40,8 @@ pub struct Lexer<'a> {
     lookahead: Option<char>,
     /// whether to print out every token as it's encountered
     pub debug: bool,
+    /// whether to recognize GNU extension keywords like `__attribute__`
+    gnu: bool,
     error_handler: ErrorHandler,
 }
 
@@ -109,16 +112,55 @@ lazy_static! {
 
         // compiler intrinsics
         "sizeof" => Keyword::Sizeof,
+        "__real__" => Keyword::Real,
+        "__real" => Keyword::Real,
+        "__imag__" => Keyword::Imag,
+        "__imag" => Keyword::Imag,
         "_Alignof" => Keyword::Alignof,
         "_Alignas" => Keyword::Alignas,
         "_Generic" => Keyword::Generic,
         "_Static_assert" => Keyword::StaticAssert,
     };
+    /// Keywords that are only recognized in GNU mode.
+    ///
+    /// This includes the alternate spellings GCC allows for standard keywords,
+    /// which system headers use so they still work with `-std=c89`.
+    static ref GNU_KEYWORDS: HashMap<&'static str, Keyword> = map!{
+        "__attribute__" => Keyword::Attribute,
+        "__attribute" => Keyword::Attribute,
+        "__extension__" => Keyword::Extension,
+        "asm" => Keyword::Asm,
+        "__asm__" => Keyword::Asm,
+        "__asm" => Keyword::Asm,
+        "typeof" => Keyword::Typeof,
+        "__typeof__" => Keyword::Typeof,
+        "__typeof" => Keyword::Typeof,
+
+        "__alignof__" => Keyword::Alignof,
+        "__alignof" => Keyword::Alignof,
+        "__const__" => Keyword::Const,
+        "__const" => Keyword::Const,
+        "__inline__" => Keyword::Inline,
+        "__inline" => Keyword::Inline,
+        "__restrict__" => Keyword::Restrict,
+        "__restrict" => Keyword::Restrict,
+        "__signed__" => Keyword::Signed,
+        "__signed" => Keyword::Signed,
+        "__volatile__" => Keyword::Volatile,
+        "__volatile" => Keyword::Volatile,
+    };
 }
 
 impl<'a> Lexer<'a> {
     /// Creates a Lexer from a filename and the contents of a file
-    pub fn new<T: AsRef<str> + Into<String>>(file: T, chars: Chars<'a>, debug: bool) -> Lexer<'a> {
+    ///
+    /// If `gnu` is set, GNU extension keywords such as `typeof` are recognized.
+    pub fn new<T: AsRef<str> + Into<String>>(
+        file: T,
+        chars: Chars<'a>,
+        debug: bool,
+        gnu: bool,
+    ) -> Lexer<'a> {
         Lexer {
             location: SingleLocation {
                 offset: 0,
@@ -128,6 +170,7 @@ impl<'a> Lexer<'a> {
             current: None,
             lookahead: None,
             debug,
+            gnu,
             error_handler: ErrorHandler::new(),
         }
     }
@@ -252,10 +295,7 @@ impl<'a> Lexer<'a> {
                 return Ok(());
             }
         }
-        Err(CompileError {
-            location: self.span(start),
-            data: LexError::UnterminatedComment.into(),
-        })
+        Err(self.span(start).error(LexError::UnterminatedComment))
     }
     /// Parse a number literal, given the starting character and whether floats are allowed.
     ///
@@ -337,8 +377,10 @@ impl<'a> Lexer<'a> {
         }
         if radix == 2 {
             let span = self.span(span_start);
-            self.error_handler
-                .warn("binary number literals are an extension", span);
+            self.error_handler.warn(
+                Warning::Extension("binary number literals are an extension"),
+                span,
+            );
         }
         Ok(Token::Literal(literal))
     }
@@ -505,7 +547,7 @@ impl<'a> Lexer<'a> {
                         '?' => '?',    // a literal '?', for trigraphs
                         _ => {
                             self.error_handler.warn(
-                                &format!("unknown character escape '\\{}'", c),
+                                Warning::UnknownEscape(c),
                                 self.span(self.location.offset - 1),
                             );
                             c
@@ -609,7 +651,16 @@ impl<'a> Lexer<'a> {
                 break;
             }
         }
-        match KEYWORDS.get::<str>(&id) {
+        let keyword = KEYWORDS.get::<str>(&id).or_else(|| {
+            GNU_KEYWORDS.get::<str>(&id).filter(|&&keyword| {
+                // `__attribute__` and `__typeof__` are reserved identifiers,
+                // so they can be lexed anyway and the parser can say they need GNU mode
+                self.gnu
+                    || id.starts_with("__")
+                        && (keyword == Keyword::Attribute || keyword == Keyword::Typeof)
+            })
+        });
+        match keyword {
             Some(keyword) => Ok(Token::Keyword(*keyword)),
             None => Ok(Token::Id(InternedStr::get_or_intern(id))),
         }
@@ -854,7 +905,11 @@ impl<'a> Iterator for Lexer<'a> {
             println!("lexeme: {:?}", c);
         }
         // oof
-        c.map(|result| result.map_err(|err| err.map(|err| LexError::Generic(err).into())))
+        c.map(|result| {
+            result.map_err(|err| {
+                CompileError::from(err.map(|err| Error::Lex(LexError::Generic(err))))
+            })
+        })
     }
 }
 
@@ -876,7 +931,7 @@ mod tests {
         lexed.pop()
     }
     fn lex_all(input: &str) -> Vec<LexType> {
-        Lexer::new("<test suite>".to_string(), input.chars(), false).collect()
+        Lexer::new("<test suite>".to_string(), input.chars(), false, false).collect()
     }
 
     fn match_data<T>(lexed: Option<LexType>, closure: T) -> bool
@@ -947,7 +1002,7 @@ mod tests {
     }
     #[test]
     fn next_char() {
-        let mut lexer = Lexer::new(String::new(), "int main(void) {}".chars(), false);
+        let mut lexer = Lexer::new(String::new(), "int main(void) {}".chars(), false, false);
         assert!(lexer.next_char() == Some('i'));
         assert!(lexer.next_char() == Some('n'));
         assert!(lexer.next_char() == Some('t'));
@@ -956,7 +1011,7 @@ mod tests {
 
     #[test]
     fn unput() {
-        let mut lexer = Lexer::new(String::new(), "int main(void) {}".chars(), false);
+        let mut lexer = Lexer::new(String::new(), "int main(void) {}".chars(), false, false);
         let first = lexer.next_char();
         assert!(first == Some('i'));
         lexer.unput(first);
@@ -964,7 +1019,7 @@ mod tests {
     }
     #[test]
     fn peek() {
-        let mut lexer = Lexer::new(String::new(), "int main(void) {}".chars(), false);
+        let mut lexer = Lexer::new(String::new(), "int main(void) {}".chars(), false, false);
         assert!(lexer.peek() == Some('i'));
         assert!(lexer.peek() == Some('i'));
         assert!(lexer.peek() == Some('i'));
@@ -973,7 +1028,7 @@ mod tests {
     }
     #[test]
     fn match_next() {
-        let mut lexer = Lexer::new(String::new(), "int main(void) {}".chars(), false);
+        let mut lexer = Lexer::new(String::new(), "int main(void) {}".chars(), false, false);
         assert!(lexer.match_next('i'));
         assert!(lexer.match_next('n'));
         assert!(lexer.next_char() == Some('t'));
@@ -1178,5 +1233,16 @@ mod tests {
                 assert_keyword(lex(&keyword.to_string()), *keyword);
             }
         }
+        for (spelling, keyword) in GNU_KEYWORDS.iter() {
+            let mut gnu = Lexer::new("<test suite>".to_string(), spelling.chars(), false, true);
+            assert_keyword(gnu.next(), *keyword);
+            let reserved = spelling.starts_with("__")
+                && (*keyword == Keyword::Attribute || *keyword == Keyword::Typeof);
+            assert!(match_data(lex(spelling), |t| match t {
+                Ok(Token::Id(_)) => !reserved,
+                Ok(Token::Keyword(k)) => reserved && *k == *keyword,
+                _ => false,
+            }));
+        }
     }
 }
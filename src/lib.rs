@@ -64,9 +64,11 @@ pub fn compile(
     debug_lex: bool,
     debug_ast: bool,
     debug_ir: bool,
+    common: bool,
+    gnu: bool,
 ) -> (Result<Product, Error>, VecDeque<CompileWarning>) {
     let filename_ref = InternedStr::get_or_intern(&filename);
-    let mut lexer = Lexer::new(filename, buf.chars(), debug_lex);
+    let mut lexer = Lexer::new(filename, buf.chars(), debug_lex, gnu);
     let (first, mut errs) = lexer.first_token();
     let eof = || Location {
         span: (buf.len() as u32..buf.len() as u32).into(),
@@ -83,7 +85,7 @@ pub fn compile(
         }
     };
 
-    let mut parser = Parser::new(first, &mut lexer, debug_ast);
+    let mut parser = Parser::new(first, &mut lexer, debug_ast, gnu);
     let (hir, parse_errors) = parser.collect_results();
     errs.extend(parse_errors.into_iter());
     if hir.is_empty() && errs.is_empty() {
@@ -95,7 +97,7 @@ pub fn compile(
     if !errs.is_empty() {
         return (Err(Error::Source(errs)), warnings);
     }
-    let (result, ir_warnings) = ir::compile(hir, debug_ir);
+    let (result, ir_warnings) = ir::compile(hir, debug_ir, common);
     warnings.extend(ir_warnings);
     (result.map_err(Error::from), warnings)
 }
@@ -110,19 +112,21 @@ pub fn assemble(product: Product, output: &Path) -> Result<(), Error> {
 pub fn link(obj_file: &Path, output: &Path) -> Result<(), io::Error> {
     use std::io::{Error, ErrorKind};
     // link the .o file using host linker
-    let status = Command::new("cc")
-        .args(&[&obj_file, Path::new("-o"), output])
-        .status()
-        .map_err(|err| {
-            if err.kind() == ErrorKind::NotFound {
-                Error::new(
-                    ErrorKind::NotFound,
-                    "could not find host cc (for linking). Is it on your PATH?",
-                )
-            } else {
-                err
-            }
-        })?;
+    let mut cc = Command::new("cc");
+    cc.args(&[&obj_file, Path::new("-o"), output]);
+    // atomic operations are calls to libatomic, which isn't linked by default
+    #[cfg(target_os = "linux")]
+    cc.args(&["-Wl,--as-needed", "-latomic", "-Wl,--no-as-needed"]);
+    let status = cc.status().map_err(|err| {
+        if err.kind() == ErrorKind::NotFound {
+            Error::new(
+                ErrorKind::NotFound,
+                "could not find host cc (for linking). Is it on your PATH?",
+            )
+        } else {
+            err
+        }
+    })?;
     if !status.success() {
         Err(Error::new(ErrorKind::Other, "linking program failed"))
     } else {
@@ -134,7 +138,16 @@ pub fn link(obj_file: &Path, output: &Path) -> Result<(), io::Error> {
 mod tests {
     use super::*;
     fn compile(src: &str) -> Result<Product, Error> {
-        super::compile(src, "<test-suite>".to_owned(), false, false, false).0
+        super::compile(
+            src,
+            "<test-suite>".to_owned(),
+            false,
+            false,
+            false,
+            false,
+            false,
+        )
+        .0
     }
     fn compile_err(src: &str) -> VecDeque<CompileError> {
         match compile(src).err().unwrap() {
@@ -1,9 +1,11 @@
+use std::cmp;
 use std::collections::VecDeque;
+use std::ffi::OsString;
 use std::fs::File;
 use std::io::{self, Read};
 use std::path::PathBuf;
 use std::process;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 extern crate ansi_term;
 extern crate codespan;
@@ -18,8 +20,8 @@ use pico_args::Arguments;
 use rcc::{
     assemble, compile,
     data::{
-        error::{CompileWarning, RecoverableResult},
-        lex::Location,
+        error::{CompileError, CompileWarning, Fix, RecoverableResult, WarningFlags, WarningLevel},
+        lex::{Locatable, Location},
     },
     link, utils, Error,
 };
@@ -28,6 +30,8 @@ use tempfile::NamedTempFile;
 
 static ERRORS: AtomicUsize = AtomicUsize::new(0);
 static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+/// Set by `--apply-fixes -`, since the fixed source is printed to stdout.
+static DIAGNOSTICS_TO_STDERR: AtomicBool = AtomicBool::new(false);
 
 const HELP: &str = concat!(
     env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"), "\n",
@@ -38,23 +42,36 @@ const HELP: &str = concat!(
 "usage: ", env!("CARGO_PKG_NAME"), " [FLAGS] [OPTIONS] [<file>]
 
 FLAGS:
+        --apply-fixes  If set, rewrite the source file with the fixes suggested for errors and warnings. With stdin, print the fixed source and send diagnostics to stderr.
         --debug-asm    If set, print the intermediate representation of the program in addition to compiling
     -a, --debug-ast    If set, print the parsed abstract syntax tree in addition to compiling
         --debug-lex    If set, print all tokens found by the lexer in addition to compiling.
+        -fcommon       If set, emit tentative definitions as common symbols, so they can be defined in more than one file.
     -h, --help         Prints help information
+        -std=gnu11     If set, accept GNU extensions such as '__attribute__', 'typeof', and statement expressions.
     -c, --no-link      If set, compile and assemble but do not link. Object file is machine-dependent.
     -V, --version      Prints version information
 
+WARNINGS:
+        -W<warning>             Enable a warning. Warnings not in -Wall or -Wextra are enabled by default.
+        -Wno-<warning>          Disable a warning.
+        -Wall                   Enable -Wparentheses, -Wswitch and -Wunused-function.
+        -Wextra                 Enable -Wempty-body and -Wignored-qualifiers.
+        -Werror[=<warning>]     Treat all warnings, or only the given warning, as errors.
+        -Wno-error=<warning>    Treat the given warning as a warning even with '-Werror'.
+
 OPTIONS:
-    -o, --output <output>    The output file to use. [default: a.out]
+        --error-format=<format>    How to print errors and warnings: 'human', 'json' (one object per line) or 'sarif'. [default: human]
+    -o, --output <output>          The output file to use. [default: a.out]
 
 ARGS:
     <file>    The file to read C source from. \"-\" means stdin (use ./- to read a file called '-').
               Only one file at a time is currently accepted. [default: -]");
 
 const USAGE: &str = "\
-usage: rcc [--help] [--version | -V] [--debug-asm] [--debug-ast | -a]
-           [--debug-lex] [--no-link | -c] [<file>]";
+usage: rcc [--help] [--version | -V] [--apply-fixes] [--debug-asm] [--debug-ast | -a]
+           [--debug-lex] [--error-format=<format>] [-fcommon] [-std=gnu11] [--no-link | -c]
+           [-W<warning> | -Wno-<warning> | -Werror[=<warning>]] [<file>]";
 
 #[derive(Debug)]
 struct Opt {
@@ -63,6 +80,9 @@ struct Opt {
     /// Only one file at a time is currently accepted.
     filename: PathBuf,
 
+    /// If set, rewrite the source file with the fixes suggested for errors and warnings.
+    apply_fixes: bool,
+
     /// If set, print all tokens found by the lexer in addition to compiling.
     debug_lex: bool,
 
@@ -72,22 +92,65 @@ struct Opt {
     /// If set, print the intermediate representation of the program in addition to compiling
     debug_asm: bool,
 
+    /// If set, emit tentative definitions as common symbols, so they can be defined in more than one file.
+    common: bool,
+
+    /// If set, accept GNU extensions such as '__attribute__', 'typeof', and statement expressions.
+    gnu: bool,
+
     /// If set, compile and assemble but do not link. Object file is machine-dependent.
     no_link: bool,
 
+    /// How to print errors and warnings.
+    error_format: ErrorFormat,
+
     /// The output file to use.
     output: PathBuf,
+
+    /// Which warnings to show, and which to treat as errors.
+    warnings: WarningFlags,
+}
+
+/// How errors and warnings are printed, chosen with `--error-format`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ErrorFormat {
+    /// Coloured text, with the source code the diagnostic points to
+    Human,
+    /// One JSON object per diagnostic, each on its own line
+    Json,
+    /// A single SARIF 2.1.0 log, for code scanning tools
+    Sarif,
+}
+
+impl std::str::FromStr for ErrorFormat {
+    type Err = String;
+    fn from_str(format: &str) -> Result<Self, String> {
+        match format {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            "sarif" => Ok(ErrorFormat::Sarif),
+            _ => Err(format!(
+                "unknown error format '{}': expected 'human', 'json' or 'sarif'",
+                format
+            )),
+        }
+    }
 }
 
 impl Default for Opt {
     fn default() -> Self {
         Opt {
             filename: "<default>".into(),
+            apply_fixes: false,
             debug_lex: false,
             debug_ast: false,
             debug_asm: false,
+            common: false,
+            gnu: false,
             no_link: false,
+            error_format: ErrorFormat::Human,
             output: PathBuf::from("a.out"),
+            warnings: WarningFlags::default(),
         }
     }
 }
@@ -102,10 +165,51 @@ fn real_main(file_db: &Files<String>, file_id: FileId, opt: Opt) -> Result<(), E
         opt.debug_lex,
         opt.debug_ast,
         opt.debug_asm,
+        opt.common,
+        opt.gnu,
     );
-    handle_warnings(warnings, file_id, file_db);
+    if opt.apply_fixes {
+        // only fix warnings that are shown
+        let mut fixes: Vec<&Fix> = warnings
+            .iter()
+            .filter(|warning| opt.warnings.level(&warning.data) != WarningLevel::Ignore)
+            .flat_map(|warning| &warning.fixes)
+            .collect();
+        if let Err(Error::Source(errs)) = &result {
+            fixes.extend(errs.iter().flat_map(|err| &err.fixes));
+        }
+        let fixed = apply_fixes(file_db.source(file_id), fixes);
+        if opt.filename == PathBuf::from("<stdin>") {
+            // keep the fixed source separate from errors and warnings
+            DIAGNOSTICS_TO_STDERR.store(true, Ordering::Relaxed);
+            print!("{}", fixed);
+        } else {
+            std::fs::write(&opt.filename, fixed)?;
+        }
+    }
+    let promoted = if opt.error_format == ErrorFormat::Human {
+        handle_warnings(warnings, &opt.warnings, file_id, file_db)
+    } else {
+        let errs: Vec<&CompileError> = match &result {
+            Err(Error::Source(errs)) => errs.iter().collect(),
+            _ => Vec::new(),
+        };
+        let reports = reports(&warnings, &errs, &opt.warnings);
+        match opt.error_format {
+            ErrorFormat::Json => print_diagnostic(json_reports(&reports, file_id, file_db)),
+            _ => print_diagnostic(format!("{}\n", sarif_log(&reports, file_id, file_db))),
+        }
+        if !errs.is_empty() {
+            process::exit(2);
+        }
+        reports.iter().any(|report| report.level == "error")
+    };
 
     let product = result?;
+    if promoted {
+        print_issues(get_warnings(), get_errors());
+        process::exit(2);
+    }
     if opt.no_link {
         return assemble(product, opt.output.as_path());
     }
@@ -114,15 +218,49 @@ fn real_main(file_db: &Files<String>, file_id: FileId, opt: Opt) -> Result<(), E
     link(tmp_file.as_ref(), opt.output.as_path()).map_err(io::Error::into)
 }
 
-fn handle_warnings(warnings: VecDeque<CompileWarning>, file: FileId, file_db: &Files<String>) {
-    WARNINGS.fetch_add(warnings.len(), Ordering::Relaxed);
+/// Print the warnings that weren't disabled with `-Wno-<warning>`.
+///
+/// Returns whether any of them were made into errors with `-Werror`.
+fn handle_warnings(
+    warnings: VecDeque<CompileWarning>,
+    flags: &WarningFlags,
+    file: FileId,
+    file_db: &Files<String>,
+) -> bool {
     let tag = Colour::Yellow.bold().paint("warning");
+    let mut promoted = false;
     for warning in warnings {
-        print!(
-            "{}",
-            pretty_print(tag.clone(), warning.data, warning.location, file, file_db)
-        );
+        let flag = warning.data.flag();
+        match flags.level(&warning.data) {
+            WarningLevel::Ignore => {}
+            WarningLevel::Warn => {
+                WARNINGS.fetch_add(1, Ordering::Relaxed);
+                let msg = format!("{} [-W{}]", warning.data, flag);
+                print_diagnostic(pretty_print(
+                    tag.clone(),
+                    msg,
+                    warning.location,
+                    &warning.notes,
+                    &warning.fixes,
+                    file,
+                    file_db,
+                ));
+            }
+            WarningLevel::Error => {
+                promoted = true;
+                let msg = format!("{} [-Werror={}]", warning.data, flag);
+                error(
+                    msg,
+                    warning.location,
+                    &warning.notes,
+                    &warning.fixes,
+                    file,
+                    file_db,
+                );
+            }
+        }
     }
+    promoted
 }
 
 fn main() {
@@ -174,7 +312,9 @@ macro_rules! type_sizes {
     };
 }
 fn parse_args() -> Result<Opt, pico_args::Error> {
-    let mut input = Arguments::from_env();
+    let (warnings, rest) = warning_flags(std::env::args_os().skip(1))?;
+    let (error_format, rest) = error_format(rest)?;
+    let mut input = Arguments::from_vec(rest);
     if input.contains(["-h", "--help"]) {
         println!("{}", HELP);
         std::process::exit(1);
@@ -201,10 +341,15 @@ fn parse_args() -> Result<Opt, pico_args::Error> {
         );
     }
     Ok(Opt {
+        apply_fixes: input.contains("--apply-fixes"),
         debug_lex: input.contains("--debug-lex"),
         debug_asm: input.contains("--debug-asm"),
         debug_ast: input.contains(["-a", "--debug-ast"]),
+        common: input.contains("-fcommon"),
+        gnu: gnu_extensions(&mut input),
         no_link: input.contains(["-c", "--no-link"]),
+        error_format,
+        warnings,
         output: input
             .opt_value_from_os_str(["-o", "--output"], os_str_to_path_buf)?
             .unwrap_or_else(|| "a.out".into()),
@@ -214,12 +359,73 @@ fn parse_args() -> Result<Opt, pico_args::Error> {
     })
 }
 
+/// Whether `-std` asks for GNU extensions.
+fn gnu_extensions(input: &mut Arguments) -> bool {
+    let mut gnu = false;
+    for std in &["-std=gnu99", "-std=gnu11", "-std=gnu17"] {
+        gnu |= input.contains(*std);
+    }
+    // ISO C is the default, but the flags still have to be consumed
+    for std in &["-std=c99", "-std=c11", "-std=c17"] {
+        input.contains(*std);
+    }
+    gnu
+}
+
+/// Take the `-W<warning>` flags out of the arguments.
+///
+/// pico-args only knows about flags with a fixed name, so these are handled separately.
+fn warning_flags<I: Iterator<Item = OsString>>(
+    args: I,
+) -> Result<(WarningFlags, Vec<OsString>), pico_args::Error> {
+    let mut flags = WarningFlags::default();
+    let mut rest = Vec::new();
+    for arg in args {
+        match arg.to_str() {
+            Some(flag) if flag.starts_with("-W") && flag.len() > 2 => flags
+                .add(&flag[2..])
+                .map_err(|cause| pico_args::Error::ArgumentParsingFailed { cause })?,
+            _ => rest.push(arg),
+        }
+    }
+    Ok((flags, rest))
+}
+
+/// Take `--error-format=<format>` out of the arguments.
+fn error_format(args: Vec<OsString>) -> Result<(ErrorFormat, Vec<OsString>), pico_args::Error> {
+    let mut format = ErrorFormat::Human;
+    let mut rest = Vec::new();
+    for arg in args {
+        let parsed = match arg.to_str() {
+            Some(flag) if flag.starts_with("--error-format=") => {
+                flag.splitn(2, '=').nth(1).map(str::parse::<ErrorFormat>)
+            }
+            _ => None,
+        };
+        match parsed {
+            Some(parsed) => {
+                format =
+                    parsed.map_err(|cause| pico_args::Error::ArgumentParsingFailed { cause })?
+            }
+            None => rest.push(arg),
+        }
+    }
+    Ok((format, rest))
+}
+
 fn err_exit(err: Error, file: FileId, file_db: &Files<String>) -> ! {
     use Error::*;
     match err {
         Source(errs) => {
             for err in errs {
-                error(&err.data, err.location(), file, file_db);
+                error(
+                    &err.data,
+                    err.location(),
+                    &err.notes,
+                    &err.fixes,
+                    file,
+                    file_db,
+                );
             }
             let (num_warnings, num_errors) = (get_warnings(), get_errors());
             print_issues(num_warnings, num_errors);
@@ -244,22 +450,360 @@ fn print_issues(warnings: usize, errors: usize) {
     eprintln!("{} generated", msg);
 }
 
-fn error<T: std::fmt::Display>(msg: T, location: Location, file: FileId, file_db: &Files<String>) {
+fn error<T: std::fmt::Display>(
+    msg: T,
+    location: Location,
+    notes: &[Locatable<String>],
+    fixes: &[Fix],
+    file: FileId,
+    file_db: &Files<String>,
+) {
     ERRORS.fetch_add(1, Ordering::Relaxed);
-    print!(
-        "{}",
-        pretty_print(
-            Colour::Red.bold().paint("error"),
-            msg,
-            location,
-            file,
-            file_db,
-        )
-    );
+    print_diagnostic(pretty_print(
+        Colour::Red.bold().paint("error"),
+        msg,
+        location,
+        notes,
+        fixes,
+        file,
+        file_db,
+    ));
 }
 
+/// Print an error or warning to stdout, or to stderr if stdout holds the fixed source.
+fn print_diagnostic(diagnostic: String) {
+    if DIAGNOSTICS_TO_STDERR.load(Ordering::Relaxed) {
+        eprint!("{}", diagnostic);
+    } else {
+        print!("{}", diagnostic);
+    }
+}
+
+/// Format a diagnostic, followed by any notes and suggested fixes that go with it.
 #[must_use]
 pub fn pretty_print<T: std::fmt::Display, S: AsRef<str>>(
+    prefix: ANSIString,
+    msg: T,
+    location: Location,
+    notes: &[Locatable<String>],
+    fixes: &[Fix],
+    file: FileId,
+    file_db: &Files<S>,
+) -> String {
+    let mut buf = snippet(prefix, msg, location, file, file_db);
+    let tag = Colour::Cyan.bold().paint("note");
+    for note in notes {
+        buf.push_str(&snippet(
+            tag.clone(),
+            &note.data,
+            note.location,
+            file,
+            file_db,
+        ));
+    }
+    for fix in fixes {
+        buf.push_str(&fix_it(fix, file, file_db));
+    }
+    buf
+}
+
+/// Format a suggested fix, with the replacement under the text it replaces.
+fn fix_it<S: AsRef<str>>(fix: &Fix, file: FileId, file_db: &Files<S>) -> String {
+    let span = fix.location.span;
+    let msg = if span.start() == span.end() {
+        format!("insert '{}'", fix.replacement)
+    } else {
+        let original = file_db.source_slice(file, span).unwrap();
+        format!("replace '{}' with '{}'", original, fix.replacement)
+    };
+    let tag = Colour::Green.bold().paint("fix");
+    let mut buf = snippet(tag, msg, fix.location, file, file_db);
+    let start = file_db
+        .location(file, span.start())
+        .expect("start location should be in bounds");
+    buf.push_str(&" ".repeat(start.column.0 as usize));
+    buf.push_str(&fix.replacement);
+    buf.push('\n');
+    buf
+}
+
+/// Apply suggested fixes to `source`.
+///
+/// If two fixes overlap, only the first is applied.
+fn apply_fixes<'a, I: IntoIterator<Item = &'a Fix>>(source: &str, fixes: I) -> String {
+    let mut fixes: Vec<_> = fixes.into_iter().collect();
+    fixes.sort_by_key(|fix| (fix.location.span.start(), fix.location.span.end()));
+    fixes.dedup();
+    let mut fixed = String::with_capacity(source.len());
+    let mut last = 0;
+    for fix in fixes {
+        let start = fix.location.span.start().0 as usize;
+        let end = fix.location.span.end().0 as usize;
+        if start < last {
+            continue;
+        }
+        fixed.push_str(&source[last..start]);
+        fixed.push_str(&fix.replacement);
+        last = end;
+    }
+    fixed.push_str(&source[last..]);
+    fixed
+}
+
+/// An error or warning, as printed by `--error-format=json` and `--error-format=sarif`.
+struct Report<'a> {
+    /// "error" or "warning". Warnings made into errors with `-Werror` are errors.
+    level: &'static str,
+    /// "lex", "syntax" or "semantic" for errors, and "warning" for warnings
+    category: &'static str,
+    /// the flag that controls a warning, e.g. "parentheses" for `-Wparentheses`
+    flag: Option<&'static str>,
+    message: String,
+    location: Location,
+    notes: &'a [Locatable<String>],
+    fixes: &'a [Fix],
+}
+
+/// Collect the warnings that weren't disabled, followed by the errors.
+fn reports<'a>(
+    warnings: &'a VecDeque<CompileWarning>,
+    errs: &[&'a CompileError],
+    flags: &WarningFlags,
+) -> Vec<Report<'a>> {
+    let mut reports = Vec::new();
+    for warning in warnings {
+        let level = match flags.level(&warning.data) {
+            WarningLevel::Ignore => continue,
+            WarningLevel::Warn => "warning",
+            WarningLevel::Error => "error",
+        };
+        reports.push(Report {
+            level,
+            category: "warning",
+            flag: Some(warning.data.flag()),
+            message: warning.data.to_string(),
+            location: warning.location,
+            notes: &warning.notes,
+            fixes: &warning.fixes,
+        });
+    }
+    for &err in errs {
+        let category = if err.is_lex_err() {
+            "lex"
+        } else if err.is_syntax_err() {
+            "syntax"
+        } else {
+            "semantic"
+        };
+        reports.push(Report {
+            level: "error",
+            category,
+            flag: None,
+            message: err.data.to_string(),
+            location: err.location,
+            notes: &err.notes,
+            fixes: &err.fixes,
+        });
+    }
+    reports
+}
+
+/// Format each report as a JSON object on its own line.
+///
+/// Every object has the keys `level`, `category`, `flag`, `message`, `file` and `span`,
+/// followed by a list of `notes`, which each have a `message` and `span`,
+/// and a list of `fixes`, which each have a `replacement` and the `span` it replaces.
+fn json_reports<S: AsRef<str>>(reports: &[Report], file: FileId, file_db: &Files<S>) -> String {
+    let span = |location: Location| json_span(location, file, file_db);
+    let mut buf = String::new();
+    for report in reports {
+        let notes = report.notes.iter().map(|note| {
+            json_object(&[
+                ("message", json_string(&note.data)),
+                ("span", span(note.location)),
+            ])
+        });
+        let fixes = report.fixes.iter().map(|fix| {
+            json_object(&[
+                ("replacement", json_string(&fix.replacement)),
+                ("span", span(fix.location)),
+            ])
+        });
+        let flag = match report.flag {
+            Some(flag) => json_string(flag),
+            None => "null".into(),
+        };
+        buf.push_str(&json_object(&[
+            ("level", json_string(report.level)),
+            ("category", json_string(report.category)),
+            ("flag", flag),
+            ("message", json_string(&report.message)),
+            ("file", json_string(&file_db.name(file).to_string())),
+            ("span", span(report.location)),
+            ("notes", json_array(notes)),
+            ("fixes", json_array(fixes)),
+        ]));
+        buf.push('\n');
+    }
+    buf
+}
+
+/// The byte offsets of `location`, and the line and column where it starts and ends.
+///
+/// Lines and columns start at 1, and byte offsets start at 0.
+fn json_span<S: AsRef<str>>(location: Location, file: FileId, file_db: &Files<S>) -> String {
+    let (start, end) = line_columns(location, file, file_db);
+    json_object(&[
+        ("start", location.span.start().0.to_string()),
+        ("end", location.span.end().0.to_string()),
+        ("line", start.line.number().to_string()),
+        ("column", start.column.number().to_string()),
+        ("end_line", end.line.number().to_string()),
+        ("end_column", end.column.number().to_string()),
+    ])
+}
+
+/// Format the reports as a SARIF 2.1.0 log with a single run.
+///
+/// Warnings use their flag as the rule ID, and errors use their category.
+/// Notes are related locations and fixes are replacements in the same file.
+fn sarif_log<S: AsRef<str>>(reports: &[Report], file: FileId, file_db: &Files<S>) -> String {
+    let uri = json_string(&file_db.name(file).to_string());
+    let artifact = json_object(&[("uri", uri)]);
+    let region = |location: Location| {
+        let (start, end) = line_columns(location, file, file_db);
+        let (start_byte, end_byte) = (location.span.start().0, location.span.end().0);
+        json_object(&[
+            ("startLine", start.line.number().to_string()),
+            ("startColumn", start.column.number().to_string()),
+            ("endLine", end.line.number().to_string()),
+            ("endColumn", end.column.number().to_string()),
+            ("byteOffset", start_byte.to_string()),
+            ("byteLength", (end_byte - start_byte).to_string()),
+        ])
+    };
+    let physical_location = |location: Location| {
+        json_object(&[
+            ("artifactLocation", artifact.clone()),
+            ("region", region(location)),
+        ])
+    };
+    let results = reports.iter().map(|report| {
+        let related = report.notes.iter().enumerate().map(|(id, note)| {
+            json_object(&[
+                ("id", id.to_string()),
+                ("physicalLocation", physical_location(note.location)),
+                ("message", json_object(&[("text", json_string(&note.data))])),
+            ])
+        });
+        let fixes = report.fixes.iter().map(|fix| {
+            let (start, end) = (fix.location.span.start().0, fix.location.span.end().0);
+            let deleted = json_object(&[
+                ("byteOffset", start.to_string()),
+                ("byteLength", (end - start).to_string()),
+            ]);
+            let replacement = json_object(&[
+                ("deletedRegion", deleted),
+                (
+                    "insertedContent",
+                    json_object(&[("text", json_string(&fix.replacement))]),
+                ),
+            ]);
+            let change = json_object(&[
+                ("artifactLocation", artifact.clone()),
+                ("replacements", json_array(vec![replacement])),
+            ]);
+            json_object(&[("artifactChanges", json_array(vec![change]))])
+        });
+        let location = json_object(&[("physicalLocation", physical_location(report.location))]);
+        json_object(&[
+            (
+                "ruleId",
+                json_string(report.flag.unwrap_or(report.category)),
+            ),
+            ("level", json_string(report.level)),
+            (
+                "message",
+                json_object(&[("text", json_string(&report.message))]),
+            ),
+            ("locations", json_array(vec![location])),
+            ("relatedLocations", json_array(related)),
+            ("fixes", json_array(fixes)),
+            (
+                "properties",
+                json_object(&[("category", json_string(report.category))]),
+            ),
+        ])
+    });
+    let driver = json_object(&[
+        ("name", json_string(env!("CARGO_PKG_NAME"))),
+        ("version", json_string(env!("CARGO_PKG_VERSION"))),
+        ("informationUri", json_string(env!("CARGO_PKG_REPOSITORY"))),
+    ]);
+    let run = json_object(&[
+        ("tool", json_object(&[("driver", driver)])),
+        ("results", json_array(results)),
+    ]);
+    json_object(&[
+        (
+            "$schema",
+            json_string("https://json.schemastore.org/sarif-2.1.0.json"),
+        ),
+        ("version", json_string("2.1.0")),
+        ("runs", json_array(vec![run])),
+    ])
+}
+
+/// The line and column where `location` starts and ends.
+fn line_columns<S: AsRef<str>>(
+    location: Location,
+    file: FileId,
+    file_db: &Files<S>,
+) -> (codespan::Location, codespan::Location) {
+    let start = file_db
+        .location(file, location.span.start())
+        .expect("start location should be in bounds");
+    let end = file_db
+        .location(file, location.span.end())
+        .expect("end location should be in bounds");
+    (start, end)
+}
+
+/// A JSON object with the given keys. The values should already be JSON.
+fn json_object(fields: &[(&str, String)]) -> String {
+    let fields: Vec<_> = fields
+        .iter()
+        .map(|(key, value)| format!("{}:{}", json_string(key), value))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+/// A JSON array of values which are already JSON.
+fn json_array<I: IntoIterator<Item = String>>(values: I) -> String {
+    format!("[{}]", values.into_iter().collect::<Vec<_>>().join(","))
+}
+
+/// Quote `s` as a JSON string, escaping it if necessary.
+fn json_string(s: &str) -> String {
+    let mut buf = String::with_capacity(s.len() + 2);
+    buf.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            c if c.is_control() => buf.push_str(&format!("\\u{:04x}", c as u32)),
+            c => buf.push(c),
+        }
+    }
+    buf.push('"');
+    buf
+}
+
+/// Format a single message, with the line of source it points to if it fits on one line.
+fn snippet<T: std::fmt::Display, S: AsRef<str>>(
     prefix: ANSIString,
     msg: T,
     location: Location,
@@ -277,8 +821,8 @@ pub fn pretty_print<T: std::fmt::Display, S: AsRef<str>>(
         prefix,
         msg
     );
-    // avoid printing spurious newline for errors and EOF
-    if location.span.end() == 0.into() {
+    // avoid printing spurious newline for errors in an empty file
+    if file_db.source(file).as_ref().is_empty() {
         return buf;
     }
     let end = file_db
@@ -293,7 +837,8 @@ pub fn pretty_print<T: std::fmt::Display, S: AsRef<str>>(
             buf,
             file_db.source_slice(file, line).unwrap(),
             " ".repeat(start.column.0 as usize),
-            "^".repeat((end.column - start.column).0 as usize)
+            // an empty span is where something should be inserted
+            "^".repeat(cmp::max((end.column - start.column).0 as usize, 1))
         )
     } else {
         buf
@@ -302,7 +847,7 @@ pub fn pretty_print<T: std::fmt::Display, S: AsRef<str>>(
 
 #[inline]
 fn get_warnings() -> usize {
-    ERRORS.load(Ordering::SeqCst)
+    WARNINGS.load(Ordering::SeqCst)
 }
 
 #[inline]
@@ -325,7 +870,7 @@ mod test {
         let mut file_db = Files::new();
         let file = file_db.add("<test-suite>", source);
         let ansi_str = Style::new().paint("");
-        super::pretty_print(ansi_str, "", location, file, &file_db)
+        super::pretty_print(ansi_str, "", location, &[], &[], file, &file_db)
     }
     #[test]
     fn pretty_print() {
@@ -334,5 +879,96 @@ mod test {
             Some("        ^^^^^^^")
         );
         pp(0..0, "");
+        // something inserted at the start of the file still shows where it goes
+        assert_eq!(pp(0..0, "f() {}\n").lines().nth(2), Some("^"));
+    }
+    #[test]
+    fn apply_fixes() {
+        let fix = |span: std::ops::Range<u32>, replacement: &str| super::Fix {
+            location: Location {
+                filename: InternedStr::get_or_intern("<test-suite>"),
+                span: span.into(),
+            },
+            replacement: replacement.into(),
+        };
+        let source = "if (a = b) return 0 }";
+        let fixes = [
+            fix(6..7, "=="),
+            fix(19..19, ";"),
+            fix(6..7, "=="),
+            fix(6..8, "c"),
+        ];
+        assert_eq!(
+            super::apply_fixes(source, fixes.iter()),
+            "if (a == b) return 0; }"
+        );
+        assert_eq!(super::apply_fixes(source, &[]), source);
+    }
+    #[test]
+    fn notes() {
+        let location = |span: std::ops::Range<u32>| Location {
+            filename: InternedStr::get_or_intern("<test-suite>"),
+            span: span.into(),
+        };
+        let mut file_db = Files::new();
+        let file = file_db.add("<test-suite>", "int i;\nint i = 1;\n");
+        let notes = [location(4..5).with(String::from("previous declaration is here"))];
+        let ansi_str = Style::new().paint("");
+        let printed = super::pretty_print(
+            ansi_str,
+            "error",
+            location(11..12),
+            &notes,
+            &[],
+            file,
+            &file_db,
+        );
+        let lines: Vec<_> = printed.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[3].starts_with("<test-suite>:1:5: "));
+        assert!(lines[3].ends_with("previous declaration is here"));
+        assert_eq!(lines[5], "    ^");
+    }
+    #[test]
+    fn json_reports() {
+        let location = |span: std::ops::Range<u32>| Location {
+            filename: InternedStr::get_or_intern("<test-suite>"),
+            span: span.into(),
+        };
+        let mut file_db = Files::new();
+        let file = file_db.add("<test-suite>", "int i\nint \"j\";\n");
+        let notes = [location(4..5).with(String::from("declared here"))];
+        let fixes = [super::Fix {
+            location: location(5..5),
+            replacement: ";".into(),
+        }];
+        let report = super::Report {
+            level: "error",
+            category: "syntax",
+            flag: None,
+            message: "expected ';', got \"int\"".into(),
+            location: location(6..9),
+            notes: &notes,
+            fixes: &fixes,
+        };
+        let json = super::json_reports(&[report], file, &file_db);
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"level":"error","category":"syntax","flag":null,"#,
+                r#""message":"expected ';', got \"int\"","file":"<test-suite>","#,
+                r#""span":{"start":6,"end":9,"line":2,"column":1,"end_line":2,"end_column":4},"#,
+                r#""notes":[{"message":"declared here","#,
+                r#""span":{"start":4,"end":5,"line":1,"column":5,"end_line":1,"end_column":6}}],"#,
+                r#""fixes":[{"replacement":";","#,
+                r#""span":{"start":5,"end":5,"line":1,"column":6,"end_line":1,"end_column":6}}]}"#,
+                "\n"
+            )
+        );
+    }
+    #[test]
+    fn json_string() {
+        assert_eq!(super::json_string("a \"b\"\n\\"), r#""a \"b\"\n\\""#);
+        assert_eq!(super::json_string("\u{1}"), r#""\u0001""#);
     }
 }
@@ -0,0 +1,390 @@
+//! The `__atomic_*` builtins from GCC and the `__c11_atomic_*` builtins from Clang,
+//! which are enough to implement `<stdatomic.h>`.
+//!
+//! GCC's builtins accept a pointer to any object of a supported size,
+//! while Clang's require a pointer to an `_Atomic` type.
+//! Both are normalized to `ExprType::Atomic`; see `AtomicOp` for the arguments of each operation.
+
+use super::{Lexeme, Parser, SyntaxResult};
+use crate::data::prelude::*;
+use crate::data::{lex::AssignmentToken, AtomicOp};
+
+const GNU_PREFIX: &str = "__atomic_";
+const C11_PREFIX: &str = "__c11_atomic_";
+
+impl<I: Iterator<Item = Lexeme>> Parser<I> {
+    /// Whether the next tokens are a call to an atomic builtin.
+    ///
+    /// Builtins are only recognized if they haven't been declared,
+    /// so a program can still define its own `__atomic_load_n`.
+    pub(super) fn is_atomic_builtin(&mut self, name: InternedStr) -> bool {
+        self.scope.get(&name).is_none()
+            && builtin_arity(&name.resolve_and_clone()).is_some()
+            && self.peek_token() == Some(&Token::LeftParen)
+    }
+    /// Parse a call to an atomic builtin. The name has already been consumed.
+    pub(super) fn atomic_builtin(&mut self, name: InternedStr, location: Location) -> SyntaxResult {
+        let name = name.resolve_and_clone();
+        self.expect(Token::LeftParen)?;
+        let args = self.argument_expr_list_opt()?;
+        self.expect(Token::RightParen)?;
+        let arity = builtin_arity(&name).expect("is_atomic_builtin should only accept builtins");
+        let lowered = if args.len() != arity {
+            Err(format!(
+                "too {} arguments to '{}': expected {}, have {}",
+                if args.len() > arity { "many" } else { "few" },
+                name,
+                arity,
+                args.len()
+            ))
+        } else if name.starts_with(C11_PREFIX) {
+            self.lower_atomic_builtin(&name[C11_PREFIX.len()..], true, args, location)
+        } else {
+            self.lower_atomic_builtin(&name[GNU_PREFIX.len()..], false, args, location)
+        };
+        Ok(lowered.unwrap_or_else(|err| {
+            self.semantic_err(err, location);
+            let mut pretend_zero = Expr::zero(location);
+            pretend_zero.ctype = Type::Error;
+            pretend_zero
+        }))
+    }
+    /// `name` is the name of the builtin without its prefix, e.g. `load_n`.
+    fn lower_atomic_builtin(
+        &mut self,
+        name: &str,
+        c11: bool,
+        args: Vec<Expr>,
+        location: Location,
+    ) -> Result<Expr, String> {
+        let mut args = args.into_iter().map(Expr::rval);
+        let mut next_arg = || args.next().expect("builtin_arity should have checked this");
+        // these don't operate on an object
+        match name {
+            "thread_fence" | "signal_fence" => {
+                let op = if name == "thread_fence" {
+                    AtomicOp::ThreadFence
+                } else {
+                    AtomicOp::SignalFence
+                };
+                let order = self.memory_order(next_arg());
+                return Ok(atomic_expr(op, vec![order], Type::Void, location));
+            }
+            // the second argument to the GNU version is an (optional) pointer to the object,
+            // which is only used to check the alignment. all our objects are suitably aligned.
+            "always_lock_free" | "is_lock_free" => {
+                let size = next_arg();
+                let size_location = size.location;
+                let lock_free = match size.constexpr() {
+                    Ok(Locatable {
+                        data: (Literal::Int(size), _),
+                        ..
+                    }) => is_lock_free_size(size as u64),
+                    Ok(Locatable {
+                        data: (Literal::UnsignedInt(size), _),
+                        ..
+                    }) => is_lock_free_size(size),
+                    _ => return Err(format!("size argument to '{}' must be a constant", name)),
+                };
+                let literal = Expr::from((Literal::Int(lock_free as i64), size_location));
+                return Ok(Expr {
+                    location,
+                    ..literal.cast(&Type::Bool).unwrap()
+                });
+            }
+            // like GCC, these always operate on a single byte
+            "test_and_set" | "clear" => {
+                let ptr = next_arg();
+                if !ptr.ctype.is_pointer() {
+                    return Err(format!(
+                        "address argument to '{}' must be a pointer, got '{}'",
+                        name, ptr.ctype
+                    ));
+                }
+                let order = self.memory_order(next_arg());
+                return Ok(if name == "test_and_set" {
+                    atomic_expr(AtomicOp::TestAndSet, vec![ptr, order], Type::Bool, location)
+                } else {
+                    atomic_expr(AtomicOp::Clear, vec![ptr, order], Type::Void, location)
+                });
+            }
+            _ => {}
+        }
+        let ptr = next_arg();
+        let object = atomic_object(&ptr, c11)?;
+        let expr = match (name, c11) {
+            ("init", true) => {
+                // initialization is not atomic (C11 7.17.2.2)
+                let value = self.atomic_value(next_arg(), &object);
+                let target = ptr.indirection(true, object.clone(), location);
+                discard(Expr {
+                    ctype: object,
+                    lval: false,
+                    constexpr: false,
+                    location,
+                    expr: ExprType::Assign(
+                        Box::new(target),
+                        Box::new(value),
+                        AssignmentToken::Equal,
+                    ),
+                })
+            }
+            ("load", true) | ("load_n", false) => {
+                let order = self.memory_order(next_arg());
+                atomic_expr(AtomicOp::Load, vec![ptr, order], object, location)
+            }
+            // __atomic_load(ptr, ret, order): *ret = __atomic_load_n(ptr, order)
+            ("load", false) => {
+                let ret = pointer_arg(next_arg(), &object, name)?;
+                let order = self.memory_order(next_arg());
+                let load = atomic_expr(AtomicOp::Load, vec![ptr, order], object.clone(), location);
+                discard(assign_through(ret, load, object, location))
+            }
+            ("store", true) | ("store_n", false) => {
+                let value = self.atomic_value(next_arg(), &object);
+                let order = self.memory_order(next_arg());
+                atomic_expr(
+                    AtomicOp::Store,
+                    vec![ptr, value, order],
+                    Type::Void,
+                    location,
+                )
+            }
+            // __atomic_store(ptr, val, order): __atomic_store_n(ptr, *val, order)
+            ("store", false) => {
+                let value = pointer_arg(next_arg(), &object, name)?;
+                let value = value.indirection(true, object, location).rval();
+                let order = self.memory_order(next_arg());
+                atomic_expr(
+                    AtomicOp::Store,
+                    vec![ptr, value, order],
+                    Type::Void,
+                    location,
+                )
+            }
+            ("exchange", true) | ("exchange_n", false) => {
+                let value = self.atomic_value(next_arg(), &object);
+                let order = self.memory_order(next_arg());
+                atomic_expr(
+                    AtomicOp::Exchange,
+                    vec![ptr, value, order],
+                    object,
+                    location,
+                )
+            }
+            // __atomic_exchange(ptr, val, ret, order): *ret = __atomic_exchange_n(ptr, *val, order)
+            ("exchange", false) => {
+                let value = pointer_arg(next_arg(), &object, name)?;
+                let value = value.indirection(true, object.clone(), location).rval();
+                let ret = pointer_arg(next_arg(), &object, name)?;
+                let order = self.memory_order(next_arg());
+                let args = vec![ptr, value, order];
+                let exchange = atomic_expr(AtomicOp::Exchange, args, object.clone(), location);
+                discard(assign_through(ret, exchange, object, location))
+            }
+            ("compare_exchange_strong", true)
+            | ("compare_exchange_weak", true)
+            | ("compare_exchange_n", false)
+            | ("compare_exchange", false) => {
+                let expected = pointer_arg(next_arg(), &object, name)?;
+                let desired = next_arg();
+                let desired = if name == "compare_exchange" {
+                    let desired = pointer_arg(desired, &object, name)?;
+                    desired.indirection(true, object, location).rval()
+                } else {
+                    self.atomic_value(desired, &object)
+                };
+                // a strong compare-exchange is always a valid implementation of a weak one
+                if !c11 {
+                    next_arg();
+                }
+                let success = self.memory_order(next_arg());
+                let failure = self.memory_order(next_arg());
+                let args = vec![ptr, expected, desired, success, failure];
+                atomic_expr(AtomicOp::CompareExchange, args, Type::Bool, location)
+            }
+            (name, _) => {
+                let (op, fetch_first) = read_modify_write(name)
+                    .expect("builtin_arity should only accept known builtins");
+                let is_add_or_sub = op == "add" || op == "sub";
+                let value = next_arg();
+                let value = if object.is_pointer() && is_add_or_sub {
+                    // C11 7.17.7.5p3: the pointer is adjusted the same way as with `+`.
+                    // GCC treats the pointer as if it were a `uintptr_t`.
+                    let value = self.atomic_value(value, &Type::Long(true));
+                    if c11 {
+                        scale_by_pointee(value, &object)?
+                    } else {
+                        value
+                    }
+                } else if object.is_integral() {
+                    self.atomic_value(value, &object)
+                } else {
+                    return Err(format!(
+                        "'{}' requires a pointer to an integer{}, got '{}'",
+                        name,
+                        if is_add_or_sub { " or pointer" } else { "" },
+                        ptr.ctype
+                    ));
+                };
+                let order = self.memory_order(next_arg());
+                let op = AtomicOp::ReadModifyWrite { op, fetch_first };
+                atomic_expr(op, vec![ptr, value, order], object, location)
+            }
+        };
+        Ok(expr)
+    }
+    fn memory_order(&mut self, order: Expr) -> Expr {
+        order
+            .cast(&Type::Int(true))
+            .recover(&mut self.error_handler)
+    }
+    fn atomic_value(&mut self, value: Expr, object: &Type) -> Expr {
+        value.cast(object).recover(&mut self.error_handler)
+    }
+}
+
+/// Check that `arg` is a pointer to a non-atomic object of type `object`.
+fn pointer_arg(arg: Expr, object: &Type, name: &str) -> Result<Expr, String> {
+    match &arg.ctype {
+        Type::Pointer(pointee, _) if **pointee == *object => Ok(arg),
+        other => Err(format!(
+            "expected pointer to '{}' for argument to '{}', got '{}'",
+            object, name, other
+        )),
+    }
+}
+
+/// Return the type of the object `ptr` points to, without `_Atomic`.
+fn atomic_object(ptr: &Expr, c11: bool) -> Result<Type, String> {
+    let pointee = match &ptr.ctype {
+        Type::Pointer(pointee, _) => pointee,
+        other => {
+            return Err(format!(
+                "address argument to atomic operation must be a pointer, got '{}'",
+                other
+            ))
+        }
+    };
+    let object = match &**pointee {
+        Type::Atomic(inner) => (**inner).clone(),
+        other if !c11 => other.clone(),
+        _ => {
+            return Err(format!(
+            "address argument to atomic operation must be a pointer to an _Atomic type, got '{}'",
+            ptr.ctype
+        ))
+        }
+    };
+    let lock_free = object.sizeof().map(is_lock_free_size).unwrap_or(false);
+    if !object.is_scalar() || object.is_complex() || !lock_free {
+        return Err(format!(
+            "atomic operations on objects of type '{}' are not supported",
+            object
+        ));
+    }
+    Ok(object)
+}
+
+/// Multiply `value` by the size of the type `pointer` points to.
+fn scale_by_pointee(value: Expr, pointer: &Type) -> Result<Expr, String> {
+    let size = match pointer {
+        Type::Pointer(pointee, _) => pointee.sizeof().map_err(|err| err.to_string())?,
+        _ => unreachable!("scale_by_pointee should only be called on pointers"),
+    };
+    let literal = Expr::from((Literal::Int(size as i64), value.location));
+    let size = Expr {
+        ctype: value.ctype.clone(),
+        constexpr: literal.constexpr,
+        lval: false,
+        location: literal.location,
+        expr: ExprType::Cast(Box::new(literal)),
+    };
+    Ok(Expr {
+        ctype: value.ctype.clone(),
+        lval: false,
+        constexpr: value.constexpr,
+        location: value.location,
+        expr: ExprType::Mul(Box::new(value), Box::new(size)),
+    })
+}
+
+/// `*ptr = value`
+fn assign_through(ptr: Expr, value: Expr, ctype: Type, location: Location) -> Expr {
+    let target = ptr.indirection(true, ctype.clone(), location);
+    Expr {
+        ctype,
+        lval: false,
+        constexpr: false,
+        location,
+        expr: ExprType::Assign(Box::new(target), Box::new(value), AssignmentToken::Equal),
+    }
+}
+
+/// Cast `expr` to void, for builtins that don't return anything.
+fn discard(expr: Expr) -> Expr {
+    Expr {
+        ctype: Type::Void,
+        lval: false,
+        constexpr: false,
+        location: expr.location,
+        expr: ExprType::Cast(Box::new(expr)),
+    }
+}
+
+fn atomic_expr(op: AtomicOp, args: Vec<Expr>, ctype: Type, location: Location) -> Expr {
+    Expr {
+        ctype,
+        lval: false,
+        constexpr: false,
+        location,
+        expr: ExprType::Atomic(op, args),
+    }
+}
+
+/// libatomic has lock-free implementations for objects of these sizes
+fn is_lock_free_size(size: u64) -> bool {
+    size == 1 || size == 2 || size == 4 || size == 8
+}
+
+/// Split `fetch_add` into `("add", true)` and `add_fetch` into `("add", false)`.
+///
+/// `fetch_add` returns the old value, `add_fetch` returns the new value.
+fn read_modify_write(name: &str) -> Option<(&'static str, bool)> {
+    const OPS: &[&str] = &["add", "sub", "and", "or", "xor", "nand"];
+    OPS.iter().find_map(|&op| {
+        if name == format!("fetch_{}", op) {
+            Some((op, true))
+        } else if name == format!("{}_fetch", op) {
+            Some((op, false))
+        } else {
+            None
+        }
+    })
+}
+
+/// The number of arguments an atomic builtin takes, or None if `name` isn't a builtin.
+fn builtin_arity(name: &str) -> Option<usize> {
+    let arity = if name.starts_with(C11_PREFIX) {
+        match &name[C11_PREFIX.len()..] {
+            "thread_fence" | "signal_fence" | "is_lock_free" => 1,
+            "init" | "load" => 2,
+            "store" | "exchange" | "fetch_add" | "fetch_sub" | "fetch_and" | "fetch_or"
+            | "fetch_xor" => 3,
+            "compare_exchange_strong" | "compare_exchange_weak" => 5,
+            _ => return None,
+        }
+    } else if name.starts_with(GNU_PREFIX) {
+        match &name[GNU_PREFIX.len()..] {
+            "thread_fence" | "signal_fence" => 1,
+            "load_n" | "test_and_set" | "clear" | "always_lock_free" | "is_lock_free" => 2,
+            "store_n" | "exchange_n" | "load" | "store" => 3,
+            "exchange" => 4,
+            "compare_exchange_n" | "compare_exchange" => 6,
+            other => read_modify_write(other).map(|_| 3)?,
+        }
+    } else {
+        return None;
+    };
+    Some(arity)
+}
@@ -0,0 +1,436 @@
+//! Semantic checks for GNU attributes.
+//!
+//! Attributes that only change how a declaration is emitted, like `weak` or `alias`,
+//! are checked here and left on the `Declaration` for the backend.
+//! `aligned` and `packed` change the layout of objects, so they are applied to the `Symbol`.
+//! Attributes that cause warnings, like `deprecated`, are remembered in `Parser::usage`
+//! for as long as the declaration is in scope.
+
+use std::cmp;
+
+use super::{Lexeme, Parser};
+use crate::arch::SIZE_T;
+use crate::data::prelude::*;
+use crate::data::StorageClass;
+
+/// The alignment of `aligned` without an argument: the largest alignment of any type.
+const BIGGEST_ALIGNMENT: SIZE_T = 16;
+
+/// Attributes that are accepted for compatibility with GCC, but have no effect.
+///
+/// These are optimization hints or enable checks that rcc doesn't have.
+const IGNORED_ATTRIBUTES: &[&str] = &[
+    "access",
+    "alloc_align",
+    "alloc_size",
+    "always_inline",
+    "artificial",
+    "cold",
+    "const",
+    "error",
+    "externally_visible",
+    "fallthrough",
+    "flatten",
+    "format",
+    "format_arg",
+    "gnu_inline",
+    "hot",
+    "leaf",
+    "malloc",
+    "may_alias",
+    "no_instrument_function",
+    "noinline",
+    "nonnull",
+    "nonstring",
+    "noplt",
+    "nothrow",
+    "pure",
+    "returns_nonnull",
+    "returns_twice",
+    "sentinel",
+    "transparent_union",
+    "warning",
+];
+
+/// Attributes that only make sense on a declaration, not on a type or struct member.
+const DECLARATION_ATTRIBUTES: &[&str] = &[
+    "alias",
+    "constructor",
+    "destructor",
+    "noreturn",
+    "section",
+    "used",
+    "visibility",
+    "warn_unused_result",
+    "weak",
+];
+
+/// What is known about the uses of an identifier, for the warnings that depend on attributes.
+///
+/// This also remembers where the identifier was declared,
+/// so errors in later declarations can point to the earlier ones.
+#[derive(Clone, Debug)]
+pub(super) struct Usage {
+    /// the declaration to point to when warning that it is unused
+    location: Location,
+    /// the most recent declaration
+    pub(super) declared: Location,
+    /// the definition, if there has been one
+    pub(super) defined: Option<Location>,
+    /// whether the identifier has been used in an expression
+    used: bool,
+    /// whether it was declared `unused` or `used`, so there's no need to warn if it isn't used
+    maybe_unused: bool,
+    /// whether this is the definition of a `static` function, which is useless if it's never used
+    static_definition: bool,
+    /// the message from `deprecated`, which may be empty
+    deprecated: Option<String>,
+    warn_unused_result: bool,
+}
+
+impl<I: Iterator<Item = Lexeme>> Parser<I> {
+    /// Check the attributes of a declaration and apply the ones that change the symbol itself.
+    ///
+    /// This is called before `decl` is added to the scope;
+    /// the result should be passed to `record_usage` afterwards.
+    pub(super) fn declaration_attributes(
+        &mut self,
+        decl: &mut Symbol,
+        attributes: &[Locatable<Attribute>],
+        location: Location,
+    ) -> Usage {
+        let is_function = decl.ctype.is_function();
+        let mut usage = Usage {
+            location,
+            declared: location,
+            defined: if decl.init { Some(location) } else { None },
+            used: false,
+            maybe_unused: false,
+            static_definition: is_function
+                && decl.init
+                && decl.storage_class == StorageClass::Static
+                && !decl.qualifiers.inline,
+            deprecated: None,
+            warn_unused_result: false,
+        };
+        let static_storage = is_function
+            || self.current_function.is_none()
+            || decl.storage_class == StorageClass::Static
+            || decl.storage_class == StorageClass::Extern;
+        for attribute in attributes {
+            let name = attribute.data.name.resolve_and_clone();
+            let location = attribute.location;
+            match name.as_str() {
+                // functions are always aligned enough
+                "aligned" if is_function => {}
+                "aligned" => {
+                    if let Some(align) = self.requested_alignment(attribute) {
+                        let natural = decl.ctype.alignof().unwrap_or(1);
+                        let align = cmp::max(align, natural);
+                        decl.qualifiers.align = cmp::max(decl.qualifiers.align, Some(align));
+                    }
+                }
+                "packed" => self.error_handler.warn(
+                    Warning::IgnoredAttribute(format!(
+                        "'packed' attribute ignored for '{}', which is not a struct member",
+                        decl.id
+                    )),
+                    location,
+                ),
+                "noreturn" => {
+                    self.no_arguments(attribute);
+                    match &mut decl.ctype {
+                        Type::Function(ftype) => ftype.noreturn = true,
+                        _ => self.not_a_function(&name, location),
+                    }
+                }
+                "section" => match attribute.data.string_argument() {
+                    None => self.argument_err(attribute, "a string literal"),
+                    // the machine code of a function can't be moved after Cranelift emits it
+                    Some(_) if is_function => self.error_handler.warn(
+                        Warning::IgnoredAttribute(format!(
+                            "'section' attribute ignored for function '{}', which is always in '.text'",
+                            decl.id
+                        )),
+                        location,
+                    ),
+                    Some(_) if !static_storage => self.semantic_err(
+                        format!(
+                            "'section' attribute cannot be specified for local variable '{}'",
+                            decl.id
+                        ),
+                        location,
+                    ),
+                    // only objects at file scope are moved, see `ir::attribute`
+                    Some(_) if self.current_function.is_some() => self.error_handler.warn(
+                        Warning::IgnoredAttribute(format!(
+                            "'section' attribute ignored for '{}' at block scope",
+                            decl.id
+                        )),
+                        location,
+                    ),
+                    Some(_) => {}
+                },
+                "weak" => {
+                    self.no_arguments(attribute);
+                    if decl.storage_class == StorageClass::Static || !static_storage {
+                        self.semantic_err(
+                            format!("weak declaration of '{}' must be public", decl.id),
+                            location,
+                        );
+                    }
+                }
+                "visibility" => match attribute.data.string_argument().as_deref() {
+                    Some("default") | Some("hidden") | Some("internal") => {}
+                    Some("protected") => self.error_handler.warn(
+                        Warning::IgnoredAttribute(
+                            "'protected' visibility is not supported, using 'default'".into(),
+                        ),
+                        location,
+                    ),
+                    _ => self.semantic_err(
+                        "visibility must be one of \"default\", \"hidden\", \"internal\" or \"protected\"",
+                        location,
+                    ),
+                },
+                "alias" => match attribute.data.string_argument() {
+                    None => self.argument_err(attribute, "a string literal"),
+                    Some(_) if decl.init => self.semantic_err(
+                        format!("'{}' is defined both normally and as an alias", decl.id),
+                        location,
+                    ),
+                    Some(_) if !static_storage => self.semantic_err(
+                        "'alias' attribute cannot be specified for local variables",
+                        location,
+                    ),
+                    Some(_) => {}
+                },
+                "constructor" | "destructor" => {
+                    // the C runtime calls it even if nothing else does
+                    usage.maybe_unused = true;
+                    if !is_function {
+                        self.not_a_function(&name, location);
+                    } else if !attribute.data.args.is_empty() {
+                        match attribute.data.integer_argument() {
+                            Some(priority) if priority > 65535 => self.semantic_err(
+                                format!("{} priorities must be integers from 0 to 65535", name),
+                                location,
+                            ),
+                            Some(priority) if priority <= 100 => self
+                                .error_handler
+                                .warn(Warning::ReservedPriority(name.clone()), location),
+                            Some(_) => {}
+                            None => self.argument_err(attribute, "an integer constant"),
+                        }
+                    }
+                }
+                "used" | "unused" => {
+                    self.no_arguments(attribute);
+                    usage.maybe_unused = true;
+                }
+                "deprecated" => {
+                    usage.deprecated = if attribute.data.args.is_empty() {
+                        Some(String::new())
+                    } else {
+                        let message = attribute.data.string_argument();
+                        if message.is_none() {
+                            self.argument_err(attribute, "a string literal");
+                        }
+                        message
+                    };
+                }
+                "warn_unused_result" => {
+                    self.no_arguments(attribute);
+                    if is_function {
+                        usage.warn_unused_result = true;
+                    } else {
+                        self.not_a_function(&name, location);
+                    }
+                }
+                _ => self.ignore_attribute(&name, location),
+            }
+        }
+        usage
+    }
+    /// Remember the attributes of `id` that affect warnings,
+    /// combined with those of any earlier declaration in the same scope.
+    pub(super) fn record_usage(&mut self, id: InternedStr, mut usage: Usage) {
+        if let Some(existing) = self.usage.get_immediate(&id) {
+            usage.used |= existing.used;
+            usage.maybe_unused |= existing.maybe_unused;
+            if existing.static_definition {
+                usage.static_definition = true;
+                usage.location = existing.location;
+            }
+            if usage.deprecated.is_none() {
+                usage.deprecated = existing.deprecated.clone();
+            }
+            usage.warn_unused_result |= existing.warn_unused_result;
+            usage.defined = existing.defined.or(usage.defined);
+        }
+        self.usage.insert(id, usage);
+    }
+    /// Note that `id` was used in an expression, warning if it is deprecated.
+    pub(super) fn use_identifier(&mut self, id: InternedStr, location: Location) {
+        if let Some(usage) = self.usage.get_mut(&id) {
+            usage.used = true;
+            if let Some(message) = &usage.deprecated {
+                let warning = if message.is_empty() {
+                    format!("'{}' is deprecated", id)
+                } else {
+                    format!("'{}' is deprecated: {}", id, message)
+                };
+                self.error_handler
+                    .warn(Warning::Deprecated(warning), location);
+            }
+        }
+    }
+    /// Warn if `expr` is a call to a `warn_unused_result` function whose result is discarded.
+    pub(super) fn check_unused_result(&mut self, expr: &Expr) {
+        if let ExprType::FuncCall(func, _) = &expr.expr {
+            if let ExprType::Id(symbol) = &func.expr {
+                let warn = self
+                    .usage
+                    .get(&symbol.id)
+                    .map_or(false, |usage| usage.warn_unused_result);
+                if warn {
+                    self.error_handler
+                        .warn(Warning::UnusedResult(symbol.id), expr.location);
+                }
+            }
+        }
+    }
+    /// Warn about `static` functions in the current scope that were defined but never used.
+    pub(super) fn warn_unused_functions(&mut self) {
+        let mut unused: Vec<_> = self
+            .usage
+            .get_all_immediate()
+            .iter()
+            .filter(|(_, usage)| usage.static_definition && !usage.used && !usage.maybe_unused)
+            .map(|(id, usage)| (*id, usage.location))
+            .collect();
+        unused.sort_by_key(|(id, _)| id.resolve_and_clone());
+        for (id, location) in unused {
+            self.error_handler
+                .warn(Warning::UnusedFunction(id), location);
+        }
+    }
+    /// The alignment of a struct member, if `packed` or `aligned` changed it.
+    pub(super) fn member_alignment(
+        &mut self,
+        attributes: &[Locatable<Attribute>],
+        ctype: &Type,
+    ) -> Option<SIZE_T> {
+        let (packed, aligned) = self.layout_attributes(attributes);
+        match aligned {
+            // `packed` allows `aligned` to decrease the alignment as well as increase it
+            Some(align) if packed => Some(align),
+            Some(align) => Some(cmp::max(align, ctype.alignof().unwrap_or(1))),
+            None if packed => Some(1),
+            None => None,
+        }
+    }
+    /// Apply `packed` and `aligned` on a struct or union to its members.
+    pub(super) fn struct_attributes(
+        &mut self,
+        attributes: &[Locatable<Attribute>],
+        members: &mut [Symbol],
+    ) {
+        let (packed, aligned) = self.layout_attributes(attributes);
+        if packed {
+            for member in members.iter_mut() {
+                // NOTE: bitfields keep the alignment of their type,
+                // so loading their storage unit never reads past the end of the struct
+                match member.ctype {
+                    Type::Bitfield(_) => {}
+                    _ if member.qualifiers.align.is_some() => {}
+                    _ => member.qualifiers.align = Some(1),
+                }
+            }
+        }
+        if let Some(align) = aligned {
+            // a struct is as aligned as its most aligned member,
+            // and the first member is always at offset 0, so this doesn't change the layout
+            if let Some(first) = members.iter_mut().find(|member| !member.is_padding()) {
+                let natural = first.alignof().unwrap_or(1);
+                first.qualifiers.align = Some(cmp::max(natural, align));
+            }
+        }
+    }
+    /// Find `packed` and `aligned` in the attributes of a type or struct member.
+    fn layout_attributes(&mut self, attributes: &[Locatable<Attribute>]) -> (bool, Option<SIZE_T>) {
+        let (mut packed, mut aligned) = (false, None);
+        for attribute in attributes {
+            let name = attribute.data.name.resolve_and_clone();
+            match name.as_str() {
+                "packed" => {
+                    self.no_arguments(attribute);
+                    packed = true;
+                }
+                "aligned" => {
+                    if let Some(align) = self.requested_alignment(attribute) {
+                        aligned = cmp::max(aligned, Some(align));
+                    }
+                }
+                "deprecated" | "unused" => {}
+                _ => self.ignore_attribute(&name, attribute.location),
+            }
+        }
+        (packed, aligned)
+    }
+    /// The argument of `aligned`, which must be a power of two.
+    fn requested_alignment(&mut self, attribute: &Locatable<Attribute>) -> Option<SIZE_T> {
+        if attribute.data.args.is_empty() {
+            return Some(BIGGEST_ALIGNMENT);
+        }
+        match attribute.data.integer_argument() {
+            Some(align) if align.is_power_of_two() => Some(align),
+            Some(align) => {
+                self.semantic_err(
+                    format!("requested alignment {} is not a power of 2", align),
+                    attribute.location,
+                );
+                None
+            }
+            None => {
+                self.argument_err(attribute, "an integer constant");
+                None
+            }
+        }
+    }
+    fn ignore_attribute(&mut self, name: &str, location: Location) {
+        if IGNORED_ATTRIBUTES.contains(&name) {
+            return;
+        }
+        let warning = if DECLARATION_ATTRIBUTES.contains(&name) {
+            format!("'{}' attribute ignored", name)
+        } else {
+            format!("unknown attribute '{}' ignored", name)
+        };
+        self.error_handler
+            .warn(Warning::IgnoredAttribute(warning), location);
+    }
+    fn no_arguments(&mut self, attribute: &Locatable<Attribute>) {
+        if !attribute.data.args.is_empty() {
+            self.semantic_err(
+                format!("'{}' attribute takes no arguments", attribute.data.name),
+                attribute.location,
+            );
+        }
+    }
+    fn argument_err(&mut self, attribute: &Locatable<Attribute>, expected: &str) {
+        self.semantic_err(
+            format!(
+                "'{}' attribute requires {} as its only argument",
+                attribute.data.name, expected
+            ),
+            attribute.location,
+        );
+    }
+    fn not_a_function(&mut self, name: &str, location: Location) {
+        self.error_handler.warn(
+            Warning::IgnoredAttribute(format!("'{}' attribute only applies to functions", name)),
+            location,
+        );
+    }
+}
@@ -1,6 +1,6 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::TryFrom;
-use std::iter::{FromIterator, Iterator};
+use std::iter::Iterator;
 use std::mem;
 
 use super::{FunctionData, Lexeme, Parser, SyntaxResult, TagEntry};
@@ -8,8 +8,8 @@ use crate::arch::SIZE_T;
 use crate::data::{
     lex::Keyword,
     prelude::*,
-    types::{ArrayType, FunctionType},
-    Initializer, Qualifiers, StorageClass,
+    types::{ArrayType, BitfieldType, FunctionType},
+    Initializer, LengthError, Qualifiers, StorageClass,
 };
 
 impl<I: Iterator<Item = Lexeme>> Parser<I> {
@@ -30,15 +30,15 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
     ///
     /// Used for casts and `sizeof` builtin.
     pub fn type_name(&mut self) -> SyntaxResult<Locatable<(Type, Qualifiers)>> {
-        let (sc, qualifiers, ctype, _) = self.declaration_specifiers()?;
-        if sc != None {
+        let (sc, mut qualifiers, ctype, _, _) = self.declaration_specifiers()?;
+        if sc != None || qualifiers.thread_local {
             self.semantic_err("type cannot have a storage class", self.last_location);
         }
         let ctype = match self.declarator(true, qualifiers)? {
             None => ctype,
             Some(decl) => {
                 let (id, ctype) = decl
-                    .parse_type(ctype, false, &self.last_location)
+                    .parse_type(ctype, &mut qualifiers, false, &self.last_location)
                     .recover(&mut self.error_handler);
                 if let Some(Locatable {
                     location,
@@ -65,12 +65,12 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
      * and return the last.
      */
     pub fn declaration(&mut self) -> SyntaxResult<VecDeque<Locatable<Declaration>>> {
-        let (sc, mut qualifiers, ctype, seen_compound_type) = self.declaration_specifiers()?;
+        let (sc, mut qualifiers, ctype, seen_compound_type, attributes) =
+            self.declaration_specifiers()?;
         if self.match_next(&Token::Semicolon).is_some() {
             if !seen_compound_type {
                 let loc = self.next_location();
-                self.error_handler
-                    .warn("declaration does not declare anything", loc);
+                self.error_handler.warn(Warning::EmptyDeclaration, loc);
             }
             return Ok(VecDeque::new());
         }
@@ -79,85 +79,152 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
         let declarator = self
             .declarator(false, qualifiers)?
             .expect("declarator should return id when called with allow_abstract: false");
-        let (id, first_type) = declarator
+        let mut first_qualifiers = qualifiers;
+        let (id, mut first_type) = declarator
             .parse_type(
                 ctype.clone(),
+                &mut first_qualifiers,
                 sc == Some(StorageClass::Typedef),
                 &self.last_location,
             )
             .recover(&mut self.error_handler);
         let id = id.expect("declarator should return id when called with allow_abstract: false");
+        let (asm_label, declarator_attributes) = self.declarator_extensions()?;
+        if qualifiers.thread_local {
+            self.check_thread_local(sc, id.location);
+        }
+        // `extern inline` provides an external definition,
+        // the same as a declaration without `inline` (C11 6.7.4p7)
+        if sc == Some(StorageClass::Extern) {
+            qualifiers.inline = false;
+            first_qualifiers.inline = false;
+        }
+        let is_definition = match self.peek_token() {
+            Some(Token::Semicolon) | Some(Token::Comma) | None => false,
+            Some(token) => *token != Token::EQUAL,
+        };
+        match &mut first_type {
+            Type::Function(ftype) if ftype.old_style && is_definition => {
+                self.old_style_params(ftype, id.location)?
+            }
+            _ => self.reject_identifier_list(&mut first_type, id.location),
+        }
         let sc = match sc {
             Some(sc) => sc,
             None if first_type.is_function() => StorageClass::Extern,
             None => StorageClass::Auto,
         };
+        if let Some(label) = asm_label {
+            self.check_asm_label(label, sc, &first_type, id.location);
+        }
+        let mut pending = self.declare_vla_lengths(&id, &mut first_type, sc);
         if sc == StorageClass::Typedef {
             // evaluated only for its side effects
-            self.parse_typedef(id, first_type, qualifiers)?;
-            return Ok(VecDeque::new());
+            self.parse_typedef(
+                id,
+                ctype,
+                qualifiers,
+                first_type,
+                first_qualifiers,
+                &mut pending,
+            )?;
+            return Ok(pending);
         }
 
         let mut symbol = Symbol {
             id: id.data,
             ctype: first_type,
-            qualifiers,
+            qualifiers: first_qualifiers,
             storage_class: sc,
             init: false,
         };
+        let first_attributes: Vec<_> = attributes
+            .iter()
+            .cloned()
+            .chain(declarator_attributes)
+            .collect();
         // if it's not a function, we still need to handle it
         let init = match (&symbol.ctype, self.peek_token()) {
-            (Type::Function(ftype), Some(Token::LeftBrace)) => {
+            (Type::Function(_), Some(Token::LeftBrace)) => {
                 symbol.init = true;
-                let ftype = ftype.clone();
-                self.declare(&mut symbol, &id.location);
+                self.declare(&mut symbol, &id.location, &first_attributes);
+                let ftype = match &symbol.ctype {
+                    Type::Function(ftype) => ftype.clone(),
+                    _ => unreachable!(),
+                };
+                // so far, every declaration is `inline` without `extern` (C11 6.7.4p7)
+                let inline_definition = symbol.storage_class == StorageClass::Extern
+                    && self
+                        .scope
+                        .get(&symbol.id)
+                        .map_or(false, |merged| merged.qualifiers.inline);
                 Some(Initializer::FunctionBody(self.function_body(
                     symbol.id.clone(),
                     ftype,
+                    inline_definition,
                     id.location.clone(),
                 )?))
             }
             (Type::Function(_), Some(t)) if *t == Token::EQUAL => {
-                return Err(id.location.with(SyntaxError::from(format!(
+                return Err(id.location.error(SyntaxError::from(format!(
                     "expected '{{', got '=' while parsing function body for {}",
                     symbol.id,
                 ))));
             }
             (ctype, Some(t)) if *t == Token::EQUAL => {
                 self.next_token();
-                let init = Some(self.initializer(ctype)?);
+                let init = self.initializer(ctype)?;
+                symbol.ctype.infer_array_size(&init);
+                let init = Some(init);
                 symbol.init = true;
-                self.declare(&mut symbol, &id.location);
+                self.declare(&mut symbol, &id.location, &first_attributes);
                 init
             }
             _ => {
-                self.declare(&mut symbol, &id.location);
+                self.declare(&mut symbol, &id.location, &first_attributes);
                 None
             }
         };
-        if symbol.ctype.is_function() && (qualifiers.c_const || qualifiers.volatile) {
+        if symbol.ctype.is_function()
+            && (first_qualifiers.c_const || first_qualifiers.volatile || first_qualifiers.restrict)
+        {
             self.error_handler.warn(
-                &format!("{} has no effect on function return type", qualifiers),
+                Warning::IgnoredQualifiers(
+                    first_qualifiers
+                        .type_qualifiers()
+                        .to_string()
+                        .trim_end()
+                        .to_string(),
+                ),
                 id.location,
             );
-            qualifiers.c_const = false;
-            qualifiers.volatile = false;
         }
         let decl = Locatable {
-            data: Declaration { symbol, init },
+            data: Declaration {
+                symbol,
+                init,
+                attributes: first_attributes,
+                asm_label,
+            },
             location: id.location,
         };
         let init = decl.data.init.is_some();
         let is_function = decl.data.symbol.ctype.is_function();
-        let mut pending = VecDeque::from_iter(std::iter::once(decl));
+        pending.push_back(decl);
         if (is_function && init) || self.match_next(&Token::Semicolon).is_some() {
             return Ok(pending);
-        } else {
-            self.expect(Token::Comma)?;
+        }
+        // a missing `;` is much more likely than a missing `,`, so suggest that instead
+        if self.match_next(&Token::Comma).is_none() {
+            self.expect(Token::Semicolon)?;
+            return Ok(pending);
         }
         loop {
-            let mut decl = self.init_declarator(sc, qualifiers, ctype.clone())?;
-            self.declare(&mut decl.data.symbol, &decl.location);
+            let mut decl = self.init_declarator(sc, qualifiers, ctype.clone(), &attributes)?;
+            let symbol = &mut decl.data.symbol;
+            let id = decl.location.with(symbol.id);
+            pending.extend(self.declare_vla_lengths(&id, &mut symbol.ctype, symbol.storage_class));
+            self.declare(&mut decl.data.symbol, &decl.location, &decl.data.attributes);
             pending.push_back(decl);
             if self.match_next(&Token::Comma).is_none() {
                 self.expect(Token::Semicolon)?;
@@ -166,6 +233,90 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
         }
         Ok(pending)
     }
+    /// Parse the parameter declarations of an old-style definition,
+    /// e.g. `int a; char *b;` in `int f(a, b) int a; char *b; { ... }`.
+    fn old_style_params(
+        &mut self,
+        ftype: &mut FunctionType,
+        location: Location,
+    ) -> SyntaxResult<()> {
+        let mut declared = vec![None; ftype.params.len()];
+        while self.peek_token() != Some(&Token::LeftBrace) {
+            let (sc, quals, base_type, ..) = self.declaration_specifiers()?;
+            match sc {
+                None | Some(StorageClass::Register) => {}
+                Some(sc) => self.semantic_err(
+                    format!("cannot specify storage class '{}' for a parameter", sc),
+                    self.last_location,
+                ),
+            }
+            if quals.thread_local {
+                self.check_thread_local(Some(StorageClass::Auto), self.last_location);
+            }
+            loop {
+                let decl = self
+                    .declarator(false, quals)?
+                    .expect("declarator should return id when called with allow_abstract: false");
+                let mut quals = quals;
+                let (id, mut ctype) = decl
+                    .parse_type(base_type.clone(), &mut quals, false, &self.last_location)
+                    .recover(&mut self.error_handler);
+                let id =
+                    id.expect("declarator should return id when called with allow_abstract: false");
+                self.attributes()?;
+                // the same adjustment as for prototypes
+                if let Type::Array(to, _) = ctype {
+                    ctype = Type::Pointer(to, quals.type_qualifiers());
+                    quals.c_const = false;
+                    quals.volatile = false;
+                }
+                let index = ftype.params.iter().position(|param| param.id == id.data);
+                match index.map(|i| (i, declared[i])) {
+                    None => self.semantic_err(
+                        format!("'{}' is declared but is not a parameter", id.data),
+                        id.location,
+                    ),
+                    Some((_, Some(previous))) => {
+                        let err = format!("redefinition of parameter '{}'", id.data);
+                        let err = CompileError::semantic(id.location.with(err))
+                            .note("previous declaration is here", previous);
+                        self.error_handler.push_back(err);
+                    }
+                    Some((i, None)) => {
+                        declared[i] = Some(id.location);
+                        ftype.params[i].ctype = ctype;
+                        ftype.params[i].qualifiers = quals;
+                    }
+                }
+                if self.match_next(&Token::Comma).is_none() {
+                    self.expect(Token::Semicolon)?;
+                    break;
+                }
+            }
+        }
+        for (param, declared) in ftype.params.iter().zip(declared) {
+            if declared.is_none() {
+                self.error_handler
+                    .warn(Warning::ImplicitIntParameter(param.id), location);
+            }
+        }
+        Ok(())
+    }
+    /// C11 6.7.6.3p3: "An identifier list in a function declarator that is not part of a
+    /// definition of that function shall be empty."
+    fn reject_identifier_list(&mut self, ctype: &mut Type, location: Location) {
+        if let Type::Function(ftype) = ctype {
+            if ftype.old_style {
+                self.semantic_err(
+                    "a parameter list without types is only allowed in a function definition",
+                    location,
+                );
+                // treat it the same as `int f()`
+                ftype.params.clear();
+                ftype.old_style = false;
+            }
+        }
+    }
     // check if this is a valid signature for 'main'
     fn is_main_func_signature(ftype: &FunctionType) -> bool {
         // main must return 'int' and must not be variadic
@@ -186,8 +337,8 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             return false;
         }
         match types[1] {
-            Type::Pointer(t) | Type::Array(t, _) => match &**t {
-                Type::Pointer(inner) => inner.is_char(),
+            Type::Pointer(t, _) | Type::Array(t, _) => match &**t {
+                Type::Pointer(inner, _) => inner.is_char(),
                 _ => false,
             },
             _ => false,
@@ -196,24 +347,34 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
     fn parse_typedef(
         &mut self,
         first_id: Locatable<InternedStr>,
+        base_ctype: Type,
+        base_qualifiers: Qualifiers,
         first_ctype: Type,
         first_qualifiers: Qualifiers,
+        vla_lengths: &mut VecDeque<Locatable<Declaration>>,
     ) -> SyntaxResult<()> {
-        self.declare_typedef(first_id, first_ctype.clone(), first_qualifiers);
+        self.declare_typedef(first_id, first_ctype, first_qualifiers);
         if self.match_next(&Token::Semicolon).is_some() {
             return Ok(());
         }
-        self.expect(Token::Comma)?;
+        if self.match_next(&Token::Comma).is_none() {
+            self.expect(Token::Semicolon)?;
+            return Ok(());
+        }
         loop {
             let decl = self
-                .declarator(false, first_qualifiers)?
+                .declarator(false, base_qualifiers)?
                 .expect("declarator should return Some when called with allow_abstract: false");
             let location = decl.id().unwrap().location;
-            let (id, ctype) = decl
-                .parse_type(first_ctype.clone(), true, &location)
+            let mut qualifiers = base_qualifiers;
+            let (id, mut ctype) = decl
+                .parse_type(base_ctype.clone(), &mut qualifiers, true, &location)
                 .recover(&mut self.error_handler);
             let id = id.unwrap();
-            self.declare_typedef(id, ctype, first_qualifiers);
+            self.attributes()?;
+            self.reject_identifier_list(&mut ctype, id.location);
+            vla_lengths.extend(self.declare_vla_lengths(&id, &mut ctype, StorageClass::Typedef));
+            self.declare_typedef(id, ctype, qualifiers);
             if self.match_next(&Token::Comma).is_none() {
                 self.expect(Token::Semicolon)?;
                 return Ok(());
@@ -227,6 +388,12 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                 id.location,
             );
         }
+        if qualifiers.noreturn {
+            self.semantic_err(
+                "`_Noreturn` is only allowed on function declarations",
+                id.location,
+            );
+        }
         let typedef = Symbol {
             id: id.data,
             ctype: ctype.clone(),
@@ -250,7 +417,13 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             self.semantic_err(message, id.location);
         }
     }
-    fn declare(&mut self, decl: &mut Symbol, location: &Location) {
+    fn declare(
+        &mut self,
+        decl: &mut Symbol,
+        location: &Location,
+        attributes: &[Locatable<Attribute>],
+    ) {
+        let usage = self.declaration_attributes(decl, attributes, *location);
         if decl.id == InternedStr::get_or_intern("main") {
             if let Type::Function(ftype) = &decl.ctype {
                 if !Self::is_main_func_signature(ftype) {
@@ -260,52 +433,317 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                     );
                 }
             }
+            // C11 6.7.4p4
+            if decl.qualifiers.inline {
+                self.semantic_err("'main' cannot be declared 'inline'", *location);
+                decl.qualifiers.inline = false;
+            }
+        }
+        if decl.qualifiers.noreturn {
+            if let Type::Function(ftype) = &mut decl.ctype {
+                ftype.noreturn = true;
+            }
+            decl.qualifiers.noreturn = false;
+        }
+        if decl.qualifiers.thread_local && decl.ctype.is_function() {
+            self.semantic_err(
+                format!("function '{}' cannot be declared '_Thread_local'", decl.id),
+                *location,
+            );
+            decl.qualifiers.thread_local = false;
+        }
+        // C11 6.7.4p3
+        if decl.storage_class == StorageClass::Static
+            && !decl.ctype.is_function()
+            && !decl.qualifiers.c_const
+        {
+            if let Some(func) = self
+                .current_function
+                .as_ref()
+                .filter(|f| f.inline_definition)
+            {
+                let err = format!(
+                    "non-const static variable '{}' cannot be defined in inline function '{}' with external linkage",
+                    decl.id, func.id
+                );
+                self.semantic_err(err, *location);
+            }
+        }
+        if self.current_function.is_some()
+            && decl.ctype.is_function()
+            && decl.storage_class == StorageClass::Static
+        {
+            // C11 6.7.1p7
+            self.semantic_err(
+                format!(
+                    "function '{}' declared at block scope cannot be 'static'",
+                    decl.id
+                ),
+                *location,
+            );
+            decl.storage_class = StorageClass::Extern;
         }
         // e.g. extern int i = 1;
         // this is a silly thing to do, but valid: https://stackoverflow.com/a/57900212/7669110
         if decl.storage_class == StorageClass::Extern && !decl.ctype.is_function() && decl.init {
-            self.error_handler.warn(
-                "this is a definition, not a declaration, the 'extern' keyword has no effect",
+            if self.current_function.is_some() {
+                // C11 6.7.9p5
+                self.semantic_err(
+                    format!(
+                        "'{}' is declared 'extern' and cannot be initialized",
+                        decl.id
+                    ),
+                    *location,
+                );
+            } else {
+                self.error_handler
+                    .warn(Warning::ExternInitializer, *location);
+            }
+            decl.storage_class = StorageClass::Auto;
+        }
+        self.check_linkage(decl, *location);
+        // e.g. `struct s; int main() { struct s my_s; }`
+        // at file scope, the type may still be completed later in the file
+        if self.current_function.is_some()
+            && decl.storage_class != StorageClass::Extern
+            && !decl.ctype.is_function()
+            && decl.ctype != Type::Void
+            && !decl.ctype.is_complete()
+        {
+            let msg = format!(
+                "variable '{}' has incomplete type '{}'",
+                decl.id, decl.ctype
+            );
+            let msg = self.with_tag_suggestion(msg, &decl.ctype);
+            self.semantic_err(msg, *location);
+        }
+        if decl.init && decl.ctype.is_vla() {
+            self.semantic_err(
+                format!("variable length array '{}' cannot be initialized", decl.id),
                 *location,
             );
-            decl.storage_class = StorageClass::Auto;
         }
         if let Some(existing) = self.scope.get_immediate(&decl.id) {
+            // remember the definition so `int i = 1; int i; int i = 2;` is still an error
+            let init = decl.init || existing.init;
+            // `aligned` can only increase the alignment of an object
+            decl.qualifiers.align = std::cmp::max(decl.qualifiers.align, existing.qualifiers.align);
+            // a function is `_Noreturn` if any of its declarations are
+            if let (Type::Function(old), Type::Function(new)) = (&existing.ctype, &mut decl.ctype) {
+                new.noreturn |= old.noreturn;
+            }
+            // but only an inline definition if all of them are `inline` (C11 6.7.4p7)
+            let inline = decl.qualifiers.inline && existing.qualifiers.inline;
             if existing == decl {
+                let conflict = match (&existing.ctype, &decl.ctype) {
+                    (Type::Function(old), Type::Function(new)) => prototype_conflict(old, new),
+                    _ => None,
+                };
                 if decl.init && existing.init {
-                    self.semantic_err(format!("redefinition of '{}'", decl.id), *location);
+                    let err = format!("redefinition of '{}'", decl.id);
+                    self.err_with_previous(err, *location, decl.id, true);
+                }
+                if let Some(conflict) = conflict {
+                    self.error_handler
+                        .warn(Warning::IncompatiblePrototype(decl.id, conflict), *location);
                 }
             } else {
                 let err = format!(
                     "redeclaration of '{}' with different type or qualifiers (originally {}, now {})",
                     existing.id, existing, decl
                 );
-                self.semantic_err(err, *location);
+                self.err_with_previous(err, *location, decl.id, false);
             }
-            self.scope.insert(decl.id.clone(), decl.clone());
+            let mut merged = decl.clone();
+            merged.init = init;
+            merged.qualifiers.inline = inline;
+            self.scope.insert(decl.id, merged);
         } else {
             self.scope.insert(decl.id.clone(), decl.clone());
         }
+        self.record_usage(decl.id, usage);
+    }
+    /// Report an error in a declaration of `id`,
+    /// with a note pointing to its previous declaration, or its definition if `definition` is set.
+    fn err_with_previous(
+        &mut self,
+        err: String,
+        location: Location,
+        id: InternedStr,
+        definition: bool,
+    ) {
+        let mut err = CompileError::semantic(location.with(err));
+        let previous = self.usage.get_immediate(&id).and_then(|usage| {
+            if definition {
+                usage.defined
+            } else {
+                Some(usage.declared)
+            }
+        });
+        if let Some(previous) = previous {
+            let note = if definition {
+                "previous definition is here"
+            } else {
+                "previous declaration is here"
+            };
+            err = err.note(note, previous);
+        }
+        self.error_handler.push_back(err);
+    }
+    /// Declarations with linkage refer to the same object or function even if they're in
+    /// different scopes, so they must have compatible types (C11 6.2.7p2).
+    ///
+    /// e.g. `int main() { extern int i; } double i;`
+    fn check_linkage(&mut self, decl: &Symbol, location: Location) {
+        let block_scope = self.current_function.is_some();
+        if decl.storage_class == StorageClass::Typedef
+            || block_scope && decl.storage_class != StorageClass::Extern
+        {
+            // no linkage
+            return;
+        }
+        // declarations at file scope are checked against each other by `declare`
+        let file_scope = if block_scope {
+            let declared = self.usage.get_global(&decl.id).map(|usage| usage.declared);
+            self.scope
+                .get_global(&decl.id)
+                .map(|other| (other, declared))
+        } else {
+            None
+        };
+        let block_scope_externs = self
+            .block_externs
+            .get(&decl.id)
+            .map(|(other, declared)| (other, Some(*declared)));
+        let conflict = file_scope
+            .into_iter()
+            .chain(block_scope_externs)
+            .find(|(other, _)| {
+                other.storage_class != StorageClass::Typedef
+                    && (other.ctype != decl.ctype
+                        || other.qualifiers.c_const != decl.qualifiers.c_const
+                        || other.qualifiers.volatile != decl.qualifiers.volatile)
+            })
+            .map(|(other, declared)| {
+                let err = format!(
+                    "redeclaration of '{}' with different type or qualifiers (originally {}, now {})",
+                    decl.id, other, decl
+                );
+                (err, declared)
+            });
+        if let Some((err, declared)) = conflict {
+            let mut err = CompileError::semantic(location.with(err));
+            if let Some(declared) = declared {
+                err = err.note("previous declaration is here", declared);
+            }
+            self.error_handler.push_back(err);
+        } else if block_scope {
+            self.block_externs.insert(decl.id, (decl.clone(), location));
+        }
+    }
+    /// Evaluate the array lengths of a variably modified type once,
+    /// when the declaration is reached (C11 6.7.6.2p5).
+    ///
+    /// Each length is stored in a hidden variable declared just before `id`,
+    /// so that later changes to the variables in the length don't change the type.
+    fn declare_vla_lengths(
+        &mut self,
+        id: &Locatable<InternedStr>,
+        ctype: &mut Type,
+        sc: StorageClass,
+    ) -> VecDeque<Locatable<Declaration>> {
+        let mut lengths = VecDeque::new();
+        if !ctype.is_variably_modified() {
+            return lengths;
+        }
+        match &mut self.current_function {
+            Some(func) => func.vlas.push(id.clone()),
+            None => {
+                let err = format!(
+                    "variably modified type '{}' is not allowed at file scope",
+                    ctype
+                );
+                self.semantic_err(err, id.location);
+                return lengths;
+            }
+        }
+        if sc == StorageClass::Static || sc == StorageClass::Extern {
+            self.semantic_err(
+                format!(
+                    "'{}' has variably modified type '{}' and cannot be declared '{}'",
+                    id.data, ctype, sc
+                ),
+                id.location,
+            );
+            return lengths;
+        }
+        self.hoist_vla_lengths(ctype, id.data, id.location, &mut lengths);
+        lengths
+    }
+    fn hoist_vla_lengths(
+        &mut self,
+        ctype: &mut Type,
+        id: InternedStr,
+        location: Location,
+        lengths: &mut VecDeque<Locatable<Declaration>>,
+    ) {
+        match ctype {
+            Type::Array(inner, size) => {
+                if let ArrayType::Variable(length) = size {
+                    let func = self
+                        .current_function
+                        .as_mut()
+                        .expect("lengths should only be hoisted inside a function");
+                    let hidden = Symbol {
+                        // not a valid identifier, so this can't conflict with user variables
+                        id: InternedStr::get_or_intern(format!("{}.len{}", id, func.vla_lengths)),
+                        ctype: Type::Long(false),
+                        qualifiers: Qualifiers::NONE,
+                        storage_class: StorageClass::Auto,
+                        init: true,
+                    };
+                    func.vla_lengths += 1;
+                    let init = mem::replace(length, Box::new(Expr::id(&hidden, location).rval()));
+                    lengths.push_back(location.with(Declaration {
+                        symbol: hidden,
+                        init: Some(Initializer::Scalar(init)),
+                        attributes: Vec::new(),
+                        asm_label: None,
+                    }));
+                }
+                self.hoist_vla_lengths(inner, id, location, lengths);
+            }
+            Type::Pointer(inner, _) => self.hoist_vla_lengths(inner, id, location, lengths),
+            _ => {}
+        }
     }
     fn init_declarator(
         &mut self,
         sc: StorageClass,
-        qualifiers: Qualifiers,
+        mut qualifiers: Qualifiers,
         ctype: Type,
+        specifier_attributes: &[Locatable<Attribute>],
     ) -> SyntaxResult<Locatable<Declaration>> {
         // parse declarator
         // declarator: Result<Symbol, SyntaxError>
         let decl = self
             .declarator(false, qualifiers)?
             .expect("declarator should never return None when called with allow_abstract: false");
-        let (id, ctype) = decl
-            .parse_type(ctype, false, &self.last_location)
+        let (id, mut ctype) = decl
+            .parse_type(ctype, &mut qualifiers, false, &self.last_location)
             .recover(&mut self.error_handler);
         let id = id.expect("declarator should return id when called with allow_abstract: false");
+        let (asm_label, declarator_attributes) = self.declarator_extensions()?;
+        if let Some(label) = asm_label {
+            self.check_asm_label(label, sc, &ctype, id.location);
+        }
+        self.reject_identifier_list(&mut ctype, id.location);
 
         // optionally, parse an initializer
         let init = if self.match_next(&Token::EQUAL).is_some() {
-            Some(self.initializer(&ctype)?)
+            let init = self.initializer(&ctype)?;
+            ctype.infer_array_size(&init);
+            Some(init)
         } else {
             None
         };
@@ -322,8 +760,18 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             ctype,
             init: init.is_some(),
         };
+        let attributes = specifier_attributes
+            .iter()
+            .cloned()
+            .chain(declarator_attributes)
+            .collect();
         Ok(Locatable {
-            data: Declaration { symbol, init },
+            data: Declaration {
+                symbol,
+                init,
+                attributes,
+                asm_label,
+            },
             location: id.location,
         })
     }
@@ -342,21 +790,31 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
      *  | type_qualifier declaration_specifiers
      *  ;
      */
+    #[allow(clippy::type_complexity)]
     fn declaration_specifiers(
         &mut self,
-    ) -> SyntaxResult<(Option<StorageClass>, Qualifiers, Type, bool)> {
+    ) -> SyntaxResult<(
+        Option<StorageClass>,
+        Qualifiers,
+        Type,
+        bool,
+        Vec<Locatable<Attribute>>,
+    )> {
         // TODO: initialization is a mess
         let mut keywords = HashSet::new();
+        let mut attributes = Vec::new();
         let mut storage_class = None;
         let mut qualifiers = Qualifiers::NONE;
         let mut ctype = None;
         let mut signed = None;
         let mut seen_compound = false;
         let mut seen_typedef = false;
+        let mut complex = None;
+        let mut atomic = None;
         if self.peek_token().is_none() {
             return Err(self
                 .last_location
-                .with(SyntaxError::EndOfFile("declaration specifier")));
+                .error(SyntaxError::EndOfFile("declaration specifier")));
         }
         // unsigned const int
         while let Some(locatable) = self.next_token() {
@@ -378,12 +836,131 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                     }
                     continue;
                 }
+                // C11 6.7.2.4p4: if `_Atomic` is immediately followed by a left parenthesis,
+                // it is a type specifier, not a qualifier
+                Token::Keyword(Keyword::Atomic) if self.peek_token() == Some(&Token::LeftParen) => {
+                    self.next_token();
+                    let inner = self.type_name()?;
+                    self.expect(Token::RightParen)?;
+                    let atomic_type = self.atomic_type(inner.data.0, inner.location);
+                    if let Some(ctype) = &ctype {
+                        self.semantic_err(
+                            format!(
+                                "cannot combine '{}' specifier with previous '{}' type specifier",
+                                atomic_type, ctype
+                            ),
+                            locatable.location,
+                        );
+                    } else {
+                        if inner.data.1 != Qualifiers::NONE {
+                            self.semantic_err(
+                                format!(
+                                    "'_Atomic' cannot be applied to qualified type '{}{}'",
+                                    inner.data.1,
+                                    atomic_type.strip_atomic()
+                                ),
+                                inner.location,
+                            );
+                        }
+                        // still a type specifier, so this isn't an implicit `int`
+                        ctype = Some(atomic_type);
+                    }
+                    continue;
+                }
+                Token::Keyword(Keyword::Attribute) => {
+                    self.unput(Some(locatable));
+                    attributes.extend(self.attributes()?);
+                    continue;
+                }
+                // `__extension__` only silences warnings about GNU extensions
+                Token::Keyword(Keyword::Extension) => continue,
+                Token::Keyword(Keyword::Typeof) => {
+                    if !self.gnu {
+                        self.semantic_err("'typeof' is a GNU extension", locatable.location);
+                    }
+                    let (typeof_type, typeof_qualifiers) = self.typeof_specifier()?;
+                    if let Some(ctype) = &ctype {
+                        self.semantic_err(
+                            format!(
+                                "cannot combine '{}' specifier with previous '{}' type specifier",
+                                Keyword::Typeof,
+                                ctype
+                            ),
+                            locatable.location,
+                        );
+                    } else {
+                        ctype = Some(typeof_type);
+                        qualifiers.c_const |= typeof_qualifiers.c_const;
+                        qualifiers.volatile |= typeof_qualifiers.volatile;
+                        qualifiers.restrict |= typeof_qualifiers.restrict;
+                    }
+                    continue;
+                }
+                Token::Keyword(Keyword::Atomic) => {
+                    if atomic.is_some() {
+                        self.error_handler.warn(
+                            Warning::DuplicateSpecifier(Keyword::Atomic.to_string()),
+                            locatable.location,
+                        );
+                    }
+                    atomic = Some(locatable.location);
+                    continue;
+                }
                 Token::Keyword(k) if k.is_decl_specifier() => (locatable.location, k),
+                // `typeof(x) y;` in ISO C
+                Token::Id(id)
+                    if ctype.is_none()
+                        && signed.is_none()
+                        && self.is_iso_typeof(id)
+                        && self.peek_token() == Some(&Token::LeftParen) =>
+                {
+                    self.unput(Some(Locatable {
+                        data: Token::Keyword(Keyword::Typeof),
+                        location: locatable.location,
+                    }));
+                    continue;
+                }
+                // `size_tt x;`: an identifier can't be followed by another one,
+                // so this was probably meant to be a type name
+                Token::Id(id)
+                    if ctype.is_none()
+                        && signed.is_none()
+                        && self.scope.get(&id).is_none()
+                        && self.peek_token().map_or(false, |next| match next {
+                            Token::Id(_) => true,
+                            _ => false,
+                        }) =>
+                {
+                    let candidates: Vec<_> = self
+                        .scope
+                        .iter()
+                        .filter(|(_, symbol)| symbol.storage_class == StorageClass::Typedef)
+                        .map(|(&name, _)| name)
+                        .chain(
+                            TYPE_KEYWORDS
+                                .iter()
+                                .map(|k| InternedStr::get_or_intern(k.to_string())),
+                        )
+                        .collect();
+                    self.err_did_you_mean(
+                        format!("unknown type name '{}'", id),
+                        id,
+                        locatable.location,
+                        candidates,
+                    );
+                    ctype = Some(Type::Error);
+                    continue;
+                }
                 Token::Id(id) => match self.scope.get(&id) {
                     Some(typedef)
                         if typedef.storage_class == StorageClass::Typedef && !seen_typedef =>
                     {
                         ctype = Some(typedef.ctype.clone());
+                        // `typedef const int cint; cint i;` declares a `const int`
+                        let typedef_qualifiers = typedef.qualifiers;
+                        qualifiers.c_const |= typedef_qualifiers.c_const;
+                        qualifiers.volatile |= typedef_qualifiers.volatile;
+                        qualifiers.restrict |= typedef_qualifiers.restrict;
                         seen_typedef = true;
                         continue;
                     }
@@ -400,6 +977,9 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                     break;
                 }
             };
+            if keyword == Keyword::Complex {
+                complex = Some(location);
+            }
             if keywords.insert(keyword) {
                 self.declaration_specifier(
                     keyword,
@@ -414,15 +994,28 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                 // we can guess that they just meant to write it once
                 if keyword.is_qualifier()
                     || keyword.is_storage_class()
+                    || keyword == Keyword::ThreadLocal
+                    || keyword == Keyword::NoReturn
                     || keyword == Keyword::Signed
                     || keyword == Keyword::Unsigned
                 {
-                    self.error_handler.warn(
-                        &format!("duplicate declaration specifier '{}'", keyword),
-                        location,
-                    );
+                    self.error_handler
+                        .warn(Warning::DuplicateSpecifier(keyword.to_string()), location);
+                } else if keyword == Keyword::Long {
+                    match ctype {
+                        Some(Type::Long(signed)) => ctype = Some(Type::LongLong(signed)),
+                        Some(Type::LongLong(_)) => {
+                            self.semantic_err("'long long long' is too long", location)
+                        }
+                        Some(ref other) => self.semantic_err(
+                            format!("cannot combine 'long' with '{}'", other),
+                            location,
+                        ),
+                        // unreachable, since we've seen `long` before, but harmless
+                        None => ctype = Some(Type::LongLong(true)),
+                    }
                 // what is `short short` supposed to be?
-                } else if keyword != Keyword::Long {
+                } else {
                     self.semantic_err(
                         format!("duplicate basic type '{}' in declarator", keyword),
                         location,
@@ -430,11 +1023,15 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                 }
             }
         }
+        if let Some(location) = complex {
+            ctype = Some(self.complex_type(ctype, signed, location));
+        }
         let ctype = match ctype {
             Some(Type::Char(ref mut s))
             | Some(Type::Short(ref mut s))
             | Some(Type::Int(ref mut s))
-            | Some(Type::Long(ref mut s)) => {
+            | Some(Type::Long(ref mut s))
+            | Some(Type::LongLong(ref mut s)) => {
                 if let Some(explicit) = signed {
                     *s = explicit;
                 }
@@ -442,63 +1039,253 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             }
             Some(ctype) => ctype,
             None => {
-                if signed.is_none() {
+                if signed.is_none() && atomic.is_none() {
                     // if it's not an id, it's invalid anyway
                     // other parts of the parser will have a better error message
                     if let Some(Token::Id(_)) = self.peek_token() {
                         let loc = self.next_location();
-                        self.error_handler
-                            .warn("type specifier missing, defaults to int", loc);
+                        let warning = CompileWarning::new(Warning::ImplicitInt, loc)
+                            .fix(loc.before(), "int ");
+                        self.error_handler.push_warning(warning);
                     }
                 }
                 Type::Int(signed.unwrap_or(true))
             }
         };
-        Ok((storage_class, qualifiers, ctype, seen_compound))
-    }
-    /*
-    rewritten grammar:
-
-    struct_or_union_specifier
-    : (struct | union) '{' struct_declaration + '}'
-    | (struct | union) identifier '{' struct_declaration + '}'
-    | (struct | union) identifier
-    ;
-
-    enum_specifier
-        : ENUM '{' enumerator_list '}'
-        | ENUM identifier '{' enumerator_list '}'
-        | ENUM identifier
-        ;
-    */
-    fn compound_specifier(&mut self, kind: Keyword, location: Location) -> SyntaxResult<Type> {
-        use std::rc::Rc;
-        let ident = match self.match_next(&Token::Id(Default::default())) {
-            Some(Locatable {
-                data: Token::Id(data),
-                location,
-            }) => Some(Locatable { data, location }),
-            None => None,
-            _ => unreachable!("match_next"),
+        let ctype = match atomic {
+            Some(location) => self.atomic_type(ctype, location),
+            None => ctype,
         };
-        if self.match_next(&Token::LeftBrace).is_none() {
-            let (ident, location) = match ident {
-                Some(token) => (token.data, token.location),
-                // struct *s; or struct;
-                None => {
-                    self.semantic_err(
-                        format!("bare {} as type specifier is not allowed", kind),
-                        location,
-                    );
-                    return Ok(match kind {
-                        Keyword::Struct => Type::Struct(StructType::Anonymous(Rc::new(vec![]))),
-                        Keyword::Union => Type::Union(StructType::Anonymous(Rc::new(vec![]))),
-                        Keyword::Enum => Type::Enum(None, vec![]),
-                        _ => unreachable!(),
-                    });
-                }
+        Ok((storage_class, qualifiers, ctype, seen_compound, attributes))
+    }
+    /// typeof_specifier: '__typeof__' '(' (type_name | expr) ')'
+    ///
+    /// Like `sizeof`, the expression is not evaluated.
+    fn typeof_specifier(&mut self) -> SyntaxResult<(Type, Qualifiers)> {
+        self.expect(Token::LeftParen)?;
+        let typeof_type = if self.next_is_type_name() {
+            self.type_name()?.data
+        } else {
+            let expr = self.expr()?;
+            // `const int c; typeof(c) d;` declares `d` as `const int`
+            let qualifiers = if expr.lval {
+                expr.lval_qualifiers()
+            } else {
+                Qualifiers::NONE
             };
-            let has_semicolon = self.peek_token() == Some(&Token::Semicolon);
+            (expr.ctype, qualifiers)
+        };
+        self.expect(Token::RightParen)?;
+        Ok(typeof_type)
+    }
+    /// attribute_specifier: '__attribute__' '(' '(' attribute_list ')' ')'
+    /// attribute_list: attribute? (',' attribute?)*
+    ///
+    /// Parse any number of GNU attribute specifiers, including none at all.
+    fn attributes(&mut self) -> SyntaxResult<Vec<Locatable<Attribute>>> {
+        let mut attributes = Vec::new();
+        while let Some(keyword) = self.match_next(&Token::Keyword(Keyword::Attribute)) {
+            if !self.gnu {
+                self.semantic_err("'__attribute__' is a GNU extension", keyword.location);
+            }
+            self.expect(Token::LeftParen)?;
+            self.expect(Token::LeftParen)?;
+            loop {
+                match self.peek_token() {
+                    Some(Token::RightParen) => break,
+                    // empty attributes are allowed, e.g. `__attribute__((,))`
+                    Some(Token::Comma) => {}
+                    _ => attributes.push(self.attribute()?),
+                }
+                if self.match_next(&Token::Comma).is_none() {
+                    break;
+                }
+            }
+            self.expect(Token::RightParen)?;
+            self.expect(Token::RightParen)?;
+        }
+        Ok(attributes)
+    }
+    /// attribute: attribute_name ('(' attribute_argument (',' attribute_argument)* ')')?
+    ///
+    /// where attribute_name is an identifier or keyword, e.g. `__attribute__((const))`
+    fn attribute(&mut self) -> SyntaxResult<Locatable<Attribute>> {
+        let Locatable { data, location } = match self.next_token() {
+            Some(token) => token,
+            None => {
+                return Err(self
+                    .last_location
+                    .error(SyntaxError::EndOfFile("attribute")))
+            }
+        };
+        let name = match data {
+            Token::Id(name) => name.resolve_and_clone(),
+            Token::Keyword(keyword) => keyword.to_string(),
+            other => {
+                return Err(location.error(SyntaxError::from(format!(
+                    "expected attribute name, got '{}'",
+                    other
+                ))))
+            }
+        };
+        // `__aligned__` is the same as `aligned`
+        let name = if name.len() > 4 && name.starts_with("__") && name.ends_with("__") {
+            &name[2..name.len() - 2]
+        } else {
+            &name
+        };
+        let mut args = Vec::new();
+        if self.match_next(&Token::LeftParen).is_some()
+            && self.match_next(&Token::RightParen).is_none()
+        {
+            loop {
+                args.push(self.attribute_argument()?);
+                if self.match_next(&Token::Comma).is_none() {
+                    self.expect(Token::RightParen)?;
+                    break;
+                }
+            }
+        }
+        Ok(location.with(Attribute {
+            name: InternedStr::get_or_intern(name),
+            args,
+        }))
+    }
+    fn attribute_argument(&mut self) -> SyntaxResult<AttributeArg> {
+        // identifiers on their own don't have to be declared, e.g. `format(printf, 1, 2)`
+        if let Some(&Token::Id(id)) = self.peek_token() {
+            match self.peek_next_token() {
+                Some(Token::Comma) | Some(Token::RightParen) => {
+                    self.next_token();
+                    return Ok(AttributeArg::Id(id));
+                }
+                _ => {}
+            }
+        }
+        Ok(AttributeArg::Expr(self.assignment_expr()?))
+    }
+    /// Parse the `__asm__` label and attributes GNU C allows after a declarator,
+    /// e.g. `int f(void) __asm__("g") __attribute__((noreturn));`
+    fn declarator_extensions(
+        &mut self,
+    ) -> SyntaxResult<(Option<InternedStr>, Vec<Locatable<Attribute>>)> {
+        let mut attributes = self.attributes()?;
+        if self.match_next(&Token::Keyword(Keyword::Asm)).is_none() {
+            return Ok((None, attributes));
+        }
+        self.expect(Token::LeftParen)?;
+        let label = match self.next_token() {
+            Some(Locatable {
+                data: Token::Literal(Literal::Str(label)),
+                ..
+            }) => {
+                // string literals include their null terminator
+                let label = label.resolve_and_clone();
+                InternedStr::get_or_intern(label.trim_end_matches('\0'))
+            }
+            Some(other) => {
+                return Err(other.location.error(SyntaxError::from(format!(
+                    "expected string literal for asm label, got '{}'",
+                    other.data
+                ))))
+            }
+            None => {
+                return Err(self
+                    .last_location
+                    .error(SyntaxError::EndOfFile("asm label")))
+            }
+        };
+        self.expect(Token::RightParen)?;
+        attributes.extend(self.attributes()?);
+        Ok((Some(label), attributes))
+    }
+    /// An asm label changes the name of the symbol the linker sees,
+    /// so it only makes sense for declarations that have a symbol.
+    fn check_asm_label(
+        &mut self,
+        label: InternedStr,
+        sc: StorageClass,
+        ctype: &Type,
+        location: Location,
+    ) {
+        let has_symbol = match sc {
+            StorageClass::Typedef => false,
+            StorageClass::Static | StorageClass::Extern => true,
+            StorageClass::Auto | StorageClass::Register => {
+                ctype.is_function() || self.current_function.is_none()
+            }
+        };
+        if !has_symbol {
+            self.semantic_err(
+                format!(
+                    "asm label '{}' can only be used on functions and variables with static storage duration",
+                    label
+                ),
+                location,
+            );
+        }
+    }
+    /// Apply `_Atomic` to `ctype`.
+    ///
+    /// C11 6.7.2.4p3 and 6.7.3p3: arrays and functions cannot be atomic.
+    fn atomic_type(&mut self, ctype: Type, location: Location) -> Type {
+        match ctype {
+            Type::Atomic(_) | Type::Error => ctype,
+            Type::Array(_, _) | Type::Function(_) | Type::Void => {
+                self.semantic_err(
+                    format!("'_Atomic' cannot be applied to type '{}'", ctype),
+                    location,
+                );
+                ctype
+            }
+            _ => Type::Atomic(Box::new(ctype)),
+        }
+    }
+    /*
+    rewritten grammar:
+
+    struct_or_union_specifier
+    : (struct | union) '{' struct_declaration + '}'
+    | (struct | union) identifier '{' struct_declaration + '}'
+    | (struct | union) identifier
+    ;
+
+    enum_specifier
+        : ENUM '{' enumerator_list '}'
+        | ENUM identifier '{' enumerator_list '}'
+        | ENUM identifier
+        ;
+    */
+    fn compound_specifier(&mut self, kind: Keyword, location: Location) -> SyntaxResult<Type> {
+        use std::rc::Rc;
+        // attributes of the type itself, e.g. `struct __attribute__((packed)) s { ... }`
+        let attributes = self.attributes()?;
+        let ident = match self.match_next(&Token::Id(Default::default())) {
+            Some(Locatable {
+                data: Token::Id(data),
+                location,
+            }) => Some(Locatable { data, location }),
+            None => None,
+            _ => unreachable!("match_next"),
+        };
+        if self.match_next(&Token::LeftBrace).is_none() {
+            let (ident, location) = match ident {
+                Some(token) => (token.data, token.location),
+                // struct *s; or struct;
+                None => {
+                    self.semantic_err(
+                        format!("bare {} as type specifier is not allowed", kind),
+                        location,
+                    );
+                    return Ok(match kind {
+                        Keyword::Struct => Type::Struct(StructType::Anonymous(Rc::new(vec![]))),
+                        Keyword::Union => Type::Union(StructType::Anonymous(Rc::new(vec![]))),
+                        Keyword::Enum => Type::Enum(None, vec![]),
+                        _ => unreachable!(),
+                    });
+                }
+            };
+            let has_semicolon = self.peek_token() == Some(&Token::Semicolon);
             let entry = match self.tag_scope.get(&ident) {
                 Some(entry) => entry,
                 // struct s;
@@ -509,7 +1296,7 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                 return Ok(self.forward_declaration(kind, ident, location));
             }
             // struct s; struct s;
-            return match entry {
+            return match &entry.data {
                 TagEntry::Struct(struct_ref) => {
                     let s = *struct_ref;
                     if kind != Keyword::Struct {
@@ -537,14 +1324,13 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
         if let Some(locatable) = self.match_next(&Token::RightBrace) {
             self.semantic_err(format!("cannot have an empty {}", kind), locatable.location);
         }
-        let ident = ident.map(|loc| loc.data);
-        let ctype = if kind == Keyword::Enum {
-            self.enumerators(ident, location)
+        if kind == Keyword::Enum {
+            let ctype = self.enumerators(ident, location)?;
+            self.expect(Token::RightBrace)?;
+            Ok(ctype)
         } else {
-            self.struct_declaration(ident, kind == Keyword::Struct, &location)
-        }?;
-        self.expect(Token::RightBrace)?;
-        Ok(ctype)
+            self.struct_declaration(ident, kind == Keyword::Struct, &location, attributes)
+        }
     }
     fn forward_declaration(
         &mut self,
@@ -554,11 +1340,11 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
     ) -> Type {
         if kind == Keyword::Enum {
             // see section 6.7.2.3 of the C11 standard
-            self.semantic_err(
-                format!("cannot have forward reference to enum type '{}'", ident),
-                location,
-            );
-            return Type::Enum(Some(ident), vec![]);
+            let ctype = Type::Enum(Some(ident), vec![]);
+            let msg = format!("cannot have forward reference to enum type '{}'", ident);
+            let msg = self.with_tag_suggestion(msg, &ctype);
+            self.semantic_err(msg, location);
+            return ctype;
         }
         let struct_ref = StructRef::new();
         let (entry_type, tag_type): (fn(_) -> _, fn(_) -> _) = if kind == Keyword::Struct {
@@ -567,7 +1353,7 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             (TagEntry::Union, Type::Union)
         };
         let entry = entry_type(struct_ref);
-        self.tag_scope.insert(ident, entry);
+        self.tag_scope.insert(ident, location.with(entry));
         tag_type(StructType::Named(ident, struct_ref))
     }
     /* rewritten grammar:
@@ -576,7 +1362,7 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
     */
     fn enumerators(
         &mut self,
-        ident: Option<InternedStr>,
+        ident: Option<Locatable<InternedStr>>,
         location: Location,
     ) -> SyntaxResult<Type> {
         let mut current = 0;
@@ -595,16 +1381,8 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                 });
                 current = match constant.data.0 {
                     Literal::Int(i) => i,
-                    Literal::UnsignedInt(u) => match i64::try_from(u) {
-                        Ok(i) => i,
-                        Err(_) => {
-                            self.semantic_err(
-                                "values between INT_MAX and UINT_MAX are not supported for enums",
-                                constant.location,
-                            );
-                            std::i64::MAX
-                        }
-                    },
+                    // too large for an enum, checked below
+                    Literal::UnsignedInt(u) => i64::try_from(u).unwrap_or(std::i64::MAX),
                     Literal::Char(c) => i64::from(c),
                     _ => {
                         self.semantic_err(
@@ -615,6 +1393,17 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                     }
                 };
             }
+            // as an extension, allow values up to UINT_MAX, like GCC
+            if current < i64::from(std::i32::MIN) || current > i64::from(std::u32::MAX) {
+                self.semantic_err(
+                    format!(
+                        "enumerator value for '{}' is not representable as 'int' or 'unsigned int'",
+                        name
+                    ),
+                    member.location,
+                );
+                current = 0;
+            }
             members.push((name, current));
             // TODO: this is such a hack
             let tmp_symbol = Symbol {
@@ -636,16 +1425,21 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
         for (name, _) in &members {
             self.scope._remove(name);
         }
+        let negative = members.iter().any(|&(_, value)| value < 0);
+        if negative && !Type::enum_type(&members).is_signed() {
+            self.semantic_err(
+                "enumerator values cannot all be represented by 'int' or by 'unsigned int'",
+                location,
+            );
+        }
         if let Some(id) = &ident {
-            if self
-                .tag_scope
-                .insert(id.clone(), TagEntry::Enum(members.clone()))
-                .is_some()
-            {
-                self.semantic_err(format!("redefition of enum '{}'", id), location);
+            let entry = id.location.with(TagEntry::Enum(members.clone()));
+            if let Some(previous) = self.tag_scope.insert(id.data, entry) {
+                let err = format!("redefinition of enum '{}'", id.data);
+                self.tag_err_with_previous(err, location, &previous);
             }
         }
-        let ctype = Type::Enum(ident, members);
+        let ctype = Type::Enum(ident.map(|id| id.data), members);
         match &ctype {
             Type::Enum(_, members) => {
                 for (id, _) in members {
@@ -668,11 +1462,14 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
     /* rewritten grammar:
     struct_declaration: (type_specifier | type_qualifier)+ struct_declarator_list ';' ;
     */
+    /// Parse the members of a struct or union, up to and including the closing brace
+    /// and any attributes after it, e.g. `struct s { char c; int i; } __attribute__((packed))`.
     fn struct_declaration(
         &mut self,
-        ident: Option<InternedStr>,
+        ident: Option<Locatable<InternedStr>>,
         c_struct: bool,
         location: &Location,
+        mut attributes: Vec<Locatable<Attribute>>,
     ) -> SyntaxResult<Type> {
         use std::rc::Rc;
         let mut members = vec![];
@@ -681,7 +1478,7 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                 break;
             } else if let Some(token) = self.match_next(&Token::Semicolon) {
                 self.error_handler.warn(
-                    "extraneous semicolon in struct declaration is not allowed by ISO C",
+                    Warning::ExtraSemicolon("in struct declaration is not allowed by ISO C"),
                     token.location,
                 );
                 continue;
@@ -689,30 +1486,37 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                 self.struct_declarator_list(&mut members)?;
             }
         }
-        if members.is_empty() {
+        if members.iter().all(Symbol::is_padding) {
             let loc = self.next_location();
             self.semantic_err("cannot have empty struct", loc);
         }
+        self.expect(Token::RightBrace)?;
+        attributes.extend(self.attributes()?);
+        self.struct_attributes(&attributes, &mut members);
+        self.check_flexible_array_members(&members, c_struct, *location);
+        self.check_duplicate_members(&members, *location);
         let constructor = if c_struct { Type::Struct } else { Type::Union };
-        if let Some(id) = ident {
-            let struct_ref = if let Some(TagEntry::Struct(struct_ref))
-            | Some(TagEntry::Union(struct_ref)) =
-                self.tag_scope.get_immediate(&id)
-            {
-                let struct_ref = *struct_ref;
-                if !struct_ref.get().is_empty() {
-                    self.semantic_err(
-                        format!(
-                            "redefinition of {} '{}'",
-                            if c_struct { "struct" } else { "union" },
-                            id
-                        ),
-                        *location,
-                    );
-                }
-                struct_ref
-            } else {
-                StructRef::new()
+        if let Some(Locatable {
+            data: id,
+            location: id_location,
+        }) = ident
+        {
+            let struct_ref = match self.tag_scope.get_immediate(&id).cloned() {
+                Some(previous) => match previous.data {
+                    TagEntry::Struct(struct_ref) | TagEntry::Union(struct_ref) => {
+                        if !struct_ref.get().is_empty() {
+                            let err = format!(
+                                "redefinition of {} '{}'",
+                                if c_struct { "struct" } else { "union" },
+                                id
+                            );
+                            self.tag_err_with_previous(err, *location, &previous);
+                        }
+                        struct_ref
+                    }
+                    TagEntry::Enum(_) => StructRef::new(),
+                },
+                None => StructRef::new(),
             };
             struct_ref.update(members);
             let entry = if c_struct {
@@ -720,18 +1524,138 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             } else {
                 TagEntry::Union
             }(struct_ref);
-            self.tag_scope.insert(id, entry);
+            self.tag_scope.insert(id, id_location.with(entry));
             Ok(constructor(StructType::Named(id, struct_ref)))
         } else {
             Ok(constructor(StructType::Anonymous(Rc::new(members))))
         }
     }
+    /// Report an error in a definition of a tag,
+    /// with a note pointing to the previous definition or forward declaration of the tag.
+    fn tag_err_with_previous(
+        &mut self,
+        err: String,
+        location: Location,
+        previous: &Locatable<TagEntry>,
+    ) {
+        let defined = match &previous.data {
+            TagEntry::Struct(struct_ref) | TagEntry::Union(struct_ref) => {
+                !struct_ref.get().is_empty()
+            }
+            TagEntry::Enum(_) => true,
+        };
+        let note = if defined {
+            "previous definition is here"
+        } else {
+            "previous declaration is here"
+        };
+        let err = CompileError::semantic(location.with(err)).note(note, previous.location);
+        self.error_handler.push_back(err);
+    }
+    /// Flexible array members, e.g. `struct buf { size_t n; char data[]; }`,
+    /// may only appear at the end of a struct with at least one other named member,
+    /// and a struct ending in one may not be nested in another struct (C11 6.7.2.1p3).
+    fn check_flexible_array_members(
+        &mut self,
+        members: &[Symbol],
+        c_struct: bool,
+        location: Location,
+    ) {
+        let named = members.iter().filter(|member| !member.is_padding()).count();
+        for (i, member) in members.iter().enumerate() {
+            let err = if member.ctype.is_flexible_array() {
+                if !c_struct {
+                    format!(
+                        "flexible array member '{}' is not allowed in a union",
+                        member.id
+                    )
+                } else if i != members.len() - 1 {
+                    format!(
+                        "flexible array member '{}' must be the last member of a struct",
+                        member.id
+                    )
+                } else if named == 1 {
+                    format!(
+                        "flexible array member '{}' cannot be the only named member of a struct",
+                        member.id
+                    )
+                } else {
+                    continue;
+                }
+            } else if c_struct && member.ctype.has_flexible_array() {
+                format!(
+                    "member '{}' has type '{}' with a flexible array member and cannot be nested in a struct",
+                    member.id, member.ctype
+                )
+            } else {
+                continue;
+            };
+            self.semantic_err(err, location);
+        }
+    }
+    /// The members of anonymous structs and unions are accessed as members
+    /// of the containing struct (C11 6.7.2.1p13), so their names must not conflict.
+    fn check_duplicate_members(&mut self, members: &[Symbol], location: Location) {
+        // whether each name came from an anonymous member
+        let mut seen = HashMap::new();
+        for member in members.iter().filter(|member| !member.is_padding()) {
+            let (names, anonymous) = match &member.ctype {
+                Type::Struct(inner) | Type::Union(inner) if member.is_anonymous_member() => {
+                    (inner.member_names(), true)
+                }
+                _ => (vec![member.id], false),
+            };
+            for name in names {
+                match seen.insert(name, anonymous) {
+                    Some(previous) if previous || anonymous => {
+                        self.semantic_err(format!("duplicate member '{}'", name), location)
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
     fn bitfield(&mut self) -> SyntaxResult<SIZE_T> {
         Ok(self.constant_expr()?.const_int().unwrap_or_else(|err| {
             self.error_handler.push_back(err);
             1
         }))
     }
+    /// Check that a bitfield of type `ctype` can be `width` bits wide,
+    /// returning the type of the bitfield.
+    fn bitfield_type(
+        &mut self,
+        ctype: Type,
+        width: SIZE_T,
+        id: Option<InternedStr>,
+        location: Location,
+    ) -> Type {
+        let name = id.map_or_else(|| "<anonymous>".to_string(), |id| format!("'{}'", id));
+        if !ctype.is_integral() {
+            let err = format!("bitfield {} has non-integral type '{}'", name, ctype);
+            self.semantic_err(err, location);
+            return ctype;
+        }
+        let type_width = if ctype.is_bool() {
+            1
+        } else {
+            ctype.sizeof().unwrap_or(0) * u64::from(crate::arch::CHAR_BIT)
+        };
+        let width = if width > type_width {
+            let err = format!(
+                "cannot have bitfield {} with size {} larger than containing type '{}'",
+                name, width, ctype
+            );
+            self.semantic_err(err, location);
+            type_width
+        } else {
+            width
+        };
+        Type::Bitfield(BitfieldType {
+            ctype: Box::new(ctype),
+            width: u8::try_from(width).expect("integers should never have more than 255 bits"),
+        })
+    }
     /*
     struct_declarator_list: struct_declarator (',' struct_declarator)* ;
     struct_declarator
@@ -741,56 +1665,102 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
         ;
     */
     fn struct_declarator_list(&mut self, members: &mut Vec<Symbol>) -> SyntaxResult<()> {
-        let (sc, qualifiers, original_ctype, _) = self.declaration_specifiers()?;
+        let (sc, qualifiers, original_ctype, _, specifier_attributes) =
+            self.declaration_specifiers()?;
         if let Some(token) = self.match_next(&Token::Semicolon) {
-            self.error_handler
-                .warn("declaration does not declare anything", token.location);
+            match original_ctype {
+                // an anonymous struct or union, e.g. `struct { int kind; union { int i; float f; }; }`
+                // its members are accessed as if they were members of the containing struct (C11 6.7.2.1p13)
+                Type::Struct(StructType::Anonymous(_)) | Type::Union(StructType::Anonymous(_)) => {
+                    members.push(Symbol {
+                        storage_class: StorageClass::Auto,
+                        qualifiers,
+                        ctype: original_ctype,
+                        init: false,
+                        // not a valid identifier, so this can't conflict with other members
+                        id: InternedStr::get_or_intern(format!(".anon{}", members.len())),
+                    });
+                    if let Some(class) = sc {
+                        self.semantic_err(
+                            format!("cannot specify storage class '{}' for struct member", class),
+                            token.location,
+                        );
+                    }
+                    if qualifiers.thread_local {
+                        self.check_thread_local(Some(StorageClass::Auto), token.location);
+                    }
+                }
+                _ => self
+                    .error_handler
+                    .warn(Warning::EmptyDeclaration, token.location),
+            }
             return Ok(());
         }
         let mut last_location;
         loop {
             if let Some(token) = self.match_next(&Token::Colon) {
-                self.bitfield()?;
-                self.error_handler.warn(
-                    "padding bits in bitfields are not implemented and will be ignored",
-                    token.location,
-                );
-                self.expect(Token::Semicolon)?;
-                return Ok(()); // NOTE: does not give a 2nd error if sc was given
+                // an unnamed bitfield, used for padding
+                let width = self.bitfield()?;
+                let ctype = self.bitfield_type(original_ctype.clone(), width, None, token.location);
+                members.push(Symbol {
+                    storage_class: StorageClass::Auto,
+                    qualifiers,
+                    ctype,
+                    init: false,
+                    id: Default::default(),
+                });
+                last_location = token.location;
+                if self.match_next(&Token::Comma).is_none() {
+                    self.expect(Token::Semicolon)?;
+                    break;
+                }
+                continue;
             }
             let decl = self.declarator(false, qualifiers)?.unwrap();
-            let (declarator, ctype) = decl
-                .parse_type(original_ctype.clone(), false, &self.last_location)
+            let mut qualifiers = qualifiers;
+            let (declarator, mut ctype) = decl
+                .parse_type(
+                    original_ctype.clone(),
+                    &mut qualifiers,
+                    false,
+                    &self.last_location,
+                )
                 .recover(&mut self.error_handler);
             // TODO: Declarator needs to be redesigned so there's only one unwrap
             let Locatable { data: id, location } = declarator.unwrap();
-            let symbol = Symbol {
-                storage_class: StorageClass::Auto,
-                qualifiers,
-                ctype,
-                init: false,
-                id,
-            };
+            let mut attributes = specifier_attributes.clone();
+            attributes.extend(self.attributes()?);
             if let Some(token) = self.match_next(&Token::Colon) {
-                let bit_size = self.bitfield()?;
-                let type_size = symbol.ctype.sizeof().unwrap_or(0);
-                if bit_size == 0 {
+                let width = self.bitfield()?;
+                if width == 0 {
                     let err = format!(
                         "C does not have zero-sized types. hint: omit the declarator {}",
-                        symbol.id
+                        id
                     );
                     self.semantic_err(err, self.last_location);
-                } else if bit_size > type_size * u64::from(crate::arch::CHAR_BIT) {
-                    let err = format!(
-                        "cannot have bitfield {} with size {} larger than containing type {}",
-                        symbol.id, bit_size, symbol.ctype
-                    );
-                    self.semantic_err(err, token.location);
                 }
-                self.error_handler.warn(
-                    "bitfields are not implemented and will be ignored",
-                    token.location,
+                ctype = self.bitfield_type(ctype, width, Some(id), token.location);
+                attributes.extend(self.attributes()?);
+            };
+            let align = self.member_alignment(&attributes, &ctype);
+            // bitfields keep the alignment of their type, see `struct_attributes`
+            match ctype {
+                Type::Bitfield(_) => {}
+                _ => qualifiers.align = align,
+            }
+            if ctype.is_variably_modified() {
+                let err = format!(
+                    "member '{}' cannot have variably modified type '{}'",
+                    id, ctype
                 );
+                self.semantic_err(err, location);
+            }
+            let symbol = Symbol {
+                storage_class: StorageClass::Auto,
+                qualifiers,
+                ctype,
+                init: false,
+                id,
             };
             match symbol.ctype {
                 Type::Struct(StructType::Named(_, members))
@@ -825,8 +1795,59 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                 last_location,
             );
         }
+        if qualifiers.thread_local {
+            self.check_thread_local(Some(StorageClass::Auto), last_location);
+        }
         Ok(())
     }
+    /// `_Thread_local` can only be used with `static` or `extern`,
+    /// or at file scope, where objects always have static storage duration (C11 6.7.1p3).
+    fn check_thread_local(&mut self, sc: Option<StorageClass>, location: Location) {
+        let valid = match sc {
+            Some(StorageClass::Static) | Some(StorageClass::Extern) => true,
+            None => self.current_function.is_none(),
+            Some(_) => false,
+        };
+        if !valid {
+            self.semantic_err(
+                "'_Thread_local' can only be used for variables with static storage duration",
+                location,
+            );
+        }
+    }
+    /// Given the type specifiers seen along with `_Complex`, return the complex type.
+    fn complex_type(
+        &mut self,
+        real: Option<Type>,
+        signed: Option<bool>,
+        location: Location,
+    ) -> Type {
+        match (real, signed) {
+            (Some(Type::Float), None) => Type::Complex(Box::new(Type::Float)),
+            (Some(Type::Double), None) => Type::Complex(Box::new(Type::Double)),
+            (Some(Type::LongDouble), None) => Type::Complex(Box::new(Type::LongDouble)),
+            (None, None) => {
+                self.error_handler.warn(
+                    Warning::Extension(
+                        "plain '_Complex' is not allowed by ISO C, assuming '_Complex double'",
+                    ),
+                    location,
+                );
+                Type::Complex(Box::new(Type::Double))
+            }
+            (real, signed) => {
+                let real = real.unwrap_or_else(|| Type::Int(signed.unwrap_or(true)));
+                self.semantic_err(
+                    format!(
+                        "'_Complex' can only be used with floating types, got '{}'",
+                        real
+                    ),
+                    location,
+                );
+                real
+            }
+        }
+    }
     /*
      * function parameters
      * reference grammar:
@@ -849,6 +1870,13 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
      *
      */
     fn parameter_type_list(&mut self) -> SyntaxResult<DeclaratorType> {
+        // parameters can be used in the types of later parameters, e.g. `int f(int n, int a[n])`
+        self.scope.enter_scope();
+        let params = self.parameter_declarations();
+        self.scope.leave_scope();
+        params
+    }
+    fn parameter_declarations(&mut self) -> SyntaxResult<DeclaratorType> {
         self.expect(Token::LeftParen)
             .expect("parameter_type_list should only be called with '(' as the next token");
         let mut params = vec![];
@@ -856,8 +1884,18 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             return Ok(DeclaratorType::Function(FunctionDeclarator {
                 params,
                 varargs: false,
+                old_style: false,
             }));
         }
+        if let Some(&Token::Id(id)) = self.peek_token() {
+            let is_typedef = match self.scope.get(&id) {
+                Some(symbol) => symbol.storage_class == StorageClass::Typedef,
+                None => false,
+            };
+            if !is_typedef {
+                return self.identifier_list();
+            }
+        }
         loop {
             if let Some(locatable) = self.match_next(&Token::Ellipsis) {
                 if params.is_empty() {
@@ -871,11 +1909,13 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                 return Ok(DeclaratorType::Function(FunctionDeclarator {
                     params,
                     varargs: true,
+                    old_style: false,
                 }));
             }
-            let (sc, quals, param_type, _) = self.declaration_specifiers()?;
+            let (sc, quals, param_type, ..) = self.declaration_specifiers()?;
             // true: allow abstract_declarators
             let declarator = self.declarator(true, quals)?;
+            self.attributes()?;
             if let Some(storage_class) = sc {
                 self.semantic_err(
                     format!(
@@ -894,14 +1934,21 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                     self.last_location,
                 );
             }
+            if quals.thread_local {
+                self.check_thread_local(Some(StorageClass::Auto), self.last_location);
+            }
             if let Some(decl) = declarator {
+                let mut quals = quals;
                 let (id, mut ctype) = decl
-                    .parse_type(param_type, false, &self.last_location)
+                    .parse_type(param_type, &mut quals, false, &self.last_location)
                     .recover(&mut self.error_handler);
                 // int f(int a[]) is the same as int f(int *a)
+                // and int f(const int a[]) is the same as int f(const int *a)
                 // TODO: parse int f(int a[static 5])
                 if let Type::Array(to, _) = ctype {
-                    ctype = Type::Pointer(to);
+                    ctype = Type::Pointer(to, quals.type_qualifiers());
+                    quals.c_const = false;
+                    quals.volatile = false;
                 }
                 // I will probably regret this in the future
                 // default() for String is "",
@@ -920,15 +1967,19 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                         location,
                     );
                 }
+                let param = Symbol {
+                    id: data,
+                    ctype,
+                    qualifiers: quals,
+                    storage_class: StorageClass::Auto,
+                    init: true,
+                };
+                if data != Default::default() {
+                    self.scope.insert(data, param.clone());
+                }
                 params.push(Locatable {
                     location,
-                    data: Symbol {
-                        id: data,
-                        ctype,
-                        qualifiers: quals,
-                        storage_class: StorageClass::Auto,
-                        init: true,
-                    },
+                    data: param,
                 });
             } else {
                 if param_type == Type::Void && !params.is_empty() {
@@ -956,6 +2007,47 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                 return Ok(DeclaratorType::Function(FunctionDeclarator {
                     params,
                     varargs: false,
+                    old_style: false,
+                }));
+            }
+        }
+    }
+    /*
+     * identifier_list
+     *      : identifier
+     *      | identifier_list ',' identifier
+     *      ;
+     *
+     * Only allowed in old-style function definitions, e.g. `int f(a, b) int a; char *b; { ... }`.
+     * The parameters have type `int` until they are declared by `old_style_params`.
+     */
+    fn identifier_list(&mut self) -> SyntaxResult<DeclaratorType> {
+        let mut params: Vec<Locatable<Symbol>> = vec![];
+        loop {
+            let Locatable { data, location } = self.expect(Token::Id(Default::default()))?;
+            let id = match data {
+                Token::Id(id) => id,
+                _ => unreachable!("bug in Parser::expect"),
+            };
+            if params.iter().any(|p| p.data.id == id) {
+                self.semantic_err(
+                    format!("duplicate parameter name '{}' in function declaration", id),
+                    location,
+                );
+            }
+            params.push(location.with(Symbol {
+                id,
+                ctype: Type::Int(true),
+                qualifiers: Qualifiers::NONE,
+                storage_class: StorageClass::Auto,
+                init: true,
+            }));
+            if self.match_next(&Token::Comma).is_none() {
+                self.expect(Token::RightParen)?;
+                return Ok(DeclaratorType::Function(FunctionDeclarator {
+                    params,
+                    varargs: false,
+                    old_style: true,
                 }));
             }
         }
@@ -966,7 +2058,7 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
      * rewritten grammar:
      *   postfix_type:
      *        '[' ']'
-     *      | '[' constant_expr ']'
+     *      | '[' assignment_expr ']'
      *      | '(' ')'
      *      | '(' parameter_type_list ')'
      *      | /* empty */
@@ -999,14 +2091,26 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                             }
                         }
 
-                        let expr = self.constant_expr()?;
+                        let expr = self.assignment_expr()?;
                         self.expect(Token::RightBracket)?;
-                        let length = expr.const_int().unwrap_or_else(|err| {
-                            self.error_handler.push_back(err);
-                            1
-                        });
+                        let size = if expr.constexpr {
+                            ArrayType::Fixed(expr.const_int().unwrap_or_else(|err| {
+                                self.error_handler.push_back(err);
+                                1
+                            }))
+                        } else if !expr.ctype.is_integral() {
+                            self.semantic_err(LengthError::NonIntegral, expr.location);
+                            ArrayType::Fixed(1)
+                        } else {
+                            // the length is only known at runtime
+                            let length = expr
+                                .rval()
+                                .cast(&Type::Long(false))
+                                .recover(&mut self.error_handler);
+                            ArrayType::Variable(Box::new(length))
+                        };
                         Some(Declarator {
-                            current: DeclaratorType::Array(ArrayType::Fixed(length)),
+                            current: DeclaratorType::Array(size),
                             next: prefix.map(Box::new),
                         })
                     }
@@ -1024,12 +2128,17 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             ..
         }) = prefix
         {
-            // `inline` is allowed on function declarations
+            // `inline` and `_Noreturn` are allowed on function declarations
         } else if qualifiers.inline {
             self.semantic_err(
                 "`inline` is only allowed on function declarations",
                 self.last_location,
             );
+        } else if qualifiers.noreturn {
+            self.semantic_err(
+                "`_Noreturn` is only allowed on function declarations",
+                self.last_location,
+            );
         }
 
         Ok(prefix)
@@ -1119,15 +2228,13 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             }
             _ if allow_abstract => None,
             Some(x) => {
-                let err = Err(Locatable::new(
-                    SyntaxError::from(format!("expected variable name or '(', got '{}'", x)),
-                    self.next_location(),
-                ));
+                let msg = format!("expected variable name or '(', got '{}'", x);
+                let err = Err(self.next_location().error(SyntaxError::from(msg)));
                 self.panic();
                 return err;
             }
             None => {
-                return Err(self.next_location().with(SyntaxError::from(
+                return Err(self.next_location().error(SyntaxError::from(
                     "expected variable name or '(', got <end-of-of-file>",
                 )));
             }
@@ -1185,30 +2292,39 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                         if keyword == Keyword::Const {
                             if pointer_qualifiers.c_const {
                                 self.error_handler
-                                    .warn("duplicate 'const' declaration specifier", location);
+                                    .warn(Warning::DuplicateSpecifier("const".into()), location);
                             } else {
                                 pointer_qualifiers.c_const = true;
                             }
                         } else if keyword == Keyword::Volatile {
                             if pointer_qualifiers.volatile {
                                 self.error_handler
-                                    .warn("duplicate 'volatile' declaration specifier", location);
+                                    .warn(Warning::DuplicateSpecifier("volatile".into()), location);
                             } else {
                                 pointer_qualifiers.volatile = true;
                             }
-                        } else {
-                            self.error_handler.warn(
-                                &format!("qualifier '{}' has not yet been implemented", keyword),
+                        } else if keyword == Keyword::ThreadLocal {
+                            self.semantic_err(
+                                "'_Thread_local' is a storage class, not a pointer qualifier",
                                 location,
                             );
+                        } else if keyword == Keyword::Restrict {
+                            if pointer_qualifiers.restrict {
+                                self.error_handler
+                                    .warn(Warning::DuplicateSpecifier("restrict".into()), location);
+                            } else {
+                                pointer_qualifiers.restrict = true;
+                            }
+                        } else {
+                            debug_assert_eq!(keyword, Keyword::Atomic);
+                            if pointer_qualifiers.atomic {
+                                self.error_handler
+                                    .warn(Warning::DuplicateSpecifier("_Atomic".into()), location);
+                            } else {
+                                pointer_qualifiers.atomic = true;
+                            }
                         }
                     }
-                    // TODO: this is wrong
-                    // const int *i; declares a pointer to const data: the pointer can
-                    // be modified but the data cannot.
-                    // int *const i; declares a const pointer to data: the data can be
-                    // modified but the pointer cannot.
-                    // We have this backwards.
                     Ok(Some(Declarator {
                         current: DeclaratorType::Pointer(pointer_qualifiers),
                         next: self.declarator(allow_abstract, qualifiers)?.map(Box::new),
@@ -1236,6 +2352,8 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
     /// initializer: assignment_expr
     ///     | '{' initializer (',' initializer)* '}'
     fn initializer(&mut self, ctype: &Type) -> SyntaxResult<Initializer> {
+        // atomic objects are initialized the same way as non-atomic ones
+        let ctype = ctype.strip_atomic();
         // initializer_list
         if self.match_next(&Token::LeftBrace).is_some() {
             let mut elements = vec![];
@@ -1267,11 +2385,10 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
         // The initializer for a scalar shall be a single expression, optionally enclosed in braces.
         // The initial value of the object is that of the expression (after conversion)
         if !is_char_array {
-            expr = expr
-                .rval()
-                // if ctype is not a scalar, this will report an error, so we don't have to handle it specially
-                .cast(ctype)
-                .recover(&mut self.error_handler);
+            expr = expr.rval();
+            self.check_pointer_qualifiers(&expr, ctype);
+            // if ctype is not a scalar, this will report an error, so we don't have to handle it specially
+            expr = self.assignment_cast(expr, ctype);
         }
         if !expr.lval && self.scope.is_global() && ctype.is_pointer() {
             expr = Expr {
@@ -1288,14 +2405,14 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
         &mut self,
         id: InternedStr,
         ftype: FunctionType,
+        inline_definition: bool,
         location: Location,
     ) -> SyntaxResult<Vec<Stmt>> {
         // if it's a function, set up state so we know the return type
         // TODO: rework all of this so semantic analysis is done _after_ parsing
         // TODO: that will remove a lot of clones and also make the logic much simpler
         if self.current_function.is_some() {
-            // TODO: allow function _declarations_ at local scope
-            // e.g. int main() { int f(); return f(); }
+            // only declarations are allowed at block scope, e.g. `int main() { int f(); return f(); }`
             self.semantic_err(
                 format!(
                     "functions cannot be nested. hint: try declaring {} as `static` at file scope",
@@ -1304,11 +2421,33 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                 location,
             );
         }
-        let existing = self.current_function.take();
+        if ftype.return_type.is_struct() && !ftype.return_type.is_complete() {
+            self.semantic_err(
+                format!(
+                    "function '{}' has incomplete return type '{}'",
+                    id, ftype.return_type
+                ),
+                location,
+            );
+        }
+        let existing = self.current_function.replace(FunctionData {
+            return_type: *ftype.return_type,
+            noreturn: ftype.noreturn,
+            inline_definition,
+            location,
+            id,
+            vla_lengths: 0,
+            vlas: Vec::new(),
+            labels: Vec::new(),
+            gotos: Vec::new(),
+            label_addresses: Vec::new(),
+            switches: Vec::new(),
+        });
         // add parameters to scope
         self.enter_scope();
         let len = ftype.params.len();
-        for (i, param) in ftype.params.into_iter().enumerate() {
+        let mut vla_lengths = VecDeque::new();
+        for (i, mut param) in ftype.params.into_iter().enumerate() {
             if param.id == Default::default() {
                 if param.ctype == Type::Void {
                     assert_eq!(len, 1);
@@ -1322,20 +2461,35 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                     location,
                 );
             }
+            if param.ctype.is_struct() && !param.ctype.is_complete() {
+                self.semantic_err(
+                    format!(
+                        "parameter '{}' has incomplete type '{}'",
+                        param.id, param.ctype
+                    ),
+                    location,
+                );
+            }
+            // e.g. `int f(int n, int a[][n])`
+            self.hoist_vla_lengths(&mut param.ctype, param.id, location, &mut vla_lengths);
             self.scope.insert(param.id.clone(), param);
         }
-        self.current_function = Some(FunctionData {
-            return_type: *ftype.return_type,
-            location,
-            id,
-        });
 
         // function body
         let body = match self.compound_statement() {
             Ok(Some(Stmt {
-                data: StmtType::Compound(stmts),
+                data: StmtType::Compound(mut stmts),
                 ..
-            })) => Ok(stmts),
+            })) => {
+                if !vla_lengths.is_empty() {
+                    let lengths = Stmt {
+                        data: StmtType::Decl(vla_lengths),
+                        location,
+                    };
+                    stmts.insert(0, lengths);
+                }
+                Ok(stmts)
+            }
             Ok(None) => Ok(vec![]),
             Ok(x) => unreachable!(
                 "expected compound_statement to return compound statement, got '{:#?}' instead",
@@ -1343,10 +2497,46 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             ),
             Err(e) => Err(e),
         };
-        self.current_function = existing;
+        let func = mem::replace(&mut self.current_function, existing)
+            .expect("current_function should still be set after parsing the function body");
+        self.check_labels(&func);
+        self.check_vla_jumps(&func);
         self.leave_scope(self.last_location);
         body
     }
+    /// Every label used by `goto` or `&&label` must be declared in the function (C11 6.8.6.1p1).
+    fn check_labels(&mut self, func: &FunctionData) {
+        let gotos = func.gotos.iter().map(|(goto, _)| goto);
+        for used in gotos.chain(&func.label_addresses) {
+            if !func.labels.iter().any(|(label, _)| label.data == used.data) {
+                self.semantic_err(
+                    format!("use of undeclared label '{}'", used.data),
+                    used.location,
+                );
+            }
+        }
+    }
+    /// C11 6.8.6.1: A `goto` shall not jump from outside the scope of an identifier
+    /// having a variably modified type to inside the scope of that identifier.
+    fn check_vla_jumps(&mut self, func: &FunctionData) {
+        for (goto, outer) in &func.gotos {
+            let label = func
+                .labels
+                .iter()
+                .find(|(label, _)| label.data == goto.data);
+            if let Some((_, inner)) = label {
+                if inner.iter().any(|vla| !outer.contains(vla)) {
+                    self.semantic_err(
+                        format!(
+                            "goto '{}' jumps into the scope of a variable length array",
+                            goto.data
+                        ),
+                        goto.location,
+                    );
+                }
+            }
+        }
+    }
     #[inline]
     /* the reason this is such a mess (instead of just putting everything into
      * the hashmap, which would be much simpler logic) is so we have a Location
@@ -1370,10 +2560,16 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             qualifiers.c_const = true;
         } else if keyword == Keyword::Volatile {
             qualifiers.volatile = true;
+        } else if keyword == Keyword::Restrict {
+            qualifiers.restrict = true;
         } else if keyword == Keyword::Inline {
             qualifiers.inline = true;
+        } else if keyword == Keyword::NoReturn {
+            qualifiers.noreturn = true;
+        } else if keyword == Keyword::ThreadLocal {
+            qualifiers.thread_local = true;
         } else if keyword == Keyword::Signed || keyword == Keyword::Unsigned {
-            if *ctype == Some(Type::Float) || *ctype == Some(Type::Double) {
+            if ctype.as_ref().map_or(false, Type::is_floating) {
                 self.semantic_err(
                     format!(
                         "invalid modifier '{}' for '{}'",
@@ -1426,16 +2622,22 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                     location,
                 );
             } else {
-                match ctype {
-                    None => {}
-                    Some(Type::Long(_)) if keyword == Keyword::Double => {}
-                    Some(x) => self.semantic_err(
-                        format!("cannot combine '{}' with '{}'", keyword, x),
-                        location,
-                    ),
-                }
-                *ctype = Some(Type::try_from(keyword).unwrap());
+                *ctype = Some(match ctype {
+                    Some(Type::Long(_)) if keyword == Keyword::Double => Type::LongDouble,
+                    None => Type::try_from(keyword).unwrap(),
+                    Some(x) => {
+                        self.semantic_err(
+                            format!("cannot combine '{}' with '{}'", keyword, x),
+                            location,
+                        );
+                        Type::try_from(keyword).unwrap()
+                    }
+                });
             }
+        } else if keyword == Keyword::Complex {
+            // handled by `complex_type` once the real type is known
+        } else if keyword == Keyword::Imaginary {
+            self.semantic_err("imaginary types are not supported", location);
         } else if keyword == Keyword::Void {
             match ctype {
                 Some(x) => {
@@ -1446,7 +2648,10 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
         // if we get this far, keyword is an int type (char - long)
         } else if keyword == Keyword::Int {
             match ctype {
-                Some(Type::Char(_)) | Some(Type::Short(_)) | Some(Type::Long(_))
+                Some(Type::Char(_))
+                | Some(Type::Short(_))
+                | Some(Type::Long(_))
+                | Some(Type::LongLong(_))
                 | Some(Type::Int(_)) => {}
                 Some(x) => {
                     self.semantic_err(format!("cannot combine 'int' with '{}'", x), location)
@@ -1460,6 +2665,9 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                         panic!("unrecognized declaration specifier {}", keyword)
                     }))
                 }
+                // `int long` and `double long`
+                Some(Type::Int(_)) if keyword == Keyword::Long => *ctype = Some(Type::Long(true)),
+                Some(Type::Double) if keyword == Keyword::Long => *ctype = Some(Type::LongDouble),
                 Some(x) => self.semantic_err(
                     format!("cannot combine '{}' modifier with type '{}'", keyword, x),
                     location,
@@ -1469,9 +2677,23 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
     }
 }
 
+/// The keywords which can be a type by themselves, for suggestions when a type name is misspelled.
+const TYPE_KEYWORDS: &[Keyword] = &[
+    Keyword::Void,
+    Keyword::Bool,
+    Keyword::Char,
+    Keyword::Short,
+    Keyword::Int,
+    Keyword::Long,
+    Keyword::Float,
+    Keyword::Double,
+    Keyword::Signed,
+    Keyword::Unsigned,
+];
+
 impl Keyword {
     fn is_qualifier(self) -> bool {
-        self == Keyword::Const || self == Keyword::Volatile
+        self == Keyword::Const || self == Keyword::Volatile || self == Keyword::Restrict
     }
     fn is_storage_class(self) -> bool {
         StorageClass::try_from(self).is_ok()
@@ -1482,7 +2704,7 @@ impl Keyword {
             Unsigned | Signed | Bool | Char | Short | Int | Long | Float | Double | Void
             | Struct | Union | Enum | VaList | Complex | Imaginary | Extern | Static | Auto
             | Register | Typedef | Const | Volatile | Restrict | Atomic | ThreadLocal | Inline
-            | NoReturn => true,
+            | NoReturn | Attribute | Typeof => true,
             _ => false,
         }
     }
@@ -1524,11 +2746,17 @@ impl Declarator {
     /// Explanation of the return type:
     /// `Option<Locatable<InternedStr>>`: the name of the declarator. May not exist for abstract parameters.
     /// `RecoverableResult<...>`: see documentation for why this exists
+    ///
+    /// `qualifiers` starts out as the qualifiers from the declaration specifiers.
+    /// Each pointer declarator moves the type qualifiers onto the type it points to,
+    /// so afterwards they are the qualifiers of the declared object itself.
+    /// e.g. for `const int *const p`, the type is `const int *` and `p` is `const`.
     // TODO: this return type is really bad
     #[allow(clippy::type_complexity)]
     fn parse_type(
         self,
         mut current: Type,
+        qualifiers: &mut Qualifiers,
         is_typedef: bool,
         location: &Location, // only used for abstract parameters
     ) -> RecoverableResult<(Option<Locatable<InternedStr>>, Type), Vec<Locatable<SemanticError>>>
@@ -1542,7 +2770,30 @@ impl Declarator {
                     identifier = Some(Locatable { data: id, location });
                     current
                 }
-                Pointer(_) => Type::Pointer(Box::new(current)),
+                Pointer(pointer_qualifiers) => {
+                    if qualifiers.restrict && !current.is_object_pointer() {
+                        let location = identifier.map_or(*location, |id| id.location);
+                        pending_errs.push(location.with(format!(
+                            "restrict requires a pointer type (got '{}')",
+                            current
+                        )));
+                    }
+                    // functions can't be qualified
+                    let pointee_qualifiers = if current.is_function() {
+                        Qualifiers::NONE
+                    } else {
+                        qualifiers.type_qualifiers()
+                    };
+                    qualifiers.c_const = pointer_qualifiers.c_const;
+                    qualifiers.volatile = pointer_qualifiers.volatile;
+                    qualifiers.restrict = pointer_qualifiers.restrict;
+                    let pointer = Type::Pointer(Box::new(current), pointee_qualifiers);
+                    if pointer_qualifiers.atomic {
+                        Type::Atomic(Box::new(pointer))
+                    } else {
+                        pointer
+                    }
+                }
                 Array(arr_type) => match current {
                     Type::Function(_) => {
                         let Locatable {
@@ -1559,6 +2810,21 @@ impl Declarator {
                         )));
                         Type::Array(Box::new(current), arr_type)
                     }
+                    Type::Struct(_) | Type::Union(_)
+                        if !current.is_complete() || current.has_flexible_array() =>
+                    {
+                        let err = if current.is_complete() {
+                            format!(
+                                "array element type '{}' cannot have a flexible array member",
+                                current
+                            )
+                        } else {
+                            format!("array has incomplete element type '{}'", current)
+                        };
+                        let location = identifier.map_or(*location, |id| id.location);
+                        pending_errs.push(location.with(err));
+                        Type::Array(Box::new(current), arr_type)
+                    }
                     _ => Type::Array(Box::new(current), arr_type),
                 },
                 Function(func_decl) => match current {
@@ -1566,6 +2832,8 @@ impl Declarator {
                         let func = mem::discriminant(&current)
                             == mem::discriminant(&Type::Function(FunctionType {
                                 varargs: false,
+                                old_style: false,
+                                noreturn: false,
                                 return_type: Box::new(Type::Int(true)),
                                 params: vec![],
                             }));
@@ -1593,12 +2861,16 @@ impl Declarator {
                             return_type: Box::new(current),
                             params: func_decl.params.into_iter().map(|x| x.data).collect(),
                             varargs: func_decl.varargs,
+                            old_style: func_decl.old_style,
+                            noreturn: false,
                         })
                     }
                     _ => Type::Function(FunctionType {
                         return_type: Box::new(current),
                         params: func_decl.params.into_iter().map(|x| x.data).collect(),
                         varargs: func_decl.varargs,
+                        old_style: func_decl.old_style,
+                        noreturn: false,
                     }),
                 },
             };
@@ -1610,6 +2882,17 @@ impl Declarator {
                 location: identifier.map_or_else(|| *location, |l| l.location),
             });
         }
+        // for functions, the qualifiers belong to the return type
+        let qualified = match &current {
+            Type::Function(ftype) => &*ftype.return_type,
+            ctype => ctype,
+        };
+        if qualifiers.restrict && !qualified.is_object_pointer() {
+            pending_errs.push(Locatable {
+                data: format!("restrict requires a pointer type (got '{}')", qualified),
+                location: identifier.map_or_else(|| *location, |l| l.location),
+            });
+        }
         if pending_errs.is_empty() {
             Ok((identifier, current))
         } else {
@@ -1619,7 +2902,68 @@ impl Declarator {
     }
 }
 
+/// Check that a prototype is compatible with a function type that has no prototype.
+///
+/// They are only compatible if calling the function without the prototype
+/// would pass the same arguments as calling it with the prototype (C11 6.7.6.3p15).
+/// The types still compare equal so that legacy code compiles, but the mismatch is
+/// almost certainly a bug.
+fn prototype_conflict(first: &FunctionType, second: &FunctionType) -> Option<String> {
+    let (prototype, other) = match (first.has_prototype(), second.has_prototype()) {
+        (true, false) => (first, second),
+        (false, true) => (second, first),
+        _ => return None,
+    };
+    if prototype.varargs {
+        return Some("a variadic function must always have a prototype".into());
+    }
+    let params: &[Symbol] = if prototype.has_params() {
+        &prototype.params[..]
+    } else {
+        &[]
+    };
+    if other.old_style && params.len() != other.params.len() {
+        return Some(format!(
+            "the prototype has {} parameters, but the definition has {}",
+            params.len(),
+            other.params.len()
+        ));
+    }
+    for (i, param) in params.iter().enumerate() {
+        let promoted = match other.params.get(i) {
+            Some(defined) if other.old_style => defined.ctype.clone().default_promote(),
+            _ => param.ctype.clone().default_promote(),
+        };
+        if param.ctype != promoted {
+            return Some(format!(
+                "parameter {} has type '{}', but the argument will be promoted to '{}'",
+                i + 1,
+                param.ctype,
+                promoted
+            ));
+        }
+    }
+    None
+}
+
 impl Type {
+    /// If `self` is an array of unknown size, infer the size from its initializer.
+    ///
+    /// e.g. `int a[] = {1, 2, 3};` has type `int[3]`
+    fn infer_array_size(&mut self, init: &Initializer) {
+        if let Type::Array(_, size @ ArrayType::Unbounded) = self {
+            if let Some(len) = match init {
+                Initializer::InitializerList(list) => Some(list.len()),
+                Initializer::Scalar(expr) => match &expr.expr {
+                    ExprType::Literal(Literal::Str(s)) => Some(s.len()),
+                    _ => None,
+                },
+                _ => None,
+            } {
+                *size = ArrayType::Fixed(SIZE_T::try_from(len).unwrap());
+            }
+        }
+    }
     fn type_at(&self, index: usize) -> Result<Type, String> {
         match self {
             ty if ty.is_scalar() => {
@@ -1634,7 +2978,7 @@ impl Type {
             }
             Type::Array(inner, _) => Ok((**inner).clone()),
             Type::Struct(struct_type) => {
-                let symbols = struct_type.members();
+                let symbols = struct_type.initialized_members();
                 symbols.get(index).map_or_else(
                     || {
                         Err(format!(
@@ -1643,17 +2987,17 @@ impl Type {
                             index
                         ))
                     },
-                    |symbol| Ok(symbol.ctype.clone()),
+                    |symbol| Ok(symbol.ctype.clone().member_type()),
                 )
             }
             Type::Union(struct_type) => {
                 if index != 0 {
                     return Err("can only initialize first member of an enum".into());
                 }
-                let members = struct_type.members();
+                let members = struct_type.initialized_members();
                 Ok(members
                     .first()
-                    .map(|m| m.ctype.clone())
+                    .map(|m| m.ctype.clone().member_type())
                     .unwrap_or(Type::Error))
             }
             Type::Error => Ok(Type::Error),
@@ -1675,6 +3019,7 @@ enum DeclaratorType {
 struct FunctionDeclarator {
     params: Vec<Locatable<Symbol>>,
     varargs: bool,
+    old_style: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -1691,7 +3036,9 @@ mod tests {
         Declaration, Initializer, Qualifiers, Symbol,
     };
     use crate::intern::InternedStr;
-    use crate::parse::tests::{match_all, match_data, parse, parse_all, ParseType};
+    use crate::parse::tests::{
+        assert_errs_decls, gnu_parser, match_all, match_data, parse, parse_all, parser, ParseType,
+    };
     use std::boxed::Box;
     use Type::*;
 
@@ -1705,20 +3052,28 @@ mod tests {
         assert!(match_type(parse("signed short i;"), Type::Short(true)));
         assert!(match_type(parse("unsigned short i;"), Type::Short(false)));
         assert!(match_type(parse("long i;"), Type::Long(true)));
-        assert!(match_type(parse("long long i;"), Type::Long(true)));
+        assert!(match_type(parse("long long i;"), Type::LongLong(true)));
+        assert!(match_type(parse("long int long i;"), Type::LongLong(true)));
+        assert!(match_type(
+            parse("unsigned long long i;"),
+            Type::LongLong(false)
+        ));
         assert!(match_type(parse("long unsigned i;"), Type::Long(false)));
         assert!(match_type(parse("int i;"), Type::Int(true)));
         assert!(match_type(parse("signed i;"), Type::Int(true)));
         assert!(match_type(parse("unsigned i;"), Type::Int(false)));
         assert!(match_type(parse("float f;"), Type::Float));
         assert!(match_type(parse("double d;"), Type::Double));
-        assert!(match_type(parse("long double d;"), Type::Double));
+        assert!(match_type(parse("long double d;"), Type::LongDouble));
+        assert!(match_type(parse("double long d;"), Type::LongDouble));
         assert!(match_type(
             parse("void f();"),
             Type::Function(FunctionType {
                 return_type: Box::new(Type::Void),
                 params: vec![],
-                varargs: false
+                varargs: false,
+                old_style: false,
+                noreturn: false,
             })
         ));
         assert!(match_type(parse("const volatile int f;"), Type::Int(true)));
@@ -1736,6 +3091,9 @@ mod tests {
         assert!(parse("short double i;").unwrap().is_err());
         assert!(parse("int void i;").unwrap().is_err());
         assert!(parse("void int i;").unwrap().is_err());
+        assert!(parse("long long long i;").unwrap().is_err());
+        assert!(parse("long long double i;").unwrap().is_err());
+        assert!(parse("unsigned long double i;").unwrap().is_err());
         // default to int if we don't have a type
         // don't panic if we see duplicate specifiers
         assert!(match_type(parse("unsigned unsigned i;"), Type::Int(false)));
@@ -1766,23 +3124,32 @@ mod tests {
     }
     #[test]
     fn test_pointers() {
-        assert!(match_type(parse("void *a;"), Pointer(Box::new(Void))));
+        assert!(match_type(
+            parse("void *a;"),
+            Pointer(Box::new(Void), Qualifiers::NONE)
+        ));
         assert!(match_type(
             parse("float *const a;"),
-            Pointer(Box::new(Float))
+            Pointer(Box::new(Float), Qualifiers::NONE)
         ));
         // cdecl: declare a as const pointer to volatile pointer to double
         assert!(match_type(
             parse("double *volatile *const a;"),
-            Pointer(Box::new(Pointer(Box::new(Double))),)
+            Pointer(
+                Box::new(Pointer(Box::new(Double), Qualifiers::NONE)),
+                Qualifiers::VOLATILE
+            )
         ));
         assert!(match_type(
             parse("_Bool *volatile const a;"),
-            Pointer(Box::new(Bool)),
+            Pointer(Box::new(Bool), Qualifiers::NONE),
         ));
         assert!(match_type(
             parse("char (*(*f));"),
-            Pointer(Box::new(Pointer(Box::new(Char(true)))))
+            Pointer(
+                Box::new(Pointer(Box::new(Char(true)), Qualifiers::NONE)),
+                Qualifiers::NONE
+            )
         ));
     }
     #[test]
@@ -1791,17 +3158,23 @@ mod tests {
         assert!(match_type(
             parse("char **foo[10];"),
             Array(
-                Box::new(Pointer(Box::new(Pointer(Box::new(Char(true)))))),
+                Box::new(Pointer(
+                    Box::new(Pointer(Box::new(Char(true)), Qualifiers::NONE)),
+                    Qualifiers::NONE
+                )),
                 ArrayType::Fixed(10),
             )
         ));
         // cdecl: declare foo as pointer to pointer to array 10 of int
         assert!(match_type(
             parse("int (**foo)[10];"),
-            Pointer(Box::new(Pointer(Box::new(Array(
-                Box::new(Int(true)),
-                ArrayType::Fixed(10)
-            )))),)
+            Pointer(
+                Box::new(Pointer(
+                    Box::new(Array(Box::new(Int(true)), ArrayType::Fixed(10))),
+                    Qualifiers::NONE
+                )),
+                Qualifiers::NONE
+            )
         ));
     }
     #[test]
@@ -1809,69 +3182,91 @@ mod tests {
         assert!(match_type(
             parse("void *f();"),
             Function(FunctionType {
-                return_type: Box::new(Pointer(Box::new(Type::Void))),
+                return_type: Box::new(Pointer(Box::new(Type::Void), Qualifiers::NONE)),
                 params: vec![],
                 varargs: false,
+                old_style: false,
+                noreturn: false,
             })
         ));
         // cdecl: declare i as pointer to function returning int;
         assert!(match_type(
             parse("int (*i)();"),
-            Pointer(Box::new(Function(FunctionType {
-                return_type: Box::new(Int(true)),
-                params: vec![],
-                varargs: false,
-            })),)
+            Pointer(
+                Box::new(Function(FunctionType {
+                    return_type: Box::new(Int(true)),
+                    params: vec![],
+                    varargs: false,
+                    old_style: false,
+                    noreturn: false,
+                })),
+                Qualifiers::NONE
+            )
         ));
         // cdecl: declare i as pointer to function (int, char, float) returning int
         assert!(match_type(
             parse("int (*i)(int, char, float);"),
-            Pointer(Box::new(Function(FunctionType {
-                return_type: Box::new(Int(true)),
-                params: vec![
-                    Symbol {
-                        id: Default::default(),
-                        ctype: Int(true),
-                        qualifiers: Default::default(),
-                        init: true,
-                        storage_class: Default::default()
-                    },
-                    Symbol {
-                        id: Default::default(),
-                        ctype: Char(true),
-                        qualifiers: Default::default(),
-                        init: true,
-                        storage_class: Default::default()
-                    },
-                    Symbol {
-                        id: Default::default(),
-                        ctype: Float,
-                        qualifiers: Default::default(),
-                        init: true,
-                        storage_class: Default::default()
-                    }
-                ],
-                varargs: false,
-            })),)
+            Pointer(
+                Box::new(Function(FunctionType {
+                    return_type: Box::new(Int(true)),
+                    params: vec![
+                        Symbol {
+                            id: Default::default(),
+                            ctype: Int(true),
+                            qualifiers: Default::default(),
+                            init: true,
+                            storage_class: Default::default()
+                        },
+                        Symbol {
+                            id: Default::default(),
+                            ctype: Char(true),
+                            qualifiers: Default::default(),
+                            init: true,
+                            storage_class: Default::default()
+                        },
+                        Symbol {
+                            id: Default::default(),
+                            ctype: Float,
+                            qualifiers: Default::default(),
+                            init: true,
+                            storage_class: Default::default()
+                        }
+                    ],
+                    varargs: false,
+                    old_style: false,
+                    noreturn: false,
+                })),
+                Qualifiers::NONE
+            )
         ));
         // cdecl: declare i as pointer to function (pointer to function returning int) returning int
         assert!(match_type(
             parse("int (*i)(int (*f)());"),
-            Pointer(Box::new(Function(FunctionType {
-                return_type: Box::new(Int(true)),
-                params: vec![Symbol {
-                    id: InternedStr::get_or_intern("f"),
-                    ctype: Pointer(Box::new(Function(FunctionType {
-                        return_type: Box::new(Int(true)),
-                        params: vec![],
-                        varargs: false
-                    })),),
-                    qualifiers: Default::default(),
-                    storage_class: Default::default(),
-                    init: true,
-                }],
-                varargs: false,
-            }),),)
+            Pointer(
+                Box::new(Function(FunctionType {
+                    return_type: Box::new(Int(true)),
+                    params: vec![Symbol {
+                        id: InternedStr::get_or_intern("f"),
+                        ctype: Pointer(
+                            Box::new(Function(FunctionType {
+                                return_type: Box::new(Int(true)),
+                                params: vec![],
+                                varargs: false,
+                                old_style: false,
+                                noreturn: false,
+                            })),
+                            Qualifiers::NONE
+                        ),
+                        qualifiers: Default::default(),
+                        storage_class: Default::default(),
+                        init: true,
+                    }],
+                    varargs: false,
+                    old_style: false,
+                    noreturn: false,
+                }),),
+                Qualifiers::NONE
+            )
         ));
         assert!(match_type(
             parse("int f(int, ...);"),
@@ -1885,6 +3280,8 @@ mod tests {
                     storage_class: Default::default()
                 }],
                 varargs: true,
+                old_style: false,
+                noreturn: false,
             })
         ));
     }
@@ -1896,18 +3293,290 @@ mod tests {
                 return_type: Box::new(Void),
                 params: vec![Symbol {
                     id: InternedStr::get_or_intern("a"),
-                    ctype: Pointer(Box::new(Int(true))),
+                    ctype: Pointer(Box::new(Int(true)), Qualifiers::NONE),
                     qualifiers: Default::default(),
                     storage_class: Default::default(),
                     init: true,
                 }],
-                varargs: false
+                varargs: false,
+                old_style: false,
+                noreturn: false,
             })
         ));
 
         assert!(parse("int b[static 10];").unwrap().is_err());
     }
     #[test]
+    fn test_old_style_definitions() {
+        assert!(match_data(
+            parse("int f(a, b) int a; char *b; { return a; }"),
+            |decl| match decl.symbol.ctype {
+                Function(ftype) => {
+                    ftype.old_style
+                        && !ftype.has_prototype()
+                        && ftype.params[0].ctype == Int(true)
+                        && ftype.params[1].ctype == Pointer(Box::new(Char(true)), Qualifiers::NONE)
+                }
+                _ => false,
+            }
+        ));
+        assert_errs_decls("int f(a, b) register int a, b; { return a; }", 0, 0, 1);
+        // implicit int
+        assert_errs_decls("int f(a) { return a; }", 0, 1, 1);
+        assert_errs_decls("int f(a, b) float b; { return a; }", 0, 1, 1);
+        assert_errs_decls("int f(a) int b; { return 0; }", 1, 1, 1);
+        assert_errs_decls("int f(a) int a; int a; { return a; }", 1, 0, 1);
+        assert_errs_decls("int f(a) static int a; { return a; }", 1, 0, 1);
+        // only allowed in definitions
+        assert_errs_decls("int f(a, b);", 1, 0, 1);
+        assert_errs_decls("int f(a), g(b);", 2, 0, 2);
+        assert_errs_decls("typedef int t; int f(t);", 0, 0, 1);
+        // the number of arguments is known, but not checked
+        assert_errs_decls(
+            "int f(a, b) int a, b; { return a; } int g() { return f(1); }",
+            0,
+            1,
+            2,
+        );
+        assert_errs_decls("int f(); int g() { return f(1, 2.0); }", 0, 0, 2);
+    }
+    #[test]
+    fn test_prototype_conflicts() {
+        assert_errs_decls("int f(); int f(int c, double d);", 0, 0, 2);
+        assert_errs_decls("int f(); int f(char c);", 0, 1, 2);
+        assert_errs_decls("int f(); int f(float x);", 0, 1, 2);
+        assert_errs_decls("int f(); int f(int, ...);", 0, 1, 2);
+        assert_errs_decls("int f(a) char a; { return a; } int f(int);", 0, 0, 2);
+        assert_errs_decls("int f(a) char a; { return a; } int f(char);", 0, 1, 2);
+        assert_errs_decls("int f(char); int f(a) char a; { return a; }", 0, 1, 2);
+        assert_errs_decls("int f(int); int f(a, b) int a, b; { return a; }", 0, 1, 2);
+    }
+    #[test]
+    fn test_block_scope_linkage() {
+        assert_errs_decls(
+            "int main() { int f(int); extern int i; return f(i); }",
+            0,
+            0,
+            1,
+        );
+        assert_errs_decls("static int i; int main() { extern int i; }", 0, 0, 2);
+        assert_errs_decls("int main() { int i; { extern int i; } }", 0, 0, 1);
+        // every declaration with linkage has to agree, even if they aren't in the same scope
+        assert_errs_decls("int i; int main() { extern double i; }", 1, 0, 2);
+        assert_errs_decls("int main() { extern int i; } double i;", 1, 0, 2);
+        assert_errs_decls(
+            "int f() { int g(void); } int h() { char g(void); }",
+            1,
+            0,
+            2,
+        );
+        assert_errs_decls("int main() { static int f(void); }", 1, 0, 1);
+        assert_errs_decls("int main() { extern int i = 1; }", 1, 0, 1);
+    }
+    #[test]
+    fn test_tentative_definitions() {
+        assert_errs_decls("int i; int i; int i = 1; int i;", 0, 0, 4);
+        assert_errs_decls("static int i; static int i = 1;", 0, 0, 2);
+        assert_errs_decls("int i = 1; int i; int i = 2;", 1, 0, 3);
+    }
+    #[test]
+    fn test_vla() {
+        // parameters can be used in the types of later parameters
+        assert!(parse("int f(int n, int a[][n]);").unwrap().is_ok());
+        assert!(parse("int f(int n) { int a[n][n + 1]; return sizeof(a); }")
+            .unwrap()
+            .is_ok());
+        // VLAs must have automatic storage duration
+        assert_errs_decls("int n; int a[n];", 1, 0, 2);
+        assert_errs_decls("int f(int n) { static int a[n]; }", 1, 0, 1);
+        assert_errs_decls("int f(int n) { extern int (*p)[n]; }", 1, 0, 1);
+        assert_errs_decls("int n; struct s { int a[n]; };", 1, 0, 1);
+        assert_errs_decls("int f(int n) { int a[n] = {1}; }", 1, 0, 1);
+        // cannot jump into the scope of a VLA, but can jump out of it
+        assert_errs_decls("int f(int n) { goto l; { int a[n]; l:; } }", 1, 0, 1);
+        assert_errs_decls(
+            "int f(int n) { { typedef int T[n]; l:; } goto l; }",
+            1,
+            0,
+            1,
+        );
+        assert_errs_decls(
+            "int f(int n) { { int a[n]; goto l; } l: return 0; }",
+            0,
+            0,
+            1,
+        );
+    }
+    #[test]
+    fn test_incomplete_types() {
+        // opaque structs can be used through pointers and completed later
+        assert_errs_decls("struct s; struct s *p; struct s { int i; };", 0, 0, 1);
+        assert_errs_decls("struct s; struct s *f(struct s *p);", 0, 0, 1);
+        assert_errs_decls("struct s my_s; struct s { int i; };", 0, 0, 1);
+        // but not by value
+        assert_errs_decls("struct s; int f() { return sizeof(struct s); }", 1, 0, 1);
+        // variables also report that the struct is never completed in their scope
+        assert_errs_decls("struct s; int f() { struct s my_s; }", 2, 0, 1);
+        assert_errs_decls("struct s; void f(struct s my_s) {}", 2, 0, 1);
+        assert_errs_decls("struct s; struct s a[2];", 1, 0, 1);
+    }
+    #[test]
+    fn test_flexible_array() {
+        assert_errs_decls("struct buf { int n; char data[]; } b;", 0, 0, 1);
+        assert_errs_decls(
+            "struct s { int n; char data[]; }; union u { struct s s; } u;",
+            0,
+            0,
+            1,
+        );
+        assert_errs_decls("struct s { char data[]; int n; } s;", 1, 0, 1);
+        assert_errs_decls("struct s { char data[]; } s;", 1, 0, 1);
+        assert_errs_decls("union u { int n; char data[]; } u;", 1, 0, 1);
+        assert_errs_decls(
+            "struct s { int n; char data[]; }; struct t { struct s s; } t;",
+            1,
+            0,
+            1,
+        );
+        assert_errs_decls("struct s { int n; char data[]; } a[2];", 1, 0, 1);
+    }
+    #[test]
+    fn test_anonymous_members() {
+        let tagged = "struct v { int kind; union { int i; float f; }; };";
+        assert_errs_decls(
+            &format!("{} int f(struct v x) {{ return x.i; }}", tagged),
+            0,
+            0,
+            1,
+        );
+        // the failed member access also can't be converted to `int`
+        assert_errs_decls(
+            &format!("{} int f(struct v x) {{ return x.j; }}", tagged),
+            2,
+            0,
+            1,
+        );
+        // a tagged struct without a declarator is not a member
+        assert_errs_decls(
+            "struct v { int kind; union u { int i; }; }; int f(struct v x) { return x.i; }",
+            2,
+            1,
+            1,
+        );
+        // members of anonymous structs and unions share a namespace with the containing struct
+        assert_errs_decls("struct v { int i; union { int i; float f; }; } v;", 1, 0, 1);
+        assert_errs_decls(
+            "struct v { union { int i; }; struct { struct { float i; }; }; } v;",
+            1,
+            0,
+            1,
+        );
+        // unnamed bitfields don't have a name to conflict
+        assert_errs_decls("struct v { int i; int : 3; int : 4; } v;", 0, 0, 1);
+    }
+    #[test]
+    fn test_complex_types() {
+        assert!(match_type(
+            parse("double _Complex d;"),
+            Complex(Box::new(Double))
+        ));
+        assert!(match_type(
+            parse("_Complex float f;"),
+            Complex(Box::new(Float))
+        ));
+        assert!(match_type(
+            parse("long double _Complex ld;"),
+            Complex(Box::new(LongDouble))
+        ));
+        // a GNU extension, but common enough to be worth supporting
+        assert_errs_decls("_Complex z;", 0, 1, 1);
+        assert_errs_decls("int _Complex i;", 1, 0, 1);
+        assert_errs_decls("unsigned _Complex u;", 1, 0, 1);
+        assert_errs_decls("double _Imaginary d;", 1, 0, 1);
+        assert_errs_decls(
+            "int f(double _Complex z) { return __real__ z + __imag__ z; }",
+            0,
+            0,
+            1,
+        );
+        assert_errs_decls("int f(int i) { return __imag__ i; }", 0, 0, 1);
+        assert_errs_decls("int f(int *p) { return __real__ p != 0; }", 1, 0, 1);
+        assert_errs_decls("int f(double _Complex z) { return z < 1; }", 1, 0, 1);
+    }
+    #[test]
+    fn test_thread_local() {
+        assert_errs_decls("_Thread_local int i;", 0, 0, 1);
+        assert_errs_decls(
+            "static _Thread_local int i; extern _Thread_local int j;",
+            0,
+            0,
+            2,
+        );
+        assert_errs_decls("int f() { static _Thread_local int i; }", 0, 0, 1);
+        assert_errs_decls("int f() { extern _Thread_local int i; }", 0, 0, 1);
+        // no static storage duration
+        assert_errs_decls("int f() { _Thread_local int i; }", 1, 0, 1);
+        assert_errs_decls("int f() { register _Thread_local int i; }", 1, 0, 1);
+        assert_errs_decls("typedef _Thread_local int t;", 1, 0, 0);
+        assert_errs_decls("int f(_Thread_local int i);", 1, 0, 1);
+        assert_errs_decls("struct s { _Thread_local int i; };", 1, 0, 0);
+        assert_errs_decls("_Thread_local int f();", 1, 0, 1);
+        assert_errs_decls("int * _Thread_local p;", 1, 0, 1);
+        // every declaration has to agree
+        assert_errs_decls("_Thread_local int i; int i;", 1, 0, 2);
+    }
+    #[test]
+    fn test_atomic_types() {
+        assert!(match_type(
+            parse("_Atomic int i;"),
+            Atomic(Box::new(Int(true)))
+        ));
+        assert!(match_type(
+            parse("_Atomic(long) l;"),
+            Atomic(Box::new(Long(true)))
+        ));
+        assert!(match_type(
+            parse("_Atomic int *p;"),
+            Pointer(Box::new(Atomic(Box::new(Int(true)))), Qualifiers::NONE)
+        ));
+        assert!(match_type(
+            parse("int *_Atomic p;"),
+            Atomic(Box::new(Pointer(Box::new(Int(true)), Qualifiers::NONE)))
+        ));
+        assert_errs_decls("_Atomic int _Atomic i;", 0, 1, 1);
+        assert_errs_decls("_Atomic(int[2]) a;", 1, 0, 1);
+        assert_errs_decls("_Atomic(const int) i;", 1, 0, 1);
+        assert_errs_decls("_Atomic(int(void)) f;", 1, 0, 1);
+        assert_errs_decls("int _Atomic(int) i;", 1, 0, 1);
+        assert_errs_decls(
+            "int f() { _Atomic int i = 1; i += 2; i++; return -i; }",
+            0,
+            0,
+            1,
+        );
+        // builtins
+        assert_errs_decls(
+            "int f(_Atomic int *p) { return __c11_atomic_fetch_add(p, 1, 5); }",
+            0,
+            0,
+            1,
+        );
+        assert_errs_decls(
+            "int f(int *p) { int e = 0; return __atomic_compare_exchange_n(p, &e, 1, 0, 5, 5); }",
+            0,
+            0,
+            1,
+        );
+        assert_errs_decls("int f(int *p) { return __c11_atomic_load(p, 5); }", 1, 0, 1);
+        assert_errs_decls("int f(int *p) { return __atomic_load_n(p); }", 1, 0, 1);
+        assert_errs_decls(
+            "double f(double *p) { return __atomic_fetch_add(p, 1, 5); }",
+            1,
+            0,
+            1,
+        );
+    }
+    #[test]
     fn test_inline_keyword() {
         // Correct usage
         assert!(match_type(
@@ -1916,6 +3585,8 @@ mod tests {
                 return_type: Box::new(Void),
                 params: vec![],
                 varargs: false,
+                old_style: false,
+                noreturn: false,
             })
         ));
 
@@ -1929,64 +3600,207 @@ mod tests {
                 .is_err()
         );
         assert!(parse("typedef a inline int;").unwrap().is_err());
+        assert!(parse("inline int main() {}").unwrap().is_err());
+
+        // declarations can disagree about `inline`
+        assert_errs_decls("inline int f(void); int f(void) { return 0; }", 0, 0, 2);
+        assert_errs_decls(
+            "int f(void); extern inline int f(void) { return 0; }",
+            0,
+            0,
+            2,
+        );
+
+        // inline definitions can't define modifiable static variables (C11 6.7.4p3)
+        assert_errs_decls("inline int f(void) { static int x; return x; }", 1, 0, 1);
+        assert_errs_decls(
+            "inline int f(void) { static const int x = 1; extern int y; return x + y; }",
+            0,
+            0,
+            1,
+        );
+        assert_errs_decls(
+            "static inline int f(void) { static int x; return x; }",
+            0,
+            0,
+            1,
+        );
+        assert_errs_decls(
+            "int f(void); inline int f(void) { static int x; return x; }",
+            0,
+            0,
+            2,
+        );
+        assert_errs_decls(
+            "extern inline int f(void) { static int x; return x; }",
+            0,
+            0,
+            1,
+        );
+    }
+    #[test]
+    fn test_noreturn() {
+        assert!(match_type(
+            parse("_Noreturn void f(void);"),
+            Function(FunctionType {
+                return_type: Box::new(Void),
+                params: vec![],
+                varargs: false,
+                old_style: false,
+                noreturn: true,
+            })
+        ));
+        assert!(parse("_Noreturn int i;").unwrap().is_err());
+        assert!(parse("typedef _Noreturn void f(void);").unwrap().is_err());
+        // declarations can disagree about `_Noreturn`
+        assert_errs_decls(
+            "_Noreturn void f(void); void f(void) { for (;;); }",
+            0,
+            0,
+            2,
+        );
+        assert_errs_decls("_Noreturn void f(void) { return; }", 0, 1, 1);
+    }
+    #[test]
+    fn test_pointer_qualifiers() {
+        // the pointer is const, not the char
+        assert!(match_data(parse("char *const p;"), |decl| decl
+            .symbol
+            .qualifiers
+            == Qualifiers::CONST
+            && decl.symbol.ctype
+                == Pointer(Box::new(Char(true)), Qualifiers::NONE)));
+        // the char is const, not the pointer
+        assert!(match_data(parse("const char *p;"), |decl| decl
+            .symbol
+            .qualifiers
+            == Qualifiers::NONE
+            && decl.symbol.ctype
+                == Pointer(Box::new(Char(true)), Qualifiers::CONST)));
+        assert_errs_decls("const char *p; void f() { p = \"hi\"; }", 0, 0, 2);
+        assert_errs_decls("const int *p; void f() { *p = 1; }", 1, 0, 2);
+        assert_errs_decls("const int a[2]; void f() { a[0] = 1; }", 1, 0, 2);
+        assert_errs_decls("int *const p; void f() { *p = 1; }", 0, 0, 2);
+        assert_errs_decls("int *const p; void f() { p = 0; }", 1, 0, 2);
+        assert_errs_decls(
+            "struct s { const int i; } s; void f() { s.i = 1; }",
+            1,
+            0,
+            2,
+        );
+        // adding qualifiers is fine, removing them is not
+        assert_errs_decls("void f(char *p) { const char *q = p; }", 0, 0, 1);
+        assert_errs_decls("void f(const char *p) { char *q = p; }", 0, 1, 1);
+        assert_errs_decls("void f(char *); void g(const char *p) { f(p); }", 0, 1, 2);
+        assert_errs_decls("char *f(const char *p) { return p; }", 0, 1, 1);
+        // redeclarations must agree on the qualifiers of the target
+        assert!(parse_all("char *p; const char *p;")
+            .into_iter()
+            .any(|decl| decl.is_err()));
+        // but not on the qualifiers of parameters
+        assert_errs_decls("void f(const int i); void f(int i);", 0, 0, 2);
+        assert_errs_decls("void f(const int a[]); void f(const int *a);", 0, 0, 2);
+    }
+    #[test]
+    fn test_restrict() {
+        assert!(match_data(parse("int *restrict p;"), |decl| decl
+            .symbol
+            .qualifiers
+            .restrict));
+        assert!(parse("restrict int i;").unwrap().is_err());
+        assert!(parse("int (*restrict f)();").unwrap().is_err());
+        assert!(parse("int *restrict *p;").unwrap().is_ok());
+        assert_errs_decls("int *restrict restrict p;", 0, 1, 1);
+        assert_errs_decls(
+            "void *memcpy(void *restrict dst, const void *restrict src, unsigned long n);",
+            0,
+            0,
+            1,
+        );
     }
     #[test]
     fn test_complex() {
         // cdecl: declare bar as const pointer to array 10 of pointer to function (int) returning const pointer to char
         assert!(match_type(
             parse("char * const (*(* const bar)[])(int );"),
-            Pointer(Box::new(Array(
-                Box::new(Pointer(Box::new(Function(FunctionType {
-                    return_type: Box::new(Pointer(Box::new(Char(true)))),
+            Pointer(
+                Box::new(Array(
+                    Box::new(Pointer(
+                        Box::new(Function(FunctionType {
+                            return_type: Box::new(Pointer(Box::new(Char(true)), Qualifiers::NONE)),
+                            params: vec![Symbol {
+                                ctype: Int(true),
+                                storage_class: Default::default(),
+                                id: Default::default(),
+                                qualifiers: Qualifiers::NONE,
+                                init: true,
+                            }],
+                            varargs: false,
+                            old_style: false,
+                            noreturn: false,
+                        })),
+                        Qualifiers::NONE
+                    )),
+                    ArrayType::Unbounded,
+                )),
+                Qualifiers::NONE
+            )
+        ));
+        // cdecl: declare foo as pointer to function (void) returning pointer to array 3 of int
+        assert!(match_type(
+            parse("int (*(*foo)(void))[];"),
+            Pointer(
+                Box::new(Function(FunctionType {
+                    return_type: Box::new(Pointer(
+                        Box::new(Array(Box::new(Int(true)), ArrayType::Unbounded)),
+                        Qualifiers::NONE
+                    )),
                     params: vec![Symbol {
-                        ctype: Int(true),
+                        ctype: Void,
                         storage_class: Default::default(),
                         id: Default::default(),
-                        qualifiers: Qualifiers::NONE,
+                        qualifiers: Default::default(),
                         init: true,
                     }],
                     varargs: false,
-                })),)),
-                ArrayType::Unbounded,
-            )),)
-        ));
-        // cdecl: declare foo as pointer to function (void) returning pointer to array 3 of int
-        assert!(match_type(
-            parse("int (*(*foo)(void))[];"),
-            Pointer(Box::new(Function(FunctionType {
-                return_type: Box::new(Pointer(Box::new(Array(
-                    Box::new(Int(true)),
-                    ArrayType::Unbounded
-                )),)),
-                params: vec![Symbol {
-                    ctype: Void,
-                    storage_class: Default::default(),
-                    id: Default::default(),
-                    qualifiers: Default::default(),
-                    init: true,
-                }],
-                varargs: false,
-            })),)
+                    old_style: false,
+                    noreturn: false,
+                })),
+                Qualifiers::NONE
+            )
         ));
         // cdecl: declare bar as volatile pointer to array 64 of const int
         assert!(match_type(
             parse("const int (* volatile bar)[];"),
-            Pointer(Box::new(Array(Box::new(Int(true)), ArrayType::Unbounded)),)
+            Pointer(
+                Box::new(Array(Box::new(Int(true)), ArrayType::Unbounded)),
+                Qualifiers::CONST
+            )
         ));
         // cdecl: declare x as function returning pointer to array 5 of pointer to function returning char
         assert!(match_type(
             parse("char (*(*x())[])();"),
             Function(FunctionType {
-                return_type: Box::new(Pointer(Box::new(Array(
-                    Box::new(Pointer(Box::new(Function(FunctionType {
-                        return_type: Box::new(Char(true)),
-                        params: vec![],
-                        varargs: false,
-                    })),)),
-                    ArrayType::Unbounded
-                )),)),
+                return_type: Box::new(Pointer(
+                    Box::new(Array(
+                        Box::new(Pointer(
+                            Box::new(Function(FunctionType {
+                                return_type: Box::new(Char(true)),
+                                params: vec![],
+                                varargs: false,
+                                old_style: false,
+                                noreturn: false,
+                            })),
+                            Qualifiers::NONE
+                        )),
+                        ArrayType::Unbounded
+                    )),
+                    Qualifiers::NONE
+                )),
                 params: vec![],
                 varargs: false,
+                old_style: false,
+                noreturn: false,
             })
         ));
     }
@@ -1999,12 +3813,15 @@ mod tests {
         assert!(parsed.len() == 4);
         assert!(match_type(
             Some(parsed.remove(0)),
-            Type::Pointer(Box::new(Type::Char(true))),
+            Type::Pointer(Box::new(Type::Char(true)), Qualifiers::NONE),
         ));
         assert!(match_type(Some(parsed.remove(0)), Type::Char(true)));
         assert!(match_type(
             Some(parsed.remove(0)),
-            Type::Pointer(Box::new(Type::Pointer(Box::new(Type::Char(true)),)),)
+            Type::Pointer(
+                Box::new(Type::Pointer(Box::new(Type::Char(true)), Qualifiers::NONE)),
+                Qualifiers::NONE
+            )
         ));
         assert!(match_type(
             Some(parsed.remove(0)),
@@ -2012,6 +3829,8 @@ mod tests {
                 params: vec![],
                 return_type: Box::new(Type::Char(true)),
                 varargs: false,
+                old_style: false,
+                noreturn: false,
             })
         ));
     }
@@ -2024,12 +3843,15 @@ mod tests {
         assert!(parsed.len() == 4);
         assert!(match_type(
             Some(parsed.remove(0)),
-            Type::Pointer(Box::new(Type::Int(true)))
+            Type::Pointer(Box::new(Type::Int(true)), Qualifiers::NONE)
         ));
         assert!(match_type(Some(parsed.remove(0)), Type::Int(true)));
         assert!(match_type(
             Some(parsed.remove(0)),
-            Type::Pointer(Box::new(Type::Pointer(Box::new(Type::Int(true)))))
+            Type::Pointer(
+                Box::new(Type::Pointer(Box::new(Type::Int(true)), Qualifiers::NONE)),
+                Qualifiers::NONE
+            )
         ));
         assert!(match_type(
             Some(parsed.remove(0)),
@@ -2037,6 +3859,8 @@ mod tests {
                 params: vec![],
                 return_type: Box::new(Type::Int(true)),
                 varargs: false,
+                old_style: false,
+                noreturn: false,
             })
         ));
     }
@@ -2108,6 +3932,14 @@ mod tests {
                 ]
             )
         ));
+        // as an extension, enumerators can be as large as UINT_MAX
+        assert!(parse("enum { A = 4294967295 } E;").unwrap().is_ok());
+        assert!(parse("enum { A = 4294967294, B } E;").unwrap().is_ok());
+        assert!(parse("enum { A = 4294967295, B } E;").unwrap().is_err());
+        assert!(parse("enum { A = -2147483649 } E;").unwrap().is_err());
+        assert!(parse("enum { A = -1, B = 4294967295 } E;")
+            .unwrap()
+            .is_err());
     }
     #[test]
     fn typedef_signed() {
@@ -2120,6 +3952,480 @@ mod tests {
         assert!(parse("struct { int a:5; } b;").unwrap().is_ok());
         assert!(parse("struct { int a:5, b:6; } c;").unwrap().is_ok());
         assert!(parse("struct { extern int a:5; } d;").unwrap().is_err());
+        assert!(parse("struct { int a:5, :0, b:6, :2; } e;")
+            .unwrap()
+            .is_ok());
+        assert!(parse("struct { _Bool a:1; unsigned long b:64; } f;")
+            .unwrap()
+            .is_ok());
+        // too large for the declared type
+        assert!(parse("struct { int a:33; } g;").unwrap().is_err());
+        assert!(parse("struct { _Bool a:2; } h;").unwrap().is_err());
+        // not an integer
+        assert!(parse("struct { float a:2; } i;").unwrap().is_err());
+        assert!(parse("struct { int a:0; } j;").unwrap().is_err());
+    }
+    fn gnu_parse_all(input: &str) -> Vec<ParseType> {
+        gnu_parser(input).collect()
+    }
+    fn attribute_names(decl: &ParseType) -> Vec<String> {
+        match decl {
+            Ok(decl) => decl
+                .data
+                .attributes
+                .iter()
+                .map(|attr| attr.data.name.resolve_and_clone())
+                .collect(),
+            Err(_) => panic!("expected declaration, got error"),
+        }
+    }
+    #[test]
+    fn gnu_attributes() {
+        let decls = gnu_parse_all(
+            "int f(const char *, ...) __attribute__((noreturn, __format__(printf, 1, 2)));
+            __attribute__((unused)) static int i, j __attribute__((aligned(8)));
+            struct __attribute__((packed)) s { int x __attribute__((aligned)); } __attribute__(());",
+        );
+        assert_eq!(decls.len(), 3);
+        assert_eq!(attribute_names(&decls[0]), vec!["noreturn", "format"]);
+        assert_eq!(attribute_names(&decls[1]), vec!["unused"]);
+        assert_eq!(attribute_names(&decls[2]), vec!["unused", "aligned"]);
+        let format = &decls[0].as_ref().unwrap().data.attributes[1].data;
+        assert!(match format.args.as_slice() {
+            [AttributeArg::Id(printf), AttributeArg::Expr(_), AttributeArg::Expr(_)] =>
+                *printf == InternedStr::get_or_intern("printf"),
+            _ => false,
+        });
+        // not allowed in ISO C, but still parsed
+        assert!(parse("int i __attribute__((unused));").unwrap().is_err());
+        assert_errs_decls("int i __attribute__((unused)), j;", 1, 0, 2);
+    }
+    /// The warnings from parsing `input` in GNU mode, which should have no errors.
+    fn gnu_warnings(input: &str) -> Vec<String> {
+        let mut parser = gnu_parser(input);
+        let (_, errs) = parser.collect_results();
+        assert!(errs.is_empty(), "unexpected errors: {:?}", errs);
+        parser
+            .warnings()
+            .into_iter()
+            .map(|warning| warning.data.to_string())
+            .collect()
+    }
+    #[test]
+    fn gnu_layout_attributes() {
+        let sizeof = |input| match gnu_parse_all(input).pop() {
+            Some(Ok(decl)) => decl.data.symbol.ctype.sizeof().unwrap(),
+            other => panic!("expected a declaration, got {:?}", other),
+        };
+        assert_eq!(
+            sizeof("struct __attribute__((packed)) { char c; int i; } s;"),
+            5
+        );
+        assert_eq!(
+            sizeof("struct { char c; int i; } __attribute__((packed)) s;"),
+            5
+        );
+        assert_eq!(
+            sizeof("struct { char c; int i __attribute__((packed)); } s;"),
+            5
+        );
+        assert_eq!(
+            sizeof("struct { char c; } __attribute__((aligned(8))) s;"),
+            8
+        );
+        assert_eq!(
+            sizeof("struct { char c; char d __attribute__((aligned(4))); } s;"),
+            8
+        );
+        let decls = gnu_parse_all("int i __attribute__((aligned(32))); int i;");
+        assert!(match_all(decls.into_iter(), |decl| decl.symbol.alignof() == Ok(32)));
+        assert!(gnu_parse_all("int i __attribute__((aligned(3)));")
+            .iter()
+            .any(Result::is_err));
+    }
+    #[test]
+    fn gnu_object_attributes() {
+        assert!(gnu_warnings(
+            r#"extern void f(void) __attribute__((weak, visibility("hidden")));
+            int x __attribute__((used)) = 1;
+            int y __attribute__((section(".mydata"))) = 2;
+            void g(void) __attribute__((alias("f")));
+            __attribute__((constructor(101))) void h(void) {}"#
+        )
+        .is_empty());
+        for invalid in &[
+            r#"static int i __attribute__((weak));"#,
+            r#"int i __attribute__((visibility("secret")));"#,
+            r#"int i __attribute__((section(1)));"#,
+            r#"int f(void) { int i __attribute__((section(".mydata"))); return i; }"#,
+            r#"int i __attribute__((alias("j"))) = 1;"#,
+        ] {
+            assert!(
+                gnu_parse_all(invalid).iter().any(Result::is_err),
+                "{} should be an error",
+                invalid
+            );
+        }
+        assert_eq!(
+            gnu_warnings("int i __attribute__((constructor, not_an_attribute));"),
+            vec![
+                "'constructor' attribute only applies to functions",
+                "unknown attribute 'not_an_attribute' ignored"
+            ]
+        );
+    }
+    #[test]
+    fn gnu_usage_attributes() {
+        assert_eq!(
+            gnu_warnings(
+                r#"int old(void) __attribute__((deprecated("use new")));
+                int main(void) { return old(); }"#
+            ),
+            vec!["'old' is deprecated: use new"]
+        );
+        assert_eq!(
+            gnu_warnings(
+                "int check(void) __attribute__((warn_unused_result));
+                int main(void) { check(); (void)check(); return check(); }"
+            ),
+            vec!["ignoring return value of 'check', declared with attribute 'warn_unused_result'"]
+        );
+        assert_eq!(
+            gnu_warnings(
+                "static int unused(void) { return 0; }
+                static int maybe(void) __attribute__((unused));
+                static int maybe(void) { return 0; }
+                static inline int helper(void) { return 0; }
+                static int used(void) { return 0; }
+                int main(void) { return used(); }"
+            ),
+            vec!["unused function 'unused'"]
+        );
+    }
+    #[test]
+    fn asm_labels() {
+        let decls = gnu_parse_all(r#"int my_abs(int) __asm__("abs"); int x asm("y") = 1;"#);
+        assert!(match_all(decls.into_iter(), |decl| decl
+            .asm_label
+            .is_some()));
+        let decls = gnu_parse_all(r#"int main(void) { int x __asm__("y"); }"#);
+        assert!(decls.iter().any(Result::is_err));
+        let decls = gnu_parse_all(r#"typedef int i __asm__("y");"#);
+        assert!(decls.iter().any(Result::is_err));
+    }
+    #[test]
+    fn gnu_typeof() {
+        let decls = gnu_parse_all(
+            "const int c = 1; __typeof__(c) d; typeof(int *) p; typeof(c + 1L) l; typeof(p[0]) *q;",
+        );
+        assert!(decls.iter().all(Result::is_ok));
+        let d = &decls[1].as_ref().unwrap().data.symbol;
+        assert_eq!(d.ctype, Int(true));
+        assert!(d.qualifiers.c_const);
+        assert_eq!(
+            decls[2].as_ref().unwrap().data.symbol.ctype,
+            Pointer(Box::new(Int(true)), Qualifiers::default())
+        );
+        let l = &decls[3].as_ref().unwrap().data.symbol;
+        assert_eq!(l.ctype, Long(true));
+        assert!(!l.qualifiers.c_const);
+        assert_eq!(
+            decls[4].as_ref().unwrap().data.symbol.ctype,
+            Pointer(Box::new(Int(true)), Qualifiers::default())
+        );
+        assert!(gnu_parse_all("int typeof(int) i;")
+            .iter()
+            .any(Result::is_err));
+        assert!(parse("typeof(int) i;").unwrap().is_err());
+        assert_errs_decls("typeof(int) i; __typeof__(i) j;", 2, 0, 2);
+        assert_errs_decls("int f(void) { typeof(1) i = 1; return i; }", 1, 0, 1);
+        // `typeof` is an ordinary identifier in ISO C
+        assert_errs_decls(
+            "int typeof(int); int f(void) { return typeof(1); }",
+            0,
+            0,
+            2,
+        );
+        assert_errs_decls("int typeof; int f(void) { return typeof; }", 0, 0, 2);
+    }
+    #[test]
+    fn gnu_extension() {
+        let decls = gnu_parse_all(
+            "__extension__ typedef long long ll; __extension__ ll x;
+            int f(void) { __extension__ int y = __extension__ 1; return y; }",
+        );
+        assert!(decls.iter().all(Result::is_ok));
+        assert_eq!(decls.len(), 2);
+    }
+    #[test]
+    fn statement_expressions() {
+        let decls = gnu_parse_all(
+            "int f(void) { int i = ({ int x = 1; x + 1; }); ({ i++; }); ({ }); return i; }",
+        );
+        assert_eq!(decls.len(), 1);
+        assert!(decls[0].is_ok());
+        // the value of a block that doesn't end in an expression is `void`
+        assert!(gnu_parse_all("int f(void) { int i = ({ ; }); }")
+            .iter()
+            .any(Result::is_err));
+        assert!(gnu_parse_all("int i = ({ 1; });")
+            .iter()
+            .any(Result::is_err));
+        assert!(parse("int f(void) { return ({ 1; }); }").unwrap().is_err());
+        assert_errs_decls("int f(void) { return ({ int y = 3; y; }); }", 1, 0, 1);
+    }
+    #[test]
+    fn test_labels() {
+        // labels can be used before they're declared
+        assert_errs_decls("int f() { goto l; l: return 0; }", 0, 0, 1);
+        assert_errs_decls("int f() { goto l; }", 1, 0, 1);
+        assert_errs_decls("int f() { l: l: return 0; }", 1, 0, 1);
+        // labels have function scope
+        assert_errs_decls("int f() { { l:; } goto l; }", 0, 0, 1);
+        assert_errs_decls("int f() { l:; } int g() { goto l; }", 1, 0, 2);
+    }
+    #[test]
+    fn computed_goto() {
+        let decls = gnu_parse_all(
+            "int f(int i) { void *labels[] = { &&a, &&b }; goto *labels[i]; a: return 1; b: return 2; }",
+        );
+        assert_eq!(decls.len(), 1);
+        assert!(decls[0].is_ok());
+        assert!(gnu_parse_all("int f() { void *p = &&l; }")
+            .iter()
+            .any(Result::is_err));
+        assert!(gnu_parse_all("int f() { goto *1; }")
+            .iter()
+            .any(Result::is_err));
+        assert!(gnu_parse_all("void *p = &&l;").iter().any(Result::is_err));
+        // labels as values are a GNU extension
+        assert_errs_decls("int f() { l: goto *&&l; }", 2, 0, 1);
+    }
+    #[test]
+    fn fixes() {
+        // every suggested fix, with the start and end of the text it replaces
+        let fixes = |input| {
+            let mut parser = parser(input);
+            let (_, errs) = parser.collect_results();
+            let warnings = parser.warnings();
+            errs.into_iter()
+                .flat_map(|err| err.fixes)
+                .chain(warnings.into_iter().flat_map(|warning| warning.fixes))
+                .map(|fix| {
+                    let span = fix.location.span;
+                    (fix.replacement, span.start().0, span.end().0)
+                })
+                .collect::<Vec<_>>()
+        };
+        let fix =
+            |replacement: &str, start: u32, end: u32| vec![(replacement.to_string(), start, end)];
+        assert_eq!(fixes("int i = 1 int j;"), fix(";", 9, 9));
+        assert_eq!(fixes("static x;"), fix("int ", 7, 7));
+        assert_eq!(
+            fixes("int f(int i) { int *p = i; return 0; }"),
+            fix("&", 24, 24)
+        );
+        assert_eq!(
+            fixes("int f(int *p) { int i = p; return i; }"),
+            fix("*", 24, 24)
+        );
+        assert_eq!(
+            fixes("int f(int a) { if (a = 1) return 1; return 0; }"),
+            fix("==", 21, 22)
+        );
+        assert!(fixes("int f(int a) { while ((a = 1)) {} return 0; }").is_empty());
+        assert!(fixes("int f(int a) { int *p = a + 1; return 0; }").is_empty());
+    }
+    #[test]
+    fn previous_declaration_notes() {
+        // the notes on the first error, and the byte offset each one points to
+        let notes = |input| {
+            let (_, errs) = parser(input).collect_results();
+            let err = errs.into_iter().next().expect("expected an error");
+            err.notes
+                .into_iter()
+                .map(|note| (note.data, note.location.span.start().0))
+                .collect::<Vec<_>>()
+        };
+        let declaration = |offset| vec![("previous declaration is here".to_string(), offset)];
+        let definition = |offset| vec![("previous definition is here".to_string(), offset)];
+        assert_eq!(notes("int i; double i;"), declaration(4));
+        assert_eq!(notes("int i = 1; int i; int i = 2;"), definition(4));
+        assert_eq!(
+            notes("int i; int main() { extern float i; }"),
+            declaration(4)
+        );
+        assert_eq!(
+            notes("int f(a) int a; int a; { return a; }"),
+            declaration(13)
+        );
+        assert_eq!(notes("int f() { a: a: return 0; }"), definition(10));
+        assert_eq!(
+            notes("struct s { int i; }; struct s { int i; };"),
+            definition(7)
+        );
+        assert_eq!(notes("enum e { A }; enum e { B };"), definition(5));
+        assert_eq!(notes("struct s; enum s { A };"), declaration(7));
+        assert_eq!(
+            notes("int f(int i) { switch (i) { default: default:; } return 0; }"),
+            vec![("previous default is here".to_string(), 28)]
+        );
+    }
+    #[test]
+    fn did_you_mean() {
+        let first_err = |input| {
+            let (_, errs) = parser(input).collect_results();
+            errs.into_iter().next().expect("expected an error")
+        };
+        let message = |input| first_err(input).data.to_string();
+        assert_eq!(
+            message("struct buf { int length; } b; int f() { return b.lenght; }"),
+            "invalid program: no member named 'lenght' in 'struct buf'; did you mean 'length'?"
+        );
+        assert_eq!(
+            message("struct { union { int length; }; } b; int f() { return b.lenght; }"),
+            "invalid program: no member named 'lenght' in '<anonymous struct>'; did you mean 'length'?"
+        );
+        assert_eq!(
+            message("int count; int f() { return cuont; }"),
+            "invalid program: use of undeclared identifier 'cuont'; did you mean 'count'?"
+        );
+        assert_eq!(
+            message("int f() { return xyz; }"),
+            "invalid program: use of undeclared identifier 'xyz'"
+        );
+        assert_eq!(
+            message("typedef unsigned long size_t; size_tt n;"),
+            "invalid program: unknown type name 'size_tt'; did you mean 'size_t'?"
+        );
+        assert_eq!(
+            message("int f() { flaot x; return 0; }"),
+            "invalid program: unknown type name 'flaot'; did you mean 'float'?"
+        );
+        assert_eq!(
+            message("struct buf { int n; }; int f() { struct bfu b; return 0; }"),
+            "invalid program: variable 'b' has incomplete type 'struct bfu'; did you mean 'struct buf'?"
+        );
+        assert_eq!(
+            message("struct buf { int n; }; struct bfu b;"),
+            "invalid program: forward declaration of bfu is never completed (used in b); did you mean 'struct buf'?"
+        );
+        assert_eq!(
+            message("enum color { RED }; enum colr c;"),
+            "invalid program: cannot have forward reference to enum type 'colr'; did you mean 'enum color'?"
+        );
+        // any short name is only an edit or two away from any other
+        assert_eq!(
+            message("int a; int f() { return b; }"),
+            "invalid program: use of undeclared identifier 'b'"
+        );
+        // suggestions are only guesses, so they aren't applied by `--apply-fixes`
+        assert!(first_err("int count; int f() { return cuont; }")
+            .fixes
+            .is_empty());
+    }
+    #[test]
+    fn test_switch() {
+        assert_errs_decls(
+            "int f(int i) { switch (i) { case 1: case 2: default:; } }",
+            0,
+            0,
+            1,
+        );
+        assert_errs_decls("int f(double d) { switch (d) {} }", 1, 1, 1);
+        // duplicates are checked after converting to the promoted type
+        assert_errs_decls(
+            "int f(int i) { switch (i) { case 1: case 2 - 1:; } }",
+            1,
+            0,
+            1,
+        );
+        assert_errs_decls(
+            "int f(unsigned u) { switch (u) { case -1: case 4294967295u:; } }",
+            1,
+            0,
+            1,
+        );
+        assert_errs_decls(
+            "int f(int i) { switch (i) { default: default:; } }",
+            1,
+            0,
+            1,
+        );
+        assert_errs_decls("int f(int i) { case 1: return i; }", 1, 0, 1);
+        // nested switches have their own cases
+        assert_errs_decls(
+            "int f(int i) { switch (i) { case 1: switch (i) { case 1: default:; } default:; } }",
+            0,
+            0,
+            1,
+        );
+        // values that can never match are a warning
+        assert_errs_decls(
+            "int f(unsigned char c) { switch (c) { case 256: case -1:; } }",
+            0,
+            2,
+            1,
+        );
+        // values that don't fit in the promoted type are a warning before they wrap around
+        assert_errs_decls(
+            "int f(int i) { switch (i) { case 4294967296: case 3000000000u:; } }",
+            0,
+            2,
+            1,
+        );
+        assert_errs_decls(
+            "int f(int i) { switch (i) { case 4294967296: case 0:; } }",
+            1,
+            1,
+            1,
+        );
+        assert_errs_decls(
+            "int f(signed char c) { switch (c) { case -128: case 127:; } }",
+            0,
+            0,
+            1,
+        );
+        // -Wswitch
+        let enums = "enum e { A, B, C };";
+        assert_errs_decls(
+            &format!(
+                "{} int f(enum e x) {{ switch (x) {{ case A: case C:; }} }}",
+                enums
+            ),
+            0,
+            1,
+            1,
+        );
+        assert_errs_decls(
+            &format!(
+                "{} int f(enum e x) {{ switch (x) {{ case A: default:; }} }}",
+                enums
+            ),
+            0,
+            0,
+            1,
+        );
+    }
+    #[test]
+    fn case_ranges() {
+        let decls =
+            gnu_parse_all("int f(int i) { switch (i) { case 1 ... 5: case 6: case -3 ... -1:; } }");
+        assert!(decls.iter().all(Result::is_ok));
+        assert!(
+            gnu_parse_all("int f(int i) { switch (i) { case 1 ... 5: case 3:; } }")
+                .iter()
+                .any(Result::is_err)
+        );
+        assert!(
+            gnu_parse_all("int f(int i) { switch (i) { case 5 ... 1:; } }")
+                .iter()
+                .any(Result::is_err)
+        );
+        assert_errs_decls("int f(int i) { switch (i) { case 1 ... 5:; } }", 1, 0, 1);
+        let decls =
+            gnu_parse_all("int f(int i) { switch (i) { case -5 ... ~0: case 'a' + 1 ... 'z':; } }");
+        assert!(decls.iter().all(Result::is_ok));
     }
     #[test]
     fn lol() {
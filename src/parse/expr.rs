@@ -4,8 +4,10 @@ use crate::data::prelude::*;
 use crate::data::{
     lex::{AssignmentToken, ComparisonToken, Keyword},
     types::ArrayType,
+    Qualifiers,
     StorageClass::Typedef,
 };
+use std::convert::TryFrom;
 
 impl<I: Iterator<Item = Lexeme>> Parser<I> {
     /// expr_opt: expr ';' | ';'
@@ -113,11 +115,14 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             self.error_handler.push_back(assign_op.location.error(err));
             Ok(lval)
         } else {
-            if rval.ctype != lval.ctype {
-                rval = rval.cast(&lval.ctype).recover(&mut self.error_handler);
+            // the value of an assignment never has atomic type (C11 6.5.16p3)
+            let ctype = lval.ctype.strip_atomic().clone();
+            if rval.ctype != ctype {
+                self.check_pointer_qualifiers(&rval, &ctype);
+                rval = self.assignment_cast(rval, &ctype);
             }
             Ok(Expr {
-                ctype: lval.ctype.clone(),
+                ctype,
                 constexpr: rval.constexpr,
                 lval: false, // `(i = j) = 4`; is invalid
                 location: assign_op.location,
@@ -126,6 +131,52 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
         }
     }
 
+    /// Warn if converting `expr` to `ctype` would lose the qualifiers of a pointer target,
+    /// e.g. assigning a `const char *` to a `char *` (C11 6.5.16.1p1).
+    pub(super) fn check_pointer_qualifiers(&mut self, expr: &Expr, ctype: &Type) {
+        if expr.is_null() {
+            return;
+        }
+        if let (Type::Pointer(_, from), Type::Pointer(_, to)) = (&expr.ctype, ctype.strip_atomic())
+        {
+            let discarded = Qualifiers {
+                c_const: from.c_const && !to.c_const,
+                volatile: from.volatile && !to.volatile,
+                restrict: from.restrict && !to.restrict,
+                ..Qualifiers::NONE
+            };
+            if discarded != Qualifiers::NONE {
+                self.error_handler.warn(
+                    Warning::DiscardedQualifiers(
+                        expr.ctype.clone(),
+                        ctype.clone(),
+                        discarded.to_string().trim_end().to_string(),
+                    ),
+                    expr.location,
+                );
+            }
+        }
+    }
+
+    /// Convert `expr` to `ctype` as if by assignment (C11 6.5.16.1).
+    ///
+    /// If the conversion isn't allowed but would be with `&` or `*` in front of a variable,
+    /// the error suggests adding it.
+    pub(super) fn assignment_cast(&mut self, expr: Expr, ctype: &Type) -> Expr {
+        let pointer_fix = expr.pointer_fix(ctype.strip_atomic());
+        match expr.cast(ctype) {
+            Ok(expr) => expr,
+            Err((err, expr)) => {
+                let mut err = CompileError::from(err);
+                if let Some(replacement) = pointer_fix {
+                    err = err.fix(expr.location.before(), replacement);
+                }
+                self.error_handler.push_back(err);
+                expr
+            }
+        }
+    }
+
     /// conditional_expr
     /// : logical_or_expr
     /// | logical_or_expr '?' expr ':' conditional_expr
@@ -340,13 +391,13 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             &[&Token::Plus, &Token::Minus],
             |mut left, mut right, token| {
                 match (&left.ctype, &right.ctype) {
-                    (Type::Pointer(to), i)
+                    (Type::Pointer(to, _), i)
                     | (Type::Array(to, _), i) if i.is_integral() && to.is_complete() => {
                         let to = to.clone();
                         let (left, right) = (left.rval(), right.rval());
                         return Expr::pointer_arithmetic(left, right, &*to, token.location);
                     }
-                    (i, Type::Pointer(to))
+                    (i, Type::Pointer(to, _))
                         // `i - p` for pointer p is not valid
                     | (i, Type::Array(to, _)) if i.is_integral() && token.data == Token::Plus && to.is_complete() => {
                         let to = to.clone();
@@ -361,7 +412,7 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                     right = Box::new(tmp.1);
                     (left.ctype.clone(), false)
                 // `p1 + p2` for pointers p1 and p2 is not valid
-                } else if token.data == Token::Minus && left.ctype.is_pointer_to_complete_object() && left.ctype == right.ctype {
+                } else if token.data == Token::Minus && left.ctype.is_pointer_to_complete_object() && (left.ctype == right.ctype || left.ctype.pointee_eq(&right.ctype)) {
                     // not sure what type to use here, C11 standard doesn't mention it
                     (left.ctype.clone(), true)
                 } else {
@@ -434,6 +485,26 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
         )
     }
 
+    /// Whether the next token starts a type name: either a declaration specifier or a typedef.
+    pub(super) fn next_is_type_name(&mut self) -> bool {
+        match self.peek_token() {
+            Some(Token::Keyword(k)) => k.is_decl_specifier(),
+            Some(&Token::Id(id)) => match self.scope.get(&id) {
+                Some(symbol) => symbol.storage_class == Typedef,
+                None => self.is_iso_typeof(id) && self.peek_next_token() == Some(&Token::LeftParen),
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether `id` is an undeclared `typeof` outside of GNU mode.
+    ///
+    /// `typeof` is an ordinary identifier in ISO C, but `typeof(x) y;` was almost certainly
+    /// meant as the GNU extension, so it's parsed as one after reporting an error.
+    pub(super) fn is_iso_typeof(&self, id: InternedStr) -> bool {
+        !self.gnu && id == InternedStr::get_or_intern("typeof") && self.scope.get(&id).is_none()
+    }
+
     /// cast_expr
     /// : unary_expr
     /// | '(' type_name ')' cast_expr
@@ -460,6 +531,8 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                 data: (ctype, _),
             } = self.type_name()?;
             self.expect(Token::RightParen)?;
+            // a cast to an atomic type is the same as a cast to its non-atomic version
+            let ctype = ctype.strip_atomic().clone();
             let expr = self.cast_expr()?.rval();
             if ctype == Type::Void {
                 // casting anything to void is allowed
@@ -477,8 +550,8 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                     format!("cannot cast to non-scalar type '{}'", ctype),
                     location,
                 );
-            } else if expr.ctype.is_floating() && ctype.is_pointer()
-                || expr.ctype.is_pointer() && ctype.is_floating()
+            } else if (expr.ctype.is_floating() || expr.ctype.is_complex()) && ctype.is_pointer()
+                || expr.ctype.is_pointer() && (ctype.is_floating() || ctype.is_complex())
             {
                 self.semantic_err(
                     format!("cannot cast pointer to float or vice versa. hint: if you really want to do this, use '({})(int)' instead",
@@ -508,9 +581,51 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
     /// | unary_operator cast_expr
     /// | SIZEOF unary_expr
     /// | SIZEOF '(' type_name ')'
+    /// | ALIGNOF '(' type_name ')'
+    /// | EXTENSION cast_expr
     /// ;
     fn unary_expr(&mut self) -> SyntaxResult {
         match self.peek_token() {
+            // `__extension__` only silences warnings about GNU extensions
+            Some(Token::Keyword(Keyword::Extension)) => {
+                self.next_token();
+                self.cast_expr()
+            }
+            Some(Token::Keyword(Keyword::Alignof)) => {
+                let Locatable { location, .. } = self.next_token().unwrap();
+                self.expect(Token::LeftParen)?;
+                let ctype = if self.next_is_type_name() {
+                    self.type_name()?.data.0
+                } else {
+                    let expr = self.expr()?;
+                    // `__alignof__ x` is a GNU extension
+                    if !self.gnu {
+                        self.semantic_err(
+                            format!("'{}' can only be applied to a type", Keyword::Alignof),
+                            expr.location,
+                        );
+                    }
+                    expr.ctype
+                };
+                self.expect(Token::RightParen)?;
+                // `alignof` returns 0 for incomplete structs instead of an error
+                let align = if ctype.is_struct() && !ctype.is_complete() {
+                    Err("cannot take `_Alignof` an incomplete type")
+                } else {
+                    ctype.alignof()
+                };
+                let align = align.unwrap_or_else(|err| {
+                    self.semantic_err(err, location);
+                    1
+                });
+                Ok(Expr {
+                    constexpr: true,
+                    expr: ExprType::Literal(Literal::UnsignedInt(align)),
+                    lval: false,
+                    location,
+                    ctype: Type::Int(false),
+                })
+            }
             Some(Token::PlusPlus) => {
                 let Locatable { location, .. } = self.next_token().unwrap();
                 let expr = self.unary_expr()?;
@@ -534,6 +649,7 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                         }
                         _ => {
                             let expr = self.expr()?;
+                            self.check_sizeof_expr(&expr);
                             (expr.location, expr.ctype)
                         }
                     };
@@ -541,8 +657,16 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                     ret
                 } else {
                     let result = self.unary_expr()?;
+                    self.check_sizeof_expr(&result);
                     (result.location, result.ctype)
                 };
+                // e.g. `struct s; sizeof(struct s)`
+                if ctype.is_struct() && !ctype.is_complete() {
+                    self.semantic_err(
+                        format!("cannot take `sizeof` incomplete type '{}'", ctype),
+                        location,
+                    );
+                }
                 Ok(Expr {
                     // the C11 standard states (6.5.3.4)
                     // "If the type of the operand is a variable length array type, the operand is evaluated; otherwise, the operand is not evaluated and the result is an integer constant."
@@ -552,18 +676,58 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                     //   char b[n+3];
                     //   return sizeof b;
                     // }
-                    // We do not currently handle this case.
-                    constexpr: true,
+                    // The length of a VLA is saved when it is declared,
+                    // so there is no need to evaluate the operand itself.
+                    constexpr: !ctype.is_vla(),
                     expr: ExprType::Sizeof(ctype),
                     lval: false,
                     location,
                     ctype: Type::Int(false),
                 })
             }
+            Some(Token::Keyword(Keyword::Real)) | Some(Token::Keyword(Keyword::Imag)) => {
+                let Locatable { location, data } = self.next_token().unwrap();
+                let imag = data == Token::Keyword(Keyword::Imag);
+                let expr = self.cast_expr()?;
+                Ok(self.complex_part(expr, imag, location))
+            }
+            // GNU extension: `&&label` is the address of a label, for use with `goto *`
+            Some(Token::LogicalAnd) => {
+                let Locatable { location, .. } = self.next_token().unwrap();
+                let label = match self.expect(Token::Id(Default::default()))?.data {
+                    Token::Id(id) => id,
+                    _ => unreachable!("expect should only return an Id if called with Token::Id"),
+                };
+                if !self.gnu {
+                    self.semantic_err("taking the address of a label is a GNU extension", location);
+                }
+                match self.current_function.as_mut() {
+                    Some(func) => func.label_addresses.push(location.with(label)),
+                    None => self.semantic_err(
+                        format!(
+                            "cannot take the address of label '{}' outside a function",
+                            label
+                        ),
+                        location,
+                    ),
+                }
+                Ok(Expr {
+                    constexpr: false,
+                    lval: false,
+                    location,
+                    ctype: Type::Pointer(Box::new(Type::Void), Qualifiers::default()),
+                    expr: ExprType::LabelAddress(label),
+                })
+            }
             Some(op) if op.is_unary_operator() => {
                 use crate::data::StorageClass;
                 let Locatable { location, data: op } = self.next_token().unwrap();
                 let expr = self.cast_expr()?;
+                let expr = if op == Token::Ampersand {
+                    expr
+                } else {
+                    expr.load_atomic()
+                };
                 match op {
                     // TODO: semantic checking for expr
                     Token::Ampersand => match expr.expr {
@@ -577,11 +741,20 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                             ));
                             Ok(expr)
                         }
+                        _ if expr.is_bitfield() => {
+                            self.error_handler.push_back(
+                                location.error(SemanticError::InvalidAddressOf("bitfield")),
+                            );
+                            Ok(expr)
+                        }
                         _ if expr.lval => Ok(Expr {
                             constexpr: false,
                             lval: false,
                             location,
-                            ctype: Type::Pointer(Box::new(expr.ctype.clone())),
+                            ctype: Type::Pointer(
+                                Box::new(expr.ctype.clone()),
+                                expr.lval_qualifiers(),
+                            ),
                             expr: expr.expr,
                         }),
                         _ => {
@@ -592,7 +765,7 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                         }
                     },
                     Token::Star => match &expr.ctype {
-                        Type::Array(t, _) | Type::Pointer(t) => {
+                        Type::Array(t, _) | Type::Pointer(t, _) => {
                             let ctype = (**t).clone();
                             Ok(expr.indirection(true, ctype, location))
                         }
@@ -691,8 +864,8 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                     let right = self.expr()?.rval();
                     self.expect(Token::RightBracket)?;
                     let (target_type, array, index) = match (&left.ctype, &right.ctype) {
-                        (Type::Pointer(target), _) => ((**target).clone(), left, right),
-                        (_, Type::Pointer(target)) => ((**target).clone(), right, left),
+                        (Type::Pointer(target, _), _) => ((**target).clone(), left, right),
+                        (_, Type::Pointer(target, _)) => ((**target).clone(), right, left),
                         (l, r) => {
                             self.semantic_err(
                                 format!("neither {} nor {} are pointers types", l, r),
@@ -714,7 +887,7 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                     self.expect(Token::RightParen)?;
                     // if fp is a function pointer, fp() desugars to (*fp)()
                     match expr.ctype {
-                        Type::Pointer(ref pointee) if pointee.is_function() => {
+                        Type::Pointer(ref pointee, _) if pointee.is_function() => {
                             expr = Expr {
                                 lval: false,
                                 location: expr.location,
@@ -740,34 +913,51 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                     if expected == 1 && functype.params[0].ctype == Type::Void {
                         expected = 0;
                     }
-                    if !functype.params.is_empty()
-                        && (args.len() < expected || args.len() > expected && !functype.varargs)
-                    {
-                        self.semantic_err(
-                            format!(
-                                "too {} arguments to function call: expected {}, have {}",
-                                if args.len() > expected { "many" } else { "few" },
-                                expected,
-                                args.len(),
-                            ),
-                            location,
+                    if args.len() < expected || args.len() > expected && !functype.varargs {
+                        let message = format!(
+                            "too {} arguments to function call: expected {}, have {}",
+                            if args.len() > expected { "many" } else { "few" },
+                            expected,
+                            args.len(),
                         );
+                        if functype.has_prototype() {
+                            self.semantic_err(message, location);
+                        } else if functype.old_style {
+                            // undefined behavior, but not a constraint violation (C11 6.5.2.2p6)
+                            self.error_handler
+                                .warn(Warning::OldStyleCall(message), location);
+                        }
                     }
                     let mut promoted_args = vec![];
                     for (i, arg) in args.into_iter().enumerate() {
-                        let maybe_err = match functype.params.get(i) {
-                            Some(expected) => arg.rval().cast(&expected.ctype),
-                            None => arg.default_promote(),
+                        let promoted = match functype.params.get(i) {
+                            Some(expected) if functype.has_prototype() => {
+                                let arg = arg.rval();
+                                self.check_pointer_qualifiers(&arg, &expected.ctype);
+                                self.assignment_cast(arg, &expected.ctype)
+                            }
+                            _ => arg.default_promote().recover(&mut self.error_handler),
                         };
-                        let promoted = maybe_err.recover(&mut self.error_handler);
                         promoted_args.push(promoted);
                     }
-                    Expr {
-                        location,
-                        constexpr: false,
-                        lval: false, // no move semantics here!
-                        ctype: *functype.return_type.clone(),
-                        expr: ExprType::FuncCall(Box::new(expr), promoted_args),
+                    let builtin = match &expr.expr {
+                        ExprType::Id(func) => complex_builtin(func.id),
+                        _ => None,
+                    };
+                    match builtin {
+                        Some(imag)
+                            if promoted_args.len() == 1 && promoted_args[0].ctype.is_complex() =>
+                        {
+                            let arg = promoted_args.remove(0);
+                            self.complex_part(arg, imag, location).rval()
+                        }
+                        _ => Expr {
+                            location,
+                            constexpr: false,
+                            lval: false, // no move semantics here!
+                            ctype: *functype.return_type.clone(),
+                            expr: ExprType::FuncCall(Box::new(expr), promoted_args),
+                        },
                     }
                 }
                 Token::Dot => {
@@ -786,8 +976,9 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                         Token::Id(id) => id,
                         _ => unreachable!("bug in Parser::expect"),
                     };
+                    expr = expr.load_atomic();
                     let struct_type = match &expr.ctype {
-                        Type::Pointer(ctype) => match **ctype {
+                        Type::Pointer(ctype, _) => match **ctype {
                             Type::Union(_) | Type::Struct(_) => (**ctype).clone(),
                             _ => {
                                 self.semantic_err(
@@ -829,7 +1020,7 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
     /// : /* empty */
     /// | assignment_expr (',' assignment_expr)*
     /// ;
-    fn argument_expr_list_opt(&mut self) -> SyntaxResult<Vec<Expr>> {
+    pub(super) fn argument_expr_list_opt(&mut self) -> SyntaxResult<Vec<Expr>> {
         if self.peek_token() == Some(&Token::RightParen) {
             return Ok(vec![]);
         }
@@ -840,6 +1031,49 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
         Ok(args)
     }
 
+    /// statement_expr: '(' compound_statement ')'
+    ///
+    /// A GNU extension. The value is the value of the last statement in the block
+    /// if that is an expression statement; otherwise the expression has type `void`.
+    fn statement_expr(&mut self, location: Location) -> SyntaxResult {
+        if self.current_function.is_none() {
+            return Err(location.error(SyntaxError::from(
+                "statement expressions are only allowed inside functions",
+            )));
+        }
+        let mut stmts = match self.statement()? {
+            Some(Stmt {
+                data: StmtType::Compound(stmts),
+                ..
+            }) => stmts,
+            Some(_) => unreachable!("a block should always be parsed as a compound statement"),
+            None => Vec::new(),
+        };
+        self.expect(Token::RightParen)?;
+        let result = match stmts.pop() {
+            Some(Stmt {
+                data: StmtType::Expr(expr),
+                ..
+            }) => Some(Box::new(expr.rval())),
+            Some(other) => {
+                stmts.push(other);
+                None
+            }
+            None => None,
+        };
+        let ctype = match &result {
+            Some(expr) => expr.ctype.clone(),
+            None => Type::Void,
+        };
+        Ok(Expr {
+            constexpr: false,
+            lval: false,
+            ctype,
+            location,
+            expr: ExprType::StmtExpr(stmts, result),
+        })
+    }
+
     /// primary_expr
     /// : identifier
     /// | INT_CONSTANT
@@ -850,18 +1084,29 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
     fn primary_expr(&mut self) -> SyntaxResult {
         if let Some(Locatable { location, data }) = self.next_token() {
             match data {
+                Token::Id(name) if self.is_atomic_builtin(name) => {
+                    self.atomic_builtin(name, location)
+                }
                 Token::Id(name) => match self.scope.get(&name) {
                     None => {
-                        self.semantic_err(
+                        let candidates: Vec<_> = self
+                            .scope
+                            .iter()
+                            .filter(|(_, symbol)| symbol.storage_class != Typedef)
+                            .map(|(&id, _)| id)
+                            .collect();
+                        self.err_did_you_mean(
                             format!("use of undeclared identifier '{}'", name),
+                            name,
                             location,
+                            candidates,
                         );
                         let mut pretend_zero = Expr::zero(location);
                         pretend_zero.ctype = Type::Error;
                         Ok(pretend_zero)
                     }
                     Some(symbol) => {
-                        if let Type::Enum(ident, members) = &symbol.ctype {
+                        if let Type::Enum(_, members) = &symbol.ctype {
                             let enumerator = members.iter().find_map(|(member, value)| {
                                 if name == *member {
                                     Some(*value)
@@ -869,33 +1114,45 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                                     None
                                 }
                             });
+                            // enumeration constants have type `int`, not the type of the enum (C11 6.4.4.3p2).
+                            // as an extension, constants larger than INT_MAX are `unsigned int`.
                             if let Some(e) = enumerator {
+                                let (literal, ctype) = if i32::try_from(e).is_ok() {
+                                    (Literal::Int(e), Type::Int(true))
+                                } else {
+                                    (Literal::UnsignedInt(e as u64), Type::Int(false))
+                                };
                                 return Ok(Expr {
                                     constexpr: true,
-                                    ctype: Type::Enum(*ident, members.clone()),
+                                    ctype,
                                     location,
                                     lval: false,
-                                    expr: ExprType::Literal(Literal::Int(e)),
+                                    expr: ExprType::Literal(literal),
                                 });
                             }
                         }
-                        Ok(Expr::id(symbol, location))
+                        let expr = Expr::id(symbol, location);
+                        self.use_identifier(name, location);
+                        Ok(expr)
                     }
                 },
                 Token::Literal(literal) => Ok(Expr::from((literal, location))),
+                Token::LeftParen if self.peek_token() == Some(&Token::LeftBrace) => {
+                    if !self.gnu {
+                        self.semantic_err("statement expressions are a GNU extension", location);
+                    }
+                    self.statement_expr(location)
+                }
                 Token::LeftParen => {
                     let expr = self.expr()?;
                     self.expect(Token::RightParen)?;
                     Ok(expr)
                 }
                 other => {
-                    let err = Err(Locatable {
-                        location,
-                        data: SyntaxError::from(format!(
-                            "expected '(' or literal in expression, got '{}'",
-                            other
-                        )),
-                    });
+                    let err = Err(location.error(SyntaxError::from(format!(
+                        "expected '(' or literal in expression, got '{}'",
+                        other
+                    ))));
                     self.unput(Some(Locatable {
                         location,
                         data: other,
@@ -904,34 +1161,87 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                 }
             }
         } else {
-            Err(Locatable {
-                data: SyntaxError::from("expected '(' or literal in expression, got <end-of-file>"),
-                location: self.next_location(),
-            })
+            Err(self.next_location().error(SyntaxError::from(
+                "expected '(' or literal in expression, got <end-of-file>",
+            )))
         }
     }
 
     // parse a struct member
     // used for both s.a and s->a
+    /// `sizeof` cannot be applied to bitfields (C11 6.5.3.4)
+    fn check_sizeof_expr(&mut self, expr: &Expr) {
+        if expr.is_bitfield() {
+            self.semantic_err("cannot take `sizeof` a bitfield", expr.location);
+        }
+    }
+    /// `__real__ expr` or `__imag__ expr`
+    ///
+    /// For a complex number, this refers to its real or imaginary part,
+    /// and is an lvalue if `expr` is.
+    /// For a real number, `__real__` is the number itself and `__imag__` is 0.
+    fn complex_part(&mut self, expr: Expr, imag: bool, location: Location) -> Expr {
+        match &expr.ctype {
+            Type::Complex(inner) => Expr {
+                ctype: (**inner).clone(),
+                constexpr: false,
+                lval: expr.lval,
+                location,
+                expr: ExprType::ComplexPart(Box::new(expr), imag),
+            },
+            ty if ty.is_arithmetic() => {
+                let expr = expr.rval();
+                if !imag {
+                    return Expr { location, ..expr };
+                }
+                let zero = Expr::zero(location).cast(&expr.ctype).unwrap();
+                // the operand is still evaluated for its side effects
+                Expr {
+                    ctype: zero.ctype.clone(),
+                    constexpr: expr.constexpr,
+                    lval: false,
+                    location,
+                    expr: ExprType::Comma(Box::new(expr), Box::new(zero)),
+                }
+            }
+            _ => {
+                self.semantic_err(
+                    format!(
+                        "'{}' requires an arithmetic operand, got '{}'",
+                        if imag { Keyword::Imag } else { Keyword::Real },
+                        expr.ctype
+                    ),
+                    location,
+                );
+                expr
+            }
+        }
+    }
     fn struct_member(&mut self, expr: Expr, id: InternedStr, location: Location) -> SyntaxResult {
         match &expr.ctype {
             Type::Struct(stype) | Type::Union(stype) => {
                 let members = stype.members();
                 if members.is_empty() {
-                    self.semantic_err(format!("{} has not yet been defined", expr.ctype), location);
+                    let msg = format!("{} has not yet been defined", expr.ctype);
+                    let msg = self.with_tag_suggestion(msg, &expr.ctype);
+                    self.semantic_err(msg, location);
                     Ok(expr)
-                } else if let Some(member) = members.iter().find(|member| member.id == id) {
-                    Ok(Expr {
-                        ctype: member.ctype.clone(),
+                } else if let Some(path) = stype.member_path(id) {
+                    // members of anonymous structs and unions are accessed through the anonymous member
+                    Ok(path.into_iter().fold(expr, |expr, member| Expr {
+                        ctype: member.ctype.member_type(),
                         constexpr: expr.constexpr,
                         lval: true,
                         location,
-                        expr: ExprType::Member(Box::new(expr), id),
-                    })
+                        expr: ExprType::Member(Box::new(expr), member.id),
+                    }))
                 } else {
-                    self.semantic_err(
+                    let candidates = stype.member_names();
+                    self.err_did_you_mean(
                         format!("no member named '{}' in '{}'", id, expr.ctype),
+                        id,
                         location,
+                        candidates,
                     );
                     Ok(expr)
                 }
@@ -1064,8 +1374,8 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
     {
         let mut expr = next_grammar_func(self)?;
         while let Some(locatable) = self.match_any(tokens) {
-            let next = next_grammar_func(self)?;
-            match expr_func(Box::new(expr), Box::new(next), locatable) {
+            let next = next_grammar_func(self)?.load_atomic();
+            match expr_func(Box::new(expr.load_atomic()), Box::new(next), locatable) {
                 Ok(combined) => expr = combined,
                 Err((err, original)) => {
                     expr = original;
@@ -1093,7 +1403,7 @@ impl Token {
 
 /* stateless helper functions */
 impl Expr {
-    fn indirection(self, lval: bool, ctype: Type, location: Location) -> Self {
+    pub(super) fn indirection(self, lval: bool, ctype: Type, location: Location) -> Self {
         Expr {
             constexpr: self.constexpr,
             location,
@@ -1114,6 +1424,38 @@ impl Expr {
             false
         }
     }
+    /// The type qualifiers of the object designated by this lvalue.
+    ///
+    /// For `*p` and `p[i]` these are the qualifiers of the pointed-to type,
+    /// for `s.x` they are the qualifiers of both `s` and the member `x`.
+    pub(super) fn lval_qualifiers(&self) -> Qualifiers {
+        match &self.expr {
+            ExprType::Id(sym) => sym.qualifiers.type_qualifiers(),
+            ExprType::Member(compound, id) => {
+                let mut qualifiers = compound.lval_qualifiers();
+                if let Type::Struct(stype) | Type::Union(stype) = &compound.ctype {
+                    if let Some(member) = stype.members().iter().find(|m| m.id == *id) {
+                        qualifiers.c_const |= member.qualifiers.c_const;
+                        qualifiers.volatile |= member.qualifiers.volatile;
+                    }
+                }
+                qualifiers
+            }
+            // `*p` and `p->x`
+            ExprType::Noop(pointer) => match pointer.ctype {
+                Type::Pointer(_, qualifiers) => qualifiers,
+                _ => Qualifiers::NONE,
+            },
+            // `p[i]`
+            ExprType::Add(left, right) if self.lval => match (&left.ctype, &right.ctype) {
+                (Type::Pointer(_, qualifiers), _) | (_, Type::Pointer(_, qualifiers)) => {
+                    *qualifiers
+                }
+                _ => Qualifiers::NONE,
+            },
+            _ => Qualifiers::NONE,
+        }
+    }
     /// See section 6.3.2.1 of the C Standard. In particular:
     /// "A modifiable lvalue is an lvalue that does not have array type,
     /// does not  have an incomplete type, does not have a const-qualified type,
@@ -1133,6 +1475,8 @@ impl Expr {
             if sym.qualifiers.c_const {
                 return err(format!("variable '{}' with `const` qualifier", sym.id));
             }
+        } else if self.lval_qualifiers().c_const {
+            return err(format!("read-only location of type 'const {}'", self.ctype));
         }
         match &self.ctype {
             // array type
@@ -1160,23 +1504,32 @@ impl Expr {
     pub fn rval(self) -> Expr {
         match self.ctype {
             // a + 1 is the same as &a + 1
-            Type::Array(to, _) => Expr {
+            Type::Array(ref to, _) => Expr {
                 lval: false,
-                ctype: Type::Pointer(to),
+                ctype: Type::Pointer(to.clone(), self.lval_qualifiers()),
                 constexpr: false,
                 ..self
             },
             Type::Function(_) => Expr {
                 lval: false,
-                ctype: Type::Pointer(Box::new(self.ctype)),
+                ctype: Type::Pointer(Box::new(self.ctype), Qualifiers::NONE),
                 constexpr: false, // TODO: is this right?
                 ..self
             },
             // HACK: structs can't be dereferenced since they're not scalar, so we just fake it
-            Type::Struct(_) | Type::Union(_) if self.lval => Expr {
+            // complex numbers are always kept in memory, so the same applies
+            Type::Struct(_) | Type::Union(_) | Type::Complex(_) if self.lval => Expr {
                 lval: false,
                 ..self
             },
+            // reading an atomic object is an atomic load
+            Type::Atomic(_) if self.lval => Expr {
+                ctype: self.ctype.strip_atomic().clone(),
+                lval: false,
+                constexpr: false,
+                location: self.location,
+                expr: ExprType::Deref(Box::new(self)),
+            },
             _ if self.lval => Expr {
                 ctype: self.ctype.clone(),
                 lval: false,
@@ -1187,6 +1540,17 @@ impl Expr {
             _ => self,
         }
     }
+    /// Convert an atomic lvalue to its value, and leave anything else alone.
+    ///
+    /// Most operators check the types of their operands before calling `rval()`,
+    /// so this has to happen first or `_Atomic int` would not be considered arithmetic.
+    fn load_atomic(self) -> Expr {
+        if self.ctype.is_atomic() {
+            self.rval()
+        } else {
+            self
+        }
+    }
     fn default_promote(self) -> RecoverableResult<Expr, Locatable<SemanticError>> {
         let expr = self.rval();
         let ctype = expr.ctype.clone().default_promote();
@@ -1195,7 +1559,7 @@ impl Expr {
     // Perform an integer conversion, including all relevant casts.
     //
     // See `Type::integer_promote` for conversion rules.
-    fn integer_promote(self) -> RecoverableResult<Expr, Locatable<SemanticError>> {
+    pub(super) fn integer_promote(self) -> RecoverableResult<Expr, Locatable<SemanticError>> {
         let expr = self.rval();
         let ctype = expr.ctype.clone().integer_promote();
         expr.cast(&ctype)
@@ -1249,17 +1613,20 @@ impl Expr {
         }
     }
     pub fn logical_not(self, location: Location) -> Expr {
+        let expr = self.rval();
         Expr {
             location,
             ctype: Type::Bool,
-            constexpr: self.constexpr,
+            constexpr: expr.constexpr,
             lval: false,
-            expr: ExprType::LogicalNot(Box::new(self)),
+            expr: ExprType::LogicalNot(Box::new(expr)),
         }
     }
     // Simple assignment rules, section 6.5.16.1 of the C standard
     // the funky return type is so we don't consume the original expression in case of an error
     pub fn cast(mut self, ctype: &Type) -> RecoverableResult<Expr, Locatable<SemanticError>> {
+        // the value being assigned to an atomic object is not itself atomic
+        let ctype = ctype.strip_atomic();
         if self.ctype == *ctype {
             Ok(self)
         } else if self.ctype.is_arithmetic() && ctype.is_arithmetic()
@@ -1276,13 +1643,16 @@ impl Expr {
                 ctype: ctype.clone(),
             })
         } else if ctype.is_pointer()
-            && (self.is_null() || self.ctype.is_void_pointer() || self.ctype.is_char_pointer())
+            && (self.is_null()
+                || self.ctype.is_void_pointer()
+                || self.ctype.is_char_pointer()
+                || self.ctype.pointee_eq(ctype))
         {
+            // qualifiers are checked separately, see `Parser::check_pointer_qualifiers`
             self.ctype = ctype.clone();
             Ok(self)
         } else if self.ctype == Type::Error {
             Ok(self)
-        // TODO: allow implicit casts of const pointers
         } else {
             Err((
                 Locatable {
@@ -1303,6 +1673,25 @@ impl Expr {
             ))
         }
     }
+    /// If this is the value of a variable and `&` or `*` would give it type `ctype`,
+    /// the operator to insert in front of it.
+    ///
+    /// e.g. `int i; int *p = i;` should probably be `int *p = &i;`
+    fn pointer_fix(&self, ctype: &Type) -> Option<&'static str> {
+        // reading a variable is `Deref(Id)`, see `rval`
+        match &self.expr {
+            ExprType::Deref(inner) if inner.location == self.location => match inner.expr {
+                ExprType::Id(_) => {}
+                _ => return None,
+            },
+            _ => return None,
+        }
+        match (ctype, &self.ctype) {
+            (Type::Pointer(pointee, _), _) if **pointee == self.ctype => Some("&"),
+            (_, Type::Pointer(pointee, _)) if **pointee == *ctype => Some("*"),
+            _ => None,
+        }
+    }
     fn pointer_arithmetic(
         base: Expr,
         index: Expr,
@@ -1318,7 +1707,15 @@ impl Expr {
         }
         .rval();
         let size = match pointee.sizeof() {
-            Ok(s) => s,
+            Ok(s) => Expr::from((Literal::UnsignedInt(s), offset.location)),
+            // e.g. indexing `int a[n][m]`
+            Err(_) if pointee.is_vla() => Expr {
+                lval: false,
+                location: offset.location,
+                ctype: Type::Long(false),
+                constexpr: false,
+                expr: ExprType::Sizeof(pointee.clone()),
+            },
             Err(_) => {
                 return Err((
                     Locatable {
@@ -1333,13 +1730,12 @@ impl Expr {
                 ))
             }
         };
-        let size_literal = Expr::from((Literal::UnsignedInt(size), offset.location));
         let size_cast = Expr {
             lval: false,
             location: offset.location,
             ctype: offset.ctype.clone(),
-            constexpr: true,
-            expr: ExprType::Cast(Box::new(size_literal)),
+            constexpr: size.constexpr,
+            expr: ExprType::Cast(Box::new(size)),
         };
         let offset = Expr {
             lval: false,
@@ -1362,9 +1758,14 @@ impl Expr {
         expr: Expr,
         location: Location,
     ) -> RecoverableResult<Expr, Locatable<SemanticError>> {
+        // for atomic objects, this is a read-modify-write operation on the non-atomic type
+        let ctype = expr.ctype.strip_atomic().clone();
         if let Err(err) = expr.modifiable_lval() {
             return Err((expr.location.with(err), expr));
-        } else if !(expr.ctype.is_arithmetic() || expr.ctype.is_pointer()) {
+        } else if !(ctype.is_arithmetic() || ctype.is_pointer())
+            // C11 6.5.2.4p1: the operand must have real or pointer type
+            || ctype.is_complex()
+        {
             return Err((
                 Locatable {
                     location: expr.location,
@@ -1382,12 +1783,12 @@ impl Expr {
             let rval = Expr {
                 constexpr: true,
                 lval: false,
-                ctype: expr.ctype.clone(),
+                ctype: ctype.clone(),
                 location,
                 expr: ExprType::Cast(Box::new(Expr::from((Literal::Int(1), location)))),
             };
             Ok(Expr {
-                ctype: expr.ctype.clone(),
+                ctype,
                 constexpr: rval.constexpr,
                 lval: false, // `(i = j) = 4`; is invalid
                 expr: ExprType::Assign(
@@ -1405,7 +1806,7 @@ impl Expr {
             Ok(Expr {
                 constexpr: expr.constexpr,
                 lval: false,
-                ctype: expr.ctype.clone(),
+                ctype,
                 // true, false: pre-decrement
                 expr: ExprType::PostIncrement(Box::new(expr), increment),
                 location,
@@ -1439,13 +1840,25 @@ impl Expr {
             Token::Comparison(c) => token.location.with(c),
             _ => unreachable!("bad use of relational_expr"),
         };
+        if (left.ctype.is_complex() || right.ctype.is_complex())
+            && token.data != ComparisonToken::EqualEqual
+            && token.data != ComparisonToken::NotEqual
+        {
+            return Err((
+                token.location.with(SemanticError::from(format!(
+                    "complex numbers cannot be compared with '{}' (got '{}' {} '{}')",
+                    token.data, left.ctype, token.data, right.ctype
+                ))),
+                *left,
+            ));
+        }
         if left.ctype.is_arithmetic() && right.ctype.is_arithmetic() {
             let tmp = Expr::binary_promote(*left, *right).map_err(flatten)?;
             *left = tmp.0;
             right = Box::new(tmp.1);
         } else {
             let (left_expr, right_expr) = (left.rval(), right.rval());
-            if !((left_expr.ctype.is_pointer() && left_expr.ctype == right_expr.ctype)
+            if !(left_expr.ctype.pointee_eq(&right_expr.ctype)
                 // equality operations have different rules :(
                 || ((token.data == ComparisonToken::EqualEqual || token.data == ComparisonToken::NotEqual)
                     // shoot me now
@@ -1477,7 +1890,7 @@ impl Expr {
             expr: ExprType::Compare(left, right, token.data),
         })
     }
-    fn id(symbol: &Symbol, location: Location) -> Self {
+    pub(super) fn id(symbol: &Symbol, location: Location) -> Self {
         Self {
             // TODO: this clone will get expensive fast
             expr: ExprType::Id(symbol.clone()),
@@ -1543,14 +1956,15 @@ impl Type {
     /// Perform the 'usual arithmetic conversions' from 6.3.1.8 of the C standard.
     ///
     /// Algorithm:
-    /// If either object is a `double`, convert the other to a double.
+    /// If either object is a `long double`, convert the other to a long double.
+    /// Else if either is a `double`, convert the other to a double.
     /// Else if either is a `float`, convert the other to a float.
     /// Else if both are signed or both are unsigned, convert the object with lesser rank to
     /// the type of the object with greater rank.
     /// Else if the unsigned object has rank >= other, convert other -> unsigned version (!!).
     /// Else if signed type can represent all values of the unsigned type,
     /// convert unsigned -> signed.
-    /// Else, convert signed -> unsigned.
+    /// Else, convert both to the unsigned version of the signed type.
     ///
     /// The exclamation marks are because the following will evaluate to MAX_UINT,
     /// _not_ -1: `1ul + (short)-2`.
@@ -1559,7 +1973,21 @@ impl Type {
     /// Pointer arithmetic should not promote either argument, see 6.5.6 of the C standard.
     fn binary_promote(mut left: Type, mut right: Type) -> Type {
         use Type::*;
-        if left == Double || right == Double {
+        // if either is complex, the result is complex,
+        // with the same real type as if both operands were real
+        match (left, right) {
+            (Complex(l), Complex(r)) => return Complex(Box::new(Type::binary_promote(*l, *r))),
+            (Complex(c), other) | (other, Complex(c)) => {
+                return Complex(Box::new(Type::binary_promote(*c, other)))
+            }
+            (l, r) => {
+                left = l;
+                right = r;
+            }
+        }
+        if left == LongDouble || right == LongDouble {
+            return LongDouble;
+        } else if left == Double || right == Double {
             return Double; // toil and trouble
         } else if left == Float || right == Float {
             return Float;
@@ -1582,13 +2010,33 @@ impl Type {
         };
         if signed.can_represent(&unsigned) {
             signed
-        } else {
+        } else if unsigned.rank() >= signed.rank() {
             unsigned
+        } else {
+            // e.g. `unsigned long + long long` is `unsigned long long`
+            match signed {
+                LongLong(_) => LongLong(false),
+                Long(_) => Long(false),
+                _ => unsigned,
+            }
         }
     }
     fn pointer_promote(left: &mut Expr, right: &mut Expr) -> bool {
         if left.ctype == right.ctype {
             true
+        } else if left.ctype.pointee_eq(&right.ctype) {
+            // the result points to a type with all the qualifiers of both (C11 6.5.15p6)
+            if let (Type::Pointer(_, l), Type::Pointer(pointee, r)) = (&left.ctype, &right.ctype) {
+                let qualifiers = Qualifiers {
+                    c_const: l.c_const || r.c_const,
+                    volatile: l.volatile || r.volatile,
+                    restrict: l.restrict || r.restrict,
+                    ..Qualifiers::NONE
+                };
+                left.ctype = Type::Pointer(pointee.clone(), qualifiers);
+                right.ctype = left.ctype.clone();
+            }
+            true
         } else if left.ctype.is_void_pointer() || left.ctype.is_char_pointer() || left.is_null() {
             left.ctype = right.ctype.clone();
             true
@@ -1607,10 +2055,9 @@ impl Type {
     fn sign(&self) -> bool {
         use Type::*;
         match self {
-            Char(sign) | Short(sign) | Int(sign) | Long(sign) => *sign,
+            Char(sign) | Short(sign) | Int(sign) | Long(sign) | LongLong(sign) => *sign,
             Bool => false,
-            // TODO: allow enums with values of UINT_MAX
-            Enum(_, _) => true,
+            Enum(_, members) => Type::enum_type(members).sign(),
             x => panic!(
                 "Type::sign can only be called on integral types (got {})",
                 x
@@ -1624,6 +2071,7 @@ impl Type {
     /// Examples:
     /// ```
     /// use rcc::data::types::Type::*;
+    /// assert!(LongLong(true).rank() > Long(true).rank());
     /// assert!(Long(true).rank() > Int(true).rank());
     /// assert!(Int(false).rank() > Short(false).rank());
     /// assert!(Short(true).rank() > Char(true).rank());
@@ -1639,7 +2087,8 @@ impl Type {
             Short(_) => 2,
             Int(_) => 3,
             Long(_) => 4,
-            // don't make this 5 in case we add `long long` at some point
+            LongLong(_) => 5,
+            Enum(_, members) => Type::enum_type(members).rank(),
             _ => std::usize::MAX,
         }
     }
@@ -1648,9 +2097,21 @@ impl Type {
     }
 }
 
+/// `creal` and `cimag` are compiled inline, as GCC does,
+/// so that programs using them do not need to link against libm.
+///
+/// Returns whether the builtin gives the imaginary part.
+fn complex_builtin(name: InternedStr) -> Option<bool> {
+    match name.resolve_and_clone().as_str() {
+        "creal" | "crealf" | "creall" => Some(false),
+        "cimag" | "cimagf" | "cimagl" => Some(true),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::data::{prelude::*, types, Scope, StorageClass};
+    use crate::data::{prelude::*, types, Qualifiers, Scope, StorageClass};
     use crate::intern::InternedStr;
     use crate::parse::tests::*;
     fn parse_expr(input: &str) -> CompileResult<Expr> {
@@ -1735,6 +2196,9 @@ mod tests {
         assert_type("1*1.0", Type::Double);
         assert_type("1*2.0 / 1.3", Type::Double);
         assert_type("3%2", Type::Long(true));
+        assert_type("(long long)3 * 2", Type::LongLong(true));
+        assert_type("(long long)3 * (unsigned long)2", Type::LongLong(false));
+        assert_type("(long double)3 * 2.0", Type::LongDouble);
     }
     #[test]
     fn test_funcall() {
@@ -1753,6 +2217,8 @@ mod tests {
                 }],
                 return_type: Box::new(Type::Int(true)),
                 varargs: false,
+                old_style: false,
+                noreturn: false,
             }),
         };
         assert!(parse_expr_with_scope("f(1,2,3)", &[&f]).is_err());
@@ -1766,6 +2232,21 @@ mod tests {
         },);
     }
     #[test]
+    fn test_alignof() {
+        assert_type("_Alignof(char)", Type::Int(false));
+        assert!(match parse_expr("_Alignof(long long)") {
+            Ok(Expr {
+                expr: ExprType::Literal(Literal::UnsignedInt(8)),
+                constexpr: true,
+                ..
+            }) => true,
+            _ => false,
+        });
+        // only GNU C allows expressions
+        assert!(parse_expr("_Alignof(1)").is_err());
+        assert!(parse_expr("_Alignof(struct s)").is_err());
+    }
+    #[test]
     fn test_type_errors() {
         assert!(parse_expr("1 % 2.0").is_err());
     }
@@ -1777,6 +2258,9 @@ mod tests {
         assert_type("(float)4.2", Type::Float);
         assert_type("(double)4.2", Type::Double);
         assert!(parse_expr("(int*)4.2").is_err());
-        assert_type("(int*)(int)4.2", Type::Pointer(Box::new(Type::Int(true))));
+        assert_type(
+            "(int*)(int)4.2",
+            Type::Pointer(Box::new(Type::Int(true)), Qualifiers::NONE),
+        );
     }
 }
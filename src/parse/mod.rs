@@ -1,18 +1,22 @@
+mod atomic;
+mod attribute;
 mod decl;
 mod expr;
 mod stmt;
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
 use std::iter::Iterator;
 use std::mem;
 
-use crate::data::{prelude::*, Scope};
+use crate::data::{lex::Keyword, prelude::*, Scope};
 
 type Lexeme = CompileResult<Locatable<Token>>;
-pub(crate) type TagScope = Scope<InternedStr, TagEntry>;
+/// Each tag is stored with the location of its name in its definition,
+/// or in its forward declaration if it hasn't been defined.
+pub(crate) type TagScope = Scope<InternedStr, Locatable<TagEntry>>;
 
-type SyntaxResult<T = Expr> = Result<T, Locatable<SyntaxError>>;
+type SyntaxResult<T = Expr> = Result<T, CompileError>;
 
 #[derive(Clone, Debug)]
 pub(crate) enum TagEntry {
@@ -34,6 +38,9 @@ pub struct Parser<I: Iterator<Item = Lexeme>> {
     scope: Scope<InternedStr, Symbol>,
     /// the compound types that have been declared (struct/union/enum)
     tag_scope: TagScope,
+    /// how each ordinary identifier has been used, for warnings like `deprecated`.
+    /// This has the same scopes as `scope`.
+    usage: Scope<InternedStr, attribute::Usage>,
     /// we iterate lazily over the tokens, so if we have a program that's mostly valid but
     /// breaks at the end, we don't only show lex errors
     tokens: I,
@@ -43,6 +50,11 @@ pub struct Parser<I: Iterator<Item = Lexeme>> {
     pending: VecDeque<Locatable<Declaration>>,
     /// in case we get to the end of the file and want to show an error
     last_location: Location,
+    /// the last token that was consumed, as opposed to peeked at,
+    /// so a missing `;` can be suggested right after it
+    last_consumed: Location,
+    /// `last_consumed` before that, in case the last token is put back with `unput`
+    consumed_before: Location,
     /// the last token we saw from the Lexer. None if we haven't looked ahead.
     /// Should only be used in this module.
     current: Option<Locatable<Token>>,
@@ -54,8 +66,13 @@ pub struct Parser<I: Iterator<Item = Lexeme>> {
     /// if `None`, we are in global scope.
     /// used for checking return types
     current_function: Option<FunctionData>,
+    /// declarations with linkage at block scope, e.g. `int main() { extern int i; }`.
+    /// These refer to the same object as any file-scope declaration, even one that comes later.
+    block_externs: HashMap<InternedStr, (Symbol, Location)>,
     /// whether to debug each declaration
     debug: bool,
+    /// whether to allow GNU extensions like statement expressions
+    gnu: bool,
     /// Internal API which makes it easier to return errors lazily
     error_handler: ErrorHandler,
 }
@@ -70,6 +87,34 @@ struct FunctionData {
     location: Location,
     /// the return type of the function
     return_type: Type,
+    /// whether the function was declared `_Noreturn`
+    noreturn: bool,
+    /// whether this is an inline definition, which can't define modifiable static variables
+    inline_definition: bool,
+    /// the number of variable length array sizes stored in hidden variables so far
+    vla_lengths: usize,
+    /// the variably modified objects currently in scope, e.g. `int a[n]`
+    vlas: Vec<Locatable<InternedStr>>,
+    /// the labels seen so far, along with the variably modified objects in scope at each
+    labels: Vec<(Locatable<InternedStr>, Vec<Location>)>,
+    /// the `goto`s seen so far, along with the variably modified objects in scope at each
+    gotos: Vec<(Locatable<InternedStr>, Vec<Location>)>,
+    /// the labels whose address was taken with `&&label`
+    label_addresses: Vec<Locatable<InternedStr>>,
+    /// the `switch` statements currently being parsed, innermost last
+    switches: Vec<SwitchCases>,
+}
+
+#[derive(Debug)]
+/// the labels seen so far in the body of a `switch` statement
+struct SwitchCases {
+    /// the type of the controlling expression, before the integer promotions
+    ctype: Type,
+    /// the values of each `case` label as an inclusive range,
+    /// converted to the promoted type of the controlling expression
+    cases: Vec<(i128, i128)>,
+    /// the location of the `default` label, if there is one
+    default: Option<Location>,
 }
 
 impl<I> Parser<I>
@@ -82,17 +127,24 @@ where
     /// I would rather ensure `I` has at least one token,
     /// but I don't know a good way to do that without requiring users to
     /// use `std::iter::once`.
-    pub fn new(first: Locatable<Token>, tokens: I, debug: bool) -> Self {
+    ///
+    /// The `tokens` should come from a lexer in GNU mode if `gnu` is set.
+    pub fn new(first: Locatable<Token>, tokens: I, debug: bool, gnu: bool) -> Self {
         Parser {
             scope: Default::default(),
             tag_scope: Default::default(),
+            usage: Default::default(),
             tokens,
             pending: Default::default(),
             last_location: first.location,
+            last_consumed: first.location,
+            consumed_before: first.location,
             current: Some(first),
             next: None,
             current_function: None,
+            block_externs: HashMap::new(),
             debug,
+            gnu,
             error_handler: ErrorHandler::new(),
         }
     }
@@ -126,7 +178,7 @@ impl<I: Iterator<Item = Lexeme>> Iterator for Parser<I> {
                 // Remove extra semicolons
                 while let Some(locatable) = self.match_next(&Token::Semicolon) {
                     self.error_handler
-                        .warn("extraneous semicolon at top level", locatable.location);
+                        .warn(Warning::ExtraSemicolon("at top level"), locatable.location);
                 }
 
                 // Check for end of file
@@ -166,32 +218,48 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
     fn enter_scope(&mut self) {
         self.scope.enter_scope();
         self.tag_scope.enter_scope();
+        self.usage.enter_scope();
     }
     fn leave_scope(&mut self, location: Location) {
         use crate::data::StorageClass;
+        let mut incomplete = Vec::new();
         for object in self.scope.get_all_immediate().values() {
             match &object.ctype {
                 Type::Struct(StructType::Named(name, members))
                 | Type::Union(StructType::Named(name, members)) => {
+                    // objects at block scope are checked as soon as they are declared
                     if members.get().is_empty()
+                        && self.current_function.is_none()
                         && object.storage_class != StorageClass::Extern
                         && object.storage_class != StorageClass::Typedef
                     {
-                        self.error_handler
-                            .push_back(CompileError::semantic(Locatable {
-                                data: format!(
-                                    "forward declaration of {} is never completed (used in {})",
-                                    name, object.id
-                                ),
-                                location,
-                            }));
+                        let msg = format!(
+                            "forward declaration of {} is never completed (used in {})",
+                            name, object.id
+                        );
+                        incomplete.push((msg, object.ctype.clone()));
                     }
                 }
                 _ => {}
             }
+            if object.ctype.is_variably_modified() {
+                if let Some(func) = &mut self.current_function {
+                    if let Some(i) = func.vlas.iter().rposition(|vla| vla.data == object.id) {
+                        func.vlas.remove(i);
+                    }
+                }
+            }
+        }
+        for (msg, ctype) in incomplete {
+            let msg = self.with_tag_suggestion(msg, &ctype);
+            self.semantic_err(msg, location);
+        }
+        if self.scope.is_global() {
+            self.warn_unused_functions();
         }
         self.scope.leave_scope();
         self.tag_scope.leave_scope();
+        self.usage.leave_scope();
     }
     // don't use this, use next_token instead
     fn __impl_next_token(&mut self) -> Option<Locatable<Token>> {
@@ -210,16 +278,20 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
         }
     }
     fn next_token(&mut self) -> Option<Locatable<Token>> {
-        if self.current.is_some() {
+        let next = if self.current.is_some() {
             let tmp = mem::replace(&mut self.next, None);
             mem::replace(&mut self.current, tmp)
         } else {
             self.__impl_next_token()
+        };
+        if let Some(token) = &next {
+            self.consumed_before = mem::replace(&mut self.last_consumed, token.location);
         }
+        next
     }
     fn peek_token(&mut self) -> Option<&Token> {
         if self.current.is_none() {
-            self.current = self.next.take().or_else(|| self.next_token());
+            self.current = self.next.take().or_else(|| self.__impl_next_token());
         }
         self.current.as_ref().map(|x| &x.data)
     }
@@ -261,6 +333,50 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                 data: msg.into(),
             }));
     }
+    /// Report `msg` about an unknown `name` at `location`,
+    /// suggesting one of `candidates` if it looks like what was meant.
+    ///
+    /// The suggestion is only a guess, so it isn't given as a fix.
+    fn err_did_you_mean<C: IntoIterator<Item = InternedStr>>(
+        &mut self,
+        msg: String,
+        name: InternedStr,
+        location: Location,
+        candidates: C,
+    ) {
+        let msg = match did_you_mean(name, candidates) {
+            Some(suggestion) => format!("{}; did you mean '{}'?", msg, suggestion),
+            None => msg,
+        };
+        self.semantic_err(msg, location);
+    }
+    /// If `ctype` is an incomplete struct or union and its tag looks like a typo
+    /// for the tag of a complete one, suggest that in `msg`.
+    fn with_tag_suggestion(&self, msg: String, ctype: &Type) -> String {
+        let (kind, tag) = match ctype {
+            Type::Struct(StructType::Named(tag, _)) => (Keyword::Struct, *tag),
+            Type::Union(StructType::Named(tag, _)) => (Keyword::Union, *tag),
+            Type::Enum(Some(tag), _) => (Keyword::Enum, *tag),
+            _ => return msg,
+        };
+        let candidates =
+            self.tag_scope
+                .iter()
+                .filter_map(|(&name, entry)| match (kind, &entry.data) {
+                    (Keyword::Struct, TagEntry::Struct(struct_ref))
+                    | (Keyword::Union, TagEntry::Union(struct_ref))
+                        if !struct_ref.get().is_empty() =>
+                    {
+                        Some(name)
+                    }
+                    (Keyword::Enum, TagEntry::Enum(_)) => Some(name),
+                    _ => None,
+                });
+        match did_you_mean(tag, candidates) {
+            Some(suggestion) => format!("{}; did you mean '{} {}'?", msg, kind, suggestion),
+            None => msg,
+        }
+    }
     /*
      * If we're in an invalid state, try to recover.
      * Consume tokens until the end of a statement - either ';' or '}'
@@ -280,30 +396,38 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
         let token = match self.peek_token() {
             Some(t) => t,
             None => {
-                let err = Err(Locatable {
-                    data: SyntaxError::from(format!("expected '{}', got '<end-of-file>'", next)),
-                    // TODO: we don't actually want this, we want the end of the file
-                    location: self.last_location,
-                });
+                // TODO: we don't actually want this, we want the end of the file
+                let mut err = self.last_location.error(SyntaxError::from(format!(
+                    "expected '{}', got '<end-of-file>'",
+                    next
+                )));
+                if next == Token::Semicolon {
+                    err = err.fix(self.last_consumed.after(), ";");
+                }
                 self.panic();
-                return err;
+                return Err(err);
             }
         };
         if token.same_kind(&next) {
             Ok(self.next_token().unwrap())
         } else {
-            let err = Err(Locatable {
-                data: SyntaxError::from(format!("expected '{}', got '{}'", next, token)),
-                location: self.next_location(),
-            });
+            let msg = format!("expected '{}', got '{}'", next, token);
+            let mut err = self.next_location().error(SyntaxError::from(msg));
+            if next == Token::Semicolon {
+                // the semicolon goes right after the previous token, not before the next one
+                err = err.fix(self.last_consumed.after(), ";");
+            }
             self.panic();
-            err
+            Err(err)
         }
     }
     /// replace `self.current` with `item`
     /// replace `self.next` with `self.current`
     /// the previous value of `self.next` is lost
     fn unput(&mut self, item: Option<Locatable<Token>>) {
+        if item.is_some() {
+            self.last_consumed = self.consumed_before;
+        }
         let tmp = mem::replace(&mut self.current, item);
         mem::replace(&mut self.next, tmp);
     }
@@ -330,6 +454,22 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
     }
 }
 
+/// The closest of `candidates` to `name`, if it is close enough to be a typo.
+fn did_you_mean<I: IntoIterator<Item = InternedStr>>(
+    name: InternedStr,
+    candidates: I,
+) -> Option<InternedStr> {
+    let candidates: Vec<_> = candidates
+        .into_iter()
+        .map(InternedStr::resolve_and_clone)
+        .collect();
+    crate::utils::closest_name(
+        &name.resolve_and_clone(),
+        candidates.iter().map(String::as_str),
+    )
+    .map(InternedStr::get_or_intern)
+}
+
 impl std::fmt::Display for TagEntry {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -417,9 +557,15 @@ mod tests {
     }
     #[inline]
     pub(crate) fn parser(input: &str) -> Parser<Lexer> {
-        let mut lexer = Lexer::new("<test suite>".to_string(), input.chars(), false);
+        let mut lexer = Lexer::new("<test suite>".to_string(), input.chars(), false, false);
+        let first = lexer.next().unwrap().unwrap();
+        Parser::new(first, lexer, false, false)
+    }
+    #[inline]
+    pub(crate) fn gnu_parser(input: &str) -> Parser<Lexer> {
+        let mut lexer = Lexer::new("<test suite>".to_string(), input.chars(), false, true);
         let first = lexer.next().unwrap().unwrap();
-        Parser::new(first, lexer, false)
+        Parser::new(first, lexer, false, true)
     }
     #[test]
     fn peek() {
@@ -1,6 +1,9 @@
-use super::{Lexeme, Parser, SyntaxResult};
+use super::{Lexeme, Parser, SwitchCases, SyntaxResult};
 use crate::data::prelude::*;
-use crate::data::{lex::Keyword, StorageClass};
+use crate::data::{
+    lex::{AssignmentToken, Keyword},
+    StorageClass,
+};
 use std::iter::Iterator;
 
 type StmtResult = SyntaxResult<Stmt>;
@@ -30,7 +33,7 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             assert!(self.peek_token().is_none()); // from the 'break' above
             let actual_err = self
                 .last_location
-                .with(SyntaxError::from("unclosed '{' delimeter at end of file"));
+                .error(SyntaxError::from("unclosed '{' delimeter at end of file"));
             pending_errs.push(actual_err);
         }
         if let Some(err) = pending_errs.pop() {
@@ -77,37 +80,8 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             }
             Some(Token::Keyword(k)) => match k {
                 // labeled_statement (excluding labels)
-                Keyword::Case => {
-                    let kw = self.next_token().unwrap();
-                    let expr = self.constant_expr()?;
-                    self.expect(Token::Colon)?;
-                    let int = match expr.expr {
-                        ExprType::Literal(Literal::Int(i)) => i as u64,
-                        ExprType::Literal(Literal::UnsignedInt(u)) => u,
-                        ExprType::Literal(Literal::Char(c)) => u64::from(c),
-                        _ => {
-                            self.semantic_err(
-                                "case expression is not an integer constant",
-                                expr.location,
-                            );
-                            0
-                        }
-                    };
-                    let inner = self.statement()?.map(Box::new);
-                    Ok(Some(Stmt {
-                        data: StmtType::Case(int, inner),
-                        location: kw.location,
-                    }))
-                }
-                Keyword::Default => {
-                    let kw = self.next_token().unwrap();
-                    self.expect(Token::Colon)?;
-                    let inner = self.statement()?.map(Box::new);
-                    Ok(Some(Stmt {
-                        data: StmtType::Default(inner),
-                        location: kw.location,
-                    }))
-                }
+                Keyword::Case => Ok(Some(self.case_statement()?)),
+                Keyword::Default => Ok(Some(self.default_statement()?)),
 
                 // selection_statement
                 Keyword::If => Ok(Some(self.if_statement()?)),
@@ -137,9 +111,32 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                     }))
                 }
                 Keyword::Return => Ok(Some(self.return_statement()?)),
+                // `__extension__` can start either a declaration or an expression
+                Keyword::Extension => {
+                    self.next_token();
+                    self.statement()
+                }
+                Keyword::Asm => {
+                    let kw = self.next_token().unwrap();
+                    self.semantic_err("inline assembly is not supported", kw.location);
+                    // skip the qualifiers and operands, e.g. `volatile ("" ::: "memory")`
+                    let mut depth = 0;
+                    while let Some(token) = self.next_token() {
+                        match token.data {
+                            Token::LeftParen => depth += 1,
+                            Token::RightParen if depth <= 1 => break,
+                            Token::RightParen => depth -= 1,
+                            _ => {}
+                        }
+                    }
+                    self.expect(Token::Semicolon)?;
+                    Ok(None)
+                }
 
                 // start of an expression statement
                 Keyword::Sizeof
+                | Keyword::Real
+                | Keyword::Imag
                 | Keyword::StaticAssert
                 | Keyword::Alignas
                 | Keyword::Alignof
@@ -171,20 +168,43 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
                     _ => unreachable!("peek should always be the same as next"),
                 };
                 if self.match_next(&Token::Colon).is_some() {
+                    let vlas = self.vlas_in_scope();
+                    let labels = &mut self
+                        .current_function
+                        .as_mut()
+                        .expect("should have current_function set when parsing statements")
+                        .labels;
+                    // labels have function scope (C11 6.2.1p3)
+                    let previous = labels
+                        .iter()
+                        .find(|(label, _)| label.data == id.data)
+                        .map(|(label, _)| label.location);
+                    labels.push((id, vlas));
+                    if let Some(previous) = previous {
+                        let err = format!("redefinition of label '{}'", id.data);
+                        let err = CompileError::semantic(id.location.with(err))
+                            .note("previous definition is here", previous);
+                        self.error_handler.push_back(err);
+                    }
                     return Ok(Some(Stmt {
                         data: StmtType::Label(id.data),
                         location: id.location,
                     }));
                 }
-                let is_typedef = match self.scope.get(&id.data) {
+                let is_declaration = match self.scope.get(&id.data) {
                     Some(typedef) => typedef.storage_class == StorageClass::Typedef,
-                    _ => false,
+                    // `size_tt x;` is a declaration with a misspelled type name
+                    None => match self.peek_token() {
+                        Some(Token::Id(_)) => true,
+                        Some(Token::LeftParen) => self.is_iso_typeof(id.data),
+                        _ => false,
+                    },
                 };
                 self.unput(Some(Locatable {
                     data: Token::Id(id.data),
                     location: id.location,
                 }));
-                if is_typedef {
+                if is_declaration {
                     let decls = self.declaration()?;
                     let location = match decls.front() {
                         Some(decl) => decl.location,
@@ -204,6 +224,7 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
     fn expression_statement(&mut self) -> SyntaxResult<Option<Stmt>> {
         let expr = self.expr()?;
         let end = self.expect(Token::Semicolon)?;
+        self.check_unused_result(&expr);
         Ok(Some(Stmt {
             data: StmtType::Expr(expr),
             location: end.location,
@@ -216,6 +237,10 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             .current_function
             .as_ref()
             .expect("should have current_function set when parsing statements");
+        if current.noreturn {
+            let warning = Warning::InvalidNoreturn(current.id, "should not return");
+            self.error_handler.warn(warning, ret_token.location);
+        }
         let ret_type = &current.return_type;
         let stmt = match (expr, *ret_type != Type::Void) {
             (None, false) => StmtType::Return(None),
@@ -233,9 +258,9 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             (Some(expr), true) => {
                 let expr = expr.rval();
                 if expr.ctype != *ret_type {
-                    StmtType::Return(Some(
-                        Expr::cast(expr, ret_type).recover(&mut self.error_handler),
-                    ))
+                    let ret_type = ret_type.clone();
+                    self.check_pointer_qualifiers(&expr, &ret_type);
+                    StmtType::Return(Some(self.assignment_cast(expr, &ret_type)))
                 } else {
                     StmtType::Return(Some(expr))
                 }
@@ -254,7 +279,7 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             .expect(Token::Keyword(Keyword::If))
             .expect("parser shouldn't call if_statement without an if");
         self.expect(Token::LeftParen)?;
-        let condition = self.expr()?.rval();
+        let condition = self.condition()?.rval();
         self.expect(Token::RightParen)?;
         let body = self.statement()?;
         let otherwise = if self.match_next(&Token::Keyword(Keyword::Else)).is_some() {
@@ -292,7 +317,41 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
         self.expect(Token::LeftParen)?;
         let expr = self.expr()?.rval();
         self.expect(Token::RightParen)?;
-        let body = self.statement()?;
+        // C11 6.8.4.2p1: the controlling expression shall have integer type
+        if !expr.ctype.is_integral() {
+            self.semantic_err(
+                format!(
+                    "switch condition must have integer type, got '{}'",
+                    expr.ctype
+                ),
+                expr.location,
+            );
+        }
+        let ctype = expr.ctype.clone();
+        let expr = if ctype.is_integral() {
+            expr.integer_promote().recover(&mut self.error_handler)
+        } else {
+            expr
+        };
+        self.current_function
+            .as_mut()
+            .expect("should have current_function set when parsing statements")
+            .switches
+            .push(SwitchCases {
+                ctype,
+                cases: Vec::new(),
+                default: None,
+            });
+        let body = self.statement();
+        let cases = self
+            .current_function
+            .as_mut()
+            .expect("should have current_function set when parsing statements")
+            .switches
+            .pop()
+            .expect("switch should still be on the stack after parsing its body");
+        let body = body?;
+        self.check_enum_cases(&cases, expr.location);
         let stmt = if let Some(body) = body {
             StmtType::Switch(expr, Box::new(body))
         } else {
@@ -309,11 +368,194 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             location: start.location,
         })
     }
+    /// case_statement: CASE constant_expr ':' statement
+    ///
+    /// GNU extension: CASE constant_expr '...' constant_expr ':' statement
+    fn case_statement(&mut self) -> StmtResult {
+        let kw = self.expect(Token::Keyword(Keyword::Case))?;
+        let low = self.constant_expr()?;
+        let high = if self.match_next(&Token::Ellipsis).is_some() {
+            if !self.gnu {
+                self.semantic_err("case ranges are a GNU extension", kw.location);
+            }
+            Some(self.constant_expr()?)
+        } else {
+            None
+        };
+        self.expect(Token::Colon)?;
+        let low = self.case_value(low);
+        let high = high.map(|high| self.case_value(high));
+        if let Some(high) = high {
+            if high < low {
+                self.semantic_err(
+                    format!("empty case range {} ... {}", low, high),
+                    kw.location,
+                );
+            }
+        }
+        self.add_case(low, high.unwrap_or(low), kw.location);
+        let inner = self.statement()?.map(Box::new);
+        // the IR only cares about the bit pattern of the value
+        let data = match high {
+            Some(high) => StmtType::CaseRange(low as u64, high as u64, inner),
+            None => StmtType::Case(low as u64, inner),
+        };
+        Ok(Stmt {
+            data,
+            location: kw.location,
+        })
+    }
+    /// default_statement: DEFAULT ':' statement
+    fn default_statement(&mut self) -> StmtResult {
+        let kw = self.expect(Token::Keyword(Keyword::Default))?;
+        self.expect(Token::Colon)?;
+        match self.current_switch() {
+            Some(SwitchCases {
+                default: Some(previous),
+                ..
+            }) => {
+                let previous = *previous;
+                let err = kw
+                    .location
+                    .error(SemanticError::from(
+                        "cannot have multiple default cases in a switch statement",
+                    ))
+                    .note("previous default is here", previous);
+                self.error_handler.push_back(err);
+            }
+            Some(switch) => switch.default = Some(kw.location),
+            None => self.semantic_err("default case outside of switch statement", kw.location),
+        }
+        let inner = self.statement()?.map(Box::new);
+        Ok(Stmt {
+            data: StmtType::Default(inner),
+            location: kw.location,
+        })
+    }
+    /// The value of a `case` label, converted to the promoted type of the controlling expression.
+    ///
+    /// Values that can't be represented by the type of the controlling expression
+    /// are a warning, since they can never match.
+    fn case_value(&mut self, expr: Expr) -> i128 {
+        let location = expr.location;
+        let folded = match expr.const_fold() {
+            Ok(folded) => folded,
+            Err(err) => {
+                self.error_handler.push_back(err);
+                return 0;
+            }
+        };
+        let value = match folded.expr {
+            ExprType::Literal(Literal::Int(i)) => i128::from(i),
+            ExprType::Literal(Literal::UnsignedInt(u)) => i128::from(u),
+            ExprType::Literal(Literal::Char(c)) => i128::from(c),
+            _ => {
+                self.semantic_err("case expression is not an integer constant", location);
+                return 0;
+            }
+        };
+        let ctype = match self.current_switch() {
+            Some(switch) if switch.ctype.is_integral() => switch.ctype.clone(),
+            // already an error
+            _ => return value,
+        };
+        // C11 6.8.4.2p5: convert to the promoted type, wrapping around if it doesn't fit (6.3.1.3).
+        // Like GCC, negative values are allowed for unsigned types, e.g. `case -1:`,
+        // since that conversion is well-defined.
+        let promoted = ctype.clone().integer_promote();
+        let (min, max) = promoted.integer_range();
+        let lowest = if promoted.is_signed() {
+            min
+        } else {
+            -(max + 1) / 2
+        };
+        let wrapped = (value - min).rem_euclid(max - min + 1) + min;
+        if value < lowest || value > max {
+            self.error_handler
+                .warn(Warning::CaseOutOfRange(value, promoted), location);
+            return wrapped;
+        }
+        let (min, max) = ctype.integer_range();
+        if wrapped < min || wrapped > max {
+            self.error_handler
+                .warn(Warning::CaseOutOfRange(wrapped, ctype), location);
+        }
+        wrapped
+    }
+    /// Record the `case` label `low ... high` in the current switch.
+    ///
+    /// C11 6.8.4.2p3: No two case constant expressions in the same switch statement
+    /// shall have the same value after conversion.
+    fn add_case(&mut self, low: i128, high: i128, location: Location) {
+        let switch = match self.current_switch() {
+            Some(switch) => switch,
+            None => {
+                self.semantic_err("case outside of switch statement", location);
+                return;
+            }
+        };
+        let duplicate = switch
+            .cases
+            .iter()
+            .any(|&(start, end)| start <= high && low <= end);
+        switch.cases.push((low, high));
+        if duplicate {
+            let err = if low == high {
+                format!("duplicate case value {}", low)
+            } else {
+                format!("case range {} ... {} overlaps a previous case", low, high)
+            };
+            self.semantic_err(err, location);
+        }
+    }
+    fn current_switch(&mut self) -> Option<&mut SwitchCases> {
+        self.current_function
+            .as_mut()
+            .and_then(|func| func.switches.last_mut())
+    }
+    /// -Wswitch: warn about enumerators without a `case` in a switch on an enum.
+    ///
+    /// A `default` label handles all the enumerators, so there's nothing to warn about.
+    fn check_enum_cases(&mut self, switch: &SwitchCases, location: Location) {
+        let members = match &switch.ctype {
+            Type::Enum(_, members) if switch.default.is_none() => members,
+            _ => return,
+        };
+        for (name, value) in members {
+            let value = i128::from(*value);
+            if !switch
+                .cases
+                .iter()
+                .any(|&(low, high)| low <= value && value <= high)
+            {
+                self.error_handler
+                    .warn(Warning::UnhandledEnumerator(*name), location);
+            }
+        }
+    }
+    /// The controlling expression of an `if`, `while`, `do` or `for` statement.
+    ///
+    /// `if (x = 1)` was probably meant to be `if (x == 1)`, so it's a warning.
+    /// Like GCC, putting the assignment in parentheses silences the warning.
+    fn condition(&mut self) -> SyntaxResult {
+        let parenthesized = self.peek_token() == Some(&Token::LeftParen);
+        let condition = self.expr()?;
+        if let ExprType::Assign(_, _, AssignmentToken::Equal) = condition.expr {
+            if !parenthesized {
+                // the location of an assignment is the `=`
+                let warning =
+                    CompileWarning::new(Warning::AssignmentInCondition, condition.location)
+                        .fix(condition.location, "==");
+                self.error_handler.push_warning(warning);
+            }
+        }
+        Ok(condition)
+    }
     /// while_statement: WHILE '(' expr ')' statement
     fn while_statement(&mut self) -> StmtResult {
         let start = self.expect(Token::Keyword(Keyword::While))?;
         self.expect(Token::LeftParen)?;
-        let condition = self.expr()?.truthy().recover(&mut self.error_handler);
+        let condition = self.condition()?.truthy().recover(&mut self.error_handler);
         self.expect(Token::RightParen)?;
         let body = self.statement()?;
         Ok(Stmt {
@@ -331,7 +573,7 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
         let body = self.statement()?;
         self.expect(Token::Keyword(Keyword::While))?;
         self.expect(Token::LeftParen)?;
-        let condition = self.expr()?.truthy().recover(&mut self.error_handler);
+        let condition = self.condition()?.truthy().recover(&mut self.error_handler);
         self.expect(Token::RightParen)?;
         self.expect(Token::Semicolon)?;
         let stmt = if let Some(body) = body {
@@ -390,12 +632,16 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
             None => {
                 return Err(self
                     .last_location
-                    .with(SyntaxError::EndOfFile("expression or ';'")));
+                    .error(SyntaxError::EndOfFile("expression or ';'")));
             }
         };
-        let controlling_expr = self
-            .expr_opt(Token::Semicolon)?
-            .map(|expr| Expr::truthy(expr).recover(&mut self.error_handler));
+        let controlling_expr = if self.match_next(&Token::Semicolon).is_some() {
+            None
+        } else {
+            let condition = self.condition()?;
+            self.expect(Token::Semicolon)?;
+            Some(condition.truthy().recover(&mut self.error_handler))
+        };
         let iter_expr = self.expr_opt(Token::RightParen)?;
         let body = self.statement()?.map(Box::new);
         self.leave_scope(self.last_location);
@@ -410,30 +656,68 @@ impl<I: Iterator<Item = Lexeme>> Parser<I> {
         })
     }
     /// goto_statement: GOTO identifier ';'
+    ///
+    /// GNU extension: GOTO '*' expr ';'
     fn goto_statement(&mut self) -> StmtResult {
         let start = self.expect(Token::Keyword(Keyword::Goto)).unwrap();
+        if self.match_next(&Token::Star).is_some() {
+            return self.indirect_goto(start.location);
+        }
         let id = match self.expect(Token::Id(Default::default()))?.data {
             Token::Id(id) => id,
             _ => unreachable!("expect should only return an Id if called with Token::Id"),
         };
         self.expect(Token::Semicolon)?;
+        let vlas = self.vlas_in_scope();
+        self.current_function
+            .as_mut()
+            .expect("should have current_function set when parsing statements")
+            .gotos
+            .push((start.location.with(id), vlas));
         Ok(Stmt {
             data: StmtType::Goto(id),
             location: start.location,
         })
     }
+    /// `goto *ptr;`, where `ptr` is the address of a label. The `goto *` has already been consumed.
+    fn indirect_goto(&mut self, location: Location) -> StmtResult {
+        let target = self.expr()?.rval();
+        self.expect(Token::Semicolon)?;
+        if !self.gnu {
+            self.semantic_err("computed goto is a GNU extension", location);
+        } else if !target.ctype.is_pointer() {
+            self.semantic_err(
+                format!(
+                    "cannot jump to an expression of type '{}', which is not a pointer",
+                    target.ctype
+                ),
+                target.location,
+            );
+        }
+        Ok(Stmt {
+            data: StmtType::IndirectGoto(target),
+            location,
+        })
+    }
+    /// The declarations of the variably modified objects currently in scope.
+    fn vlas_in_scope(&self) -> Vec<Location> {
+        self.current_function
+            .as_ref()
+            .expect("should have current_function set when parsing statements")
+            .vlas
+            .iter()
+            .map(|vla| vla.location)
+            .collect()
+    }
     fn not_executed_warning(
         &mut self,
-        description: &str,
-        from: &str,
-        to: &str,
+        description: &'static str,
+        from: &'static str,
+        to: &'static str,
         location: Location,
     ) {
-        let warning = format!(
-            "{} will be rewritten internally. help: to silence this warning, rewrite it yourself: `{}` => `{}`",
-            description, from, to
-        );
-        self.error_handler.warn(&warning, location);
+        self.error_handler
+            .warn(Warning::EmptyBody(description, from, to), location);
     }
 }
 
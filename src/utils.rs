@@ -7,6 +7,58 @@ pub fn fatal<T: std::fmt::Display>(msg: T, code: i32) -> ! {
     process::exit(code);
 }
 
+/// The number of single-character insertions, deletions, substitutions
+/// and swaps of adjacent characters needed to turn `a` into `b`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // `distances[i][j]` is the distance between the first `i` characters of `a`
+    // and the first `j` characters of `b`
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + substitution);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// Find the candidate closest to `name`, if any is close enough to be a typo.
+///
+/// Names shorter than 3 characters are never close enough, since any other short name
+/// would be only one or two edits away.
+/// Ties are broken alphabetically, so the suggestion doesn't depend on the order of a `HashMap`.
+pub fn closest_name<'a, I: IntoIterator<Item = &'a str>>(
+    name: &str,
+    candidates: I,
+) -> Option<&'a str> {
+    let len = name.chars().count();
+    if len < 3 {
+        return None;
+    }
+    let max_distance = std::cmp::max(1, len / 3);
+    candidates
+        .into_iter()
+        .filter(|&candidate| candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
 /// ensure that a condition is true at compile time
 /// thanks to https://nikolaivazquez.com/posts/programming/rust-static-assertions/
 macro_rules! const_assert {
@@ -63,6 +115,6 @@ macro_rules! semantic_err {
         return Err(CompileError::semantic(Locatable {
             data: $message,
             location: $location,
-        }));
+        }))
     };
 }
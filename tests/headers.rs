@@ -27,12 +27,10 @@ fn cpp_and_save(header: &str, dest: &Path) -> Result<(), Error> {
 fn all_headers() -> Result<(), Error> {
     const STANDARD_HEADERS: &[&str] = &[
         "assert",
-        // _Complex is not yet supported
-        // "complex",
+        "complex",
         "ctype",
         "errno",
-        // fenv uses bitfields on my machine
-        //"fenv",
+        "fenv",
         "float",
         "inttypes",
         "limits",
@@ -42,11 +40,9 @@ fn all_headers() -> Result<(), Error> {
         "signal",
         "stdalign",
         "stdarg",
-        // _Atomic is not yet supported
-        //"stdatomic",
+        "stdatomic",
         "stdbool",
-        // uses __attribute__
-        //"stddef",
+        "stddef",
         "stdint",
         "stdio",
         "stdlib",
@@ -68,7 +64,8 @@ fn all_headers() -> Result<(), Error> {
         let path = header_file.path();
         println!("compiling {}", path.display());
         let header_code = fs::read_to_string(path)?;
-        utils::assert_compiles_no_main(&header_code);
+        // system headers use GNU extensions
+        utils::assert_compiles_no_main(&header_code, true);
     }
     Ok(())
 }
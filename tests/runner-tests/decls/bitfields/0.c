@@ -0,0 +1,17 @@
+// code: 13
+struct flags {
+    unsigned a: 3;
+    int b: 4;
+    unsigned : 0;
+    unsigned c: 5;
+};
+
+int main() {
+    struct flags f;
+    f.a = 9;  // truncated to 1
+    f.b = -3;
+    f.c = 16;
+    // sign extension: b is still negative
+    if (f.b != -3) return 1;
+    return f.a + f.b + f.c - 2 + sizeof(struct flags) - 8 + (f.a == 1);
+}
@@ -0,0 +1,17 @@
+// code: 7
+struct s {
+    char c;
+    unsigned x: 3, y: 4;
+    long l;
+};
+
+struct s global = { 'a', 5, 9, 100 };
+
+int main() {
+    struct s local = { 'b', 3, 7, 10 };
+    global.x += 2;
+    global.y++;
+    local.x--;
+    // 7 + 10 + (2 - 2) - 10
+    return global.x + global.y + (local.x - 2) + local.l - local.l - 10 + (global.c == 'a') - 1;
+}
@@ -0,0 +1,4 @@
+// fail
+struct s { int x: 5; } s;
+int *p = &s.x;
+int main() {}
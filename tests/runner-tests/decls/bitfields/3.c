@@ -0,0 +1,3 @@
+// fail
+struct s { char x: 9; };
+int main() {}
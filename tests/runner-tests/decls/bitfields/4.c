@@ -0,0 +1,11 @@
+// succeeds
+// bitfields share a storage unit unless they would cross its boundary
+struct s {
+    unsigned a: 30;
+    unsigned b: 4;
+    unsigned char c: 4, d: 4;
+};
+int main() {
+    struct s x = { 0, 15, 1, 2 };
+    return sizeof(struct s) != 8 || x.b != 15 || x.d - x.c != 1;
+}
@@ -0,0 +1,7 @@
+// fail
+int main() {
+    int i = 0;
+    const int *p = &i;
+    *p = 1;
+    return i;
+}
@@ -0,0 +1,12 @@
+// code: 3
+// the pointer is not const, only what it points to
+const char *s = "abc";
+int *const t = 0;
+
+int main() {
+    s++;
+    const int a[] = {1, 2};
+    const int *p = a;
+    int *restrict q = 0;
+    return *s - 'a' + p[1] - 1 + (q == t);
+}
@@ -0,0 +1,9 @@
+// code: 5
+static int twice(int);
+int main() {
+    int helper(int);
+    int twice(int);
+    return helper(2) + twice(1) + 1;
+}
+int helper(int x) { return x; }
+static int twice(int x) { return x * 2; }
@@ -0,0 +1,6 @@
+// fail
+int helper(int);
+int main() {
+    double helper(int);
+    return 0;
+}
@@ -0,0 +1,6 @@
+// fail
+int main() {
+    int helper(int);
+    return helper(1);
+}
+double helper(int x) { return x; }
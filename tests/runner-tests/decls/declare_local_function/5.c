@@ -0,0 +1,5 @@
+// fail
+int main() {
+    static int helper(void);
+    return 0;
+}
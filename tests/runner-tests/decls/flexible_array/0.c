@@ -0,0 +1,20 @@
+// code: 42
+void *malloc(unsigned long);
+
+struct buf {
+    int n;
+    char data[];
+};
+
+int main() {
+    struct buf *b = malloc(sizeof(struct buf) + 3);
+    b->n = 3;
+    for (int i = 0; i < b->n; i++) {
+        b->data[i] = i + 1;
+    }
+    // the flexible array member does not add to the size of the struct
+    if (sizeof(struct buf) != sizeof(int) || b->data != (char *)b + sizeof(int)) {
+        return 1;
+    }
+    return 36 + b->data[0] + b->data[1] + b->data[2];
+}
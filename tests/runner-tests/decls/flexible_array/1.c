@@ -0,0 +1,10 @@
+// code: 8
+struct s {
+    char c;
+    double d[];
+};
+
+int main() {
+    // the struct is still padded to the alignment of the flexible array member
+    return sizeof(struct s);
+}
@@ -0,0 +1,6 @@
+// fail
+struct s {
+    char data[];
+    int n;
+};
+int main() {}
@@ -0,0 +1,5 @@
+// fail
+struct s {
+    char data[];
+};
+int main() {}
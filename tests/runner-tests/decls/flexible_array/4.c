@@ -0,0 +1,6 @@
+// fail
+union u {
+    int n;
+    char data[];
+};
+int main() {}
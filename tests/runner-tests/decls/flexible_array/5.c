@@ -0,0 +1,10 @@
+// fail
+struct s {
+    int n;
+    char data[];
+};
+struct t {
+    struct s s;
+    int x;
+};
+int main() {}
@@ -0,0 +1,7 @@
+// fail
+struct s {
+    int n;
+    char data[];
+};
+struct s arr[2];
+int main() {}
@@ -0,0 +1,7 @@
+// fail
+struct s {
+    int n;
+    char data[];
+};
+struct s my_s = { 1, { 2 } };
+int main() {}
@@ -0,0 +1,4 @@
+// fail
+struct s;
+struct s arr[2];
+int main() {}
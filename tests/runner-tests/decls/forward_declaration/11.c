@@ -0,0 +1,4 @@
+// fail
+struct s;
+void f(struct s my_s) {}
+int main() {}
@@ -0,0 +1,23 @@
+// code: 7
+struct node;
+int sum(struct node *head);
+
+struct node {
+    int value;
+    struct node *next;
+};
+
+int main() {
+    struct node c = { 4, 0 };
+    struct node b = { 2, &c };
+    struct node a = { 1, &b };
+    return sum(&a);
+}
+
+int sum(struct node *head) {
+    int total = 0;
+    for (; head; head = head->next) {
+        total += head->value;
+    }
+    return total;
+}
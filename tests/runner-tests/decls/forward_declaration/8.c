@@ -0,0 +1,5 @@
+// fail
+struct s;
+int main() {
+    struct s my_s;
+}
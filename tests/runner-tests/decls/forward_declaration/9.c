@@ -0,0 +1,5 @@
+// fail
+struct s;
+int main() {
+    return sizeof(struct s);
+}
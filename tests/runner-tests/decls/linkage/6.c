@@ -0,0 +1,14 @@
+// code: 13
+int counter = 1;
+int increment() {
+    extern int counter;
+    return ++counter;
+}
+int main() {
+    int counter = 10;
+    {
+        extern int counter;
+        counter++;
+    }
+    return increment() + counter;
+}
@@ -0,0 +1,6 @@
+// code: 7
+int main() {
+    extern int later;
+    return later;
+}
+int later = 7;
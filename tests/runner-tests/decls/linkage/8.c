@@ -0,0 +1,5 @@
+// fail
+int main() {
+    extern int i = 1;
+    return i;
+}
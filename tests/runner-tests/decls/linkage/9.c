@@ -0,0 +1,6 @@
+// fail
+int x;
+int main() {
+    extern double x;
+    return 0;
+}
@@ -0,0 +1,16 @@
+// code: 0
+long double x = 1.5;
+struct s { char c; long double d; };
+int main() {
+    if (sizeof(long long) != 8 || sizeof(unsigned long long) != 8) return 1;
+    if (sizeof(long double) != 16) return 2;
+    if (sizeof(struct s) != 32) return 3;
+    long long big = 1;
+    big <<= 40;
+    unsigned long u = 1;
+    if (sizeof(big * u) != 8 || big * u < 0) return 4;
+    // x87 stores the integer bit of the significand explicitly
+    unsigned char *p = (unsigned char *)&x;
+    if (p[7] != 0xc0 || p[8] != 0xff || p[9] != 0x3f) return 5;
+    return 0;
+}
@@ -0,0 +1,8 @@
+// fail
+// Cranelift has no x87 extended precision, so this must not silently become a double
+long double half(long double x) {
+    return x / 2;
+}
+int main() {
+    return half(4);
+}
@@ -0,0 +1,14 @@
+// code: 0
+// long double objects can't be computed with, but their address can still be used
+struct s { long double d; int i; };
+long double g = 1.5;
+long double *addr(void) { return &g; }
+int main(void) {
+    long double local;
+    long double *p = &local;
+    struct s a, b;
+    a.i = 3;
+    b = a;
+    if (b.i != 3 || p != &local || addr() != &g) return 1;
+    return sizeof(local) != 16;
+}
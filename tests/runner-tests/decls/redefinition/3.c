@@ -0,0 +1,4 @@
+// code: 0
+int x;
+int x;
+int main() { return x; }
@@ -0,0 +1,5 @@
+// code: 3
+int x;
+int x = 3;
+int x;
+int main() { return x; }
@@ -0,0 +1,5 @@
+// code: 4
+static int s;
+static int s = 4;
+int *p = &s;
+int main() { return *p; }
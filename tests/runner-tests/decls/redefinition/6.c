@@ -0,0 +1,6 @@
+// code: 5
+int a[];
+int main() {
+    a[0] = 5;
+    return a[0];
+}
@@ -0,0 +1,4 @@
+// fail
+int x = 1;
+int x;
+int x = 2;
@@ -0,0 +1,8 @@
+// code: 1
+int main() {
+    int x = 1;
+    {
+        int x = 2;
+    }
+    return x;
+}
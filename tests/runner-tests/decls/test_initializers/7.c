@@ -0,0 +1,7 @@
+// code: 10
+int main() {
+    int a[4] = {1, 2, 3, 4};
+    int x = 5;
+    struct { char c; int i; long l; } s = { 'a', x, x * 2 };
+    return a[0] + a[3] + s.l - s.i;
+}
@@ -0,0 +1,11 @@
+// succeeds
+int main() {
+    int a[10] = {1, 2};
+    struct { int i, j; double d; } s = { 1 };
+    int b[2][3] = {{1}, {4, 5}};
+    for (int i = 2; i < 10; i++) {
+        if (a[i]) return 1;
+    }
+    if (s.j != 0 || s.d != 0) return 1;
+    return b[0][1] + b[0][2] + b[1][2];
+}
@@ -0,0 +1,8 @@
+// code: 40
+int main() {
+    // large enough to be copied from static memory
+    int table[16] = {1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12};
+    char s[] = "hello";
+    char t[10] = "hi";
+    return table[11] + table[15] + s[5] + sizeof(s) + sizeof(t) + t[9] + (t[1] == 'i') + 11;
+}
@@ -0,0 +1,6 @@
+// fail
+// Cranelift cannot emit thread-local storage, so this must not silently become a normal global
+_Thread_local int counter;
+int main() {
+    return counter;
+}
@@ -0,0 +1,6 @@
+// fail
+int main() {
+    static _Thread_local int ok;
+    _Thread_local int automatic;
+    return 0;
+}
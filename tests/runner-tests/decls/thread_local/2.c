@@ -0,0 +1,5 @@
+// fail
+_Thread_local int f();
+int main() {
+    return 0;
+}
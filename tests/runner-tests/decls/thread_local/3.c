@@ -0,0 +1,7 @@
+// code: 0
+// declaring a thread-local variable defined elsewhere is fine, as long as it isn't used
+extern _Thread_local int errno_value;
+int main() {
+    extern _Thread_local int other;
+    return 0;
+}
@@ -0,0 +1,5 @@
+// fail
+extern _Thread_local int counter;
+int main() {
+    return counter;
+}
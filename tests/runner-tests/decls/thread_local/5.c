@@ -0,0 +1,6 @@
+// fail
+extern _Thread_local int counter;
+int *p = &counter;
+int main() {
+    return 0;
+}
@@ -0,0 +1,14 @@
+// code: 10
+int main() {
+    int n = 5;
+    int a[n];
+    for (int i = 0; i < n; i++) {
+        a[i] = i;
+    }
+    int total = 0;
+    for (int i = 0; i < n; i++) {
+        total += a[i];
+    }
+    // the size is computed at runtime
+    return total + sizeof(a) - 20;
+}
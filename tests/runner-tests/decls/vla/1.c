@@ -0,0 +1,12 @@
+// code: 15
+int main() {
+    int rows = 3, cols = 4;
+    int grid[rows][cols];
+    for (int i = 0; i < rows; i++) {
+        for (int j = 0; j < cols; j++) {
+            grid[i][j] = i * cols + j;
+        }
+    }
+    // each row is `cols` ints apart
+    return grid[2][3] + grid[1][0] + sizeof(grid[0]) - sizeof(int[cols]);
+}
@@ -0,0 +1,15 @@
+// code: 3
+int main() {
+    int n = 3;
+    char s[n];
+    // the length is saved when the array is declared
+    n = 100;
+    int count = 0;
+    for (int i = 1; i <= 10; i++) {
+        // reuses the allocation each time through the loop
+        long a[i];
+        a[i - 1] = i;
+        count += sizeof(a) == i * sizeof(long);
+    }
+    return sizeof(s) + count - 10;
+}
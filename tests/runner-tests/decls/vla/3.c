@@ -0,0 +1,14 @@
+// code: 12
+int sum(int rows, int cols, int matrix[][cols]) {
+    int total = 0;
+    for (int i = 0; i < rows; i++) {
+        for (int j = 0; j < cols; j++) {
+            total += matrix[i][j];
+        }
+    }
+    return total;
+}
+int main() {
+    int matrix[2][3] = {{1, 1, 2}, {2, 3, 3}};
+    return sum(2, 3, matrix);
+}
@@ -0,0 +1,10 @@
+// fail
+int main() {
+    int n = 3;
+    goto inside;
+    {
+        int a[n];
+        inside:
+        return 0;
+    }
+}
@@ -0,0 +1,3 @@
+// fail
+int n = 3;
+int a[n];
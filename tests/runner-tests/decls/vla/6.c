@@ -0,0 +1,5 @@
+// fail
+int main() {
+    int n = 3;
+    static int a[n];
+}
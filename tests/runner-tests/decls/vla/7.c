@@ -0,0 +1,5 @@
+// fail
+int main() {
+    int n = 3;
+    int a[n] = {1, 2, 3};
+}
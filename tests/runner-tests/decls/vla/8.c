@@ -0,0 +1,10 @@
+// crash
+// the allocation fails, which must not become a write through a null pointer
+int fill(unsigned long n) {
+    char a[n];
+    a[0] = 1;
+    return a[0];
+}
+int main(void) {
+    return fill(-1);
+}
@@ -0,0 +1,8 @@
+// code: 6
+int main() {
+    _Atomic int i = 1;
+    i = i + 2;
+    i += 2;
+    i++;
+    return i;
+}
@@ -0,0 +1,11 @@
+// code: 10
+_Atomic(long) counter;
+int main() {
+    for (int i = 0; i < 5; i++) {
+        ++counter;
+        counter *= 1;
+        counter--;
+        counter += 2;
+    }
+    return counter;
+}
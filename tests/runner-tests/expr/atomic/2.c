@@ -0,0 +1,11 @@
+// code: 15
+int main() {
+    _Atomic int i;
+    __c11_atomic_init(&i, 4);
+    int old = __c11_atomic_fetch_add(&i, 3, 5);
+    int loaded = __c11_atomic_load(&i, 2);
+    __c11_atomic_store(&i, 1, 3);
+    int exchanged = __c11_atomic_exchange(&i, 2, 5);
+    // 4 + 7 + 1 + 2 + 1
+    return old + loaded + exchanged + i + __c11_atomic_is_lock_free(sizeof(int));
+}
@@ -0,0 +1,16 @@
+// code: 3
+int main() {
+    int i = 5;
+    int expected = 4;
+    int result = 0;
+    if (!__atomic_compare_exchange_n(&i, &expected, 6, 0, 5, 5) && expected == 5) {
+        result++;
+    }
+    if (__atomic_compare_exchange_n(&i, &expected, 6, 0, 5, 5) && i == 6) {
+        result++;
+    }
+    if (__atomic_fetch_sub(&i, 1, 0) == 6 && __atomic_sub_fetch(&i, 1, 0) == 4) {
+        result++;
+    }
+    return result;
+}
@@ -0,0 +1,9 @@
+// code: 3
+int main() {
+    int a[4] = {1, 2, 3, 4};
+    _Atomic(int *) p = a;
+    int *old = __c11_atomic_fetch_add(&p, 2, 5);
+    // unlike the C11 builtins, GCC's builtins don't scale by the size of the pointed-to type
+    __atomic_fetch_add(&p, sizeof(int), 5);
+    return *p - *old;
+}
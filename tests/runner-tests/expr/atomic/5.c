@@ -0,0 +1,10 @@
+// code: 1
+int main() {
+    unsigned char flag = 0;
+    int first = __atomic_test_and_set(&flag, 5);
+    int second = __atomic_test_and_set(&flag, 5);
+    __atomic_clear(&flag, 5);
+    __atomic_thread_fence(5);
+    __atomic_signal_fence(5);
+    return !first && second && !flag;
+}
@@ -0,0 +1,10 @@
+// code: 7
+int main() {
+    double d = 1.5, two_and_a_half = 2.5;
+    double result;
+    __atomic_store(&d, &two_and_a_half, 5);
+    __atomic_load(&d, &result, 5);
+    _Atomic int b = 0;
+    b = 1;
+    return (int)(result * 2) + b + __atomic_always_lock_free(sizeof(double), 0);
+}
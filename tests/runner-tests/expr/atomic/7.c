@@ -0,0 +1,4 @@
+// fail
+typedef int array[2];
+_Atomic array a;
+int main() {}
@@ -0,0 +1,5 @@
+// fail
+int main() {
+    int i;
+    return __c11_atomic_load(&i, 5);
+}
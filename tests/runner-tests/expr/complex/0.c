@@ -0,0 +1,7 @@
+// code: 7
+int main() {
+    double _Complex z;
+    __real__ z = 3;
+    __imag__ z = 4;
+    return __real__ z + __imag__ z;
+}
@@ -0,0 +1,11 @@
+// code: 1
+int main() {
+    double _Complex a, b;
+    __real__ a = 1;
+    __imag__ a = 2;
+    __real__ b = 3;
+    __imag__ b = -5;
+    double _Complex sum = a + b, difference = a - b;
+    return __real__ sum == 4 && __imag__ sum == -3
+        && __real__ difference == -2 && __imag__ difference == 7;
+}
@@ -0,0 +1,14 @@
+// code: 1
+// multiplication and division go through __muldc3 and __divdc3 in the runtime library
+int main() {
+    double _Complex a, b;
+    __real__ a = 1;
+    __imag__ a = 2;
+    __real__ b = 3;
+    __imag__ b = 4;
+    // (1 + 2i)(3 + 4i) = -5 + 10i
+    double _Complex product = a * b;
+    double _Complex quotient = product / b;
+    return __real__ product == -5 && __imag__ product == 10
+        && quotient == a;
+}
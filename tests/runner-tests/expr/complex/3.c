@@ -0,0 +1,17 @@
+// code: 1
+// float _Complex is packed into a single register
+float _Complex conjugate(float _Complex z) {
+    __imag__ z = -__imag__ z;
+    return z;
+}
+double _Complex scale(double _Complex z, double factor) {
+    return z * factor;
+}
+int main() {
+    float _Complex f;
+    __real__ f = 1.5;
+    __imag__ f = 2.5;
+    f = conjugate(f);
+    double _Complex d = scale(f, 2);
+    return __real__ d == 3 && __imag__ d == -5;
+}
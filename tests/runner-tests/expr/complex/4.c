@@ -0,0 +1,13 @@
+// code: 4
+double creal(double _Complex);
+double cimag(double _Complex);
+int main() {
+    // converting a real number to a complex type gives an imaginary part of zero
+    double _Complex z = 2;
+    z += 1;
+    z = z * 2 - z;
+    __imag__ z += 3;
+    // converting back discards the imaginary part
+    int i = z;
+    return i + creal(z) - cimag(z) + (z != 3);
+}
@@ -0,0 +1,5 @@
+// fail
+int main() {
+    double _Complex a = 1, b = 2;
+    return a < b;
+}
@@ -0,0 +1,2 @@
+// fail
+int _Complex i;
@@ -0,0 +1,9 @@
+// code: 2
+double _Complex global = 3;
+struct wrapper {
+    int tag;
+    float _Complex value;
+} wrapped = { 1, 2 };
+int main() {
+    return !(__imag__ global || __imag__ wrapped.value) + wrapped.value + (!global - 1);
+}
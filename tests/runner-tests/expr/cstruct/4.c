@@ -0,0 +1,21 @@
+// code: 10
+struct value {
+    int kind;
+    union {
+        int i;
+        float f;
+    };
+    struct {
+        char tag;
+        unsigned flags : 3;
+    };
+};
+
+int main() {
+    struct value v;
+    v.kind = 1;
+    v.i = 3;
+    v.tag = 2;
+    v.flags = 4;
+    return v.kind + v.i + v.tag + v.flags;
+}
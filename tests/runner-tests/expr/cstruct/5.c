@@ -0,0 +1,19 @@
+// code: 13
+struct value {
+    int kind;
+    union {
+        int i;
+        float f;
+    };
+    int after;
+};
+
+// static and automatic initializers initialize the anonymous member as a whole
+struct value global = { 1, { 2 }, 3 };
+int *p = &global.i;
+
+int main() {
+    struct value local = { 1, { 2 }, 3 };
+    *p += 1;
+    return global.kind + global.i + global.after + local.kind + local.i + local.after;
+}
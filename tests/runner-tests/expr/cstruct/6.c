@@ -0,0 +1,13 @@
+// fail
+struct value {
+    int kind;
+    union {
+        int i;
+        float f;
+    };
+};
+
+int main() {
+    struct value v;
+    return v.j;
+}
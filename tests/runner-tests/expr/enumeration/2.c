@@ -0,0 +1,13 @@
+// code: 0
+enum small { A, B };
+enum big { C = 4294967295 };
+struct s { char c; enum small e; };
+int main() {
+    if (sizeof(enum small) != sizeof(int)) return 1;
+    if (sizeof(struct s) != 2 * sizeof(int)) return 2;
+    if (sizeof(A) != sizeof(int)) return 3;
+    if (sizeof(enum big) != sizeof(int)) return 4;
+    if (C < 0 || C != 4294967295u) return 5;
+    enum small e = B;
+    return e + A - 1;
+}
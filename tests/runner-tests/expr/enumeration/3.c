@@ -0,0 +1,5 @@
+// fail
+enum too_big { A = 4294967296 };
+int main() {
+    return A;
+}
@@ -0,0 +1,17 @@
+// code: 7
+// enums are compatible with int, so pointers to them can be converted without a cast
+enum e { A, B };
+enum big { C = 4294967295 };
+int set(int *p) {
+    *p = 7;
+    return 0;
+}
+int main() {
+    enum e x = A;
+    int *p = &x;
+    enum e *q = p;
+    enum big y = C;
+    unsigned *u = &y;
+    set(&x);
+    return *q == x && *u == 4294967295u ? x : 1;
+}
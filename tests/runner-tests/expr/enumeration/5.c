@@ -0,0 +1,7 @@
+// fail
+enum e { A, B };
+int main() {
+    enum e x = A;
+    long *p = &x;
+    return 0;
+}
@@ -0,0 +1,5 @@
+// code: 1
+int main() {
+    int zero = 0;
+    return !zero;
+}
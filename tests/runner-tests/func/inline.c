@@ -0,0 +1,8 @@
+// code: 6
+inline int twice(int x) { return 2 * x; }
+extern inline int thrice(int x) { return 3 * x; }
+static inline int square(int x) { return x * x; }
+int main() {
+    int (*f)(int) = square;
+    return twice(thrice(1)) + f(0);
+}
@@ -0,0 +1,6 @@
+// code: 4
+inline int four(void);
+int main() { return four(); }
+inline int four(void) { return 4; }
+// a declaration without `inline` makes the definition external
+int four(void);
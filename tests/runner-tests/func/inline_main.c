@@ -0,0 +1,2 @@
+// fail
+inline int main() { return 0; }
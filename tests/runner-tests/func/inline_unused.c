@@ -0,0 +1,9 @@
+// code: 3
+// these are never defined, so this only links if the unused inline functions aren't emitted
+int undefined(void);
+int also_undefined(void);
+static inline int unused(void) { return undefined(); }
+inline int unused_inline_definition(void) { return also_undefined(); }
+static inline int three(void) { return 3; }
+static inline int used(void) { return three(); }
+int main() { return used(); }
@@ -1,4 +1,3 @@
-// ignore: https://github.com/jyn514/rcc/issues/61
 // succeeds
 void f() {}
 int main() {
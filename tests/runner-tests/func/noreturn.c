@@ -0,0 +1,21 @@
+// code: 3
+_Noreturn void exit(int);
+_Noreturn void die(int code) {
+    exit(code);
+}
+_Noreturn void spin(void) {
+    while (1) {}
+}
+int check(int x) {
+    if (x > 0) {
+        return x;
+    }
+    die(3);
+}
+int dummy_return(void) {
+    die(4);
+    return 0;
+}
+int main() {
+    return check(0);
+}
@@ -0,0 +1,23 @@
+// code: 5
+_Noreturn void exit(int);
+int both(int x) {
+    if (x) {
+        exit(5);
+    } else {
+        exit(6);
+    }
+}
+int cases(int x) {
+    switch (x) {
+        case 1:
+            return 1;
+        default:
+            exit(x);
+    }
+}
+int main() {
+    if (cases(1) != 1) {
+        return 1;
+    }
+    return both(1);
+}
@@ -0,0 +1,10 @@
+// code: 42
+int add(a, b)
+int a;
+int b;
+{
+    return a + b;
+}
+int main() {
+    return add(40, 2);
+}
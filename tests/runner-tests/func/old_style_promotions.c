@@ -0,0 +1,16 @@
+// code: 15
+int f(c, s, x, d)
+    char c;
+    short s;
+    float x;
+    double d;
+{
+    return c + s + (int)(x * 2) + (int)d;
+}
+int g();
+int main() {
+    char c = 1;
+    float x = 1.5;
+    return f(c, 2, x, 6.0) + g(3);
+}
+int g(a) int a; { return a; }
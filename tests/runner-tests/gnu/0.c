@@ -0,0 +1,9 @@
+// code: 6
+int main(void) {
+    int i = 0;
+    int x = ({ int y = 2; i++; y * 3; });
+    ({ i += 1; });
+    ({ });
+    if (i != 2) return 1;
+    return x;
+}
@@ -0,0 +1,12 @@
+// code: 0
+int main(void) {
+    const long l = 1;
+    __typeof__(l) m = 5;
+    typeof(int *) p = 0;
+    if (sizeof(m) != sizeof(long)) return 1;
+    typeof(l + 1.0) d = 1.5;
+    if (d != 1.5) return 2;
+    __typeof__(char[3]) arr;
+    if (sizeof arr != 3) return 3;
+    return p != 0;
+}
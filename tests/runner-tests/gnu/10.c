@@ -0,0 +1,13 @@
+// code: 19
+// case ranges
+int kind(int c) {
+    switch (c) {
+        case '0' ... '9': return 1;
+        case 'a' ... 'z': case 'A' ... 'Z': return 2;
+        case -100 ... -1: return 3;
+        default: return 0;
+    }
+}
+int main(void) {
+    return kind('5') + kind('q') + kind('Q') + kind(-100) + kind(-1) + kind(' ') + kind(0x7fffffff) + 4 * kind('a');
+}
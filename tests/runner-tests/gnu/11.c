@@ -0,0 +1,16 @@
+// code: 8
+// the linker defines __start_<section> and __stop_<section> for sections named like identifiers
+int first __attribute__((section("counters"))) = 1;
+int unrelated = 100;
+int another __attribute__((section("counters"))) = 2;
+int *pointer __attribute__((section("pointers"))) = &another;
+static int zeroed __attribute__((section("zeroed")));
+extern int __start_counters[], __stop_counters[];
+int main(void) {
+    long size = (long)__stop_counters - (long)__start_counters;
+    if (size != 2 * sizeof(int)) {
+        return 1;
+    }
+    another = 3;
+    return __start_counters[0] + __start_counters[1] + *pointer + zeroed + (__start_counters == &first);
+}
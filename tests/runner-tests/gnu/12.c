@@ -0,0 +1,5 @@
+// fail
+int main(void) {
+    int local __attribute__((section("locals"))) = 1;
+    return local;
+}
@@ -0,0 +1,6 @@
+// code: 3
+// the linker sees `abs`, not `my_abs`
+extern int my_abs(int) __asm__("abs");
+int main(void) {
+    return my_abs(-3);
+}
@@ -0,0 +1,12 @@
+// code: 0
+// the kind of declarations found in glibc headers
+__extension__ typedef long long int64;
+extern int printf(const char *__restrict, ...) __attribute__((__format__(__printf__, 1, 2), __nonnull__(1)));
+static int helper(int x) __attribute__((__unused__));
+static int helper(int x) { return x; }
+struct __attribute__((__packed__)) pair { int first; int second __attribute__((aligned(4))); };
+int main(void) {
+    __extension__ int64 big = (int64)1 << 40;
+    struct pair p = { 1, 2 };
+    return big != (int64)1099511627776 ? 1 : helper(p.first - 1);
+}
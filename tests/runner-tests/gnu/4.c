@@ -0,0 +1,4 @@
+// fail
+int main(void) {
+    __asm__ volatile ("nop");
+}
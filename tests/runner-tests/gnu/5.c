@@ -0,0 +1,17 @@
+// code: 0
+// packed and aligned change the layout of structs and objects
+struct __attribute__((packed)) packed { char c; int i; };
+struct wide { char c; } __attribute__((aligned(8)));
+struct member { char c; short s __attribute__((aligned(8))); };
+int global __attribute__((aligned(64)));
+int main(void) {
+    struct packed p = { 1, 2 };
+    int local __attribute__((aligned(16))) = p.i;
+    if (sizeof(struct packed) != 5 || sizeof(struct wide) != 8 || sizeof(struct member) != 16) {
+        return 1;
+    }
+    if ((unsigned long)&global % 64 != 0 || (unsigned long)&local % 16 != 0) {
+        return 2;
+    }
+    return p.c + p.i == 3 && local == 2 ? 0 : 3;
+}
@@ -0,0 +1,9 @@
+// output: BEGIN: first second main destructor END
+int printf(const char *, ...);
+__attribute__((constructor)) static void second(void) { printf("second "); }
+__attribute__((constructor(101))) static void first(void) { printf("first "); }
+__attribute__((destructor)) static void cleanup(void) { printf("destructor"); }
+int main(void) {
+    printf("main ");
+    return 0;
+}
@@ -0,0 +1,11 @@
+// code: 3
+// a plugin hook that may or may not be defined elsewhere
+extern int plugin_init(void) __attribute__((weak));
+__attribute__((weak)) int plugin_version(void) { return 3; }
+__attribute__((visibility("hidden"))) int internal_helper(void) { return 0; }
+int main(void) {
+    if (plugin_init) {
+        return 1;
+    }
+    return plugin_version() + internal_helper();
+}
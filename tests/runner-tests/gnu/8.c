@@ -0,0 +1,8 @@
+// code: 7
+// alias and used
+int real(void) { return 5; }
+int fake(void) __attribute__((alias("real")));
+int counter __attribute__((used)) = 2;
+int main(void) {
+    return fake() + counter;
+}
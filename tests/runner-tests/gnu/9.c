@@ -0,0 +1,32 @@
+// code: 42
+// labels as values and computed goto
+enum { PUSH, ADD, MUL, HALT };
+
+int run(const int *program) {
+    void *dispatch[] = { &&push, &&add, &&mul, &&halt };
+    int stack[8];
+    int pc = 0, top = 0;
+    goto *dispatch[program[pc]];
+push:
+    stack[top] = program[pc + 1];
+    top++;
+    pc += 2;
+    goto *dispatch[program[pc]];
+add:
+    top--;
+    stack[top - 1] += stack[top];
+    pc++;
+    goto *dispatch[program[pc]];
+mul:
+    top--;
+    stack[top - 1] *= stack[top];
+    pc++;
+    goto *dispatch[program[pc]];
+halt:
+    return stack[top - 1];
+}
+
+int main(void) {
+    int program[] = { PUSH, 4, PUSH, 2, ADD, PUSH, 7, MUL, HALT };
+    return run(program);
+}
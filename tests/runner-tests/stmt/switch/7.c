@@ -0,0 +1,20 @@
+// code: 4
+// case values are converted to the promoted type of the condition
+int classify(signed char c) {
+    switch (c) {
+        case -1: return 1;
+        case -128: return 2;
+        case 127: return 3;
+        default: return 0;
+    }
+}
+int unsigned_classify(unsigned u) {
+    switch (u) {
+        case -1: return 0;
+        case 0: return 1;
+        default: return 2;
+    }
+}
+int main(void) {
+    return classify(-1) + classify(-128) + unsigned_classify(4294967295u) + unsigned_classify(0) - 1 + (classify(5) == 0);
+}
@@ -0,0 +1,8 @@
+// fail
+int main(void) {
+    int i = 1;
+    switch (i) {
+        case 1: return 1;
+        case 2 - 1: return 2;
+    }
+}
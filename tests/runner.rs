@@ -42,6 +42,8 @@ fn run_one(path: &path::Path) -> Result<(), io::Error> {
     reader.read_line(&mut first_line)?;
     // remove trailing \n
     first_line.pop();
+    // only the tests of GNU extensions are compiled with `-std=gnu11`
+    let gnu = path.starts_with("tests/runner-tests/gnu");
     let test_func = match first_line.as_str() {
         "// compile" => utils::assert_compiles,
         "// no-main" => utils::assert_compiles_no_main,
@@ -54,13 +56,13 @@ fn run_one(path: &path::Path) -> Result<(), io::Error> {
                 let code = line["// code: ".len()..]
                     .parse()
                     .expect("tests should have an integer after code:");
-                utils::assert_code(&program, code);
+                utils::assert_code(&program, code, gnu);
                 return Ok(());
             } else if line.starts_with("// errors: ") {
                 let errors = line["// errors: ".len()..]
                     .parse()
                     .expect("tests should have an integer after code:");
-                utils::assert_num_errs(&program, errors);
+                utils::assert_num_errs(&program, errors, gnu);
                 return Ok(());
             } else if line.starts_with("// ignore: ") {
                 let url = &line["// ignore: ".len()..];
@@ -70,7 +72,7 @@ fn run_one(path: &path::Path) -> Result<(), io::Error> {
                 );
                 return Ok(());
             } else if line.starts_with("// output: ") {
-                return output_test(&line["// output: ".len()..], &mut reader, &program);
+                return output_test(&line["// output: ".len()..], &mut reader, &program, gnu);
             } else {
                 // seems like a reasonable default
                 utils::assert_succeeds
@@ -78,7 +80,7 @@ fn run_one(path: &path::Path) -> Result<(), io::Error> {
         }
     };
 
-    test_func(&program);
+    test_func(&program, gnu);
     Ok(())
 }
 
@@ -86,7 +88,12 @@ fn run_one(path: &path::Path) -> Result<(), io::Error> {
 /// syntax: '// output: ' expected_output
 /// expected_output: '[^\n]*' | 'BEGIN: ' (comment_line* '\n' | [^\n]+) 'END'
 /// comment_line: '\n// ' [^\n+]
-fn output_test<B: BufRead>(line: &str, reader: &mut B, program: &str) -> Result<(), io::Error> {
+fn output_test<B: BufRead>(
+    line: &str,
+    reader: &mut B,
+    program: &str,
+    gnu: bool,
+) -> Result<(), io::Error> {
     const BEGIN: &str = "BEGIN: ";
     const END: &str = "END";
     let tmp_str;
@@ -110,7 +117,7 @@ fn output_test<B: BufRead>(line: &str, reader: &mut B, program: &str) -> Result<
             &tmp_str
         }
     };
-    utils::assert_output(program, expected);
+    utils::assert_output(program, expected, gnu);
     Ok(())
 }
 
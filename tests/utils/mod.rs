@@ -24,8 +24,40 @@ pub fn cpp() -> std::process::Command {
         "-D__DBL_MIN__=2.225073858507201383e-308L",
         "-D__FLT_MAX__=3.402823466385288598e+38F",
         "-D__FLT_MIN__=1.175494350822287507e-38F",
-        "-D__INTPTR_TYPE__=8",
         "-D__INT32_TYPE__=4",
+        // used by stdatomic.h
+        "-D__ATOMIC_RELAXED=0",
+        "-D__ATOMIC_CONSUME=1",
+        "-D__ATOMIC_ACQUIRE=2",
+        "-D__ATOMIC_RELEASE=3",
+        "-D__ATOMIC_ACQ_REL=4",
+        "-D__ATOMIC_SEQ_CST=5",
+        // the types GCC uses on LP64 targets
+        "-D__CHAR16_TYPE__=short unsigned int",
+        "-D__CHAR32_TYPE__=unsigned int",
+        "-D__WCHAR_TYPE__=int",
+        "-D__INT_LEAST8_TYPE__=signed char",
+        "-D__INT_LEAST16_TYPE__=short int",
+        "-D__INT_LEAST32_TYPE__=int",
+        "-D__INT_LEAST64_TYPE__=long int",
+        "-D__UINT_LEAST8_TYPE__=unsigned char",
+        "-D__UINT_LEAST16_TYPE__=short unsigned int",
+        "-D__UINT_LEAST32_TYPE__=unsigned int",
+        "-D__UINT_LEAST64_TYPE__=long unsigned int",
+        "-D__INT_FAST8_TYPE__=signed char",
+        "-D__INT_FAST16_TYPE__=long int",
+        "-D__INT_FAST32_TYPE__=long int",
+        "-D__INT_FAST64_TYPE__=long int",
+        "-D__UINT_FAST8_TYPE__=unsigned char",
+        "-D__UINT_FAST16_TYPE__=long unsigned int",
+        "-D__UINT_FAST32_TYPE__=long unsigned int",
+        "-D__UINT_FAST64_TYPE__=long unsigned int",
+        "-D__INTPTR_TYPE__=long int",
+        "-D__UINTPTR_TYPE__=long unsigned int",
+        "-D__SIZE_TYPE__=long unsigned int",
+        "-D__PTRDIFF_TYPE__=long int",
+        "-D__INTMAX_TYPE__=long int",
+        "-D__UINTMAX_TYPE__=long unsigned int",
         #[cfg(linux)]
         "-D__linux__",
         #[cfg(target_arch = "x86_64")]
@@ -34,19 +66,29 @@ pub fn cpp() -> std::process::Command {
     cpp
 }
 
-pub fn compile_and_run(program: &str, args: &[&str]) -> Result<Output, Error> {
-    let output = compile(program, false)?;
+pub fn compile_and_run(program: &str, args: &[&str], gnu: bool) -> Result<Output, Error> {
+    let output = compile_with(program, false, gnu)?;
     info!("running file {:?}", output);
     run(&output, args).map_err(Error::IO)
 }
 
+/// Compile `program` as ISO C.
 pub fn compile(program: &str, no_link: bool) -> Result<tempfile::TempPath, Error> {
+    compile_with(program, no_link, false)
+}
+
+/// Compile `program` as ISO C, or in GNU mode (`-std=gnu11`) if `gnu` is set.
+///
+/// Only system headers and the tests of GNU extensions should need GNU mode.
+fn compile_with(program: &str, no_link: bool, gnu: bool) -> Result<tempfile::TempPath, Error> {
     let (result, _warnings) = rcc::compile(
         program,
         "<integration-test>".to_string(),
         false,
         false,
         false,
+        false,
+        gnu,
     );
     let module = result?;
     let output = tempfile::NamedTempFile::new()
@@ -70,26 +112,26 @@ pub fn run(program: &Path, args: &[&str]) -> Result<Output, std::io::Error> {
     Command::new(program).args(args).output()
 }
 
-pub fn assert_compiles(program: &str) {
+pub fn assert_compiles(program: &str, gnu: bool) {
     assert!(
-        compile(program, true).is_err(),
+        compile_with(program, true, gnu).is_err(),
         "{} failed to compile",
         program
     );
 }
 
-pub fn assert_compiles_no_main(fragment: &str) {
+pub fn assert_compiles_no_main(fragment: &str, gnu: bool) {
     let program = format!("int main() {{}}\n{}", fragment);
     assert!(
-        compile(&program, true).is_ok(),
+        compile_with(&program, true, gnu).is_ok(),
         "{} failed to compile",
         fragment
     );
 }
 
-pub fn assert_compile_error(program: &str) {
+pub fn assert_compile_error(program: &str, gnu: bool) {
     assert!(
-        match compile(program, true) {
+        match compile_with(program, true, gnu) {
             Err(Error::Source(_)) => true,
             _ => false,
         },
@@ -98,8 +140,8 @@ pub fn assert_compile_error(program: &str) {
     );
 }
 
-pub fn assert_crash(program: &str) {
-    let output = compile(program, false).expect("could not compile program");
+pub fn assert_crash(program: &str, gnu: bool) {
+    let output = compile_with(program, false, gnu).expect("could not compile program");
     log::debug!("running compiled program at {:?}", output);
     let path: &Path = output.as_ref();
     let mut handle = Command::new(path)
@@ -122,9 +164,9 @@ pub fn assert_crash(program: &str) {
     }
 }
 
-pub fn assert_output(program: &str, output: &str) {
+pub fn assert_output(program: &str, output: &str, gnu: bool) {
     assert!(
-        match compile_and_run(program, &[]) {
+        match compile_and_run(program, &[], gnu) {
             Err(_) => false,
             Ok(actual) => actual.stdout == output.as_bytes(),
         },
@@ -134,9 +176,9 @@ pub fn assert_output(program: &str, output: &str) {
     );
 }
 
-pub fn assert_succeeds(program: &str) {
+pub fn assert_succeeds(program: &str, gnu: bool) {
     assert!(
-        match compile_and_run(program, &[]) {
+        match compile_and_run(program, &[], gnu) {
             Err(_) => false,
             Ok(output) => output.status.success(),
         },
@@ -145,9 +187,9 @@ pub fn assert_succeeds(program: &str) {
     );
 }
 
-pub fn assert_code(program: &str, code: i32) {
+pub fn assert_code(program: &str, code: i32, gnu: bool) {
     assert!(
-        match compile_and_run(program, &[]) {
+        match compile_and_run(program, &[], gnu) {
             Err(_) => false,
             Ok(output) => match output.status.code() {
                 Some(actual) => actual == code,
@@ -160,8 +202,8 @@ pub fn assert_code(program: &str, code: i32) {
     );
 }
 
-pub fn assert_num_errs<S: AsRef<str>>(program: S, n: usize) {
-    match compile(program.as_ref(), true) {
+pub fn assert_num_errs<S: AsRef<str>>(program: S, n: usize, gnu: bool) {
+    match compile_with(program.as_ref(), true, gnu) {
         Err(Error::Source(errs)) => assert!(errs.len() == n),
         _ => panic!("program should have an error"),
     }
@@ -12,7 +12,7 @@ fn printf(args: &[&str], expected: &str) {
     }}",
         args.join(", ")
     );
-    utils::assert_output(&program, expected);
+    utils::assert_output(&program, expected, false);
 }
 
 fn printf_helper(format: &str, args: &[&str]) {
@@ -75,6 +75,7 @@ fn multiple_given_args() {
             puts(buf);
         }",
         "it is 2019\n\n",
+        false,
     );
 }
 